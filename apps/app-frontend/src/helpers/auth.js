@@ -33,6 +33,20 @@ export async function login() {
 	return await invoke('plugin:auth|login')
 }
 
+/**
+ * Authenticate a user with a Yggdrasil-compatible authentication server,
+ * such as one used with authlib-injector.
+ * The account is added and becomes the default user.
+ *
+ * @param {string} serverUrl - The API root of the server, or a URL pointing to it
+ * @param {string} username
+ * @param {string} password
+ * @returns {Promise<Credential>}
+ */
+export async function login_yggdrasil(serverUrl, username, password) {
+	return await invoke('plugin:auth|login_yggdrasil', { serverUrl, username, password })
+}
+
 /**
  * Retrieves the default user
  * @return {Promise<UUID | undefined>}
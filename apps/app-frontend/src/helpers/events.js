@@ -57,6 +57,23 @@ export async function process_listener(callback) {
 	return await listen('process', (event) => callback(event.payload))
 }
 
+/// Payload for the 'crash_analysis' event, sent when a game process crashes or is killed by a signal
+/*
+    CrashAnalysisPayload {
+        profile_path_id: relative path to the profile
+        uuid: unique identification of the process
+        exit_code: exit code of the process, if it exited normally
+        signal: signal that killed the process, if any (Unix only)
+        crash_report: file name of the analyzed crash report, if any
+        description: crash report description
+        exception: first exception found
+        suspects: suspected mods, most suspicious first
+    }
+*/
+export async function crash_analysis_listener(callback) {
+	return await listen('crash_analysis', (event) => callback(event.payload))
+}
+
 /// Payload for the 'profile' event
 /*
     ProfilePayload {
@@ -77,6 +94,8 @@ export async function profile_listener(callback) {
     event: event type ("InstallMod", "InstallModpack", "InstallVersion"),
     id: string id of the mod/modpack/version to install
   }
+  OpenUrl { event: "OpenUrl", url: http(s) URL the game asked to open }
+  OpenProfileFolder { event: "OpenProfileFolder", profile_path: profile whose folder the game asked to open }
 */
 export async function command_listener(callback) {
 	return await listen('command', (event) => {
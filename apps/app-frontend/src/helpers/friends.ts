@@ -6,9 +6,20 @@ import dayjs from 'dayjs'
 import { get_user_many } from '@/helpers/cache'
 import type { ModrinthCredentials } from '@/helpers/mr_auth'
 
+export type UserActivity = {
+	project_id?: string
+	version_id?: string
+	game_version?: string
+	loader?: string
+	server?: string
+	world?: string
+	started?: string
+}
+
 export type UserStatus = {
 	user_id: string
 	profile_name: string | null
+	activity?: UserActivity
 	last_update: string
 }
 
@@ -35,10 +46,74 @@ export async function remove_friend(userId: string): Promise<void> {
 	return await invoke('plugin:friends|remove_friend', { userId })
 }
 
+export type TunnelConnection = {
+	socket: string
+	bytes_sent: number
+	bytes_received: number
+}
+
+export type TunnelInfo = {
+	id: string
+	relay_connected: boolean
+	connections: TunnelConnection[]
+} & (
+	| { type: 'hosting'; port: number; allowed_users: string[] | null }
+	| { type: 'joining'; to_socket: string; host: string | null; local_address: string }
+)
+
+export type FriendTunnel = {
+	user: string
+	socket: string
+}
+
+// Hosts a world that is open to LAN on the given port for friends, or only the given friends
+export async function host_world(port: number, allowedUsers?: string[]): Promise<TunnelInfo> {
+	return await invoke('plugin:friends|friends_host_world', { port, allowedUsers })
+}
+
+// Joins a world a friend is hosting. The game connects to the returned local_address
+export async function join_world(socket: string): Promise<TunnelInfo> {
+	return await invoke('plugin:friends|friends_join_world', { socket })
+}
+
+export async function close_tunnel(id: string): Promise<void> {
+	return await invoke('plugin:friends|friends_close_tunnel', { id })
+}
+
+export async function tunnels(): Promise<TunnelInfo[]> {
+	return await invoke('plugin:friends|friends_tunnels')
+}
+
+export async function friend_tunnels(): Promise<FriendTunnel[]> {
+	return await invoke('plugin:friends|friends_friend_tunnels')
+}
+
+// Joins the server a friend is playing on with quick play, using a matching instance
+export async function join_friend_server(user: string): Promise<unknown> {
+	return await invoke('plugin:friends|friends_join_friend_server', { user })
+}
+
+// Describes what a friend is playing, e.g. "Playing X on server Y"
+export function describeStatus(status: UserStatus): string | null {
+	const activity = status.activity
+	if (!status.profile_name && !activity) {
+		return null
+	}
+
+	let description = `Playing ${status.profile_name ?? 'Minecraft'}`
+	if (activity?.server) {
+		description += ` on ${activity.server}`
+	} else if (activity?.world) {
+		description += ` in ${activity.world}`
+	}
+	return description
+}
+
 export type FriendWithUserData = {
 	id: string
 	friend_id: string | null
 	status: string | null
+	activity: UserActivity | null
 	last_updated: Dayjs | null
 	created: Dayjs
 	username: string
@@ -67,7 +142,8 @@ export async function transformFriends(
 		return {
 			id: friend.id,
 			friend_id: friend.friend_id,
-			status: status?.profile_name ?? null,
+			status: status ? describeStatus(status) : null,
+			activity: status?.activity ?? null,
 			last_updated: status && status.last_update ? dayjs(status.last_update) : null,
 			created: dayjs(friend.created),
 			avatar: user?.avatar_url ?? '',
@@ -47,6 +47,24 @@ export async function import_instance(launcherType, basePath, instanceFolder) {
 	})
 }
 
+/// Import a packwiz pack from a local folder containing a pack.toml
+/// eg: import_packwiz("C:/packs/my-pack")
+export async function import_packwiz(packFolder) {
+	// create a basic, empty instance (most properties will be filled in by the import process)
+	const profilePath = await create('packwiz', '1.19.4', 'vanilla', 'latest', null, true)
+
+	return await invoke('plugin:import|import_packwiz', {
+		profilePath,
+		packFolder,
+	})
+}
+
+/// Import an offline bundle exported from another machine as a new instance
+/// eg: import_offline_bundle("C:/bundles/my-instance.zip")
+export async function import_offline_bundle(bundlePath) {
+	return await invoke('plugin:import|import_offline_bundle', { bundlePath })
+}
+
 /// Checks if this instance is valid for importing, given a certain launcher type
 /// eg: is_valid_importable_instance("C:/MultiMC/Instance 1", "MultiMC")
 export async function is_valid_importable_instance(instanceFolder, launcherType) {
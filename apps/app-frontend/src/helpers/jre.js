@@ -41,8 +41,26 @@ export async function test_jre(path, majorVersion) {
 }
 
 // Automatically installs specified java version
-export async function auto_install_java(javaVersion) {
-	return await invoke('plugin:jre|jre_auto_install_java', { javaVersion })
+// provider ('zulu', 'adoptium' or 'mojang') is tried first, falling back to the others
+export async function auto_install_java(javaVersion, provider) {
+	return await invoke('plugin:jre|jre_auto_install_java', { javaVersion, provider })
+}
+
+// Lists the java versions installed by the app
+// Returns [{ major_version, provider, version, path }]
+export async function get_managed_java_installs() {
+	return await invoke('plugin:jre|jre_get_managed_installs')
+}
+
+// Checks the java versions installed by the app for updates
+// Returns [{ major_version, provider, installed_version, latest_version }]
+export async function check_java_updates() {
+	return await invoke('plugin:jre|jre_check_updates')
+}
+
+// Updates the java version installed by the app for a major version
+export async function update_java(majorVersion) {
+	return await invoke('plugin:jre|jre_update_java', { majorVersion })
 }
 
 // Get max memory in KiB
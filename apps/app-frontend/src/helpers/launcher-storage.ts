@@ -0,0 +1,65 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type ContentStoreStats = {
+	objects: number
+	size: number
+	references: number
+	deduplicated_size: number
+}
+
+export async function get_content_store_stats(): Promise<ContentStoreStats> {
+	return await invoke('plugin:storage|storage_get_content_store_stats')
+}
+
+export async function deduplicate_content(): Promise<ContentStoreStats> {
+	return await invoke('plugin:storage|storage_deduplicate_content')
+}
+
+export async function prune_content_store(): Promise<ContentStoreStats> {
+	return await invoke('plugin:storage|storage_prune_content_store')
+}
+
+export type StorageCategory =
+	| 'versions'
+	| 'libraries'
+	| 'assets'
+	| 'natives'
+	| 'java_versions'
+	| 'log_configs'
+	| 'content_store'
+	| 'caches'
+	| 'other'
+
+export type ProfileDiskUsage = {
+	path: string
+	name: string
+	size: number
+}
+
+export type DiskUsage = {
+	total: number
+	profiles: ProfileDiskUsage[]
+	categories: Partial<Record<StorageCategory, number>>
+}
+
+export type GarbageEntry = {
+	category: StorageCategory
+	path: string
+	size: number
+}
+
+export type GarbageCollectionReport = {
+	dry_run: boolean
+	entries: GarbageEntry[]
+	size: number
+	skipped: StorageCategory[]
+}
+
+export async function get_disk_usage(): Promise<DiskUsage> {
+	return await invoke('plugin:storage|storage_get_disk_usage')
+}
+
+// Removes game files no instance uses anymore. With dryRun, only lists them.
+export async function collect_garbage(dryRun: boolean): Promise<GarbageCollectionReport> {
+	return await invoke('plugin:storage|storage_collect_garbage', { dryRun })
+}
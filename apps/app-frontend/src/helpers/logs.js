@@ -72,3 +72,16 @@ export async function get_live_log_buffer(profilePath) {
 export async function clear_log_buffer(profilePath) {
 	return await invoke('plugin:logs|logs_clear_live_log_buffer', { profilePath })
 }
+
+/// Analyze the latest crash of a profile, looking for the mods that caused it
+/// Returns the crash report description, exception, and suspected mods (most suspicious first)
+export async function analyze_crash(profilePath) {
+	return await invoke('plugin:logs|logs_analyze_crash', { profilePath })
+}
+
+/// Search the logs of a profile, including archived ones
+/// query: { filenames, levels, logger, thread, since, until, regex, context_lines, offset, limit }
+/// Returns { matches, total_matches, level_counts }, where each match has its lines and surrounding context
+export async function query_logs(profilePath, query) {
+	return await invoke('plugin:logs|logs_query', { profilePath, query })
+}
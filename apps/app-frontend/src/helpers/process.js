@@ -17,6 +17,12 @@ export async function get_all() {
 	return await invoke('plugin:process|process_get_all')
 }
 
+/// Gets the mods the game running in a profile reported as loaded
+/// Returns [{ id, name, version }], or null if the game has not reported any
+export async function get_reported_mods(path) {
+	return await invoke('plugin:process|process_get_reported_mods', { path })
+}
+
 /// Kills a process by UUID
 export async function kill(uuid) {
 	return await invoke('plugin:process|process_kill', { uuid })
@@ -135,6 +135,43 @@ export async function get_dependencies_as_content_items(
 	})
 }
 
+export type ContentIssue =
+	| { type: 'missing_dependency'; project_id: string; required_by: string[] }
+	| { type: 'disabled_dependency'; file_path: string; required_by: string[] }
+	| { type: 'duplicate_project'; project_id: string; file_paths: string[] }
+	| { type: 'loader_mismatch'; file_path: string; loader: string; loaders: string[] }
+	| {
+			type: 'game_version_mismatch'
+			file_path: string
+			game_version: string
+			game_versions: string[]
+	  }
+
+// Disabled files to enable, versions to install and files to remove to resolve a content issue
+export interface ContentFix {
+	enable: string[]
+	remove: string[]
+	add: string[]
+}
+
+export interface ContentDiagnostic {
+	issue: ContentIssue
+	fix: ContentFix | null
+}
+
+// Check a profile's installed content for missing dependencies, duplicates and mismatches
+export async function get_content_diagnostics(
+	path: string,
+	cacheBehaviour?: CacheBehaviour,
+): Promise<ContentDiagnostic[]> {
+	return await invoke('plugin:profile|profile_get_content_diagnostics', { path, cacheBehaviour })
+}
+
+// Apply a fix suggested by get_content_diagnostics
+export async function apply_content_fix(path: string, fix: ContentFix): Promise<void> {
+	return await invoke('plugin:profile|profile_apply_content_fix', { path, fix })
+}
+
 // Get a profile's full fs path
 // Returns a path
 export async function get_full_path(path: string): Promise<string> {
@@ -179,6 +216,29 @@ export async function install(path: string, force: boolean): Promise<void> {
 	return await invoke('plugin:profile|profile_install', { path, force })
 }
 
+export type VerificationProblem =
+	| { type: 'missing' }
+	| { type: 'hash_mismatch'; expected: string; actual: string }
+	| { type: 'corrupted' }
+
+export type VerificationIssue = {
+	kind: 'client' | 'library' | 'natives' | 'asset_index' | 'asset' | 'log_config' | 'content'
+	path: string
+	problem: VerificationProblem
+	repaired: boolean
+	repair_error: string | null
+}
+
+export type VerificationReport = {
+	checked: number
+	issues: VerificationIssue[]
+}
+
+// Checks a profile's files, and downloads missing or corrupted files again if repair is set
+export async function verify(path: string, repair: boolean): Promise<VerificationReport> {
+	return await invoke('plugin:profile|profile_verify', { path, repair })
+}
+
 // Updates all of a profile's projects
 export async function update_all(path: string): Promise<Record<string, string>> {
 	return await invoke('plugin:profile|profile_update_all', { path })
@@ -266,6 +326,59 @@ export async function export_profile_mrpack(
 	})
 }
 
+// Export a profile as a self-contained offline bundle, including game files and optionally Java
+export async function export_offline_bundle(
+	path: string,
+	exportLocation: string,
+	includedOverrides: string[],
+	includeJava: boolean,
+): Promise<void> {
+	return await invoke('plugin:profile|profile_export_offline_bundle', {
+		path,
+		exportLocation,
+		includedOverrides,
+		includeJava,
+	})
+}
+
+// Export a profile to a packwiz pack in the given folder
+// included_overrides is an array of paths to override folders to include (ie: 'mods', 'resource_packs')
+export async function export_profile_packwiz(
+	path: string,
+	exportLocation: string,
+	includedOverrides: string[],
+	version?: string,
+	author?: string,
+): Promise<void> {
+	return await invoke('plugin:profile|profile_export_packwiz', {
+		path,
+		exportLocation,
+		includedOverrides,
+		version,
+		author,
+	})
+}
+
+// Export a profile to a CurseForge modpack zip
+// Requires a CurseForge API key to match files against CurseForge
+export async function export_profile_curseforge(
+	path: string,
+	exportLocation: string,
+	includedOverrides: string[],
+	version?: string,
+	author?: string,
+	curseforgeApiKey?: string,
+): Promise<void> {
+	return await invoke('plugin:profile|profile_export_curseforge', {
+		path,
+		exportLocation,
+		includedOverrides,
+		version,
+		author,
+		curseforgeApiKey,
+	})
+}
+
 // Given a folder path, populate an array of all the subfolders
 // Intended to be used for finding potential override folders
 // profile
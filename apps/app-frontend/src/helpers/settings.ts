@@ -47,6 +47,7 @@ export type AppSettings = {
 	telemetry: boolean
 	discord_rpc: boolean
 	personalized_ads: boolean
+	status_privacy: StatusPrivacy
 
 	onboarded: boolean
 
@@ -69,9 +70,46 @@ export type AppSettings = {
 	pending_update_toast_for_version: string | null
 	auto_download_updates: boolean | null
 
+	endpoints: Endpoints
+	shared_content_store: boolean
+
 	version: number
 }
 
+// Which details about the instance being played are shared with friends and shown on Discord
+export type StatusPrivacy = {
+	instance_name: boolean
+	project: boolean
+	game_version: boolean
+	loader: boolean
+	server: boolean
+	world: boolean
+	play_time: boolean
+}
+
+export type DownloadMirror = {
+	prefix: string
+	mirrors: string[]
+}
+
+// A public key launcher metadata must be signed with
+export type TrustedKey = {
+	key_id: string
+	public_key: string
+}
+
+// API and metadata endpoints, for self-hosted instances
+export type Endpoints = {
+	site_url: string
+	api_url: string
+	api_url_v3: string
+	api_base_url: string
+	socket_url: string
+	launcher_meta_url: string
+	launcher_meta_keys: TrustedKey[]
+	download_mirrors: DownloadMirror[]
+}
+
 // Get full settings object
 export async function get() {
 	return (await invoke('plugin:settings|settings_get')) as AppSettings
@@ -28,6 +28,8 @@ export type OpeningCommandEvent =
 	| 'InstallVersion'
 	| 'InstallMod'
 	| 'InstallModpack'
+	| 'OpenUrl'
+	| 'OpenProfileFolder'
 	| string
 
 export interface OpeningCommand {
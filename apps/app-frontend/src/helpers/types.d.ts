@@ -46,7 +46,16 @@ type LinkedData = {
 	locked: boolean
 }
 
-export type InstanceLoader = 'vanilla' | 'forge' | 'fabric' | 'quilt' | 'neoforge'
+export type InstanceLoader =
+	| 'vanilla'
+	| 'forge'
+	| 'fabric'
+	| 'quilt'
+	| 'neoforge'
+	| 'liteloader'
+	| 'rift'
+	| 'legacy-fabric'
+	| 'babric'
 
 type ContentFile = {
 	metadata?: {
@@ -186,6 +186,13 @@ export async function get_server_status(
 	return await invoke('plugin:worlds|get_server_status', { address, protocolVersion })
 }
 
+export async function get_profile_server_statuses(
+	path: string,
+	forceRefresh = false,
+): Promise<Record<string, ServerStatus | null>> {
+	return await invoke('plugin:worlds|get_profile_server_statuses', { path, forceRefresh })
+}
+
 export async function start_join_singleplayer_world(path: string, world: string): Promise<unknown> {
 	return await invoke('plugin:worlds|start_join_singleplayer_world', { path, world })
 }
@@ -194,6 +201,41 @@ export async function start_join_server(path: string, address: string): Promise<
 	return await invoke('plugin:worlds|start_join_server', { path, address })
 }
 
+export type ServerProjectContent =
+	| { kind: 'modpack'; version_id: string }
+	| {
+			kind: 'vanilla'
+			supported_game_versions: string[]
+			recommended_game_version: string | null
+	  }
+
+export type ServerCompatibility =
+	| { status: 'compatible' }
+	| { status: 'incompatible'; profile_protocol: number; server_protocol: number }
+	| { status: 'unknown' }
+
+export type ServerProjectStatus = {
+	project_id: string
+	name: string
+	address: string
+	content: ServerProjectContent
+	profile_path: string | null
+	update_available: boolean
+	compatibility: ServerCompatibility
+}
+
+export async function get_server_project_status(projectId: string): Promise<ServerProjectStatus> {
+	return await invoke('plugin:worlds|get_server_project_status', { projectId })
+}
+
+export async function install_server_project(projectId: string): Promise<string> {
+	return await invoke('plugin:worlds|install_server_project', { projectId })
+}
+
+export async function join_server_project(projectId: string): Promise<unknown> {
+	return await invoke('plugin:worlds|join_server_project', { projectId })
+}
+
 export async function showWorldInFolder(instancePath: string, worldPath: string) {
 	const fullPath = await get_full_path(instancePath)
 	return await openPath(fullPath + '/saves/' + worldPath)
@@ -392,16 +434,14 @@ export async function refreshServerData(
 		})
 		.catch((err) => {
 			console.error(`Refreshing addr ${address}`, protocolVersion, err)
-			if (!protocolVersion?.legacy) {
-				refreshServerData(serverData, { version: 74, legacy: true }, address)
-			}
 		})
 }
 
-export function refreshServers(
+export async function refreshServers(
+	instancePath: string,
 	worlds: World[],
 	serverData: Record<string, ServerData>,
-	protocolVersion: ProtocolVersion | null,
+	forceRefresh = false,
 ) {
 	const servers = worlds.filter(isServerWorld)
 	servers.forEach((server) => {
@@ -414,10 +454,29 @@ export function refreshServers(
 		}
 	})
 
-	// noinspection ES6MissingAwait - handled with .then by refreshServerData already
-	Object.keys(serverData).forEach((address) =>
-		refreshServerData(serverData[address], protocolVersion, address),
-	)
+	const refreshTime = Date.now()
+	const statuses = await get_profile_server_statuses(instancePath, forceRefresh).catch((err) => {
+		console.error(`Refreshing servers for instance: ${instancePath}`, err)
+		return {} as Record<string, ServerStatus | null>
+	})
+
+	for (const server of servers) {
+		const data = serverData[server.address]
+		data.refreshing = false
+
+		const status = statuses[server.address]
+		if (!status) continue
+		if (data.lastSuccessfulRefresh && data.lastSuccessfulRefresh > refreshTime) {
+			// Don't update if there was a more recent successful refresh
+			continue
+		}
+		data.lastSuccessfulRefresh = Date.now()
+		data.status = status
+		if (status.description) {
+			data.rawMotd = status.description
+			data.renderedMotd = autoToHTML(status.description)
+		}
+	}
 }
 
 export async function refreshWorld(worlds: World[], instancePath: string, worldPath: string) {
@@ -53,7 +53,17 @@ interface IncompatibilityWarningModalRef {
 }
 
 const LOADER_ORDER = ['vanilla', 'fabric', 'quilt', 'neoforge', 'forge']
-const SUPPORTED_LOADERS: Set<string> = new Set(['vanilla', 'forge', 'fabric', 'quilt', 'neoforge'])
+const SUPPORTED_LOADERS: Set<string> = new Set([
+	'vanilla',
+	'forge',
+	'fabric',
+	'quilt',
+	'neoforge',
+	'liteloader',
+	'rift',
+	'legacy-fabric',
+	'babric',
+])
 const VANILLA_COMPATIBLE_LOADERS: Set<string> = new Set(['minecraft', 'datapack'])
 
 function sortLoaders(loaders: string[]): string[] {
@@ -5,13 +5,15 @@ import { type Ref, ref } from 'vue'
 import type { Router } from 'vue-router'
 
 import { trackEvent } from '@/helpers/analytics'
-import { get_project, get_project_v3, get_version } from '@/helpers/cache.js'
-import { install_to_existing_profile } from '@/helpers/pack.js'
-import { create, edit, edit_icon, get, install as installProfile, list } from '@/helpers/profile.js'
+import { get_project_v3 } from '@/helpers/cache.js'
+import { get } from '@/helpers/profile.js'
 import type { GameInstance } from '@/helpers/types'
-import { start_join_server } from '@/helpers/worlds.ts'
+import {
+	get_server_project_status,
+	install_server_project,
+	join_server_project,
+} from '@/helpers/worlds.ts'
 import { handleSevereError } from '@/store/error.js'
-import { ensureManagedServerWorldExists, getServerAddress } from '@/store/install.js'
 
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 interface ModalRef<TShow extends (...args: any[]) => void = () => void> {
@@ -99,108 +101,35 @@ export function createServerInstall(opts: {
 		return installingServerProjects.value.includes(projectId)
 	}
 
-	async function joinServer(profilePath: string, serverAddress: string | null) {
-		if (!serverAddress) return
-		await start_join_server(profilePath, serverAddress)
-	}
-
-	async function findInstalledInstance(projectId: string) {
-		const packs = await list()
-		return packs.find((pack) => pack.linked_data?.project_id === projectId) ?? null
+	async function joinServer(projectId: string, instance: GameInstance) {
+		try {
+			await join_server_project(projectId)
+			trackEvent('InstanceStart', {
+				loader: instance.loader,
+				game_version: instance.game_version,
+				source: 'ServerProject',
+			})
+		} catch (err) {
+			handleSevereError(err, { profilePath: instance.path })
+		}
 	}
 
-	async function createVanillaInstance(
-		project: Labrinth.Projects.v2.Project,
-		gameVersion: string,
-		serverAddress: string | null,
+	function showInstallComplete(
+		projectId: string,
+		instance: GameInstance,
+		title: string,
+		text: string,
 	) {
-		const profilePath = await create(
-			project.title,
-			gameVersion,
-			'vanilla',
-			null,
-			project.icon_url ?? null,
-			false,
-			{
-				project_id: project.id,
-				version_id: '',
-				locked: true,
-			},
-		)
-
-		await ensureManagedServerWorldExists(profilePath, project.title, serverAddress)
-
-		return profilePath
-	}
-
-	async function updateVanillaGameVersion(instance: GameInstance, targetGameVersion: string) {
-		if (instance.game_version === targetGameVersion) return
-
-		await edit(instance.path, { game_version: targetGameVersion })
-		await installProfile(instance.path, false)
-	}
-
-	function showModpackInstallSuccess(project: GameInstance, serverAddress: string | null) {
 		opts.popupNotificationManager.addPopupNotification({
-			title: 'Install complete',
-			text: `${project.name} is installed and ready to play.`,
+			title,
+			text,
 			type: 'success',
 			buttons: [
-				...(serverAddress
-					? [
-							{
-								label: 'Launch game',
-								action: async () => {
-									try {
-										await joinServer(project.path, serverAddress)
-										trackEvent('InstanceStart', {
-											loader: project.loader,
-											game_version: project.game_version,
-											source: 'ServerProject',
-										})
-									} catch (err) {
-										handleSevereError(err, { profilePath: project.path })
-									}
-								},
-								color: 'brand' as const,
-							},
-						]
-					: []),
 				{
-					label: 'Instance',
-					action: () => opts.router.push(`/instance/${encodeURIComponent(project.path)}`),
+					label: 'Launch game',
+					action: () => joinServer(projectId, instance),
+					color: 'brand' as const,
 				},
-			],
-			autoCloseMs: null,
-		})
-	}
-
-	function showUpdateSuccess(instance: GameInstance, serverAddress: string | null) {
-		opts.popupNotificationManager.addPopupNotification({
-			title: 'Update complete',
-			text: `${instance.name} has been updated and is ready to play.`,
-			type: 'success',
-			buttons: [
-				...(serverAddress
-					? [
-							{
-								label: 'Launch game',
-								action: async () => {
-									try {
-										if (serverAddress) await start_join_server(instance.path, serverAddress)
-										trackEvent('InstanceStart', {
-											loader: instance.loader,
-											game_version: instance.game_version,
-											source: 'ServerProject',
-										})
-									} catch (err) {
-										handleSevereError(err, { profilePath: instance.path })
-									}
-								},
-								color: 'brand' as const,
-							},
-						]
-					: []),
 				{
 					label: 'Instance',
 					action: () => opts.router.push(`/instance/${encodeURIComponent(instance.path)}`),
@@ -210,152 +139,88 @@ export function createServerInstall(opts: {
 		})
 	}
 
-	/**
-	 * Server projects that use modpack content have linked_data.project_id as
-	 * the server project id and linked_data.version_id as the modpack content version id.
-	 * The modpack content version can be of the same server project, or from a different project.
-	 */
-	async function installServerProject(serverProjectId: string) {
-		const [project, projectV3] = await Promise.all([
-			get_project(serverProjectId, 'bypass'),
-			get_project_v3(serverProjectId, 'bypass'),
-		])
-
-		const serverAddress = getServerAddress(projectV3?.minecraft_java_server)
-
-		const content = projectV3?.minecraft_java_server?.content
-		if (!content || content.kind !== 'modpack') return
-
-		const contentVersionId = content.version_id
-		const contentVersion = await get_version(contentVersionId, 'bypass')
-		const contentProjectId = contentVersion.project_id
-		const gameVersion = contentVersion.game_versions?.[0] ?? ''
-
-		const profilePath = await create(
-			project.title,
-			gameVersion,
-			'vanilla',
-			null,
-			project.icon_url,
-			true,
-			{
-				project_id: serverProjectId,
-				version_id: contentVersionId,
-				locked: true,
-			},
+	function showModpackInstallSuccess(projectId: string, instance: GameInstance) {
+		showInstallComplete(
+			projectId,
+			instance,
+			'Install complete',
+			`${instance.name} is installed and ready to play.`,
 		)
+	}
 
-		// Save the icon path before pack install overwrites it
-		const profileBeforeInstall = await get(profilePath)
-		const originalIconPath = profileBeforeInstall?.icon_path ?? null
-
-		await install_to_existing_profile(
-			contentProjectId,
-			contentVersionId,
-			project.title,
-			profilePath,
+	function showUpdateSuccess(projectId: string, instance: GameInstance) {
+		showInstallComplete(
+			projectId,
+			instance,
+			'Update complete',
+			`${instance.name} has been updated and is ready to play.`,
 		)
+	}
 
-		// Pack install overwrites name, icon, and linked_data with the content project's values.
-		// Restore them to point to the server project.
-		await edit(profilePath, {
-			name: project.title,
-			linked_data: {
-				project_id: serverProjectId,
-				version_id: contentVersionId,
-				locked: true,
-			},
-		})
-		await edit_icon(profilePath, originalIconPath)
+	/**
+	 * Creates an instance with the server project's content, or updates the existing one, and
+	 * adds the server to its server list.
+	 */
+	async function installServerProject(serverProjectId: string) {
+		await install_server_project(serverProjectId)
+	}
 
-		await ensureManagedServerWorldExists(profilePath, project.title, serverAddress)
+	/**
+	 * Installs or updates the server's content, then shows a notification to join the server.
+	 */
+	async function installAndNotify(projectId: string, notify: typeof showUpdateSuccess) {
+		if (installingServerProjects.value.includes(projectId)) return
+		startInstallingServer(projectId)
+		try {
+			const instance = await get(await install_server_project(projectId))
+			if (instance) notify(projectId, instance)
+		} finally {
+			stopInstallingServer(projectId)
+		}
 	}
 
 	/**
 	 * Handles logic when clicking "Play" on a server project. This includes:
-	 * - Checking if need to install modpack content. If so, opens install to play modal
-	 * - Checking if need to update modpack content. If so, open update to play modal
-	 * - Checking if need to create instance for vanilla server. If so, creates instance.
-	 * - Adding server to worlds list if not already there
-	 * - Joining server
+	 * - Installing the server's content. Modpacks are confirmed in the install to play modal first
+	 * - Updating the server's content. Modpack updates are confirmed in the update to play modal first
+	 * - Joining the server, which also adds it to the worlds list if not already there
 	 */
 	async function playServerProject(projectId: string) {
-		const [project, projectV3] = await Promise.all([
-			get_project(projectId, 'bypass'),
-			get_project_v3(projectId, 'bypass'),
-		])
-
-		if (projectV3?.minecraft_server == null) {
-			console.warn('playServerProject failed: project is not a server project')
-			return
-		}
-
-		const content = projectV3?.minecraft_java_server?.content
-		const serverAddress = getServerAddress(projectV3?.minecraft_java_server)
-		const isVanilla = content?.kind === 'vanilla'
-		const isModpack = content?.kind === 'modpack'
-		const modpackVersionId = content?.version_id ?? null
-		const recommendedGameVersion = content?.recommended_game_version
+		const status = await get_server_project_status(projectId)
+		const content = status.content
 
-		let instance = await findInstalledInstance(project.id)
-
-		if (isVanilla && !instance) {
-			if (installingServerProjects.value.includes(projectId)) return
-			startInstallingServer(projectId)
-			try {
-				const path = await createVanillaInstance(project, recommendedGameVersion, serverAddress)
-				if (path) {
-					instance = await get(path)
-					if (instance) showModpackInstallSuccess(instance, serverAddress)
-				}
-			} finally {
-				stopInstallingServer(projectId)
+		if (!status.profile_path) {
+			if (content.kind === 'vanilla') {
+				await installAndNotify(projectId, showModpackInstallSuccess)
+				return
 			}
-			return
-		}
-		if (isModpack && !instance) {
-			installToPlayModalRef?.show(projectV3, modpackVersionId, async () => {
-				const newInstance = await findInstalledInstance(project.id)
-				if (!newInstance) return
-				showModpackInstallSuccess(newInstance, serverAddress)
+
+			const projectV3 = await get_project_v3(projectId, 'bypass')
+			installToPlayModalRef?.show(projectV3, content.version_id, async () => {
+				const { profile_path } = await get_server_project_status(projectId)
+				const instance = profile_path ? await get(profile_path) : null
+				if (instance) showModpackInstallSuccess(projectId, instance)
 			})
 			return
 		}
 
+		const instance = await get(status.profile_path)
 		if (!instance) return
 
-		await ensureManagedServerWorldExists(instance.path, project.title, serverAddress)
+		if (status.update_available) {
+			if (content.kind === 'vanilla') {
+				await installAndNotify(projectId, showUpdateSuccess)
+				return
+			}
 
-		// Update existing instance if needed
-		if (isModpack && instance.linked_data?.version_id !== modpackVersionId) {
-			updateToPlayModalRef?.show(instance, modpackVersionId, () => {
-				showUpdateSuccess(instance, serverAddress)
+			updateToPlayModalRef?.show(instance, content.version_id, async () => {
+				const updated = await get(instance.path)
+				showUpdateSuccess(projectId, updated ?? instance)
 			})
 			return
 		}
-		if (isVanilla && instance.game_version !== recommendedGameVersion) {
-			if (installingServerProjects.value.includes(projectId)) return
-			startInstallingServer(projectId)
-			try {
-				await updateVanillaGameVersion(instance, recommendedGameVersion)
-				showUpdateSuccess(instance, serverAddress)
-			} finally {
-				stopInstallingServer(projectId)
-			}
-			return
-		}
 
-		// Join server
-		try {
-			await joinServer(instance.path, serverAddress)
-			trackEvent('InstanceStart', {
-				loader: instance.loader,
-				game_version: instance.game_version,
-				source: 'ServerProject',
-			})
-		} catch (err) {
-			handleSevereError(err, { profilePath: instance.path })
-		}
+		await joinServer(projectId, instance)
 	}
 
 	const context: ServerInstallContext = {
@@ -14,6 +14,7 @@ fn main() {
                     .commands(&[
                         "check_reachable",
                         "login",
+                        "login_yggdrasil",
                         "remove_user",
                         "get_default_user",
                         "set_default_user",
@@ -58,6 +59,8 @@ fn main() {
                         "import_instance",
                         "is_valid_importable_instance",
                         "get_default_launcher_path",
+                        "import_packwiz",
+                        "import_offline_bundle",
                     ])
                     .default_permission(
                         DefaultPermissionRule::AllowAllCommands,
@@ -73,6 +76,9 @@ fn main() {
                         "jre_get_jre",
                         "jre_test_jre",
                         "jre_auto_install_java",
+                        "jre_get_managed_installs",
+                        "jre_check_updates",
+                        "jre_update_java",
                         "jre_get_max_memory",
                     ])
                     .default_permission(
@@ -91,6 +97,8 @@ fn main() {
                         "logs_get_latest_log_cursor",
                         "logs_get_live_log_buffer",
                         "logs_clear_live_log_buffer",
+                        "logs_analyze_crash",
+                        "logs_query",
                     ])
                     .default_permission(
                         DefaultPermissionRule::AllowAllCommands,
@@ -152,6 +160,7 @@ fn main() {
                     .commands(&[
                         "process_get_all",
                         "process_get_by_profile_path",
+                        "process_get_reported_mods",
                         "process_kill",
                         "process_wait_for",
                     ])
@@ -172,6 +181,8 @@ fn main() {
                         "profile_get_dependencies_as_content_items",
                         "profile_get_linked_modpack_info",
                         "profile_get_linked_modpack_content",
+                        "profile_get_content_diagnostics",
+                        "profile_apply_content_fix",
                         "profile_get_optimal_jre_key",
                         "profile_get_full_path",
                         "profile_get_mod_full_path",
@@ -179,6 +190,7 @@ fn main() {
                         "profile_check_installed",
                         "profile_check_installed_batch",
                         "profile_install",
+                        "profile_verify",
                         "profile_update_all",
                         "profile_update_project",
                         "profile_add_project_from_version",
@@ -192,6 +204,9 @@ fn main() {
                         "profile_edit",
                         "profile_edit_icon",
                         "profile_export_mrpack",
+                        "profile_export_packwiz",
+                        "profile_export_curseforge",
+                        "profile_export_offline_bundle",
                         "profile_get_pack_export_candidates",
                     ])
                     .default_permission(
@@ -218,6 +233,20 @@ fn main() {
                         DefaultPermissionRule::AllowAllCommands,
                     ),
             )
+            .plugin(
+                "storage",
+                InlinedPlugin::new()
+                    .commands(&[
+                        "storage_get_disk_usage",
+                        "storage_collect_garbage",
+                        "storage_get_content_store_stats",
+                        "storage_deduplicate_content",
+                        "storage_prune_content_store",
+                    ])
+                    .default_permission(
+                        DefaultPermissionRule::AllowAllCommands,
+                    ),
+            )
             .plugin(
                 "tags",
                 InlinedPlugin::new()
@@ -281,6 +310,12 @@ fn main() {
                         "friend_statuses",
                         "add_friend",
                         "remove_friend",
+                        "friends_host_world",
+                        "friends_join_world",
+                        "friends_close_tunnel",
+                        "friends_tunnels",
+                        "friends_friend_tunnels",
+                        "friends_join_friend_server",
                     ])
                     .default_permission(
                         DefaultPermissionRule::AllowAllCommands,
@@ -303,8 +338,12 @@ fn main() {
                         "remove_server_from_profile",
                         "get_profile_protocol_version",
                         "get_server_status",
+                        "get_profile_server_statuses",
                         "start_join_singleplayer_world",
                         "start_join_server",
+                        "get_server_project_status",
+                        "install_server_project",
+                        "join_server_project",
                     ])
                     .default_permission(
                         DefaultPermissionRule::AllowAllCommands,
@@ -9,6 +9,7 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
         .invoke_handler(tauri::generate_handler![
             check_reachable,
             login,
+            login_yggdrasil,
             remove_user,
             get_default_user,
             set_default_user,
@@ -83,6 +84,19 @@ pub async fn login<R: Runtime>(
     Ok(None)
 }
 
+/// Authenticate a user with a Yggdrasil-compatible authentication server
+#[tauri::command]
+pub async fn login_yggdrasil(
+    server_url: String,
+    username: String,
+    password: String,
+) -> Result<Credentials> {
+    Ok(
+        minecraft_auth::login_yggdrasil(&server_url, &username, &password)
+            .await?,
+    )
+}
+
 #[tauri::command]
 pub async fn remove_user(user: uuid::Uuid) -> Result<()> {
     Ok(minecraft_auth::remove_user(user).await?)
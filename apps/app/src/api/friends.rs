@@ -1,5 +1,8 @@
 use tauri::plugin::TauriPlugin;
-use theseus::prelude::{UserFriend, UserStatus};
+use theseus::prelude::{
+    FriendTunnel, ProcessMetadata, TunnelInfo, UserFriend, UserId, UserStatus,
+};
+use uuid::Uuid;
 
 pub fn init<R: tauri::Runtime>() -> TauriPlugin<R> {
     tauri::plugin::Builder::new("friends")
@@ -7,7 +10,13 @@ pub fn init<R: tauri::Runtime>() -> TauriPlugin<R> {
             friends,
             friend_statuses,
             add_friend,
-            remove_friend
+            remove_friend,
+            friends_host_world,
+            friends_join_world,
+            friends_close_tunnel,
+            friends_tunnels,
+            friends_friend_tunnels,
+            friends_join_friend_server,
         ])
         .build()
 }
@@ -31,3 +40,40 @@ pub async fn add_friend(user_id: &str) -> crate::api::Result<()> {
 pub async fn remove_friend(user_id: &str) -> crate::api::Result<()> {
     Ok(theseus::friends::remove_friend(user_id).await?)
 }
+
+#[tauri::command]
+pub async fn friends_host_world(
+    port: u16,
+    allowed_users: Option<Vec<UserId>>,
+) -> crate::api::Result<TunnelInfo> {
+    Ok(theseus::friends::host_world(port, allowed_users).await?)
+}
+
+#[tauri::command]
+pub async fn friends_join_world(
+    socket: Uuid,
+) -> crate::api::Result<TunnelInfo> {
+    Ok(theseus::friends::join_world(socket).await?)
+}
+
+#[tauri::command]
+pub async fn friends_close_tunnel(id: Uuid) -> crate::api::Result<()> {
+    Ok(theseus::friends::close_tunnel(id).await?)
+}
+
+#[tauri::command]
+pub async fn friends_tunnels() -> crate::api::Result<Vec<TunnelInfo>> {
+    Ok(theseus::friends::tunnels().await?)
+}
+
+#[tauri::command]
+pub async fn friends_friend_tunnels() -> crate::api::Result<Vec<FriendTunnel>> {
+    Ok(theseus::friends::friend_tunnels().await?)
+}
+
+#[tauri::command]
+pub async fn friends_join_friend_server(
+    user: UserId,
+) -> crate::api::Result<ProcessMetadata> {
+    Ok(theseus::friends::join_friend_server(user).await?)
+}
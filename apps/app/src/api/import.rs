@@ -12,6 +12,8 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
             import_instance,
             is_valid_importable_instance,
             get_default_launcher_path,
+            import_packwiz,
+            import_offline_bundle,
         ])
         .build()
 }
@@ -68,3 +70,23 @@ pub async fn get_default_launcher_path(
 ) -> Result<Option<PathBuf>> {
     Ok(import::get_default_launcher_path(launcher_type))
 }
+
+/// Import a packwiz pack from a local folder containing a pack.toml
+/// profile_path should be a blank profile for this purpose- if the function fails, it will be deleted
+#[tauri::command]
+pub async fn import_packwiz(
+    profile_path: &str,
+    pack_folder: PathBuf,
+) -> Result<()> {
+    import::packwiz::import_packwiz(pack_folder, profile_path).await?;
+    Ok(())
+}
+
+/// Import an offline bundle as a new profile, returning the new profile's path
+#[tauri::command]
+pub async fn import_offline_bundle(bundle_path: PathBuf) -> Result<String> {
+    Ok(
+        theseus::pack::offline_bundle::import_offline_bundle(bundle_path)
+            .await?,
+    )
+}
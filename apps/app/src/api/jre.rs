@@ -2,6 +2,8 @@ use crate::api::Result;
 use dashmap::DashMap;
 use std::path::PathBuf;
 use tauri::plugin::TauriPlugin;
+use theseus::jre::providers::JavaProviderKind;
+use theseus::jre::{JavaUpdate, ManagedJavaInstall};
 use theseus::prelude::JavaVersion;
 use theseus::prelude::*;
 
@@ -14,6 +16,9 @@ pub fn init<R: tauri::Runtime>() -> TauriPlugin<R> {
             jre_get_jre,
             jre_test_jre,
             jre_auto_install_java,
+            jre_get_managed_installs,
+            jre_check_updates,
+            jre_update_java,
             jre_get_max_memory,
         ])
         .build()
@@ -51,10 +56,31 @@ pub async fn jre_test_jre(path: PathBuf, major_version: u32) -> Result<bool> {
     Ok(jre::test_jre(path, major_version).await?)
 }
 
-// Auto installs java for the given java version
+// Auto installs java for the given java version, preferring the given provider
 #[tauri::command]
-pub async fn jre_auto_install_java(java_version: u32) -> Result<PathBuf> {
-    Ok(jre::auto_install_java(java_version).await?)
+pub async fn jre_auto_install_java(
+    java_version: u32,
+    provider: Option<JavaProviderKind>,
+) -> Result<PathBuf> {
+    Ok(jre::auto_install_java(java_version, provider).await?)
+}
+
+// Lists the java installs managed by the app
+#[tauri::command]
+pub async fn jre_get_managed_installs() -> Result<Vec<ManagedJavaInstall>> {
+    Ok(jre::get_managed_java_installs().await?)
+}
+
+// Checks the java installs managed by the app for updates
+#[tauri::command]
+pub async fn jre_check_updates() -> Result<Vec<JavaUpdate>> {
+    Ok(jre::check_java_updates().await?)
+}
+
+// Updates the managed java install of the given major version
+#[tauri::command]
+pub async fn jre_update_java(major_version: u32) -> Result<PathBuf> {
+    Ok(jre::update_java(major_version).await?)
 }
 
 // Gets the maximum memory a system has available.
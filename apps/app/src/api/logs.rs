@@ -1,6 +1,8 @@
 use crate::api::Result;
 use theseus::logs::LogType;
+use theseus::logs::query::{LogQuery, LogQueryResult};
 use theseus::logs::{self, CensoredString, LatestLogCursor, Logs};
+use theseus::prelude::CrashAnalysis;
 
 /*
 A log is a struct containing the filename string, stdout, and stderr, as follows:
@@ -23,6 +25,8 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
             logs_get_latest_log_cursor,
             logs_get_live_log_buffer,
             logs_clear_live_log_buffer,
+            logs_analyze_crash,
+            logs_query,
         ])
         .build()
 }
@@ -100,3 +104,18 @@ pub async fn logs_clear_live_log_buffer(profile_path: &str) -> Result<()> {
     logs::clear_live_log_buffer(profile_path);
     Ok(())
 }
+
+/// Analyze the latest crash of a profile, looking for the mods that caused it
+#[tauri::command]
+pub async fn logs_analyze_crash(profile_path: &str) -> Result<CrashAnalysis> {
+    Ok(logs::analyze_crash(profile_path).await?)
+}
+
+/// Search the logs of a profile by level, logger, thread, time range and regex
+#[tauri::command]
+pub async fn logs_query(
+    profile_path: &str,
+    query: LogQuery,
+) -> Result<LogQueryResult> {
+    Ok(logs::query::query_logs(profile_path, query).await?)
+}
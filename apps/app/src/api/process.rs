@@ -7,6 +7,7 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
         .invoke_handler(tauri::generate_handler![
             process_get_all,
             process_get_by_profile_path,
+            process_get_reported_mods,
             process_kill,
             process_wait_for,
         ])
@@ -25,6 +26,13 @@ pub async fn process_get_by_profile_path(
     Ok(process::get_by_profile_path(path).await?)
 }
 
+#[tauri::command]
+pub async fn process_get_reported_mods(
+    path: &str,
+) -> Result<Option<Vec<ReportedMod>>> {
+    Ok(process::get_reported_mods(path).await?)
+}
+
 #[tauri::command]
 pub async fn process_kill(uuid: Uuid) -> Result<()> {
     Ok(process::kill(uuid).await?)
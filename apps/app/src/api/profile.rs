@@ -5,9 +5,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use theseus::DownloadReason;
-use theseus::data::{ContentItem, Dependency, LinkedModpackInfo};
+use theseus::data::{
+    ContentDiagnostic, ContentFix, ContentItem, Dependency, LinkedModpackInfo,
+};
 use theseus::prelude::*;
 use theseus::profile::QuickPlayType;
+use theseus::profile::verify::VerificationReport;
 use theseus::server_address::ServerAddress;
 
 pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
@@ -22,6 +25,8 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
             profile_get_dependencies_as_content_items,
             profile_get_linked_modpack_info,
             profile_get_linked_modpack_content,
+            profile_get_content_diagnostics,
+            profile_apply_content_fix,
             profile_get_optimal_jre_key,
             profile_get_full_path,
             profile_get_mod_full_path,
@@ -29,6 +34,7 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
             profile_check_installed,
             profile_check_installed_batch,
             profile_install,
+            profile_verify,
             profile_update_all,
             profile_update_project,
             profile_add_project_from_version,
@@ -42,6 +48,9 @@ pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
             profile_edit,
             profile_edit_icon,
             profile_export_mrpack,
+            profile_export_packwiz,
+            profile_export_curseforge,
+            profile_export_offline_bundle,
             profile_get_pack_export_candidates,
         ])
         .build()
@@ -114,6 +123,27 @@ pub async fn profile_get_dependencies_as_content_items(
     Ok(res)
 }
 
+/// Check a profile's installed content for problems
+/// See [`profile::get_content_diagnostics`]
+#[tauri::command]
+pub async fn profile_get_content_diagnostics(
+    path: &str,
+    cache_behaviour: Option<CacheBehaviour>,
+) -> Result<Vec<ContentDiagnostic>> {
+    let res = profile::get_content_diagnostics(path, cache_behaviour).await?;
+    Ok(res)
+}
+
+/// Apply a fix suggested by the content diagnostics
+#[tauri::command]
+pub async fn profile_apply_content_fix(
+    path: &str,
+    fix: ContentFix,
+) -> Result<()> {
+    profile::apply_content_fix(path, fix).await?;
+    Ok(())
+}
+
 /// Get linked modpack info for a profile
 ///
 /// Returns project, version, and owner information for the linked modpack,
@@ -227,6 +257,17 @@ pub async fn profile_install(path: &str, force: bool) -> Result<()> {
     Ok(())
 }
 
+/// Checks a profile's files against their hashes, downloading missing or
+/// corrupted files again if `repair` is set
+/// invoke('plugin:profile|profile_verify')
+#[tauri::command]
+pub async fn profile_verify(
+    path: &str,
+    repair: bool,
+) -> Result<VerificationReport> {
+    Ok(profile::verify::verify(path, repair).await?)
+}
+
 /// Updates all of the profile's projects
 /// invoke('plugin:profile|profile_update_all')
 #[tauri::command]
@@ -330,6 +371,66 @@ pub async fn profile_export_mrpack(
     Ok(())
 }
 
+/// See [`profile::export::export_packwiz`]
+#[tauri::command]
+pub async fn profile_export_packwiz(
+    path: &str,
+    export_location: PathBuf,
+    included_overrides: Vec<String>,
+    version: Option<String>,
+    author: Option<String>,
+) -> Result<()> {
+    profile::export::export_packwiz(
+        path,
+        export_location,
+        included_overrides,
+        version,
+        author,
+    )
+    .await?;
+    Ok(())
+}
+
+/// See [`profile::export::export_curseforge_manifest`]
+#[tauri::command]
+pub async fn profile_export_curseforge(
+    path: &str,
+    export_location: PathBuf,
+    included_overrides: Vec<String>,
+    version: Option<String>,
+    author: Option<String>,
+    curseforge_api_key: Option<String>,
+) -> Result<()> {
+    profile::export::export_curseforge_manifest(
+        path,
+        export_location,
+        included_overrides,
+        version,
+        author,
+        curseforge_api_key,
+    )
+    .await?;
+    Ok(())
+}
+
+/// See [`theseus::pack::offline_bundle::export_offline_bundle`]
+#[tauri::command]
+pub async fn profile_export_offline_bundle(
+    path: &str,
+    export_location: PathBuf,
+    included_overrides: Vec<String>,
+    include_java: bool,
+) -> Result<()> {
+    theseus::pack::offline_bundle::export_offline_bundle(
+        path,
+        export_location,
+        included_overrides,
+        include_java,
+    )
+    .await?;
+    Ok(())
+}
+
 /// See [`profile::get_pack_export_candidates`]
 #[tauri::command]
 pub async fn profile_get_pack_export_candidates(
@@ -0,0 +1,48 @@
+use crate::api::Result;
+use theseus::storage::gc::GarbageCollectionReport;
+use theseus::storage::{ContentStoreStats, DiskUsage};
+
+pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri::plugin::Builder::new("storage")
+        .invoke_handler(tauri::generate_handler![
+            storage_get_disk_usage,
+            storage_collect_garbage,
+            storage_get_content_store_stats,
+            storage_deduplicate_content,
+            storage_prune_content_store,
+        ])
+        .build()
+}
+
+/// Gets the disk usage of every profile and of the data shared between them
+#[tauri::command]
+pub async fn storage_get_disk_usage() -> Result<DiskUsage> {
+    Ok(theseus::storage::get_disk_usage().await?)
+}
+
+/// Removes game files no profile uses anymore, or only lists them if
+/// `dry_run` is set
+#[tauri::command]
+pub async fn storage_collect_garbage(
+    dry_run: bool,
+) -> Result<GarbageCollectionReport> {
+    Ok(theseus::storage::gc::collect_garbage(dry_run).await?)
+}
+
+/// Gets statistics about the content store shared between profiles
+#[tauri::command]
+pub async fn storage_get_content_store_stats() -> Result<ContentStoreStats> {
+    Ok(theseus::storage::get_content_store_stats().await?)
+}
+
+/// Moves identical content files of all profiles into the content store
+#[tauri::command]
+pub async fn storage_deduplicate_content() -> Result<ContentStoreStats> {
+    Ok(theseus::storage::deduplicate_content().await?)
+}
+
+/// Removes unreferenced files from the content store
+#[tauri::command]
+pub async fn storage_prune_content_store() -> Result<ContentStoreStats> {
+    Ok(theseus::storage::prune_content_store().await?)
+}
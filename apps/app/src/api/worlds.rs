@@ -1,10 +1,12 @@
 use crate::api::Result;
 use either::Either;
 use enumset::EnumSet;
+use std::collections::HashMap;
 use tauri::{AppHandle, Manager, Runtime};
 use theseus::prelude::ProcessMetadata;
 use theseus::profile::{QuickPlayType, get_full_path};
 use theseus::server_address::ServerAddress;
+use theseus::server_project::{self, ServerProjectStatus};
 use theseus::worlds::{
     DisplayStatus, ProtocolVersion, ServerPackStatus, ServerStatus, World,
     WorldType, WorldWithProfile,
@@ -27,8 +29,12 @@ pub fn init<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
             remove_server_from_profile,
             get_profile_protocol_version,
             get_server_status,
+            get_profile_server_statuses,
             start_join_singleplayer_world,
             start_join_server,
+            get_server_project_status,
+            install_server_project,
+            join_server_project,
         ])
         .build()
 }
@@ -206,6 +212,14 @@ pub async fn get_server_status(
     Ok(worlds::get_server_status(address, protocol_version).await?)
 }
 
+#[tauri::command]
+pub async fn get_profile_server_statuses(
+    path: &str,
+    force_refresh: bool,
+) -> Result<HashMap<String, Option<ServerStatus>>> {
+    Ok(worlds::get_profile_server_statuses(path, force_refresh).await?)
+}
+
 #[tauri::command]
 pub async fn start_join_singleplayer_world(
     path: &str,
@@ -230,3 +244,20 @@ pub async fn start_join_server(
 
     Ok(process)
 }
+
+#[tauri::command]
+pub async fn get_server_project_status(
+    project_id: &str,
+) -> Result<ServerProjectStatus> {
+    Ok(server_project::get_server_project_status(project_id).await?)
+}
+
+#[tauri::command]
+pub async fn install_server_project(project_id: &str) -> Result<String> {
+    Ok(server_project::install_server_project(project_id).await?)
+}
+
+#[tauri::command]
+pub async fn join_server_project(project_id: &str) -> Result<ProcessMetadata> {
+    Ok(server_project::join_server_project(project_id).await?)
+}
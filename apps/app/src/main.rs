@@ -242,6 +242,7 @@ fn main() {
         .plugin(api::profile::init())
         .plugin(api::profile_create::init())
         .plugin(api::settings::init())
+        .plugin(api::storage::init())
         .plugin(api::tags::init())
         .plugin(api::utils::init())
         .plugin(api::cache::init())
@@ -27,6 +27,8 @@ pub enum ErrorKind {
         inner: Box<s3::error::S3Error>,
         file: String,
     },
+    #[error("Error while accessing output file: {file}")]
+    Io { inner: std::io::Error, file: String },
     #[error("Error acquiring semaphore: {0}")]
     Acquire(#[from] tokio::sync::AcquireError),
     #[error("Tracing error: {0}")]
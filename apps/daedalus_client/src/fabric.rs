@@ -1,4 +1,4 @@
-use crate::util::{download_file, fetch_json, format_url};
+use crate::util::{download_file, fetch_json, format_url, read_output_json};
 use crate::{
     Error, FetchResult, MirrorArtifact, UploadFile, insert_mirrored_artifact,
 };
@@ -17,6 +17,7 @@ pub async fn fetch_fabric(
         "fabric",
         "https://meta.fabricmc.net/v2",
         "https://maven.fabricmc.net/",
+        "1.21",
         &[],
         semaphore,
     )
@@ -32,6 +33,7 @@ pub async fn fetch_quilt(
         "quilt",
         "https://meta.quiltmc.org/v3",
         "https://maven.quiltmc.org/repository/release/",
+        "1.21",
         &[
             // This version is broken as it contains invalid library coordinates
             "0.17.5-beta.4",
@@ -41,6 +43,41 @@ pub async fn fetch_quilt(
     .await
 }
 
+#[tracing::instrument(skip(semaphore))]
+pub async fn fetch_legacy_fabric(
+    semaphore: Arc<Semaphore>,
+) -> Result<FetchResult, Error> {
+    fetch(
+        daedalus::modded::CURRENT_LEGACY_FABRIC_FORMAT_VERSION,
+        "legacy-fabric",
+        "https://meta.legacyfabric.net/v2",
+        "https://repo.legacyfabric.net/repository/legacyfabric/",
+        "1.8.9",
+        &[],
+        semaphore,
+    )
+    .await
+}
+
+#[tracing::instrument(skip(semaphore))]
+pub async fn fetch_babric(
+    semaphore: Arc<Semaphore>,
+) -> Result<FetchResult, Error> {
+    fetch(
+        daedalus::modded::CURRENT_BABRIC_FORMAT_VERSION,
+        "babric",
+        "https://meta.babric.glass-launcher.net/v2",
+        "https://maven.glass-launcher.net/babric/",
+        "b1.7.3",
+        &[],
+        semaphore,
+    )
+    .await
+}
+
+/// Fetches a loader with a Fabric-compatible meta server. Loader profiles
+/// are requested for `dummy_game_version`, which must have intermediary
+/// mappings, and are then made game version independent
 #[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip(semaphore))]
 async fn fetch(
@@ -48,13 +85,14 @@ async fn fetch(
     mod_loader: &str,
     meta_url: &str,
     maven_url: &str,
+    dummy_game_version: &str,
     skip_versions: &[&str],
     semaphore: Arc<Semaphore>,
 ) -> Result<FetchResult, Error> {
     let upload_files = DashMap::new();
     let mirror_artifacts = DashMap::<String, MirrorArtifact>::new();
-    let modrinth_manifest = fetch_json::<Manifest>(
-        &format_url(&format!("{mod_loader}/v{format_version}/manifest.json",)),
+    let modrinth_manifest = read_output_json::<Manifest>(
+        &format!("{mod_loader}/v{format_version}/manifest.json",),
         &semaphore,
     )
     .await
@@ -109,8 +147,6 @@ async fn fetch(
             )
         };
 
-    const DUMMY_GAME_VERSION: &str = "1.21";
-
     if !fetch_intermediary_versions.is_empty() {
         for x in &fetch_intermediary_versions {
             insert_mirrored_artifact(
@@ -129,7 +165,7 @@ async fn fetch(
             .map(|x| {
                 format!(
                     "{}/versions/loader/{}/{}/profile/json",
-                    meta_url, DUMMY_GAME_VERSION, x.version
+                    meta_url, dummy_game_version, x.version
                 )
             })
             .collect::<Vec<_>>();
@@ -149,7 +185,7 @@ async fn fetch(
                 for lib in &mut version_info.libraries {
                     let new_name = lib
                         .name
-                        .replace(DUMMY_GAME_VERSION, DUMMY_REPLACE_STRING);
+                        .replace(dummy_game_version, DUMMY_REPLACE_STRING);
 
                     // Hard-code: This library is not present on fabric's maven, so we fetch it from MC libraries
                     if &*lib.name == "net.minecraft:launchwrapper:1.12" {
@@ -180,10 +216,10 @@ async fn fetch(
 
                 version_info.id = version_info
                     .id
-                    .replace(DUMMY_GAME_VERSION, DUMMY_REPLACE_STRING);
+                    .replace(dummy_game_version, DUMMY_REPLACE_STRING);
                 version_info.inherits_from = version_info
                     .inherits_from
-                    .replace(DUMMY_GAME_VERSION, DUMMY_REPLACE_STRING);
+                    .replace(dummy_game_version, DUMMY_REPLACE_STRING);
 
                 Ok(version_info)
             })
@@ -1,4 +1,6 @@
-use crate::util::{download_file, fetch_json, fetch_xml, format_url};
+use crate::util::{
+    download_file, fetch_json, fetch_xml, format_url, read_output_json,
+};
 use crate::{
     Error, FetchResult, MirrorArtifact, UploadFile, insert_mirrored_artifact,
 };
@@ -218,8 +220,8 @@ async fn fetch(
 ) -> Result<FetchResult, Error> {
     let upload_files = DashMap::new();
     let mirror_artifacts = DashMap::<String, MirrorArtifact>::new();
-    let modrinth_manifest = fetch_json::<daedalus::modded::Manifest>(
-        &format_url(&format!("{mod_loader}/v{format_version}/manifest.json",)),
+    let modrinth_manifest = read_output_json::<daedalus::modded::Manifest>(
+        &format!("{mod_loader}/v{format_version}/manifest.json",),
         &semaphore,
     )
     .await
@@ -0,0 +1,234 @@
+use crate::util::{fetch_json, format_url, read_output_json};
+use crate::{
+    Error, FetchResult, MirrorArtifact, UploadFile, insert_mirrored_artifact,
+};
+use chrono::{DateTime, Utc};
+use daedalus::minecraft::{Argument, ArgumentType, Library, VersionType};
+use daedalus::modded::{Manifest, PartialVersionInfo};
+use dashmap::DashMap;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+const MOD_LOADER: &str = "liteloader";
+const VERSIONS_URL: &str = "https://dl.liteloader.com/versions/versions.json";
+const MINECRAFT_LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+
+#[tracing::instrument(skip(semaphore))]
+pub async fn fetch_liteloader(
+    semaphore: Arc<Semaphore>,
+) -> Result<FetchResult, Error> {
+    let format_version = daedalus::modded::CURRENT_LITELOADER_FORMAT_VERSION;
+    let upload_files = DashMap::new();
+    let mirror_artifacts = DashMap::<String, MirrorArtifact>::new();
+    let modrinth_manifest = read_output_json::<Manifest>(
+        &format!("{MOD_LOADER}/v{format_version}/manifest.json"),
+        &semaphore,
+    )
+    .await
+    .ok();
+    let liteloader_manifest =
+        fetch_json::<LiteLoaderVersions>(VERSIONS_URL, &semaphore).await?;
+
+    // Every game version lists its builds along with a `latest` alias, so
+    // builds are deduplicated by their version
+    let loader_versions = liteloader_manifest
+        .versions
+        .into_iter()
+        .map(|(game_version, entry)| {
+            let mut builds = IndexMap::new();
+            for (artefacts, snapshot) in
+                [(entry.artefacts, false), (entry.snapshots, true)]
+            {
+                let Some(artefacts) = artefacts else {
+                    continue;
+                };
+
+                for build in artefacts.liteloader.into_values() {
+                    let libraries = if build.libraries.is_empty() {
+                        artefacts.libraries.clone()
+                    } else {
+                        build.libraries.clone()
+                    };
+
+                    builds
+                        .entry(build.version.clone())
+                        .or_insert((build, libraries, snapshot));
+                }
+            }
+
+            (game_version, entry.repo.url, builds)
+        })
+        .collect::<Vec<_>>();
+
+    let fetch_versions = loader_versions
+        .iter()
+        .flat_map(|(game_version, repo_url, builds)| {
+            builds
+                .values()
+                .map(move |build| (game_version, repo_url, build))
+        })
+        .filter(|(game_version, _, (build, _, _))| {
+            !modrinth_manifest.as_ref().is_some_and(|manifest| {
+                manifest.game_versions.iter().any(|x| {
+                    &x.id == *game_version
+                        && x.loaders.iter().any(|x| x.id == build.version)
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if fetch_versions.is_empty() {
+        return Ok(FetchResult {
+            upload_files,
+            mirror_artifacts,
+        });
+    }
+
+    for (game_version, repo_url, (build, libraries, _)) in fetch_versions {
+        let release_time = build
+            .timestamp
+            .as_deref()
+            .and_then(|x| x.parse::<i64>().ok())
+            .and_then(|x| DateTime::from_timestamp(x, 0))
+            .unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+
+        let liteloader_library = Library {
+            downloads: None,
+            extract: None,
+            name: format!("com.mumfrey:liteloader:{}", build.version),
+            url: Some(repo_url.clone()),
+            natives: None,
+            rules: None,
+            checksums: None,
+            include_in_classpath: true,
+            downloadable: true,
+        };
+
+        let libraries = std::iter::once(liteloader_library)
+            .chain(libraries.iter().cloned())
+            .map(|mut lib| {
+                insert_mirrored_artifact(
+                    &lib.name,
+                    None,
+                    vec![lib.url.clone().unwrap_or_else(|| {
+                        MINECRAFT_LIBRARIES_URL.to_string()
+                    })],
+                    false,
+                    &mirror_artifacts,
+                )?;
+                lib.url = Some(format_url("maven/"));
+
+                Ok(lib)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let version_info = PartialVersionInfo {
+            id: build.version.clone(),
+            inherits_from: game_version.clone(),
+            release_time,
+            time: release_time,
+            main_class: Some("net.minecraft.launchwrapper.Launch".to_string()),
+            minecraft_arguments: None,
+            arguments: Some(HashMap::from([(
+                ArgumentType::Game,
+                vec![
+                    Argument::Normal("--tweakClass".to_string()),
+                    Argument::Normal(build.tweak_class.clone()),
+                ],
+            )])),
+            libraries,
+            type_: VersionType::Release,
+            data: None,
+            processors: None,
+        };
+
+        upload_files.insert(
+            format!(
+                "{MOD_LOADER}/v{format_version}/versions/{}.json",
+                build.version
+            ),
+            UploadFile {
+                file: bytes::Bytes::from(serde_json::to_vec(&version_info)?),
+                content_type: Some("application/json".to_string()),
+            },
+        );
+    }
+
+    let manifest = Manifest {
+        game_versions: loader_versions
+            .into_iter()
+            .map(|(game_version, _, builds)| daedalus::modded::Version {
+                id: game_version,
+                stable: true,
+                loaders: builds
+                    .into_values()
+                    .map(|(build, _, snapshot)| {
+                        daedalus::modded::LoaderVersion {
+                            url: format_url(&format!(
+                                "{MOD_LOADER}/v{format_version}/versions/{}.json",
+                                build.version
+                            )),
+                            stable: !snapshot
+                                && build.stream.as_deref() != Some("SNAPSHOT"),
+                            id: build.version,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    upload_files.insert(
+        format!("{MOD_LOADER}/v{format_version}/manifest.json"),
+        UploadFile {
+            file: bytes::Bytes::from(serde_json::to_vec(&manifest)?),
+            content_type: Some("application/json".to_string()),
+        },
+    );
+
+    Ok(FetchResult {
+        upload_files,
+        mirror_artifacts,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct LiteLoaderVersions {
+    versions: IndexMap<String, LiteLoaderGameVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiteLoaderGameVersion {
+    repo: LiteLoaderRepository,
+    artefacts: Option<LiteLoaderArtefacts>,
+    snapshots: Option<LiteLoaderArtefacts>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiteLoaderRepository {
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiteLoaderArtefacts {
+    #[serde(rename = "com.mumfrey:liteloader", default)]
+    liteloader: IndexMap<String, LiteLoaderBuild>,
+    /// Libraries shared by all builds, used by snapshots
+    #[serde(default)]
+    libraries: Vec<Library>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LiteLoaderBuild {
+    version: String,
+    tweak_class: String,
+    #[serde(default)]
+    libraries: Vec<Library>,
+    stream: Option<String>,
+    /// Seconds since the Unix epoch, as a string
+    timestamp: Option<String>,
+}
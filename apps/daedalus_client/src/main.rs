@@ -1,3 +1,4 @@
+use crate::output::Output;
 use crate::util::{
     REQWEST_CLIENT, format_url, upload_file_to_bucket,
     upload_url_to_bucket_mirrors,
@@ -12,7 +13,11 @@ use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 mod error;
 mod fabric;
 mod forge;
+mod liteloader;
 mod minecraft;
+mod output;
+mod rift;
+mod signing;
 pub mod util;
 
 pub use error::{Error, ErrorKind, Result};
@@ -36,6 +41,11 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // With `--dry-run`, the files that would be written are printed instead
+    let dry_run = std::env::args().skip(1).any(|x| x == "--dry-run");
+    Output::from_env(dry_run)?.init();
+    signing::init()?;
+
     let semaphore = Arc::new(Semaphore::new(
         dotenvy::var("CONCURRENCY_LIMIT")
             .ok()
@@ -70,11 +80,57 @@ async fn main() -> Result<()> {
         Err(err) => tracing::warn!(error = %err, "Forge fetch failed"),
     }
 
+    match fabric::fetch_legacy_fabric(semaphore.clone()).await {
+        Ok(fetched) => merge_fetch_result(&mut fetch_result, fetched),
+        Err(err) => tracing::warn!(error = %err, "Legacy Fabric fetch failed"),
+    }
+
+    match fabric::fetch_babric(semaphore.clone()).await {
+        Ok(fetched) => merge_fetch_result(&mut fetch_result, fetched),
+        Err(err) => tracing::warn!(error = %err, "Babric fetch failed"),
+    }
+
+    match liteloader::fetch_liteloader(semaphore.clone()).await {
+        Ok(fetched) => merge_fetch_result(&mut fetch_result, fetched),
+        Err(err) => tracing::warn!(error = %err, "LiteLoader fetch failed"),
+    }
+
+    match rift::fetch_rift(semaphore.clone()).await {
+        Ok(fetched) => merge_fetch_result(&mut fetch_result, fetched),
+        Err(err) => tracing::warn!(error = %err, "Rift fetch failed"),
+    }
+
     let FetchResult {
         upload_files,
         mirror_artifacts,
     } = fetch_result;
 
+    // Launchers verify metadata with detached signatures next to each file
+    if let Some(signer) = signing::signer() {
+        let signatures = upload_files
+            .iter()
+            .filter(|x| {
+                x.value().content_type.as_deref() == Some("application/json")
+            })
+            .map(|x| {
+                let signature = signer.sign(x.key(), &x.value().file);
+                Ok((
+                    signing::signature_path(x.key()),
+                    UploadFile {
+                        file: bytes::Bytes::from(serde_json::to_vec(
+                            &signature,
+                        )?),
+                        content_type: Some("application/json".to_string()),
+                    },
+                ))
+            })
+            .collect::<serde_json::Result<Vec<_>>>()?;
+
+        for (path, file) in signatures {
+            upload_files.insert(path, file);
+        }
+    }
+
     futures::future::try_join_all(upload_files.iter().map(|entry| {
         upload_file_to_bucket(
             entry.key().clone(),
@@ -106,10 +162,13 @@ async fn main() -> Result<()> {
     }))
     .await?;
 
-    if dotenvy::var("CLOUDFLARE_INTEGRATION")
-        .ok()
-        .and_then(|x| x.parse::<bool>().ok())
-        .unwrap_or(false)
+    let output = Output::get();
+    if !output.dry_run()
+        && !output.is_local()
+        && dotenvy::var("CLOUDFLARE_INTEGRATION")
+            .ok()
+            .and_then(|x| x.parse::<bool>().ok())
+            .unwrap_or(false)
         && let Ok(token) = dotenvy::var("CLOUDFLARE_TOKEN")
         && let Ok(zone_id) = dotenvy::var("CLOUDFLARE_ZONE_ID")
     {
@@ -248,11 +307,15 @@ fn check_env_vars() -> bool {
 
     failed |= check_var::<String>("BASE_URL");
 
-    failed |= check_var::<String>("S3_ACCESS_TOKEN");
-    failed |= check_var::<String>("S3_SECRET");
-    failed |= check_var::<String>("S3_URL");
-    failed |= check_var::<String>("S3_REGION");
-    failed |= check_var::<String>("S3_BUCKET_NAME");
+    if dotenvy::var("OUTPUT_TARGET").is_ok_and(|x| x == "local") {
+        failed |= check_var::<String>("OUTPUT_DIRECTORY");
+    } else {
+        failed |= check_var::<String>("S3_ACCESS_TOKEN");
+        failed |= check_var::<String>("S3_SECRET");
+        failed |= check_var::<String>("S3_URL");
+        failed |= check_var::<String>("S3_REGION");
+        failed |= check_var::<String>("S3_BUCKET_NAME");
+    }
 
     if dotenvy::var("CLOUDFLARE_INTEGRATION")
         .ok()
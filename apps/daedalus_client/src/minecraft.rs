@@ -1,34 +1,42 @@
-use crate::util::fetch_json;
+use crate::util::{fetch_json, read_output_json};
 use crate::{
-    Error, FetchResult, UploadFile, util::download_file, util::format_url,
-    util::sha1_async,
+    Error, ErrorKind, FetchResult, UploadFile, util::download_file,
+    util::format_url, util::sha1_async,
 };
+use chrono::{DateTime, Utc};
 use daedalus::minecraft::{
-    Library, PartialLibrary, VERSION_MANIFEST_URL, VersionInfo,
-    VersionManifest, merge_partial_library,
+    Library, PartialLibrary, VERSION_MANIFEST_URL, Version, VersionInfo,
+    VersionManifest, VersionType, merge_partial_library,
 };
 use dashmap::DashMap;
+use futures::io::Cursor;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
+/// The manifest format launchers from before the experiment and April Fools
+/// version types read. It is still published, without versions they can't
+/// parse
+const LEGACY_FORMAT_VERSION: usize = 0;
+
 #[tracing::instrument(skip(semaphore))]
 pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
     let upload_files = DashMap::new();
-    let modrinth_manifest = fetch_json::<VersionManifest>(
-        &format_url(&format!(
+    let modrinth_manifest = read_output_json::<VersionManifest>(
+        &format!(
             "minecraft/v{}/manifest.json",
             daedalus::minecraft::CURRENT_FORMAT_VERSION
-        )),
+        ),
         &semaphore,
     )
     .await
     .ok();
     let mojang_manifest =
         fetch_json::<VersionManifest>(VERSION_MANIFEST_URL, &semaphore).await?;
+    let extra_versions = fetch_extra_versions()?;
 
-    // TODO: experimental snapshots: https://github.com/PrismLauncher/meta/blob/main/meta/common/mojang-minecraft-experiments.json
-    // TODO: old snapshots: https://github.com/PrismLauncher/meta/blob/main/meta/common/mojang-minecraft-old-snapshots.json
+    let sources = version_sources(&mojang_manifest, extra_versions);
 
     // We check Modrinth's version manifest and compare if the version 1) exists in Modrinth's database and 2) is unchanged
     // If they are not, we will fetch them
@@ -37,11 +45,11 @@ pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
             let (mut fetch_versions, mut existing_versions) =
                 (Vec::new(), Vec::new());
 
-            for version in mojang_manifest.versions {
+            for source in sources {
                 if let Some(index) = modrinth_manifest
                     .versions
                     .iter()
-                    .position(|x| x.id == version.id)
+                    .position(|x| x.id == source.id)
                 {
                     let modrinth_version =
                         modrinth_manifest.versions.remove(index);
@@ -49,37 +57,34 @@ pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
                     if modrinth_version
                         .original_sha1
                         .as_ref()
-                        .is_some_and(|x| x == &version.sha1)
+                        .is_some_and(|x| x == &source.original_sha1)
+                        && modrinth_version.type_ == source.type_
                     {
                         existing_versions.push(modrinth_version);
                     } else {
-                        fetch_versions.push(version);
+                        fetch_versions.push(source);
                     }
                 } else {
-                    fetch_versions.push(version);
+                    fetch_versions.push(source);
                 }
             }
 
             (fetch_versions, existing_versions)
         } else {
-            (mojang_manifest.versions, Vec::new())
+            (sources, Vec::new())
         };
 
     if !fetch_versions.is_empty() {
         let version_manifests = futures::future::try_join_all(
-            fetch_versions
-                .iter()
-                .map(|x| download_file(&x.url, Some(&x.sha1), &semaphore)),
+            fetch_versions.iter().map(|x| x.fetch(&semaphore)),
         )
-        .await?
-        .into_iter()
-        .map(|x| serde_json::from_slice(&x))
-        .collect::<Result<Vec<VersionInfo>, serde_json::Error>>()?;
+        .await?;
 
         // Patch libraries of Minecraft versions for M-series Mac Support, Better Linux Compatibility, etc
         let library_patches = fetch_library_patches()?;
         let patched_version_manifests = version_manifests
             .into_iter()
+            .flatten()
             .map(|mut x| {
                 if !library_patches.is_empty() {
                     let mut new_libraries = Vec::new();
@@ -111,7 +116,9 @@ pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
             .into_iter()
             .zip(serialized_version_manifests.into_iter())
             .zip(hashes_version_manifests.into_iter())
-            .map(|((version, bytes), hash)| {
+            .filter_map(|((version, bytes), hash)| {
+                let source =
+                    fetch_versions.iter().find(|x| x.id == version.id)?;
                 let version_path = format!(
                     "minecraft/v{}/versions/{}.json",
                     daedalus::minecraft::CURRENT_FORMAT_VERSION,
@@ -127,25 +134,32 @@ pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
                     },
                 );
 
-                daedalus::minecraft::Version {
-                    original_sha1: fetch_versions
-                        .iter()
-                        .find(|x| x.id == version.id)
-                        .map(|x| x.sha1.clone()),
+                Some(daedalus::minecraft::Version {
+                    original_sha1: Some(source.original_sha1.clone()),
                     id: version.id,
-                    type_: version.type_,
+                    type_: source.type_.clone(),
                     url,
                     time: version.time,
                     release_time: version.release_time,
                     sha1: hash,
                     compliance_level: 1,
-                }
+                })
             })
             .chain(existing_versions.into_iter())
             .collect::<Vec<_>>();
 
         new_versions.sort_by_key(|b| std::cmp::Reverse(b.release_time));
 
+        let legacy_manifest = legacy_manifest(&mojang_manifest, &new_versions);
+
+        upload_files.insert(
+            format!("minecraft/v{LEGACY_FORMAT_VERSION}/manifest.json"),
+            UploadFile {
+                file: bytes::Bytes::from(serde_json::to_vec(&legacy_manifest)?),
+                content_type: Some("application/json".to_string()),
+            },
+        );
+
         // create and upload the new manifest
         let version_manifest_path = format!(
             "minecraft/v{}/manifest.json",
@@ -172,6 +186,219 @@ pub async fn fetch(semaphore: Arc<Semaphore>) -> Result<FetchResult, Error> {
     })
 }
 
+/// Lists the versions in Mojang's manifest followed by the extra versions it
+/// doesn't list. Mojang's manifest lists April Fools versions as snapshots,
+/// so their types are corrected from the extra versions file.
+///
+/// Extra versions are limited to the experiments Mojang still hosts. Alpha,
+/// beta and April Fools builds that Mojang never published, such as 2.0,
+/// have no official source to verify them against and are not provided.
+// TODO: old snapshots and unpublished builds: https://github.com/PrismLauncher/meta/blob/main/meta/common/mojang-minecraft-old-snapshots.json
+fn version_sources(
+    mojang_manifest: &VersionManifest,
+    extra_versions: ExtraVersions,
+) -> Vec<VersionSource> {
+    mojang_manifest
+        .versions
+        .iter()
+        .map(|version| VersionSource {
+            id: version.id.clone(),
+            type_: extra_versions
+                .types
+                .get(&version.id)
+                .cloned()
+                .unwrap_or_else(|| version.type_.clone()),
+            original_sha1: version.sha1.clone(),
+            location: VersionLocation::Manifest {
+                url: version.url.clone(),
+            },
+        })
+        .chain(
+            extra_versions
+                .versions
+                .into_iter()
+                .filter(|x| {
+                    !mojang_manifest.versions.iter().any(|v| v.id == x.id)
+                })
+                .map(ExtraVersion::into_source),
+        )
+        .collect()
+}
+
+/// Builds the manifest for launchers reading the legacy format, which fail on
+/// version types they don't know. Corrected April Fools versions are listed
+/// with Mojang's type there, and versions Mojang never listed are left out
+fn legacy_manifest(
+    mojang_manifest: &VersionManifest,
+    versions: &[Version],
+) -> VersionManifest {
+    VersionManifest {
+        latest: mojang_manifest.latest.clone(),
+        versions: versions
+            .iter()
+            .filter_map(|version| {
+                if !matches!(
+                    version.type_,
+                    VersionType::Experiment | VersionType::AprilFools
+                ) {
+                    return Some(version.clone());
+                }
+
+                let mojang_version = mojang_manifest
+                    .versions
+                    .iter()
+                    .find(|x| x.id == version.id)?;
+                let mut version = version.clone();
+                version.type_ = mojang_version.type_.clone();
+                Some(version)
+            })
+            .collect(),
+    }
+}
+
+/// A version to publish, and where its version JSON comes from
+struct VersionSource {
+    id: String,
+    type_: VersionType,
+    /// The SHA1 hash of the file the version is read from, used to tell
+    /// whether the version changed since the last run
+    original_sha1: String,
+    location: VersionLocation,
+}
+
+#[derive(Debug, PartialEq)]
+enum VersionLocation {
+    /// A version JSON listed in Mojang's manifest
+    Manifest { url: String },
+    /// A zip archive containing the version JSON, which is how experimental
+    /// snapshots and builds missing from Mojang's manifest are distributed
+    Archive {
+        url: String,
+        release_time: Option<DateTime<Utc>>,
+    },
+}
+
+impl VersionSource {
+    /// Fetches the version JSON. Versions from the extra versions file are
+    /// skipped with a warning if they can't be fetched, so one broken link
+    /// doesn't hold up the rest of the metadata
+    async fn fetch(
+        &self,
+        semaphore: &Arc<Semaphore>,
+    ) -> Result<Option<VersionInfo>, Error> {
+        let result = match &self.location {
+            VersionLocation::Manifest { url } => {
+                let bytes =
+                    download_file(url, Some(&self.original_sha1), semaphore)
+                        .await?;
+                return Ok(Some(serde_json::from_slice(&bytes)?));
+            }
+            VersionLocation::Archive { url, release_time } => {
+                self.fetch_archive(url, *release_time, semaphore).await
+            }
+        };
+
+        match result {
+            Ok(version) => Ok(Some(version)),
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    "Failed to fetch extra version {}",
+                    self.id
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    async fn fetch_archive(
+        &self,
+        url: &str,
+        release_time: Option<DateTime<Utc>>,
+        semaphore: &Arc<Semaphore>,
+    ) -> Result<VersionInfo, Error> {
+        type ZipFileReader =
+            async_zip::base::read::seek::ZipFileReader<Cursor<bytes::Bytes>>;
+
+        let bytes =
+            download_file(url, Some(&self.original_sha1), semaphore).await?;
+        let mut zip = ZipFileReader::new(Cursor::new(bytes)).await?;
+
+        let index = zip
+            .file()
+            .entries()
+            .iter()
+            .position(|x| {
+                x.filename().as_str().is_ok_and(|x| x.ends_with(".json"))
+            })
+            .ok_or_else(|| {
+                ErrorKind::InvalidInput(format!(
+                    "No version JSON in archive for {}",
+                    self.id
+                ))
+            })?;
+        let mut buffer = Vec::new();
+        zip.reader_with_entry(index)
+            .await?
+            .read_to_end_checked(&mut buffer)
+            .await?;
+
+        // Experiments use types launchers don't know, such as `pending`
+        let mut value: serde_json::Value = serde_json::from_slice(&buffer)?;
+        value["type"] = serde_json::to_value(&self.type_)?;
+        let mut version: VersionInfo = serde_json::from_value(value)?;
+
+        version.id.clone_from(&self.id);
+        if let Some(release_time) = release_time {
+            version.release_time = release_time;
+        }
+
+        Ok(version)
+    }
+}
+
+/// Versions missing from Mojang's manifest, and corrections to the types of
+/// versions in it
+#[derive(Deserialize, Debug, Default)]
+struct ExtraVersions {
+    #[serde(default)]
+    types: HashMap<String, VersionType>,
+    #[serde(default)]
+    versions: Vec<ExtraVersion>,
+}
+
+/// A version distributed as a zip archive containing its version JSON
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ExtraVersion {
+    id: String,
+    #[serde(rename = "type")]
+    type_: VersionType,
+    /// Overrides the release time in the version JSON
+    release_time: Option<DateTime<Utc>>,
+    url: String,
+    sha1: String,
+}
+
+impl ExtraVersion {
+    fn into_source(self) -> VersionSource {
+        VersionSource {
+            id: self.id,
+            type_: self.type_,
+            original_sha1: self.sha1,
+            location: VersionLocation::Archive {
+                url: self.url,
+                release_time: self.release_time,
+            },
+        }
+    }
+}
+
+fn fetch_extra_versions() -> Result<ExtraVersions, Error> {
+    let versions = include_bytes!("../extra-versions.json");
+    Ok(serde_json::from_slice(versions)?)
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct LibraryPatch {
@@ -229,3 +456,140 @@ pub fn patch_library(
 
     val
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use daedalus::minecraft::LatestVersion;
+    use std::collections::HashSet;
+
+    fn version(id: &str, type_: VersionType) -> Version {
+        Version {
+            id: id.to_string(),
+            type_,
+            url: format!("https://example.com/{id}.json"),
+            time: Utc::now(),
+            release_time: Utc::now(),
+            sha1: format!("{id}-sha1"),
+            compliance_level: 1,
+            original_sha1: None,
+        }
+    }
+
+    fn mojang_manifest() -> VersionManifest {
+        VersionManifest {
+            latest: LatestVersion {
+                release: "1.21".to_string(),
+                snapshot: "24w14potato".to_string(),
+            },
+            versions: vec![
+                version("24w14potato", VersionType::Snapshot),
+                version("1.21", VersionType::Release),
+            ],
+        }
+    }
+
+    fn extra_version(id: &str) -> ExtraVersion {
+        ExtraVersion {
+            id: id.to_string(),
+            type_: VersionType::Experiment,
+            release_time: None,
+            url: format!("https://example.com/{id}.zip"),
+            sha1: format!("{id}-archive"),
+        }
+    }
+
+    #[test]
+    fn extra_versions_file_is_valid() {
+        let extra_versions = fetch_extra_versions().unwrap();
+
+        let mut ids = HashSet::new();
+        for version in &extra_versions.versions {
+            assert!(ids.insert(&version.id), "duplicate {}", version.id);
+            assert_eq!(version.sha1.len(), 40, "{}", version.id);
+        }
+        assert!(
+            extra_versions
+                .types
+                .values()
+                .all(|x| *x == VersionType::AprilFools)
+        );
+    }
+
+    #[test]
+    fn merges_extra_versions_into_mojang_versions() {
+        let extra_versions = ExtraVersions {
+            types: HashMap::from([(
+                "24w14potato".to_string(),
+                VersionType::AprilFools,
+            )]),
+            versions: vec![
+                extra_version("1.14_combat-0"),
+                // Versions Mojang lists are read from Mojang's manifest
+                extra_version("1.21"),
+            ],
+        };
+
+        let sources = version_sources(&mojang_manifest(), extra_versions);
+        let sources = sources
+            .iter()
+            .map(|x| (&*x.id, &x.type_, &*x.original_sha1, &x.location))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            sources,
+            [
+                (
+                    "24w14potato",
+                    &VersionType::AprilFools,
+                    "24w14potato-sha1",
+                    &VersionLocation::Manifest {
+                        url: "https://example.com/24w14potato.json".to_string()
+                    }
+                ),
+                (
+                    "1.21",
+                    &VersionType::Release,
+                    "1.21-sha1",
+                    &VersionLocation::Manifest {
+                        url: "https://example.com/1.21.json".to_string()
+                    }
+                ),
+                (
+                    "1.14_combat-0",
+                    &VersionType::Experiment,
+                    "1.14_combat-0-archive",
+                    &VersionLocation::Archive {
+                        url: "https://example.com/1.14_combat-0.zip"
+                            .to_string(),
+                        release_time: None
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_manifest_only_has_known_types() {
+        let versions = [
+            version("24w14potato", VersionType::AprilFools),
+            version("1.21", VersionType::Release),
+            version("1.14_combat-0", VersionType::Experiment),
+        ];
+
+        let manifest = legacy_manifest(&mojang_manifest(), &versions);
+        let versions = manifest
+            .versions
+            .iter()
+            .map(|x| (&*x.id, &x.type_))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            versions,
+            [
+                ("24w14potato", &VersionType::Snapshot),
+                ("1.21", &VersionType::Release),
+            ]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::util::{download_file, format_url};
+use crate::{Error, ErrorKind};
+use bytes::Bytes;
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Semaphore;
+
+static OUTPUT: OnceLock<Output> = OnceLock::new();
+
+/// Where generated metadata is written to
+pub enum OutputTarget {
+    /// An S3-compatible bucket. Previous output is read back through
+    /// `BASE_URL`
+    S3(Box<Bucket>),
+    /// A directory on the local filesystem, for example to be served inside
+    /// a network without internet access
+    Local(PathBuf),
+}
+
+pub struct Output {
+    target: OutputTarget,
+    /// Whether to only print what would be written
+    dry_run: bool,
+}
+
+impl Output {
+    /// Creates the output configured by `OUTPUT_TARGET`, which is either
+    /// `s3` (the default) or `local`
+    pub fn from_env(dry_run: bool) -> Result<Self, Error> {
+        let target = match dotenvy::var("OUTPUT_TARGET").as_deref() {
+            Ok("s3") | Err(_) => OutputTarget::S3(Box::new(s3_from_env()?)),
+            Ok("local") => OutputTarget::Local(
+                dotenvy::var("OUTPUT_DIRECTORY")
+                    .map_err(|_| {
+                        ErrorKind::InvalidInput(
+                            "OUTPUT_DIRECTORY must be set for local output"
+                                .to_string(),
+                        )
+                    })?
+                    .into(),
+            ),
+            Ok(other) => {
+                return Err(ErrorKind::InvalidInput(format!(
+                    "Unknown output target: {other}"
+                ))
+                .into());
+            }
+        };
+
+        Ok(Self { target, dry_run })
+    }
+
+    pub fn init(self) {
+        if OUTPUT.set(self).is_err() {
+            tracing::warn!("Output was already initialized");
+        }
+    }
+
+    pub fn get() -> &'static Output {
+        OUTPUT.get().expect("Output must be initialized first")
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self.target, OutputTarget::Local(_))
+    }
+
+    /// Reads a file written by a previous run
+    #[tracing::instrument(skip(self, semaphore))]
+    pub async fn read(
+        &self,
+        path: &str,
+        semaphore: &Arc<Semaphore>,
+    ) -> Result<Bytes, Error> {
+        match &self.target {
+            OutputTarget::S3(_) => {
+                download_file(&format_url(path), None, semaphore).await
+            }
+            OutputTarget::Local(directory) => {
+                let file = local_path(directory, path)?;
+                let _permit = semaphore.acquire().await?;
+                Ok(tokio::fs::read(&file)
+                    .await
+                    .map_err(|inner| ErrorKind::Io {
+                        inner,
+                        file: file.display().to_string(),
+                    })?
+                    .into())
+            }
+        }
+    }
+
+    /// Whether a file already exists. Only local output can tell, for S3
+    /// this is always `false`
+    pub async fn exists(&self, path: &str) -> Result<bool, Error> {
+        match &self.target {
+            OutputTarget::S3(_) => Ok(false),
+            OutputTarget::Local(directory) => {
+                Ok(tokio::fs::try_exists(local_path(directory, path)?)
+                    .await
+                    .unwrap_or(false))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self, bytes, semaphore))]
+    pub async fn write(
+        &self,
+        path: String,
+        bytes: Bytes,
+        content_type: Option<String>,
+        semaphore: &Arc<Semaphore>,
+    ) -> Result<(), Error> {
+        let _permit = semaphore.acquire().await?;
+
+        match &self.target {
+            OutputTarget::S3(bucket) => {
+                if self.dry_run {
+                    println!("upload {path} ({} bytes)", bytes.len());
+                    return Ok(());
+                }
+
+                write_s3(bucket, path, bytes, content_type).await
+            }
+            OutputTarget::Local(directory) => {
+                let file = local_path(directory, &path)?;
+                let existing = tokio::fs::read(&file).await.ok();
+                if existing.as_deref() == Some(&*bytes) {
+                    tracing::trace!("Skipping unchanged file");
+                    return Ok(());
+                }
+
+                if self.dry_run {
+                    let action = if existing.is_some() {
+                        "update"
+                    } else {
+                        "create"
+                    };
+                    println!("{action} {path} ({} bytes)", bytes.len());
+                    return Ok(());
+                }
+
+                write_local(&file, &bytes).await.map_err(|inner| {
+                    ErrorKind::Io {
+                        inner,
+                        file: file.display().to_string(),
+                    }
+                    .into()
+                })
+            }
+        }
+    }
+}
+
+fn s3_from_env() -> Result<Bucket, Error> {
+    let var = |name: &str| {
+        dotenvy::var(name).map_err(|_| {
+            ErrorKind::InvalidInput(format!("{name} must be set for S3 output"))
+        })
+    };
+    let s3_error = |err: s3::error::S3Error| ErrorKind::S3 {
+        inner: Box::new(err),
+        file: "bucket".to_string(),
+    };
+
+    let region = var("S3_REGION")?;
+    let bucket = Bucket::new(
+        &var("S3_BUCKET_NAME")?,
+        if &*region == "r2" {
+            Region::R2 {
+                account_id: var("S3_URL")?,
+            }
+        } else {
+            Region::Custom {
+                region: region.clone(),
+                endpoint: var("S3_URL")?,
+            }
+        },
+        Credentials::new(
+            Some(&*var("S3_ACCESS_TOKEN")?),
+            Some(&*var("S3_SECRET")?),
+            None,
+            None,
+            None,
+        )
+        .map_err(|err| {
+            ErrorKind::InvalidInput(format!("Invalid S3 credentials: {err}"))
+        })?,
+    )
+    .map_err(s3_error)?;
+
+    Ok(if region == "path-style" {
+        *bucket.with_path_style()
+    } else {
+        *bucket
+    })
+}
+
+async fn write_s3(
+    bucket: &Bucket,
+    path: String,
+    bytes: Bytes,
+    content_type: Option<String>,
+) -> Result<(), Error> {
+    const RETRIES: i32 = 3;
+    for attempt in 1..=(RETRIES + 1) {
+        tracing::trace!("Attempting file upload, attempt {attempt}");
+        let result = if let Some(ref content_type) = content_type {
+            bucket
+                .put_object_with_content_type(&path, &bytes, content_type)
+                .await
+        } else {
+            bucket.put_object(&path, &bytes).await
+        }
+        .map_err(|err| ErrorKind::S3 {
+            inner: Box::new(err),
+            file: path.clone(),
+        });
+
+        match result {
+            Ok(_) => return Ok(()),
+            Err(_) if attempt <= RETRIES => continue,
+            Err(_) => {
+                result?;
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Resolves a path inside the output directory, rejecting paths that would
+/// escape it
+fn local_path(directory: &Path, path: &str) -> Result<PathBuf, Error> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|x| matches!(x, Component::Normal(_)))
+    {
+        return Err(ErrorKind::InvalidInput(format!(
+            "Invalid output path: {path}"
+        ))
+        .into());
+    }
+
+    Ok(directory.join(relative))
+}
+
+/// Writes a file by writing a temporary file next to it and renaming it, so
+/// clients never see a partially written file
+async fn write_local(file: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(parent) = file.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut temp_name = file.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = file.with_file_name(temp_name);
+
+    if let Err(err) = tokio::fs::write(&temp, bytes).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(err);
+    }
+    if let Err(err) = tokio::fs::rename(&temp, file).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(err);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use crate::util::{download_file, fetch_xml, format_url, read_output_json};
+use crate::{
+    Error, ErrorKind, FetchResult, MirrorArtifact, UploadFile,
+    insert_mirrored_artifact,
+};
+use daedalus::modded::{Manifest, PartialVersionInfo};
+use dashmap::DashMap;
+use futures::io::Cursor;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+const MOD_LOADER: &str = "rift";
+const MAVEN_URL: &str = "https://www.dimdev.org/maven/";
+const MINECRAFT_LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+
+#[tracing::instrument(skip(semaphore))]
+pub async fn fetch_rift(
+    semaphore: Arc<Semaphore>,
+) -> Result<FetchResult, Error> {
+    #[derive(Debug, Deserialize)]
+    struct Metadata {
+        versioning: Versioning,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Versioning {
+        versions: Versions,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Versions {
+        version: Vec<String>,
+    }
+
+    let format_version = daedalus::modded::CURRENT_RIFT_FORMAT_VERSION;
+    let upload_files = DashMap::new();
+    let mirror_artifacts = DashMap::<String, MirrorArtifact>::new();
+    let modrinth_manifest = read_output_json::<Manifest>(
+        &format!("{MOD_LOADER}/v{format_version}/manifest.json"),
+        &semaphore,
+    )
+    .await
+    .ok();
+    let rift_versions = fetch_xml::<Metadata>(
+        &format!("{MAVEN_URL}org/dimdev/rift/maven-metadata.xml"),
+        &semaphore,
+    )
+    .await?
+    .versioning
+    .versions
+    .version;
+
+    // Rift doesn't publish which game version a build is for, so the game
+    // versions of builds that were already read are taken from the manifest
+    let mut known_versions = modrinth_manifest
+        .map(|manifest| {
+            manifest
+                .game_versions
+                .into_iter()
+                .flat_map(|game_version| {
+                    game_version
+                        .loaders
+                        .into_iter()
+                        .map(move |x| (game_version.id.clone(), x))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let fetch_versions = rift_versions
+        .iter()
+        .filter(|version| {
+            !known_versions.iter().any(|(_, x)| &x.id == *version)
+        })
+        .collect::<Vec<_>>();
+
+    if fetch_versions.is_empty() {
+        return Ok(FetchResult {
+            upload_files,
+            mirror_artifacts,
+        });
+    }
+
+    let version_infos =
+        futures::future::join_all(fetch_versions.iter().map(|version| {
+            read_rift_profile(version, &mirror_artifacts, &semaphore)
+        }))
+        .await;
+
+    for (version, version_info) in fetch_versions.into_iter().zip(version_infos)
+    {
+        // Builds that can't be read are retried on the next run
+        let version_info = match version_info {
+            Ok(version_info) => version_info,
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    "Failed to read Rift {version}"
+                );
+                continue;
+            }
+        };
+
+        let version_path =
+            format!("{MOD_LOADER}/v{format_version}/versions/{version}.json");
+
+        known_versions.push((
+            version_info.inherits_from.clone(),
+            daedalus::modded::LoaderVersion {
+                id: version.clone(),
+                url: format_url(&version_path),
+                stable: true,
+            },
+        ));
+        upload_files.insert(
+            version_path,
+            UploadFile {
+                file: bytes::Bytes::from(serde_json::to_vec(&version_info)?),
+                content_type: Some("application/json".to_string()),
+            },
+        );
+    }
+
+    let manifest = Manifest {
+        game_versions: known_versions
+            .into_iter()
+            .filter(|(_, x)| rift_versions.contains(&x.id))
+            .sorted_by(|a, b| b.0.cmp(&a.0))
+            .chunk_by(|x| x.0.clone())
+            .into_iter()
+            .map(|(game_version, loaders)| daedalus::modded::Version {
+                id: game_version,
+                stable: true,
+                loaders: loaders.map(|x| x.1).collect(),
+            })
+            .collect(),
+    };
+
+    upload_files.insert(
+        format!("{MOD_LOADER}/v{format_version}/manifest.json"),
+        UploadFile {
+            file: bytes::Bytes::from(serde_json::to_vec(&manifest)?),
+            content_type: Some("application/json".to_string()),
+        },
+    );
+
+    Ok(FetchResult {
+        upload_files,
+        mirror_artifacts,
+    })
+}
+
+/// Reads the launcher profile Rift's installer ships inside the loader jar
+#[tracing::instrument(skip(mirror_artifacts, semaphore))]
+async fn read_rift_profile(
+    version: &str,
+    mirror_artifacts: &DashMap<String, MirrorArtifact>,
+    semaphore: &Arc<Semaphore>,
+) -> Result<PartialVersionInfo, Error> {
+    type ZipFileReader =
+        async_zip::base::read::seek::ZipFileReader<Cursor<bytes::Bytes>>;
+
+    let jar = download_file(
+        &format!("{MAVEN_URL}org/dimdev/rift/{version}/rift-{version}.jar"),
+        None,
+        semaphore,
+    )
+    .await?;
+    let mut zip = ZipFileReader::new(Cursor::new(jar)).await?;
+
+    let index = zip
+        .file()
+        .entries()
+        .iter()
+        .position(|x| {
+            x.filename().as_str().unwrap_or_default() == "profile.json"
+        })
+        .ok_or_else(|| {
+            ErrorKind::InvalidInput(format!(
+                "No profile.json in Rift {version}"
+            ))
+        })?;
+    let mut buffer = Vec::new();
+    zip.reader_with_entry(index)
+        .await?
+        .read_to_end_checked(&mut buffer)
+        .await?;
+
+    // The profile is a template filled in by the installer
+    let profile =
+        String::from_utf8_lossy(&buffer).replace("@VERSION@", version);
+    let mut version_info: PartialVersionInfo = serde_json::from_str(&profile)?;
+
+    version_info.id = version.to_string();
+    let rift_library = format!("org.dimdev:rift:{version}");
+    if !version_info
+        .libraries
+        .iter()
+        .any(|x| x.name == rift_library)
+    {
+        version_info.libraries.push(daedalus::minecraft::Library {
+            downloads: None,
+            extract: None,
+            name: rift_library,
+            url: Some(MAVEN_URL.to_string()),
+            natives: None,
+            rules: None,
+            checksums: None,
+            include_in_classpath: true,
+            downloadable: true,
+        });
+    }
+
+    for lib in &mut version_info.libraries {
+        insert_mirrored_artifact(
+            &lib.name,
+            None,
+            vec![
+                lib.url
+                    .clone()
+                    .unwrap_or_else(|| MINECRAFT_LIBRARIES_URL.to_string()),
+            ],
+            false,
+            mirror_artifacts,
+        )?;
+        lib.url = Some(format_url("maven/"));
+    }
+
+    Ok(version_info)
+}
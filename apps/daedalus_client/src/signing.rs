@@ -0,0 +1,53 @@
+use crate::Error;
+use daedalus::signing::{SIGNATURE_SUFFIX, Signer};
+use std::sync::OnceLock;
+
+static SIGNER: OnceLock<Option<Signer>> = OnceLock::new();
+
+/// Loads the key metadata is signed with from `SIGNING_KEY`, a base64
+/// Ed25519 seed, and `SIGNING_KEY_ID`. Metadata is left unsigned without
+/// them
+pub fn init() -> Result<(), Error> {
+    let signer = match (
+        dotenvy::var("SIGNING_KEY").ok().filter(|x| !x.is_empty()),
+        dotenvy::var("SIGNING_KEY_ID")
+            .ok()
+            .filter(|x| !x.is_empty()),
+    ) {
+        (Some(key), Some(key_id)) => {
+            let signer = Signer::from_seed(key_id, &key)?;
+            let key = signer.trusted_key();
+            tracing::info!(
+                "Signing metadata with key {}:{}",
+                key.key_id,
+                key.public_key
+            );
+            Some(signer)
+        }
+        (Some(_), None) => {
+            return Err(crate::ErrorKind::InvalidInput(
+                "SIGNING_KEY_ID must be set along with SIGNING_KEY".to_string(),
+            )
+            .into());
+        }
+        _ => {
+            tracing::warn!("SIGNING_KEY is not set, metadata will be unsigned");
+            None
+        }
+    };
+
+    if SIGNER.set(signer).is_err() {
+        tracing::warn!("Signer was already initialized");
+    }
+
+    Ok(())
+}
+
+pub fn signer() -> Option<&'static Signer> {
+    SIGNER.get().and_then(Option::as_ref)
+}
+
+/// The path of the detached signature of a file
+pub fn signature_path(path: &str) -> String {
+    format!("{path}{SIGNATURE_SUFFIX}")
+}
@@ -1,43 +1,11 @@
+use crate::output::Output;
+use crate::signing;
 use crate::{Error, ErrorKind};
 use bytes::Bytes;
-use s3::creds::Credentials;
-use s3::{Bucket, Region};
 use serde::de::DeserializeOwned;
 use std::sync::{Arc, LazyLock};
 use tokio::sync::Semaphore;
 
-static BUCKET: LazyLock<Bucket> = LazyLock::new(|| {
-    let region = dotenvy::var("S3_REGION").unwrap();
-    let b = Bucket::new(
-        &dotenvy::var("S3_BUCKET_NAME").unwrap(),
-        if &*region == "r2" {
-            Region::R2 {
-                account_id: dotenvy::var("S3_URL").unwrap(),
-            }
-        } else {
-            Region::Custom {
-                region: region.clone(),
-                endpoint: dotenvy::var("S3_URL").unwrap(),
-            }
-        },
-        Credentials::new(
-            Some(&*dotenvy::var("S3_ACCESS_TOKEN").unwrap()),
-            Some(&*dotenvy::var("S3_SECRET").unwrap()),
-            None,
-            None,
-            None,
-        )
-        .unwrap(),
-    )
-    .unwrap();
-
-    if region == "path-style" {
-        *b.with_path_style()
-    } else {
-        *b
-    }
-});
-
 pub static REQWEST_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
     let mut headers = reqwest::header::HeaderMap::new();
     if let Ok(header) = reqwest::header::HeaderValue::from_str(&format!(
@@ -55,6 +23,7 @@ pub static REQWEST_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
         .unwrap()
 });
 
+/// Writes a file to the configured output
 #[tracing::instrument(skip(bytes, semaphore))]
 pub async fn upload_file_to_bucket(
     path: String,
@@ -62,33 +31,9 @@ pub async fn upload_file_to_bucket(
     content_type: Option<String>,
     semaphore: &Arc<Semaphore>,
 ) -> Result<(), Error> {
-    let _permit = semaphore.acquire().await?;
-    let key = path.clone();
-
-    const RETRIES: i32 = 3;
-    for attempt in 1..=(RETRIES + 1) {
-        tracing::trace!("Attempting file upload, attempt {attempt}");
-        let result = if let Some(ref content_type) = content_type {
-            BUCKET
-                .put_object_with_content_type(key.clone(), &bytes, content_type)
-                .await
-        } else {
-            BUCKET.put_object(key.clone(), &bytes).await
-        }
-        .map_err(|err| ErrorKind::S3 {
-            inner: Box::new(err),
-            file: path.clone(),
-        });
-
-        match result {
-            Ok(_) => return Ok(()),
-            Err(_) if attempt <= RETRIES => continue,
-            Err(_) => {
-                result?;
-            }
-        }
-    }
-    unreachable!()
+    Output::get()
+        .write(path, bytes, content_type, semaphore)
+        .await
 }
 
 pub async fn upload_url_to_bucket_mirrors(
@@ -128,6 +73,17 @@ pub async fn upload_url_to_bucket(
     sha1: Option<String>,
     semaphore: &Arc<Semaphore>,
 ) -> Result<(), Error> {
+    let output = Output::get();
+    // Mirrored artifacts never change, so ones written by a previous run can
+    // be kept as they are
+    if output.exists(&path).await? {
+        return Ok(());
+    }
+    if output.dry_run() {
+        println!("mirror {path} from {url}");
+        return Ok(());
+    }
+
     let data = download_file(&url, sha1.as_deref(), semaphore).await?;
 
     upload_file_to_bucket(path, data, None, semaphore).await?;
@@ -216,6 +172,34 @@ pub async fn fetch_json<T: DeserializeOwned>(
     )?)
 }
 
+/// Reads a JSON file written to the output by a previous run
+///
+/// When signing, files that aren't signed with the current key are rejected,
+/// so they are generated and signed again
+pub async fn read_output_json<T: DeserializeOwned>(
+    path: &str,
+    semaphore: &Arc<Semaphore>,
+) -> Result<T, Error> {
+    let output = Output::get();
+    let bytes = output.read(path, semaphore).await?;
+
+    if let Some(signer) = signing::signer() {
+        let signature = serde_json::from_slice(
+            &output
+                .read(&signing::signature_path(path), semaphore)
+                .await?,
+        )?;
+        daedalus::signing::verify(
+            &[signer.trusted_key()],
+            path,
+            &bytes,
+            &signature,
+        )?;
+    }
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
 pub async fn fetch_xml<T: DeserializeOwned>(
     url: &str,
     semaphore: &Arc<Semaphore>,
@@ -0,0 +1,13 @@
+CREATE TABLE minecraft_server_verifications (
+	project_id BIGINT PRIMARY KEY REFERENCES mods(id) ON DELETE CASCADE,
+	address VARCHAR(255) NOT NULL,
+	token VARCHAR(64) NOT NULL,
+	created TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	verified TIMESTAMPTZ NULL,
+	method VARCHAR(16) NULL,
+	last_checked TIMESTAMPTZ NULL,
+	failed_checks INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX minecraft_server_verifications_last_checked_idx
+	ON minecraft_server_verifications(last_checked);
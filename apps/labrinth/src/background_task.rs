@@ -34,6 +34,12 @@ pub enum BackgroundTask {
     /// Attempts to ping Minecraft Java servers as if we were a client, to
     /// collect info on if they're online, game version, description, etc.
     PingMinecraftJavaServers,
+    /// Attempts to ping Minecraft Bedrock servers over RakNet, to collect info
+    /// on if they're online, game version, MOTD, etc.
+    PingMinecraftBedrockServers,
+    /// Re-checks that the owners of verified Minecraft server projects still
+    /// control their server's address.
+    VerifyMinecraftServers,
 }
 
 impl BackgroundTask {
@@ -90,6 +96,13 @@ impl BackgroundTask {
             PingMinecraftJavaServers => {
                 ping_minecraft_java_servers(pool, redis_pool, clickhouse).await
             }
+            PingMinecraftBedrockServers => {
+                ping_minecraft_bedrock_servers(pool, redis_pool, clickhouse)
+                    .await
+            }
+            VerifyMinecraftServers => {
+                verify_minecraft_servers(pool, redis_pool).await
+            }
         }
     }
 }
@@ -254,6 +267,46 @@ pub async fn ping_minecraft_java_servers(
     Ok(())
 }
 
+pub async fn ping_minecraft_bedrock_servers(
+    pool: PgPool,
+    redis_pool: RedisPool,
+    clickhouse: clickhouse::Client,
+) -> eyre::Result<()> {
+    info!("Started pinging Minecraft Bedrock servers");
+
+    let server_ping_queue = crate::queue::server_ping::ServerPingQueue::new(
+        pool, redis_pool, clickhouse,
+    );
+
+    server_ping_queue
+        .ping_minecraft_bedrock_servers()
+        .await
+        .wrap_err("failed to ping Minecraft Bedrock servers")?;
+
+    info!("Done pinging Minecraft Bedrock servers");
+    Ok(())
+}
+
+pub async fn verify_minecraft_servers(
+    pool: PgPool,
+    redis_pool: RedisPool,
+) -> eyre::Result<()> {
+    info!("Started verifying Minecraft servers");
+
+    let server_verification_queue =
+        crate::queue::server_verification::ServerVerificationQueue::new(
+            pool, redis_pool,
+        );
+
+    server_verification_queue
+        .verify_minecraft_servers()
+        .await
+        .wrap_err("failed to verify Minecraft servers")?;
+
+    info!("Done verifying Minecraft servers");
+    Ok(())
+}
+
 mod version_updater {
     use std::sync::LazyLock;
 
@@ -20,6 +20,8 @@ pub async fn init_client_with_database(
     database: &str,
 ) -> clickhouse::error::Result<clickhouse::Client> {
     const MINECRAFT_JAVA_SERVER_PINGS: &str = server_ping::CLICKHOUSE_TABLE;
+    const MINECRAFT_BEDROCK_SERVER_PINGS: &str =
+        server_ping::BEDROCK_CLICKHOUSE_TABLE;
 
     let client = {
         let https_connector = HttpsConnectorBuilder::new()
@@ -192,6 +194,31 @@ pub async fn init_client_with_database(
         .execute()
         .await?;
 
+    client
+        .query(&format!(
+            "
+            CREATE TABLE IF NOT EXISTS {database}.{MINECRAFT_BEDROCK_SERVER_PINGS} {cluster_line}
+            (
+                recorded DateTime64(4),
+                project_id UInt64,
+                address String,
+                latency_ms Nullable(UInt32),
+                description Nullable(String),
+                version_name Nullable(String),
+                version_protocol Nullable(Int32),
+                game_mode Nullable(String),
+                players_online Nullable(Int32),
+                players_max Nullable(Int32)
+            )
+            ENGINE = {engine}
+            {ttl}
+            PRIMARY KEY (project_id, recorded)
+            SETTINGS index_granularity = 8192
+            "
+        ))
+        .execute()
+        .await?;
+
     client
         .query(&format!(
             "
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{DBProjectId, DatabaseError};
+use crate::models::exp::minecraft::ServerVerificationMethod;
+
+/// Prefix of verification tokens, so owners and support can recognize them
+/// in a MOTD or TXT record.
+pub const TOKEN_PREFIX: &str = "modrinth-verify=";
+
+/// Ownership verification of a server project's address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DBMinecraftServerVerification {
+    pub project_id: DBProjectId,
+    /// Address the token was issued for. Verification only applies while the
+    /// project still lists this address.
+    pub address: String,
+    pub token: String,
+    pub created: DateTime<Utc>,
+    /// When the token was first found, if it's currently verified.
+    pub verified: Option<DateTime<Utc>>,
+    pub method: Option<ServerVerificationMethod>,
+    pub last_checked: Option<DateTime<Utc>>,
+    /// Number of checks in a row which didn't find the token.
+    pub failed_checks: i32,
+}
+
+impl DBMinecraftServerVerification {
+    /// Creates an unverified verification with a new token.
+    pub fn new(project_id: DBProjectId, address: String) -> Self {
+        let token = ChaCha20Rng::from_entropy()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect::<String>();
+
+        Self {
+            project_id,
+            address,
+            token: format!("{TOKEN_PREFIX}{token}"),
+            created: Utc::now(),
+            verified: None,
+            method: None,
+            last_checked: None,
+            failed_checks: 0,
+        }
+    }
+
+    pub async fn get<'a, E>(
+        project_id: DBProjectId,
+        exec: E,
+    ) -> Result<Option<Self>, DatabaseError>
+    where
+        E: crate::database::Executor<'a, Database = sqlx::Postgres>,
+    {
+        Ok(Self::get_many(&[project_id], exec).await?.pop())
+    }
+
+    pub async fn get_many<'a, E>(
+        project_ids: &[DBProjectId],
+        exec: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: crate::database::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query!(
+            r#"
+            SELECT project_id, address, token, created, verified, method, last_checked, failed_checks
+            FROM minecraft_server_verifications
+            WHERE project_id = ANY($1)
+            "#,
+            &project_ids.iter().map(|id| id.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Self {
+                project_id: DBProjectId(row.project_id),
+                address: row.address,
+                token: row.token,
+                created: row.created,
+                verified: row.verified,
+                method: row
+                    .method
+                    .as_deref()
+                    .and_then(ServerVerificationMethod::from_string),
+                last_checked: row.last_checked,
+                failed_checks: row.failed_checks,
+            })
+            .collect())
+    }
+
+    /// Gets verifications which haven't been checked since `checked_before`.
+    ///
+    /// Verifications for an address the project no longer lists, and tokens
+    /// which were issued before `issued_after` and never found, are skipped.
+    pub async fn get_due<'a, E>(
+        checked_before: DateTime<Utc>,
+        issued_after: DateTime<Utc>,
+        exec: E,
+    ) -> Result<Vec<Self>, DatabaseError>
+    where
+        E: crate::database::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let rows = sqlx::query!(
+            r#"
+            SELECT v.project_id, v.address, v.token, v.created, v.verified, v.method, v.last_checked, v.failed_checks
+            FROM minecraft_server_verifications v
+            INNER JOIN mods m ON m.id = v.project_id
+            WHERE
+                (v.last_checked IS NULL OR v.last_checked < $1)
+                AND (v.verified IS NOT NULL OR v.created > $2)
+                AND LOWER(m.components -> 'minecraft_java_server' ->> 'address') = LOWER(v.address)
+            "#,
+            checked_before,
+            issued_after,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Self {
+                project_id: DBProjectId(row.project_id),
+                address: row.address,
+                token: row.token,
+                created: row.created,
+                verified: row.verified,
+                method: row
+                    .method
+                    .as_deref()
+                    .and_then(ServerVerificationMethod::from_string),
+                last_checked: row.last_checked,
+                failed_checks: row.failed_checks,
+            })
+            .collect())
+    }
+
+    pub async fn upsert<'a, E>(&self, exec: E) -> Result<(), DatabaseError>
+    where
+        E: crate::database::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO minecraft_server_verifications (project_id, address, token, created, verified, method, last_checked, failed_checks)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (project_id)
+            DO UPDATE
+                SET address = EXCLUDED.address,
+                    token = EXCLUDED.token,
+                    created = EXCLUDED.created,
+                    verified = EXCLUDED.verified,
+                    method = EXCLUDED.method,
+                    last_checked = EXCLUDED.last_checked,
+                    failed_checks = EXCLUDED.failed_checks
+            "#,
+            self.project_id.0,
+            self.address,
+            self.token,
+            self.created,
+            self.verified,
+            self.method.map(|method| method.as_str()),
+            self.last_checked,
+            self.failed_checks,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+}
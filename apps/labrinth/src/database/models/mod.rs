@@ -12,6 +12,7 @@ pub mod ids;
 pub mod image_item;
 pub mod legacy_loader_fields;
 pub mod loader_fields;
+pub mod minecraft_server_verification_item;
 pub mod moderation_external_item;
 pub mod moderation_lock_item;
 pub mod moderation_note_item;
@@ -302,4 +302,9 @@ vars! {
     SERVER_PING_MIN_INTERVAL_SEC: u64 = 30u64 * 60;
     SERVER_PING_TIMEOUT_MS: u64 = 3u64 * 1000;
     SERVER_PING_MAX_FAIL_COUNT: u64 = 3u64;
+
+    // server ownership verification
+    SERVER_VERIFICATION_INTERVAL_SEC: u64 = 6u64 * 60 * 60;
+    SERVER_VERIFICATION_MAX_FAILED_CHECKS: u32 = 4u32;
+    SERVER_VERIFICATION_TOKEN_EXPIRY_SEC: u64 = 7u64 * 24 * 60 * 60;
 }
@@ -162,17 +162,6 @@ component::define! {
     /// Version of a Minecraft Java server listing.
     #[derive(Debug, Clone, Serialize, Deserialize, Validate, utoipa::ToSchema)]
     pub struct JavaServerVersion {}
-
-    /// Listing for a Minecraft Bedrock server.
-    #[derive(Debug, Clone, Serialize, Deserialize, Validate, utoipa::ToSchema)]
-    pub struct BedrockServerProject {
-        #[base()]
-        #[edit(serde(default))]
-        #[create(required)]
-        /// Address (IP or domain name) of the Bedrock server, excluding port.
-        #[validate(length(max = 255))]
-        pub address: String,
-    }
 }
 
 impl ProjectComponent for ModProject {
@@ -224,6 +213,8 @@ pub struct JavaServerProjectQuery {
     pub address: String,
     pub content: ServerContentQuery,
     pub ping: Option<JavaServerPing>,
+    /// Set if the owners of this project proved they control `address`.
+    pub verification: Option<ServerVerification>,
     pub verified_plays_2w: Option<u64>,
     pub verified_plays_4w: Option<u64>,
 }
@@ -251,6 +242,9 @@ impl ComponentQuery for JavaServerProjectQuery {
             }
         }
         requirements.minecraft_java_server_pings.insert(project_id);
+        requirements
+            .minecraft_server_verifications
+            .insert(project_id);
         requirements.minecraft_server_analytics.insert(project_id);
     }
 
@@ -260,6 +254,14 @@ impl ComponentQuery for JavaServerProjectQuery {
         context: &ProjectQueryContext,
     ) -> Result<Self> {
         let analytics = context.minecraft_server_analytics.get(&project_id);
+        // verification only applies to the address it was issued for
+        let verification = context
+            .minecraft_server_verifications
+            .get(&project_id)
+            .filter(|verification| {
+                verification.address.eq_ignore_ascii_case(&serial.address)
+            })
+            .cloned();
         Ok(Self {
             address: serial.address,
             content: match serial.content {
@@ -296,6 +298,7 @@ impl ComponentQuery for JavaServerProjectQuery {
                 .minecraft_java_server_pings
                 .get(&project_id)
                 .cloned(),
+            verification,
             verified_plays_2w: analytics.map(|a| a.verified_plays_2w),
             verified_plays_4w: analytics.map(|a| a.verified_plays_4w),
         })
@@ -323,6 +326,80 @@ impl ComponentEdit for JavaServerProjectEdit {
     }
 }
 
+/// Listing for a Minecraft Bedrock server.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, utoipa::ToSchema)]
+pub struct BedrockServerProject {
+    /// Address (IP or domain name) of the Bedrock server, excluding port.
+    #[validate(length(max = 255))]
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, utoipa::ToSchema)]
+pub struct BedrockServerProjectEdit {
+    #[validate(length(max = 255))]
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BedrockServerProjectQuery {
+    pub address: String,
+    pub ping: Option<BedrockServerPing>,
+}
+
+impl Component for BedrockServerProject {
+    type EntityId = ProjectId;
+    type Query = BedrockServerProjectQuery;
+    type Edit = BedrockServerProjectEdit;
+}
+
+impl ComponentQuery for BedrockServerProjectQuery {
+    type Component = BedrockServerProject;
+    type Requirements = ProjectQueryRequirements;
+    type Context = ProjectQueryContext;
+
+    fn collect_requirements(
+        _serial: &Self::Component,
+        project_id: ProjectId,
+        requirements: &mut ProjectQueryRequirements,
+    ) {
+        requirements
+            .minecraft_bedrock_server_pings
+            .insert(project_id);
+    }
+
+    fn populate(
+        serial: Self::Component,
+        project_id: ProjectId,
+        context: &ProjectQueryContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            address: serial.address,
+            ping: context
+                .minecraft_bedrock_server_pings
+                .get(&project_id)
+                .cloned(),
+        })
+    }
+}
+
+impl ComponentEdit for BedrockServerProjectEdit {
+    type Component = BedrockServerProject;
+
+    fn create(self) -> Result<Self::Component> {
+        Ok(BedrockServerProject {
+            address: self.address.wrap_err("missing `address`")?,
+        })
+    }
+
+    async fn apply_to(self, component: &mut Self::Component) -> Result<()> {
+        if let Some(address) = self.address {
+            component.address = address;
+        }
+        Ok(())
+    }
+}
+
 /// What game content a [`JavaServerProject`] is using.
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(tag = "kind", rename_all = "snake_case")]
@@ -398,6 +475,47 @@ pub enum ServerRegion {
     Russia,
 }
 
+/// How the owners of a server project proved they control its address.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerVerificationMethod {
+    /// The verification token was in the server's MOTD.
+    Motd,
+    /// The verification token was in a DNS TXT record on the server's
+    /// address.
+    Dns,
+}
+
+impl ServerVerificationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerVerificationMethod::Motd => "motd",
+            ServerVerificationMethod::Dns => "dns",
+        }
+    }
+
+    pub fn from_string(string: &str) -> Option<Self> {
+        match string {
+            "motd" => Some(ServerVerificationMethod::Motd),
+            "dns" => Some(ServerVerificationMethod::Dns),
+            _ => None,
+        }
+    }
+}
+
+/// Proof that the owners of a server project control its address.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerVerification {
+    /// Address which was verified.
+    pub address: String,
+    /// When the address was first verified.
+    pub verified: DateTime<Utc>,
+    /// How the owners proved they control the address.
+    pub method: ServerVerificationMethod,
+}
+
 /// Recorded ping attempt that Labrinth made to a Minecraft Java server project.
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct JavaServerPing {
@@ -430,6 +548,39 @@ pub struct JavaServerPingData {
     pub players_max: i32,
 }
 
+/// Recorded ping attempt that Labrinth made to a Minecraft Bedrock server
+/// project.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BedrockServerPing {
+    /// When the ping was performed.
+    pub when: DateTime<Utc>,
+    /// Address of the server at the time of the ping.
+    pub address: String,
+    /// If the ping was successful, info on the ping response.
+    pub data: Option<BedrockServerPingData>,
+}
+
+/// Ping response data for a Minecraft Bedrock server.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BedrockServerPingData {
+    /// How long it took for the Labrinth worker to ping the server.
+    ///
+    /// See [`JavaServerPingData::latency`].
+    pub latency: Duration,
+    /// Reported version name of the server.
+    pub version_name: String,
+    /// Reported network protocol number of the server.
+    pub version_protocol: i32,
+    /// MOTD of the server as shown in the server list.
+    pub description: String,
+    /// Default game mode of the server, if reported.
+    pub game_mode: Option<String>,
+    /// Number of players online at the time.
+    pub players_online: i32,
+    /// Maximum number of players allowed on the server.
+    pub players_max: i32,
+}
+
 component::relations! {
     pub(super) static PROJECT_COMPONENT_RELATIONS: ProjectComponentKind = {
         use ProjectComponentKind::*;
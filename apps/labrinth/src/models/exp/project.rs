@@ -21,7 +21,7 @@ use crate::{
         analytics::cache::{
             MINECRAFT_SERVER_ANALYTICS, MinecraftServerAnalytics,
         },
-        server_ping,
+        server_ping, server_verification,
     },
     util::error::Context,
 };
@@ -180,6 +180,8 @@ component::relations! {
 pub struct ProjectQueryRequirements {
     pub partial_versions: HashSet<VersionId>,
     pub minecraft_java_server_pings: HashSet<ProjectId>,
+    pub minecraft_bedrock_server_pings: HashSet<ProjectId>,
+    pub minecraft_server_verifications: HashSet<ProjectId>,
     pub minecraft_server_analytics: HashSet<ProjectId>,
 }
 
@@ -187,6 +189,10 @@ pub struct ProjectQueryContext {
     pub partial_versions: HashMap<VersionId, PartialVersion>,
     pub minecraft_java_server_pings:
         HashMap<ProjectId, minecraft::JavaServerPing>,
+    pub minecraft_bedrock_server_pings:
+        HashMap<ProjectId, minecraft::BedrockServerPing>,
+    pub minecraft_server_verifications:
+        HashMap<ProjectId, minecraft::ServerVerification>,
     pub minecraft_server_analytics:
         HashMap<ProjectId, MinecraftServerAnalytics>,
 }
@@ -210,6 +216,8 @@ pub async fn fetch_query_context(
     let ProjectQueryRequirements {
         partial_versions,
         minecraft_java_server_pings,
+        minecraft_bedrock_server_pings,
+        minecraft_server_verifications,
         minecraft_server_analytics,
     } = requirements;
 
@@ -274,6 +282,56 @@ pub async fn fetch_query_context(
             .collect::<HashMap<_, _>>()
     };
 
+    let minecraft_bedrock_server_pings = minecraft_bedrock_server_pings
+        .into_iter()
+        .collect::<Vec<_>>();
+    let minecraft_bedrock_server_pings =
+        if minecraft_bedrock_server_pings.is_empty() {
+            HashMap::new()
+        } else {
+            redis
+            .get_many_deserialized_from_json::<minecraft::BedrockServerPing>(
+                server_ping::BEDROCK_REDIS_NAMESPACE,
+                &minecraft_bedrock_server_pings
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+            .await?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, ping)| {
+                ping.map(|ping| (minecraft_bedrock_server_pings[idx], ping))
+            })
+            .collect::<HashMap<_, _>>()
+        };
+
+    let minecraft_server_verifications = minecraft_server_verifications
+        .into_iter()
+        .collect::<Vec<_>>();
+    let minecraft_server_verifications =
+        if minecraft_server_verifications.is_empty() {
+            HashMap::new()
+        } else {
+            redis
+            .get_many_deserialized_from_json::<minecraft::ServerVerification>(
+                server_verification::REDIS_NAMESPACE,
+                &minecraft_server_verifications
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+            .await?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, verification)| {
+                verification.map(|verification| {
+                    (minecraft_server_verifications[idx], verification)
+                })
+            })
+            .collect::<HashMap<_, _>>()
+        };
+
     let minecraft_server_analytics =
         minecraft_server_analytics.into_iter().collect::<Vec<_>>();
 
@@ -300,6 +358,8 @@ pub async fn fetch_query_context(
     Ok(ProjectQueryContext {
         partial_versions,
         minecraft_java_server_pings,
+        minecraft_bedrock_server_pings,
+        minecraft_server_verifications,
         minecraft_server_analytics,
     })
 }
@@ -4,5 +4,6 @@ pub mod email;
 pub mod moderation;
 pub mod payouts;
 pub mod server_ping;
+pub mod server_verification;
 pub mod session;
 pub mod socket;
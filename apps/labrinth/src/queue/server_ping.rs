@@ -7,10 +7,12 @@ use crate::models::ids::ProjectId;
 use crate::models::projects::ProjectStatus;
 use crate::{database::PgPool, util::error::Context};
 use async_minecraft_ping::ServerDescription;
-use chrono::{TimeDelta, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use clickhouse::{Client, Row};
 use serde::Serialize;
+use serde::de::DeserializeOwned;
 use sqlx::types::Json;
+use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
@@ -27,6 +29,182 @@ pub const REDIS_NAMESPACE: &str = "minecraft_java_server_ping";
 pub const REDIS_FAILURE_NAMESPACE: &str = "minecraft_java_server_ping_failures";
 pub const CLICKHOUSE_TABLE: &str = "minecraft_java_server_pings";
 
+pub const BEDROCK_REDIS_NAMESPACE: &str = "minecraft_bedrock_server_ping";
+pub const BEDROCK_REDIS_FAILURE_NAMESPACE: &str =
+    "minecraft_bedrock_server_ping_failures";
+pub const BEDROCK_CLICKHOUSE_TABLE: &str = "minecraft_bedrock_server_pings";
+
+/// Edition of Minecraft whose server listings the queue pings.
+trait ServerEdition: 'static {
+    /// Name of the project component holding the server's address.
+    const COMPONENT: &'static str;
+    const REDIS_NAMESPACE: &'static str;
+    const REDIS_FAILURE_NAMESPACE: &'static str;
+
+    type Ping: Debug + Serialize + DeserializeOwned + Send + Sync + 'static;
+
+    fn address(components: &exp::ProjectSerial) -> Option<&str>;
+
+    fn ping(
+        address: &str,
+    ) -> impl Future<Output = eyre::Result<Self::Ping>> + Send;
+
+    fn failed_ping(address: String) -> Self::Ping;
+
+    /// When the ping was performed, if it succeeded.
+    fn succeeded_at(ping: &Self::Ping) -> Option<DateTime<Utc>>;
+
+    /// Inserts a row into this edition's ClickHouse table for each ping.
+    fn insert_records(
+        clickhouse: &Client,
+        pings: &[(ProjectId, Self::Ping)],
+    ) -> impl Future<Output = eyre::Result<()>> + Send;
+}
+
+struct Java;
+
+impl ServerEdition for Java {
+    const COMPONENT: &'static str = "minecraft_java_server";
+    const REDIS_NAMESPACE: &'static str = REDIS_NAMESPACE;
+    const REDIS_FAILURE_NAMESPACE: &'static str = REDIS_FAILURE_NAMESPACE;
+
+    type Ping = exp::minecraft::JavaServerPing;
+
+    fn address(components: &exp::ProjectSerial) -> Option<&str> {
+        components
+            .minecraft_java_server
+            .as_ref()
+            .map(|server| server.address.as_str())
+    }
+
+    async fn ping(address: &str) -> eyre::Result<Self::Ping> {
+        let data = ping_server(address, None).await?;
+        Ok(exp::minecraft::JavaServerPing {
+            when: Utc::now(),
+            address: address.to_string(),
+            data: Some(data),
+        })
+    }
+
+    fn failed_ping(address: String) -> Self::Ping {
+        exp::minecraft::JavaServerPing {
+            when: Utc::now(),
+            address,
+            data: None,
+        }
+    }
+
+    fn succeeded_at(ping: &Self::Ping) -> Option<DateTime<Utc>> {
+        ping.data.as_ref().map(|_| ping.when)
+    }
+
+    async fn insert_records(
+        clickhouse: &Client,
+        pings: &[(ProjectId, Self::Ping)],
+    ) -> eyre::Result<()> {
+        let mut ch = clickhouse
+            .insert::<ServerPingRecord>(CLICKHOUSE_TABLE)
+            .await
+            .wrap_err("failed to begin inserting ping records")?;
+
+        for (project_id, ping) in pings {
+            let data = ping.data.as_ref();
+            let row = ServerPingRecord {
+                recorded: ping.when.timestamp_nanos_opt().unwrap() / 100_000,
+                project_id: project_id.0,
+                address: ping.address.clone(),
+                latency_ms: data.map(|d| d.latency.as_millis() as u32),
+                description: data.map(|d| d.description.clone()),
+                version_name: data.map(|d| d.version_name.clone()),
+                version_protocol: data.map(|d| d.version_protocol),
+                players_online: data.map(|d| d.players_online),
+                players_max: data.map(|d| d.players_max),
+            };
+
+            ch.write(&row)
+                .await
+                .wrap_err("failed to write ping record")?;
+        }
+
+        ch.end()
+            .await
+            .wrap_err("failed to end inserting ping records")
+    }
+}
+
+struct Bedrock;
+
+impl ServerEdition for Bedrock {
+    const COMPONENT: &'static str = "minecraft_bedrock_server";
+    const REDIS_NAMESPACE: &'static str = BEDROCK_REDIS_NAMESPACE;
+    const REDIS_FAILURE_NAMESPACE: &'static str =
+        BEDROCK_REDIS_FAILURE_NAMESPACE;
+
+    type Ping = exp::minecraft::BedrockServerPing;
+
+    fn address(components: &exp::ProjectSerial) -> Option<&str> {
+        components
+            .minecraft_bedrock_server
+            .as_ref()
+            .map(|server| server.address.as_str())
+    }
+
+    async fn ping(address: &str) -> eyre::Result<Self::Ping> {
+        let data = ping_bedrock_server(address, None).await?;
+        Ok(exp::minecraft::BedrockServerPing {
+            when: Utc::now(),
+            address: address.to_string(),
+            data: Some(data),
+        })
+    }
+
+    fn failed_ping(address: String) -> Self::Ping {
+        exp::minecraft::BedrockServerPing {
+            when: Utc::now(),
+            address,
+            data: None,
+        }
+    }
+
+    fn succeeded_at(ping: &Self::Ping) -> Option<DateTime<Utc>> {
+        ping.data.as_ref().map(|_| ping.when)
+    }
+
+    async fn insert_records(
+        clickhouse: &Client,
+        pings: &[(ProjectId, Self::Ping)],
+    ) -> eyre::Result<()> {
+        let mut ch = clickhouse
+            .insert::<BedrockServerPingRecord>(BEDROCK_CLICKHOUSE_TABLE)
+            .await
+            .wrap_err("failed to begin inserting ping records")?;
+
+        for (project_id, ping) in pings {
+            let data = ping.data.as_ref();
+            let row = BedrockServerPingRecord {
+                recorded: ping.when.timestamp_nanos_opt().unwrap() / 100_000,
+                project_id: project_id.0,
+                address: ping.address.clone(),
+                latency_ms: data.map(|d| d.latency.as_millis() as u32),
+                description: data.map(|d| d.description.clone()),
+                version_name: data.map(|d| d.version_name.clone()),
+                version_protocol: data.map(|d| d.version_protocol),
+                game_mode: data.and_then(|d| d.game_mode.clone()),
+                players_online: data.map(|d| d.players_online),
+                players_max: data.map(|d| d.players_max),
+            };
+
+            ch.write(&row)
+                .await
+                .wrap_err("failed to write ping record")?;
+        }
+
+        ch.end()
+            .await
+            .wrap_err("failed to end inserting ping records")
+    }
+}
+
 impl ServerPingQueue {
     pub fn new(db: PgPool, redis: RedisPool, clickhouse: Client) -> Self {
         Self {
@@ -37,31 +215,38 @@ impl ServerPingQueue {
     }
 
     pub async fn ping_minecraft_java_servers(&self) -> eyre::Result<()> {
-        let server_projects = self.find_servers_to_ping().await?;
+        self.ping_servers::<Java>().await
+    }
+
+    pub async fn ping_minecraft_bedrock_servers(&self) -> eyre::Result<()> {
+        self.ping_servers::<Bedrock>().await
+    }
+
+    async fn ping_servers<E: ServerEdition>(&self) -> eyre::Result<()> {
+        let server_projects = self.find_servers_to_ping::<E>().await?;
         info!("Found {} servers to ping", server_projects.len());
 
         let active_pings =
             Arc::new(Semaphore::new(ENV.SERVER_PING_MAX_CONCURRENT));
         let pings = server_projects
             .into_iter()
-            .map(|(project_id, java_server)| {
-                let span = info_span!("ping", %project_id, address = %java_server.address);
+            .map(|(project_id, address)| {
+                let span = info_span!("ping", %project_id, %address);
 
                 let active_pings = active_pings.clone();
-                let address = java_server.address;
                 let task = async move {
                     let _permit = active_pings.acquire().await.expect("semaphore should not be closed now");
 
                     let mut retries = ENV.SERVER_PING_RETRIES;
-                    let result = loop {
-                        match ping_server(&address, None).await {
+                    let ping = loop {
+                        match E::ping(&address).await {
                             Ok(ping) => {
                                 info!(?ping, "Received successful ping");
-                                break Ok(ping);
+                                break ping;
                             }
                             Err(err) if retries == 0 => {
                                 info!("Failed to ping server in {:?}ms, no retries left: {err:#}", ENV.SERVER_PING_TIMEOUT_MS);
-                                break Err(err);
+                                break E::failed_ping(address);
                             }
                             Err(err) => {
                                 trace!(%retries, "Failed to ping server in {:?}ms, retrying: {err:#}", ENV.SERVER_PING_TIMEOUT_MS);
@@ -71,11 +256,7 @@ impl ServerPingQueue {
                         };
                     };
 
-                    (project_id, exp::minecraft::JavaServerPing {
-                        when: Utc::now(),
-                        address,
-                        data: result.ok(),
-                    })
+                    (project_id, ping)
                 };
                 tokio::spawn(task.instrument(span))
             })
@@ -87,11 +268,7 @@ impl ServerPingQueue {
             .collect::<Vec<_>>();
 
         if !pings.is_empty() {
-            let mut ch = self
-                .clickhouse
-                .insert::<ServerPingRecord>(CLICKHOUSE_TABLE)
-                .await
-                .wrap_err("failed to begin inserting ping records")?;
+            E::insert_records(&self.clickhouse, &pings).await?;
 
             let mut redis = self
                 .redis
@@ -100,32 +277,13 @@ impl ServerPingQueue {
                 .wrap_err("failed to connect to redis")?;
 
             for (project_id, ping) in &pings {
-                let data = ping.data.as_ref();
-
-                let row = ServerPingRecord {
-                    recorded: ping.when.timestamp_nanos_opt().unwrap()
-                        / 100_000,
-                    project_id: project_id.0,
-                    address: ping.address.clone(),
-                    latency_ms: data.map(|d| d.latency.as_millis() as u32),
-                    description: data.map(|d| d.description.clone()),
-                    version_name: data.map(|d| d.version_name.clone()),
-                    version_protocol: data.map(|d| d.version_protocol),
-                    players_online: data.map(|d| d.players_online),
-                    players_max: data.map(|d| d.players_max),
-                };
-
-                ch.write(&row)
-                    .await
-                    .wrap_err("failed to write ping record")?;
-
                 let mut updated_project = false;
-                if data.is_some() {
+                if E::succeeded_at(ping).is_some() {
                     // ping succeeded; immediately update its online status in redis
 
                     redis
                         .set_serialized_to_json(
-                            REDIS_NAMESPACE,
+                            E::REDIS_NAMESPACE,
                             project_id,
                             ping,
                             None,
@@ -135,7 +293,7 @@ impl ServerPingQueue {
                     updated_project = true;
 
                     redis
-                        .delete(REDIS_FAILURE_NAMESPACE, project_id)
+                        .delete(E::REDIS_FAILURE_NAMESPACE, project_id)
                         .await
                         .wrap_err("failed to delete failure count")?;
                 } else {
@@ -143,7 +301,10 @@ impl ServerPingQueue {
                     // otherwise, just add to the fail counter
 
                     let failure_count = redis
-                        .incr(REDIS_FAILURE_NAMESPACE, &project_id.to_string())
+                        .incr(
+                            E::REDIS_FAILURE_NAMESPACE,
+                            &project_id.to_string(),
+                        )
                         .await
                         .wrap_err("failed to increment failure count")?;
 
@@ -152,7 +313,7 @@ impl ServerPingQueue {
                     {
                         redis
                             .set_serialized_to_json(
-                                REDIS_NAMESPACE,
+                                E::REDIS_NAMESPACE,
                                 project_id,
                                 ping,
                                 None,
@@ -179,14 +340,12 @@ impl ServerPingQueue {
                     .ok();
                 }
             }
-
-            ch.end()
-                .await
-                .wrap_err("failed to end inserting ping records")?;
         }
 
-        let num_success =
-            pings.iter().filter(|(_, ping)| ping.data.is_some()).count();
+        let num_success = pings
+            .iter()
+            .filter(|(_, ping)| E::succeeded_at(ping).is_some())
+            .count();
         let num_total = pings.len();
 
         info!(
@@ -196,22 +355,23 @@ impl ServerPingQueue {
         Ok(())
     }
 
-    async fn find_servers_to_ping(
+    async fn find_servers_to_ping<E: ServerEdition>(
         &self,
-    ) -> eyre::Result<Vec<(ProjectId, exp::minecraft::JavaServerProject)>> {
-        // first select all java servers
+    ) -> eyre::Result<Vec<(ProjectId, String)>> {
+        // first select all servers of this edition
         let all_server_projects = sqlx::query!(
             r#"
             SELECT id, components AS "components: Json<exp::ProjectSerial>"
             FROM mods
             WHERE
                 status = ANY($1)
-                AND components ? 'minecraft_java_server'
+                AND components ? $2
             "#,
             &ProjectStatus::iterator()
                 .filter(|s| s.is_approved())
                 .map(|s| s.to_string())
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>(),
+            E::COMPONENT,
         )
         .fetch_all(&self.db)
         .await
@@ -240,8 +400,8 @@ impl ServerPingQueue {
             .collect::<Vec<_>>();
 
         let all_server_last_pings = redis
-            .get_many_deserialized_from_json::<exp::minecraft::JavaServerPing>(
-                REDIS_NAMESPACE,
+            .get_many_deserialized_from_json::<E::Ping>(
+                E::REDIS_NAMESPACE,
                 &all_project_ids,
             )
             .await
@@ -255,29 +415,24 @@ impl ServerPingQueue {
             // - have not had a ping in redis yet
             // - OR their last ping was a failure
             // - OR their last successful ping was more than `SERVER_PING_MIN_INTERVAL_SEC` seconds ago
-            .filter(|(row, ping)| {
-                if row
-                    .components
-                    .0
-                    .minecraft_java_server
-                    .as_ref()
-                    .is_none_or(|p| p.address.trim().is_empty())
-                {
-                    return false;
-                }
+            .filter_map(|(row, ping)| {
+                let address = E::address(&row.components.0)
+                    .filter(|address| !address.trim().is_empty())?;
 
-                let Some(ping) = ping else { return true };
-                if ping.data.is_none() {
-                    return true;
+                let should_ping = match ping.as_ref().and_then(E::succeeded_at)
+                {
+                    None => true,
+                    Some(when) => {
+                        when.signed_duration_since(now)
+                            > TimeDelta::seconds(
+                                ENV.SERVER_PING_MIN_INTERVAL_SEC as i64,
+                            )
+                    }
                 };
-                ping.when.signed_duration_since(now)
-                    > TimeDelta::seconds(
-                        ENV.SERVER_PING_MIN_INTERVAL_SEC as i64,
-                    )
-            })
-            .filter_map(|(row, _)| {
-                let server = row.components.0.minecraft_java_server?;
-                Some((ProjectId::from(DBProjectId(row.id)), server))
+
+                should_ping.then(|| {
+                    (ProjectId::from(DBProjectId(row.id)), address.to_string())
+                })
             })
             .collect::<Vec<_>>();
 
@@ -285,23 +440,34 @@ impl ServerPingQueue {
     }
 }
 
+/// Splits an optional `:port` suffix off of a server address.
+fn split_address(
+    address: &str,
+    default_port: u16,
+) -> eyre::Result<(&str, u16)> {
+    match address.rsplit_once(':') {
+        Some((addr, port)) => {
+            let port = port.parse::<u16>().wrap_err("invalid port number")?;
+            Ok((addr, port))
+        }
+        None => Ok((address, default_port)),
+    }
+}
+
+fn ping_timeout(timeout: Option<Duration>) -> Duration {
+    let default_duration = Duration::from_millis(ENV.SERVER_PING_TIMEOUT_MS);
+    timeout
+        .map(|duration| duration.min(default_duration))
+        .unwrap_or(default_duration)
+}
+
 pub async fn ping_server(
     address: &str,
     timeout: Option<Duration>,
 ) -> eyre::Result<exp::minecraft::JavaServerPingData> {
     let start = Instant::now();
-    let default_duration = Duration::from_millis(ENV.SERVER_PING_TIMEOUT_MS);
-    let timeout = timeout
-        .map(|duration| duration.min(default_duration))
-        .unwrap_or(default_duration);
-
-    let (address, port) = match address.rsplit_once(':') {
-        Some((addr, port)) => {
-            let port = port.parse::<u16>().wrap_err("invalid port number")?;
-            (addr, port)
-        }
-        None => (address, 25565),
-    };
+    let timeout = ping_timeout(timeout);
+    let (address, port) = split_address(address, 25565)?;
 
     let task = async move {
         let conn = async_minecraft_ping::ConnectionConfig::build(address)
@@ -336,6 +502,32 @@ pub async fn ping_server(
         .flatten()
 }
 
+pub async fn ping_bedrock_server(
+    address: &str,
+    timeout: Option<Duration>,
+) -> eyre::Result<exp::minecraft::BedrockServerPingData> {
+    let start = Instant::now();
+    let timeout = ping_timeout(timeout);
+    let (address, port) = split_address(address, 19132)?;
+
+    let status = async_minecraft_ping::BedrockConfig::build(address)
+        .with_port(port)
+        .with_timeout(timeout)
+        .ping()
+        .await
+        .wrap_err("failed to ping server")?;
+
+    Ok(exp::minecraft::BedrockServerPingData {
+        latency: start.elapsed(),
+        version_name: status.version,
+        version_protocol: status.protocol,
+        description: status.motd,
+        game_mode: status.game_mode,
+        players_online: status.players_online,
+        players_max: status.players_max,
+    })
+}
+
 #[derive(Debug, Row, Serialize, Clone)]
 struct ServerPingRecord {
     recorded: i64,
@@ -349,6 +541,20 @@ struct ServerPingRecord {
     players_max: Option<i32>,
 }
 
+#[derive(Debug, Row, Serialize, Clone)]
+struct BedrockServerPingRecord {
+    recorded: i64,
+    project_id: u64,
+    address: String,
+    latency_ms: Option<u32>,
+    description: Option<String>,
+    version_name: Option<String>,
+    version_protocol: Option<i32>,
+    game_mode: Option<String>,
+    players_online: Option<i32>,
+    players_max: Option<i32>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
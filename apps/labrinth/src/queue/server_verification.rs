@@ -0,0 +1,411 @@
+use crate::database::DBProject;
+use crate::database::PgPool;
+use crate::database::models::minecraft_server_verification_item::DBMinecraftServerVerification;
+use crate::database::redis::RedisPool;
+use crate::env::ENV;
+use crate::models::exp::minecraft::{
+    ServerVerification, ServerVerificationMethod,
+};
+use crate::models::ids::ProjectId;
+use crate::queue::server_ping;
+use crate::util::error::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use hickory_resolver::TokioResolver;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{Instrument, info, info_span, warn};
+
+/// Mirrors the current verification of each server project, so project
+/// queries don't need to hit the database for it.
+pub const REDIS_NAMESPACE: &str = "minecraft_server_verification";
+
+pub struct ServerVerificationQueue {
+    pub db: PgPool,
+    pub redis: RedisPool,
+}
+
+impl ServerVerificationQueue {
+    pub fn new(db: PgPool, redis: RedisPool) -> Self {
+        Self { db, redis }
+    }
+
+    /// Re-checks every verification which hasn't been checked in
+    /// `SERVER_VERIFICATION_INTERVAL_SEC`, so servers which removed their
+    /// token or changed hands lose their verification.
+    ///
+    /// Tokens which were never found within
+    /// `SERVER_VERIFICATION_TOKEN_EXPIRY_SEC` of being issued aren't checked
+    /// anymore, until the owner issues a new one or checks it manually.
+    pub async fn verify_minecraft_servers(&self) -> eyre::Result<()> {
+        let now = Utc::now();
+        let checked_before = now
+            - TimeDelta::seconds(ENV.SERVER_VERIFICATION_INTERVAL_SEC as i64);
+        let issued_after = now
+            - TimeDelta::seconds(
+                ENV.SERVER_VERIFICATION_TOKEN_EXPIRY_SEC as i64,
+            );
+        let verifications = DBMinecraftServerVerification::get_due(
+            checked_before,
+            issued_after,
+            &self.db,
+        )
+        .await
+        .wrap_err("failed to fetch verifications to check")?;
+        info!(
+            "Found {} server verifications to check",
+            verifications.len()
+        );
+
+        let active_checks =
+            Arc::new(Semaphore::new(ENV.SERVER_PING_MAX_CONCURRENT));
+        let verifications = verifications
+            .into_iter()
+            .map(|mut verification| {
+                let span = info_span!(
+                    "verify",
+                    project_id = verification.project_id.0,
+                    address = %verification.address
+                );
+
+                let active_checks = active_checks.clone();
+                let task = async move {
+                    let _permit = active_checks
+                        .acquire()
+                        .await
+                        .expect("semaphore should not be closed now");
+
+                    let method =
+                        find_token(&verification.address, &verification.token)
+                            .await;
+                    let changed = apply_check(&mut verification, method);
+                    (verification, changed)
+                };
+                tokio::spawn(task.instrument(span))
+            })
+            .collect::<JoinSet<_>>()
+            .join_all()
+            .await
+            .into_iter()
+            .filter_map(|result| result.ok())
+            .collect::<Vec<_>>();
+
+        for (verification, changed) in &verifications {
+            verification
+                .upsert(&self.db)
+                .await
+                .wrap_err("failed to update verification")?;
+
+            // the mirror has no expiry, so it's refreshed on every check in
+            // case it was flushed
+            if *changed {
+                cache_verification(verification, &self.redis).await?;
+            } else if verification.verified.is_some() {
+                mirror_verification(verification, &self.redis).await?;
+            }
+        }
+
+        let num_verified = verifications
+            .iter()
+            .filter(|(verification, _)| verification.verified.is_some())
+            .count();
+        info!(
+            "Checked {} server verifications - {num_verified} verified",
+            verifications.len()
+        );
+        Ok(())
+    }
+}
+
+/// Updates `verification` with the result of a check, which found the token
+/// with `method` if it succeeded.
+///
+/// Verified servers keep their verification until enough checks in a row
+/// fail, so a restart or DNS hiccup doesn't unverify them.
+///
+/// Returns whether the verification status changed.
+pub fn apply_check(
+    verification: &mut DBMinecraftServerVerification,
+    method: Option<ServerVerificationMethod>,
+) -> bool {
+    apply_check_at(
+        verification,
+        method,
+        Utc::now(),
+        ENV.SERVER_VERIFICATION_MAX_FAILED_CHECKS,
+    )
+}
+
+fn apply_check_at(
+    verification: &mut DBMinecraftServerVerification,
+    method: Option<ServerVerificationMethod>,
+    now: DateTime<Utc>,
+    max_failed_checks: u32,
+) -> bool {
+    let was_verified = verification.verified.is_some();
+    verification.last_checked = Some(now);
+
+    match method {
+        Some(method) => {
+            verification.verified.get_or_insert(now);
+            verification.method = Some(method);
+            verification.failed_checks = 0;
+        }
+        None => {
+            verification.failed_checks += 1;
+            if !was_verified
+                || verification.failed_checks as u32 >= max_failed_checks
+            {
+                verification.verified = None;
+                verification.method = None;
+            }
+        }
+    }
+
+    was_verified != verification.verified.is_some()
+}
+
+/// Looks for `token` in the MOTD of the server at `address`, then in the
+/// TXT records of its host.
+pub async fn find_token(
+    address: &str,
+    token: &str,
+) -> Option<ServerVerificationMethod> {
+    match server_ping::ping_server(address, None).await {
+        Ok(ping) if ping.description.contains(token) => {
+            return Some(ServerVerificationMethod::Motd);
+        }
+        Ok(_) => {}
+        Err(err) => info!("Failed to ping server: {err:#}"),
+    }
+
+    let host = address
+        .rsplit_once(':')
+        .map_or(address, |(host, _)| host)
+        .trim_end_matches('.');
+    match txt_records(host).await {
+        Ok(records) if records.iter().any(|record| record.contains(token)) => {
+            Some(ServerVerificationMethod::Dns)
+        }
+        Ok(_) => None,
+        Err(err) => {
+            info!("Failed to look up TXT records: {err:#}");
+            None
+        }
+    }
+}
+
+async fn txt_records(host: &str) -> eyre::Result<Vec<String>> {
+    let resolver = TokioResolver::builder_tokio()
+        .wrap_err("failed to create resolver")?
+        .build();
+
+    let lookup = match resolver.txt_lookup(format!("{host}.")).await {
+        Ok(lookup) => lookup,
+        Err(err)
+            if err
+                .proto()
+                .is_some_and(|err| err.kind().is_no_records_found()) =>
+        {
+            return Ok(Vec::new());
+        }
+        Err(err) => return Err(err).wrap_err("failed to look up TXT records"),
+    };
+
+    // long TXT records are split into multiple strings, which are joined back
+    // together
+    Ok(lookup
+        .iter()
+        .map(|record| {
+            record
+                .txt_data()
+                .iter()
+                .map(|data| String::from_utf8_lossy(data))
+                .collect::<String>()
+        })
+        .collect())
+}
+
+/// Mirrors `verification` into Redis and clears the project's cache, so the
+/// project shows the new status.
+pub async fn cache_verification(
+    verification: &DBMinecraftServerVerification,
+    redis: &RedisPool,
+) -> eyre::Result<()> {
+    mirror_verification(verification, redis).await?;
+
+    DBProject::clear_cache(verification.project_id, None, None, redis)
+        .await
+        .inspect_err(|err| warn!("failed to clear project cache: {err:#}"))
+        .ok();
+
+    Ok(())
+}
+
+/// Mirrors `verification` into Redis, or removes the mirror if it isn't
+/// verified.
+async fn mirror_verification(
+    verification: &DBMinecraftServerVerification,
+    redis: &RedisPool,
+) -> eyre::Result<()> {
+    let project_id = ProjectId::from(verification.project_id);
+    let mut conn = redis
+        .connect()
+        .await
+        .wrap_err("failed to connect to redis")?;
+
+    match (verification.verified, verification.method) {
+        (Some(verified), Some(method)) => {
+            conn.set_serialized_to_json(
+                REDIS_NAMESPACE,
+                project_id,
+                ServerVerification {
+                    address: verification.address.clone(),
+                    verified,
+                    method,
+                },
+                None,
+            )
+            .await
+            .wrap_err("failed to cache verification")?;
+        }
+        _ => {
+            conn.delete(REDIS_NAMESPACE, project_id)
+                .await
+                .wrap_err("failed to delete cached verification")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::DBProjectId;
+
+    fn verification() -> DBMinecraftServerVerification {
+        DBMinecraftServerVerification::new(
+            DBProjectId(1),
+            "play.example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn found_token_verifies() {
+        let mut verification = verification();
+        let now = Utc::now();
+
+        let changed = apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Dns),
+            now,
+            4,
+        );
+
+        assert!(changed);
+        assert_eq!(verification.verified, Some(now));
+        assert_eq!(verification.method, Some(ServerVerificationMethod::Dns));
+        assert_eq!(verification.last_checked, Some(now));
+        assert_eq!(verification.failed_checks, 0);
+    }
+
+    #[test]
+    fn reverifying_keeps_the_first_timestamp() {
+        let mut verification = verification();
+        let first = Utc::now();
+        let second = first + TimeDelta::hours(6);
+        apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Dns),
+            first,
+            4,
+        );
+
+        let changed = apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Motd),
+            second,
+            4,
+        );
+
+        assert!(!changed);
+        assert_eq!(verification.verified, Some(first));
+        assert_eq!(verification.method, Some(ServerVerificationMethod::Motd));
+        assert_eq!(verification.last_checked, Some(second));
+    }
+
+    #[test]
+    fn verified_server_is_unverified_after_grace_period() {
+        let mut verification = verification();
+        let verified = Utc::now();
+        apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Motd),
+            verified,
+            3,
+        );
+
+        for checks in 1..3 {
+            let changed = apply_check_at(
+                &mut verification,
+                None,
+                verified + TimeDelta::hours(checks),
+                3,
+            );
+            assert!(!changed);
+            assert_eq!(verification.verified, Some(verified));
+            assert_eq!(verification.failed_checks, checks as i32);
+        }
+
+        let changed = apply_check_at(
+            &mut verification,
+            None,
+            verified + TimeDelta::hours(3),
+            3,
+        );
+        assert!(changed);
+        assert_eq!(verification.verified, None);
+        assert_eq!(verification.method, None);
+        assert_eq!(verification.failed_checks, 3);
+    }
+
+    #[test]
+    fn found_token_resets_failed_checks() {
+        let mut verification = verification();
+        let now = Utc::now();
+        apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Motd),
+            now,
+            3,
+        );
+        apply_check_at(&mut verification, None, now, 3);
+        apply_check_at(&mut verification, None, now, 3);
+
+        apply_check_at(
+            &mut verification,
+            Some(ServerVerificationMethod::Motd),
+            now,
+            3,
+        );
+        apply_check_at(&mut verification, None, now, 3);
+
+        assert!(verification.verified.is_some());
+        assert_eq!(verification.failed_checks, 1);
+    }
+
+    #[test]
+    fn unverified_server_has_no_grace_period() {
+        let mut verification = verification();
+        let now = Utc::now();
+
+        let changed = apply_check_at(&mut verification, None, now, 3);
+
+        assert!(!changed);
+        assert_eq!(verification.verified, None);
+        assert_eq!(verification.method, None);
+        assert_eq!(verification.last_checked, Some(now));
+        assert_eq!(verification.failed_checks, 1);
+    }
+}
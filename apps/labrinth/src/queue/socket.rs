@@ -3,12 +3,22 @@
 use crate::models::users::UserStatus;
 use actix_ws::Session;
 use ariadne::ids::UserId;
+use ariadne::networking::message::{
+    MAX_SOCKET_DATA_SIZE, SOCKET_WINDOW_SIZE, SocketCloseReason,
+};
 use dashmap::{DashMap, DashSet};
-use std::sync::atomic::AtomicU32;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 
 pub type SocketId = u32;
 
+/// Bytes a connection without flow control may have queued for the other end
+/// before it is closed with [`SocketCloseReason::BufferFull`]
+pub const MAX_SOCKET_BUFFER_SIZE: u32 = 4 * SOCKET_WINDOW_SIZE;
+
 pub struct ActiveSockets {
     pub sockets: DashMap<SocketId, ActiveSocket>,
     pub sockets_by_user_id: DashMap<UserId, DashSet<SocketId>>,
@@ -55,15 +65,145 @@ impl ActiveSocket {
 pub struct TunnelSocket {
     pub owner: SocketId,
     pub socket_type: TunnelSocketType,
+    /// Bytes relayed from this socket that the other end hasn't acknowledged
+    /// yet, if the connection uses flow control
+    pub unacknowledged: AtomicU32,
+    /// Bytes queued in `relay` that weren't sent to the other end yet
+    pub buffered: Arc<AtomicU32>,
+    /// Queue relaying data to the other end in order, if the connection
+    /// doesn't use flow control
+    pub relay: Option<UnboundedSender<Vec<u8>>>,
 }
 
 impl TunnelSocket {
     pub fn new(owner: SocketId, socket_type: TunnelSocketType) -> Self {
-        Self { owner, socket_type }
+        Self {
+            owner,
+            socket_type,
+            unacknowledged: AtomicU32::new(0),
+            buffered: Arc::new(AtomicU32::new(0)),
+            relay: None,
+        }
+    }
+
+    /// Accounts for `len` bytes sent on this socket, returning why the
+    /// connection has to be closed if they exceed its limits
+    pub fn reserve(&self, len: usize) -> Result<(), SocketCloseReason> {
+        let TunnelSocketType::Connected { flow_control, .. } = self.socket_type
+        else {
+            return Ok(());
+        };
+        if len > MAX_SOCKET_DATA_SIZE {
+            return Err(SocketCloseReason::MessageTooLarge);
+        }
+
+        let (counter, limit, reason) = if flow_control {
+            (
+                &self.unacknowledged,
+                SOCKET_WINDOW_SIZE,
+                SocketCloseReason::FlowControlViolation,
+            )
+        } else {
+            (
+                &*self.buffered,
+                MAX_SOCKET_BUFFER_SIZE,
+                SocketCloseReason::BufferFull,
+            )
+        };
+        let len = len as u32;
+        if counter.fetch_add(len, Ordering::Relaxed) + len > limit {
+            return Err(reason);
+        }
+
+        Ok(())
     }
 }
 
 pub enum TunnelSocketType {
-    Listening,
-    Connected { connected_to: Uuid },
+    /// A socket friends can connect to. If `allowed_users` is set, only those
+    /// friends can see and connect to it
+    Listening {
+        allowed_users: Option<HashSet<UserId>>,
+        flow_control: bool,
+    },
+    /// One end of a connection to a listening socket. If `flow_control` is
+    /// set, both ends acknowledge the data they receive
+    Connected {
+        connected_to: Uuid,
+        flow_control: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected(flow_control: bool) -> TunnelSocket {
+        TunnelSocket::new(
+            0,
+            TunnelSocketType::Connected {
+                connected_to: Uuid::nil(),
+                flow_control,
+            },
+        )
+    }
+
+    #[test]
+    fn rejects_messages_over_the_size_limit() {
+        for flow_control in [true, false] {
+            assert_eq!(
+                connected(flow_control).reserve(MAX_SOCKET_DATA_SIZE + 1),
+                Err(SocketCloseReason::MessageTooLarge)
+            );
+        }
+    }
+
+    #[test]
+    fn enforces_the_flow_control_window() {
+        let socket = connected(true);
+        let chunks = SOCKET_WINDOW_SIZE as usize / MAX_SOCKET_DATA_SIZE;
+        for _ in 0..chunks {
+            assert_eq!(socket.reserve(MAX_SOCKET_DATA_SIZE), Ok(()));
+        }
+        assert_eq!(
+            socket.reserve(1),
+            Err(SocketCloseReason::FlowControlViolation)
+        );
+
+        // Acknowledged bytes free up the window
+        socket
+            .unacknowledged
+            .fetch_sub(MAX_SOCKET_DATA_SIZE as u32 + 1, Ordering::Relaxed);
+        assert_eq!(socket.reserve(1), Ok(()));
+        assert_eq!(socket.buffered.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn caps_buffered_bytes_without_flow_control() {
+        let socket = connected(false);
+        let chunks = MAX_SOCKET_BUFFER_SIZE as usize / MAX_SOCKET_DATA_SIZE;
+        for _ in 0..chunks {
+            assert_eq!(socket.reserve(MAX_SOCKET_DATA_SIZE), Ok(()));
+        }
+        assert_eq!(socket.reserve(1), Err(SocketCloseReason::BufferFull));
+
+        // Relayed bytes free up the buffer
+        socket
+            .buffered
+            .fetch_sub(MAX_SOCKET_DATA_SIZE as u32 + 1, Ordering::Relaxed);
+        assert_eq!(socket.reserve(1), Ok(()));
+        assert_eq!(socket.unacknowledged.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn listening_sockets_never_reserve() {
+        let socket = TunnelSocket::new(
+            0,
+            TunnelSocketType::Listening {
+                allowed_users: None,
+                flow_control: false,
+            },
+        );
+        assert_eq!(socket.reserve(MAX_SOCKET_DATA_SIZE + 1), Ok(()));
+    }
 }
@@ -14,6 +14,7 @@ use crate::{
 
 pub fn config(cfg: &mut utoipa_actix_web::service_config::ServiceConfig) {
     cfg.service(ping_minecraft_java);
+    cfg.service(ping_minecraft_bedrock);
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
@@ -47,3 +48,29 @@ pub async fn ping_minecraft_java(
 
     Ok(())
 }
+
+#[utoipa::path]
+#[post("/minecraft-bedrock")]
+pub async fn ping_minecraft_bedrock(
+    req: HttpRequest,
+    web::Json(request): web::Json<PingRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<(), ApiError> {
+    let (_, _user) = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Scopes::SESSION_ACCESS,
+    )
+    .await?;
+
+    let timeout = request.timeout_ms.map(Duration::from_millis);
+    server_ping::ping_bedrock_server(&request.address, timeout)
+        .await
+        .wrap_request_err("failed to ping server")?;
+
+    Ok(())
+}
@@ -7,7 +7,7 @@ use crate::models::pats::Scopes;
 use crate::models::users::User;
 use crate::queue::session::AuthQueue;
 use crate::queue::socket::{
-    ActiveSocket, ActiveSockets, SocketId, TunnelSocketType,
+    ActiveSocket, ActiveSockets, SocketId, TunnelSocket, TunnelSocketType,
 };
 use crate::routes::ApiError;
 use crate::sync::friends::{FRIENDS_CHANNEL_NAME, RedisFriendsMessage};
@@ -16,22 +16,27 @@ use crate::sync::status::{
 };
 use actix_web::web::{Data, Payload};
 use actix_web::{HttpRequest, HttpResponse, get, web};
-use actix_ws::Message;
+use actix_ws::{Message, Session};
 use ariadne::ids::UserId;
 use ariadne::networking::message::{
-    ClientToServerMessage, ServerToClientMessage,
+    ClientToServerMessage, ServerToClientMessage, SocketCloseReason,
 };
 use ariadne::users::UserStatus;
 use chrono::Utc;
+use dashmap::mapref::entry::Entry::Vacant;
 use either::Either;
 use futures_util::future::select;
 use futures_util::{StreamExt, TryStreamExt};
 use redis::AsyncCommands;
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::pin::pin;
-use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tokio::sync::mpsc::{self, UnboundedSender};
 use tokio::sync::oneshot::error::TryRecvError;
 use tokio::time::{Duration, sleep};
+use uuid::Uuid;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(ws_init);
@@ -82,6 +87,7 @@ pub async fn ws_init(
     let status = UserStatus {
         user_id: user.id,
         profile_name: None,
+        activity: None,
         last_update: Utc::now(),
     };
 
@@ -127,6 +133,54 @@ pub async fn ws_init(
         )?)
         .await;
 
+    // Tell the user about worlds their friends are already hosting
+    let friend_ids: HashSet<UserId> = friends
+        .iter()
+        .map(|x| {
+            UserId::from(if x.user_id == user_id.into() {
+                x.friend_id
+            } else {
+                x.user_id
+            })
+        })
+        .collect();
+    let listening_sockets = db
+        .tunnel_sockets
+        .iter()
+        .filter_map(|x| {
+            let TunnelSocketType::Listening {
+                allowed_users,
+                flow_control,
+            } = &x.socket_type
+            else {
+                return None;
+            };
+            if allowed_users
+                .as_ref()
+                .is_some_and(|allowed| !allowed.contains(&user_id))
+            {
+                return None;
+            }
+            let owner = db.sockets.get(&x.owner)?.status.user_id;
+            friend_ids.contains(&owner).then_some((
+                owner,
+                *x.key(),
+                *flow_control,
+            ))
+        })
+        .collect::<Vec<_>>();
+    for (owner, socket, flow_control) in listening_sockets {
+        let _ = session
+            .text(serde_json::to_string(
+                &ServerToClientMessage::FriendSocketListening {
+                    user: owner,
+                    socket,
+                    flow_control,
+                },
+            )?)
+            .await;
+    }
+
     let db = db.clone();
     let socket_id = db.next_socket_id.fetch_add(1, Ordering::Relaxed);
     db.sockets
@@ -209,21 +263,23 @@ pub async fn ws_init(
             }
 
             match message {
-                ClientToServerMessage::StatusUpdate { profile_name } => {
+                ClientToServerMessage::StatusUpdate {
+                    profile_name,
+                    activity,
+                } => {
+                    if profile_name.as_ref().is_some_and(|x| x.len() > 64)
+                        || activity.as_ref().is_some_and(|x| !x.is_valid())
+                    {
+                        continue;
+                    }
+
                     if let Some(mut pair) = db.sockets.get_mut(&socket_id) {
                         let ActiveSocket { status, .. } = pair.value_mut();
 
                         let old_status = status.clone();
 
-                        if status
-                            .profile_name
-                            .as_ref()
-                            .is_some_and(|x| x.len() > 64)
-                        {
-                            return;
-                        }
-
                         status.profile_name = profile_name;
+                        status.activity = activity;
                         status.last_update = Utc::now();
 
                         let user_status = status.clone();
@@ -246,35 +302,96 @@ pub async fn ws_init(
                     }
                 }
 
-                ClientToServerMessage::SocketListen { .. } => {
-                    // TODO: Listen to socket
-                    // The code below probably won't need changes, but there's no way to connect to
-                    // a tunnel socket yet, so we shouldn't be storing them
-
-                    // let Some(active_socket) = db.sockets.get(&socket_id) else {
-                    //     return;
-                    // };
-                    // let Vacant(entry) = db.tunnel_sockets.entry(socket) else {
-                    //     continue;
-                    // };
-                    // entry.insert(TunnelSocket::new(
-                    //     socket_id,
-                    //     TunnelSocketType::Listening,
-                    // ));
-                    // active_socket.owned_tunnel_sockets.insert(socket);
-                    // let _ = broadcast_friends(
-                    //     user.id,
-                    //     ServerToClientMessage::FriendSocketListening {
-                    //         user: user.id,
-                    //         socket,
-                    //     },
-                    //     &pool,
-                    //     &db,
-                    //     None,
-                    // )
-                    // .await;
+                ClientToServerMessage::SocketListen {
+                    socket,
+                    allowed_users,
+                    flow_control,
+                } => {
+                    let Some(active_socket) = db.sockets.get(&socket_id) else {
+                        return;
+                    };
+                    let Vacant(entry) = db.tunnel_sockets.entry(socket) else {
+                        continue;
+                    };
+                    let allowed_users: Option<HashSet<UserId>> =
+                        allowed_users.map(|x| x.into_iter().collect());
+                    entry.insert(TunnelSocket::new(
+                        socket_id,
+                        TunnelSocketType::Listening {
+                            allowed_users: allowed_users.clone(),
+                            flow_control,
+                        },
+                    ));
+                    active_socket.owned_tunnel_sockets.insert(socket);
+                    drop(active_socket);
+
+                    let friends = match DBFriend::get_user_friends(
+                        user.id.into(),
+                        Some(true),
+                        &*pool,
+                    )
+                    .await
+                    {
+                        Ok(friends) => friends,
+                        Err(_) => continue,
+                    };
+                    let _ = broadcast_to_known_local_friends(
+                        user.id,
+                        ServerToClientMessage::FriendSocketListening {
+                            user: user.id,
+                            socket,
+                            flow_control,
+                        },
+                        &db,
+                        friends
+                            .into_iter()
+                            .filter(|x| {
+                                let friend_id: UserId =
+                                    if x.user_id == user.id.into() {
+                                        x.friend_id
+                                    } else {
+                                        x.user_id
+                                    }
+                                    .into();
+                                allowed_users.as_ref().is_none_or(
+                                    |allowed: &HashSet<UserId>| {
+                                        allowed.contains(&friend_id)
+                                    },
+                                )
+                            })
+                            .collect(),
+                    )
+                    .await;
+                }
+                ClientToServerMessage::SocketConnect {
+                    to_socket,
+                    new_socket,
+                    flow_control,
+                } => {
+                    let connected = connect_tunnel_socket(
+                        socket_id,
+                        user.id,
+                        to_socket,
+                        new_socket,
+                        flow_control,
+                        &pool,
+                        &db,
+                    )
+                    .await;
+                    if !connected
+                        && let Some(active_socket) = db.sockets.get(&socket_id)
+                    {
+                        let _ = send_message(
+                            &active_socket,
+                            &ServerToClientMessage::SocketClosed {
+                                socket: new_socket,
+                                reason: Some(SocketCloseReason::Refused),
+                            },
+                        )
+                        .await;
+                    }
                 }
-                ClientToServerMessage::SocketClose { socket } => {
+                ClientToServerMessage::SocketClose { socket, reason } => {
                     let Some(active_socket) = db.sockets.get(&socket_id) else {
                         return;
                     };
@@ -291,46 +408,96 @@ pub async fn ws_init(
                         continue;
                     };
                     match tunnel_socket.socket_type {
-                        TunnelSocketType::Listening => {
+                        TunnelSocketType::Listening { .. } => {
                             let _ = broadcast_to_local_friends(
                                 user.id,
-                                ServerToClientMessage::FriendSocketStoppedListening { user: user.id },
+                                ServerToClientMessage::FriendSocketStoppedListening {
+                                    user: user.id,
+                                    socket: Some(socket),
+                                },
                                 &pool,
                                 &db,
                             )
                             .await;
                         }
-                        TunnelSocketType::Connected { connected_to } => {
-                            let Some((_, other)) =
-                                db.tunnel_sockets.remove(&connected_to)
-                            else {
-                                continue;
-                            };
-                            let Some(other_user) = db.sockets.get(&other.owner)
-                            else {
-                                continue;
-                            };
-                            let _ = send_message(
-                                &other_user,
-                                &ServerToClientMessage::SocketClosed { socket },
+                        TunnelSocketType::Connected {
+                            connected_to, ..
+                        } => {
+                            close_tunnel_peer(
+                                &db,
+                                connected_to,
+                                reason.unwrap_or(SocketCloseReason::Closed),
                             )
                             .await;
                         }
                     }
                 }
-                ClientToServerMessage::SocketSend { socket, data } => {
-                    let Some(tunnel_socket) = db.tunnel_sockets.get(&socket)
+                ClientToServerMessage::SocketAck { socket, bytes } => {
+                    let Some(connected_to) = db
+                        .tunnel_sockets
+                        .get(&socket)
+                        .and_then(|x| match x.socket_type {
+                            TunnelSocketType::Connected {
+                                connected_to,
+                                flow_control: true,
+                            } if x.owner == socket_id => Some(connected_to),
+                            _ => None,
+                        })
                     else {
                         continue;
                     };
-                    if tunnel_socket.owner != socket_id {
-                        continue;
-                    }
-                    let TunnelSocketType::Connected { connected_to } =
-                        tunnel_socket.socket_type
+                    let Some(other_owner) =
+                        db.tunnel_sockets.get(&connected_to).map(|other| {
+                            let _ = other.unacknowledged.fetch_update(
+                                Ordering::Relaxed,
+                                Ordering::Relaxed,
+                                |x| Some(x.saturating_sub(bytes)),
+                            );
+                            other.owner
+                        })
                     else {
                         continue;
                     };
+                    let Some(other_user) = db.sockets.get(&other_owner) else {
+                        continue;
+                    };
+                    let _ = send_message(
+                        &other_user,
+                        &ServerToClientMessage::SocketAck {
+                            socket: connected_to,
+                            bytes,
+                        },
+                    )
+                    .await;
+                }
+                ClientToServerMessage::SocketSend { socket, data } => {
+                    let relay = db.tunnel_sockets.get(&socket).and_then(|x| {
+                        let TunnelSocketType::Connected {
+                            connected_to, ..
+                        } = x.socket_type
+                        else {
+                            return None;
+                        };
+                        if x.owner != socket_id {
+                            return None;
+                        }
+                        Some((
+                            connected_to,
+                            x.relay.clone(),
+                            x.reserve(data.len()).err(),
+                        ))
+                    });
+                    let Some((connected_to, relay, violation)) = relay else {
+                        continue;
+                    };
+                    if let Some(reason) = violation {
+                        close_tunnel_socket(&db, socket, reason).await;
+                        continue;
+                    }
+                    if let Some(relay) = relay {
+                        let _ = relay.send(data);
+                        continue;
+                    }
                     let Some(other_tunnel) =
                         db.tunnel_sockets.get(&connected_to)
                     else {
@@ -359,6 +526,181 @@ pub async fn ws_init(
     Ok(res)
 }
 
+/// Connects a new tunnel socket owned by `socket_id` to a socket a friend is
+/// listening on. The friend is sent a new socket of their own for their end
+/// of the connection. Returns whether the connection was made
+async fn connect_tunnel_socket(
+    socket_id: SocketId,
+    user_id: UserId,
+    to_socket: Uuid,
+    new_socket: Uuid,
+    flow_control: bool,
+    pool: &PgPool,
+    db: &ActiveSockets,
+) -> bool {
+    let (listener_owner, flow_control) = {
+        let Some(listener) = db.tunnel_sockets.get(&to_socket) else {
+            return false;
+        };
+        let TunnelSocketType::Listening {
+            allowed_users,
+            flow_control: listener_flow_control,
+        } = &listener.socket_type
+        else {
+            return false;
+        };
+        if allowed_users
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(&user_id))
+        {
+            return false;
+        }
+        (listener.owner, flow_control && *listener_flow_control)
+    };
+    if listener_owner == socket_id
+        || db.tunnel_sockets.contains_key(&new_socket)
+    {
+        return false;
+    }
+
+    let Some(host_user) =
+        db.sockets.get(&listener_owner).map(|x| x.status.user_id)
+    else {
+        return false;
+    };
+    match DBFriend::get_friend(user_id.into(), host_user.into(), pool).await {
+        Ok(Some(friend)) if friend.accepted => {}
+        _ => return false,
+    }
+
+    let host_socket = Uuid::new_v4();
+    let (Some(own), Some(host)) =
+        (db.sockets.get(&socket_id), db.sockets.get(&listener_owner))
+    else {
+        return false;
+    };
+    let mut own_tunnel = TunnelSocket::new(
+        socket_id,
+        TunnelSocketType::Connected {
+            connected_to: host_socket,
+            flow_control,
+        },
+    );
+    let mut host_tunnel = TunnelSocket::new(
+        listener_owner,
+        TunnelSocketType::Connected {
+            connected_to: new_socket,
+            flow_control,
+        },
+    );
+    if !flow_control {
+        own_tunnel.relay = Some(spawn_relay(
+            host.socket.clone(),
+            host_socket,
+            own_tunnel.buffered.clone(),
+        ));
+        host_tunnel.relay = Some(spawn_relay(
+            own.socket.clone(),
+            new_socket,
+            host_tunnel.buffered.clone(),
+        ));
+    }
+    db.tunnel_sockets.insert(new_socket, own_tunnel);
+    db.tunnel_sockets.insert(host_socket, host_tunnel);
+    own.owned_tunnel_sockets.insert(new_socket);
+    host.owned_tunnel_sockets.insert(host_socket);
+    drop(own);
+
+    let _ = send_message(
+        &host,
+        &ServerToClientMessage::SocketConnected {
+            to_socket,
+            new_socket: host_socket,
+            flow_control,
+        },
+    )
+    .await;
+
+    true
+}
+
+/// Relays data sent without flow control to `session` in order, so a slow
+/// receiver doesn't hold up the sender. The relay stops once the sending
+/// socket is closed and its queue is drained
+fn spawn_relay(
+    session: Session,
+    socket: Uuid,
+    buffered: Arc<AtomicU32>,
+) -> UnboundedSender<Vec<u8>> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(data) = receiver.recv().await {
+            let len = data.len() as u32;
+            let _ = send_to_session(
+                session.clone(),
+                &ServerToClientMessage::SocketData { socket, data },
+            )
+            .await;
+            buffered.fetch_sub(len, Ordering::Relaxed);
+        }
+    });
+    sender
+}
+
+/// Closes a connection on behalf of the relay, telling both ends why
+async fn close_tunnel_socket(
+    db: &ActiveSockets,
+    socket: Uuid,
+    reason: SocketCloseReason,
+) {
+    let Some((_, tunnel_socket)) =
+        db.tunnel_sockets.remove_if(&socket, |_, x| {
+            matches!(x.socket_type, TunnelSocketType::Connected { .. })
+        })
+    else {
+        return;
+    };
+    if let Some(owner) = db.sockets.get(&tunnel_socket.owner) {
+        owner.owned_tunnel_sockets.remove(&socket);
+        let _ = send_message(
+            &owner,
+            &ServerToClientMessage::SocketClosed {
+                socket,
+                reason: Some(reason),
+            },
+        )
+        .await;
+    }
+    if let TunnelSocketType::Connected { connected_to, .. } =
+        tunnel_socket.socket_type
+    {
+        close_tunnel_peer(db, connected_to, reason).await;
+    }
+}
+
+/// Closes the other end of a connection after one end was closed
+async fn close_tunnel_peer(
+    db: &ActiveSockets,
+    connected_to: Uuid,
+    reason: SocketCloseReason,
+) {
+    let Some((_, other)) = db.tunnel_sockets.remove(&connected_to) else {
+        return;
+    };
+    let Some(other_user) = db.sockets.get(&other.owner) else {
+        return;
+    };
+    other_user.owned_tunnel_sockets.remove(&connected_to);
+    let _ = send_message(
+        &other_user,
+        &ServerToClientMessage::SocketClosed {
+            socket: connected_to,
+            reason: Some(reason),
+        },
+    )
+    .await;
+}
+
 pub async fn broadcast_friends_message(
     redis: &RedisPool,
     message: RedisFriendsMessage,
@@ -421,8 +763,13 @@ pub async fn send_message(
     socket: &ActiveSocket,
     message: &ServerToClientMessage,
 ) -> Result<(), crate::database::models::DatabaseError> {
-    let mut socket = socket.socket.clone();
+    send_to_session(socket.socket.clone(), message).await
+}
 
+async fn send_to_session(
+    mut socket: Session,
+    message: &ServerToClientMessage,
+) -> Result<(), crate::database::models::DatabaseError> {
     // FIXME Probably shouldn't swallow sending errors
     let _ = match message.serialize() {
         Ok(Either::Left(text)) => socket.text(text).await,
@@ -478,31 +825,23 @@ pub async fn close_socket(
                 continue;
             };
             match tunnel_socket.socket_type {
-                TunnelSocketType::Listening => {
+                TunnelSocketType::Listening { .. } => {
                     let _ = broadcast_to_local_friends(
                         user_id,
-                        ServerToClientMessage::SocketClosed {
-                            socket: owned_socket,
+                        ServerToClientMessage::FriendSocketStoppedListening {
+                            user: user_id,
+                            socket: Some(owned_socket),
                         },
                         pool,
                         db,
                     )
                     .await;
                 }
-                TunnelSocketType::Connected { connected_to } => {
-                    let Some((_, other)) =
-                        db.tunnel_sockets.remove(&connected_to)
-                    else {
-                        continue;
-                    };
-                    let Some(other_user) = db.sockets.get(&other.owner) else {
-                        continue;
-                    };
-                    let _ = send_message(
-                        &other_user,
-                        &ServerToClientMessage::SocketClosed {
-                            socket: connected_to,
-                        },
+                TunnelSocketType::Connected { connected_to, .. } => {
+                    close_tunnel_peer(
+                        db,
+                        connected_to,
+                        SocketCloseReason::Disconnected,
                     )
                     .await;
                 }
@@ -85,6 +85,61 @@ pub struct TimeRange {
     pub resolution: TimeRangeResolution,
 }
 
+impl TimeRange {
+    /// Gets how many time slices this range is split into, checking that it
+    /// falls within [`MIN_RESOLUTION`] and [`MAX_TIME_SLICES`].
+    pub fn num_time_slices(&self) -> Result<usize, ApiError> {
+        let full_time_range = self.end - self.start;
+        if full_time_range < TimeDelta::zero() {
+            return Err(ApiError::InvalidInput(
+                "End date must be after start date".into(),
+            ));
+        }
+
+        let (num_time_slices, resolution) = match self.resolution {
+            TimeRangeResolution::Slices(slices) => {
+                let slices = i32::try_from(slices.get()).map_err(|_| {
+                    ApiError::InvalidInput(
+                        "Number of slices must fit into an `i32`".into(),
+                    )
+                })?;
+                let resolution = full_time_range / slices;
+                (slices as usize, resolution)
+            }
+            TimeRangeResolution::Minutes(resolution_minutes) => {
+                let resolution_minutes =
+                    i64::try_from(resolution_minutes.get()).map_err(|_| {
+                        ApiError::InvalidInput(
+                            "Resolution must fit into a `i64`".into(),
+                        )
+                    })?;
+                let resolution = TimeDelta::try_minutes(resolution_minutes)
+                    .ok_or_else(|| {
+                        ApiError::InvalidInput("Resolution overflow".into())
+                    })?;
+
+                let num_slices = full_time_range.as_seconds_f64()
+                    / resolution.as_seconds_f64();
+
+                (num_slices as usize, resolution)
+            }
+        };
+
+        if num_time_slices > MAX_TIME_SLICES {
+            return Err(ApiError::Request(eyre!(
+                "Resolution is too fine or range is too large - maximum of {MAX_TIME_SLICES} time slices, was {num_time_slices}"
+            )));
+        }
+        if resolution < MIN_RESOLUTION {
+            return Err(ApiError::Request(eyre!(
+                "Resolution must be at least {MIN_RESOLUTION}, was {resolution}",
+            )));
+        }
+
+        Ok(num_time_slices)
+    }
+}
+
 /// Determines how many time slices between the start and end will be
 /// included, and how fine-grained those time slices will be.
 #[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
@@ -719,53 +774,7 @@ pub async fn fetch_analytics(
     )
     .await?;
 
-    let full_time_range = req.time_range.end - req.time_range.start;
-    if full_time_range < TimeDelta::zero() {
-        return Err(ApiError::InvalidInput(
-            "End date must be after start date".into(),
-        ));
-    }
-
-    let (num_time_slices, resolution) = match req.time_range.resolution {
-        TimeRangeResolution::Slices(slices) => {
-            let slices = i32::try_from(slices.get()).map_err(|_| {
-                ApiError::InvalidInput(
-                    "Number of slices must fit into an `i32`".into(),
-                )
-            })?;
-            let resolution = full_time_range / slices;
-            (slices as usize, resolution)
-        }
-        TimeRangeResolution::Minutes(resolution_minutes) => {
-            let resolution_minutes = i64::try_from(resolution_minutes.get())
-                .map_err(|_| {
-                    ApiError::InvalidInput(
-                        "Resolution must fit into a `i64`".into(),
-                    )
-                })?;
-            let resolution = TimeDelta::try_minutes(resolution_minutes)
-                .ok_or_else(|| {
-                    ApiError::InvalidInput("Resolution overflow".into())
-                })?;
-
-            let num_slices =
-                full_time_range.as_seconds_f64() / resolution.as_seconds_f64();
-
-            (num_slices as usize, resolution)
-        }
-    };
-
-    if num_time_slices > MAX_TIME_SLICES {
-        return Err(ApiError::Request(eyre!(
-            "Resolution is too fine or range is too large - maximum of {MAX_TIME_SLICES} time slices, was {num_time_slices}"
-        )));
-    }
-    if resolution < MIN_RESOLUTION {
-        return Err(ApiError::Request(eyre!(
-            "Resolution must be at least {MIN_RESOLUTION}, was {resolution}",
-        )));
-    }
-
+    let num_time_slices = req.time_range.num_time_slices()?;
     let mut time_slices = vec![TimeSlice::default(); num_time_slices];
 
     let project_ids = {
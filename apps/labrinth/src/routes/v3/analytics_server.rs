@@ -0,0 +1,337 @@
+//! Uptime and player count history of Minecraft server projects, built from
+//! the pings recorded by [`crate::queue::server_ping`].
+//!
+//! Unlike the rest of analytics, this is public for any project the user can
+//! see, so players can check how reliable a server is before joining.
+
+use actix_web::{HttpRequest, post, web};
+use serde::{Deserialize, Serialize};
+
+use super::analytics_get::TimeRange;
+use crate::{
+    auth::{checks::filter_visible_project_ids, get_user_from_headers},
+    database::{DBProject, PgPool, models::DBProjectId, redis::RedisPool},
+    models::{ids::ProjectId, pats::Scopes},
+    queue::session::AuthQueue,
+    routes::ApiError,
+};
+
+pub fn config(cfg: &mut utoipa_actix_web::service_config::ServiceConfig) {
+    cfg.service(fetch_server_analytics);
+}
+
+/// Maximum number of projects in a [`ServerGetRequest`].
+pub const MAX_SERVER_PROJECTS: usize = 32;
+
+// request
+
+/// Requests the ping history of server projects, returning the data in a
+/// list of time slices.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerGetRequest {
+    /// What time range to return history for.
+    pub time_range: TimeRange,
+    /// What server project IDs to return history for.
+    ///
+    /// This must contain between 1 and [`MAX_SERVER_PROJECTS`] projects.
+    pub project_ids: Vec<ProjectId>,
+}
+
+// response
+
+/// Response for a [`ServerGetRequest`].
+#[derive(Debug, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerGetResponse {
+    /// List of N [`ServerTimeSlice`]s, where each slice represents an equal
+    /// time interval. The number of slices is determined by
+    /// [`ServerGetRequest::time_range`].
+    pub metrics: Vec<ServerTimeSlice>,
+}
+
+/// Single time interval of server pings.
+///
+/// Projects which weren't pinged during this interval are not included.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerTimeSlice(pub Vec<ServerAnalytics>);
+
+/// Which edition of Minecraft a server was pinged as.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerEdition {
+    Java,
+    Bedrock,
+}
+
+/// Ping history of a single server in a [`ServerTimeSlice`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerAnalytics {
+    /// What project these metrics are for.
+    pub source_project: ProjectId,
+    /// Which server of the project was pinged.
+    pub edition: ServerEdition,
+    /// Number of times the server was pinged.
+    pub pings: u64,
+    /// Percentage of pings which the server answered, from 0 to 100.
+    pub uptime: f64,
+    /// Latency percentiles of answered pings.
+    ///
+    /// [`None`] if no pings were answered.
+    pub latency_ms: Option<LatencyPercentiles>,
+    /// Average number of players online across answered pings.
+    pub players_online_avg: Option<f64>,
+    /// Highest number of players online across answered pings.
+    pub players_online_peak: Option<i32>,
+    /// Highest maximum player count the server reported.
+    pub players_max: Option<i32>,
+}
+
+/// Latency percentiles in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct LatencyPercentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+// logic
+
+/// Clickhouse queries - separate from [`sqlx`] queries.
+mod query {
+    use crate::database::models::DBProjectId;
+    use crate::queue::server_ping::{
+        BEDROCK_CLICKHOUSE_TABLE, CLICKHOUSE_TABLE,
+    };
+    use const_format::formatcp;
+
+    const TIME_RANGE_START: &str = "{time_range_start: UInt64}";
+    const TIME_RANGE_END: &str = "{time_range_end: UInt64}";
+    const TIME_SLICES: &str = "{time_slices: UInt64}";
+    const PROJECT_IDS: &str = "{project_ids: Array(UInt64)}";
+
+    #[derive(Debug, clickhouse::Row, serde::Deserialize)]
+    pub struct ServerPingRow {
+        pub bucket: u64,
+        pub project_id: DBProjectId,
+        pub edition: String,
+        pub pings: u64,
+        pub successful_pings: u64,
+        pub latency_p50: Option<f64>,
+        pub latency_p90: Option<f64>,
+        pub latency_p99: Option<f64>,
+        pub players_online_avg: Option<f64>,
+        pub players_online_peak: Option<i32>,
+        pub players_max: Option<i32>,
+    }
+
+    // a ping was answered if it has a latency - aggregate functions skip
+    // nulls, so the latency and player aggregates only cover answered pings
+    pub const SERVER_PINGS: &str = formatcp!(
+        "SELECT
+            bucket,
+            project_id,
+            edition,
+            COUNT(*) AS pings,
+            COUNT(latency_ms) AS successful_pings,
+            quantile(0.5)(latency_ms) AS latency_p50,
+            quantile(0.9)(latency_ms) AS latency_p90,
+            quantile(0.99)(latency_ms) AS latency_p99,
+            avg(players_online) AS players_online_avg,
+            max(players_online) AS players_online_peak,
+            max(players_max) AS players_max
+        FROM (
+            SELECT
+                widthBucket(toUnixTimestamp(recorded), {TIME_RANGE_START}, {TIME_RANGE_END}, {TIME_SLICES}) AS bucket,
+                project_id,
+                'java' AS edition,
+                latency_ms,
+                players_online,
+                players_max
+            FROM {CLICKHOUSE_TABLE}
+            WHERE
+                toUnixTimestamp(recorded) BETWEEN {TIME_RANGE_START} AND {TIME_RANGE_END}
+                AND project_id IN {PROJECT_IDS}
+
+            UNION ALL
+
+            SELECT
+                widthBucket(toUnixTimestamp(recorded), {TIME_RANGE_START}, {TIME_RANGE_END}, {TIME_SLICES}) AS bucket,
+                project_id,
+                'bedrock' AS edition,
+                latency_ms,
+                players_online,
+                players_max
+            FROM {BEDROCK_CLICKHOUSE_TABLE}
+            WHERE
+                toUnixTimestamp(recorded) BETWEEN {TIME_RANGE_START} AND {TIME_RANGE_END}
+                AND project_id IN {PROJECT_IDS}
+        )
+        GROUP BY bucket, project_id, edition"
+    );
+}
+
+/// Fetches the uptime, latency and player count history of server projects.
+#[utoipa::path(
+    responses((status = OK, body = inline(ServerGetResponse))),
+)]
+#[post("/server")]
+pub async fn fetch_server_analytics(
+    http_req: HttpRequest,
+    req: web::Json<ServerGetRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    clickhouse: web::Data<clickhouse::Client>,
+) -> Result<web::Json<ServerGetResponse>, ApiError> {
+    let user_option = get_user_from_headers(
+        &http_req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Scopes::PROJECT_READ,
+    )
+    .await
+    .map(|(_, user)| user)
+    .ok();
+
+    if req.project_ids.is_empty() || req.project_ids.len() > MAX_SERVER_PROJECTS
+    {
+        return Err(ApiError::InvalidInput(format!(
+            "Must request between 1 and {MAX_SERVER_PROJECTS} projects"
+        )));
+    }
+
+    let num_time_slices = req.time_range.num_time_slices()?;
+    let mut time_slices = vec![ServerTimeSlice::default(); num_time_slices];
+
+    let project_ids = req
+        .project_ids
+        .iter()
+        .map(|id| DBProjectId::from(*id))
+        .collect::<Vec<_>>();
+    let projects = DBProject::get_many_ids(&project_ids, &**pool, &redis)
+        .await?
+        .into_iter()
+        .filter(|project| {
+            project.inner.components.minecraft_java_server.is_some()
+                || project.inner.components.minecraft_bedrock_server.is_some()
+        })
+        .collect::<Vec<_>>();
+    let project_ids = filter_visible_project_ids(
+        projects.iter().map(|project| &project.inner).collect(),
+        &user_option,
+        &pool,
+        false,
+    )
+    .await?;
+
+    let mut cursor = clickhouse
+        .query(query::SERVER_PINGS)
+        .param("time_range_start", req.time_range.start.timestamp())
+        .param("time_range_end", req.time_range.end.timestamp())
+        .param("time_slices", num_time_slices)
+        .param("project_ids", &project_ids)
+        .fetch::<query::ServerPingRow>()?;
+
+    while let Some(row) = cursor.next().await? {
+        // row.recorded <  time_range_start => bucket = 0
+        // row.recorded >= time_range_end   => bucket = num_time_slices + 1
+        let Some(slice) = (row.bucket as usize)
+            .checked_sub(1)
+            .and_then(|bucket| time_slices.get_mut(bucket))
+        else {
+            continue;
+        };
+
+        if let Some(analytics) = row_to_analytics(row) {
+            slice.0.push(analytics);
+        }
+    }
+
+    Ok(web::Json(ServerGetResponse {
+        metrics: time_slices,
+    }))
+}
+
+fn row_to_analytics(row: query::ServerPingRow) -> Option<ServerAnalytics> {
+    let edition = match row.edition.as_str() {
+        "java" => ServerEdition::Java,
+        "bedrock" => ServerEdition::Bedrock,
+        _ => return None,
+    };
+    if row.pings == 0 {
+        return None;
+    }
+
+    let latency_ms = match (row.latency_p50, row.latency_p90, row.latency_p99) {
+        (Some(p50), Some(p90), Some(p99)) if row.successful_pings > 0 => {
+            Some(LatencyPercentiles { p50, p90, p99 })
+        }
+        _ => None,
+    };
+
+    Some(ServerAnalytics {
+        source_project: row.project_id.into(),
+        edition,
+        pings: row.pings,
+        uptime: row.successful_pings as f64 / row.pings as f64 * 100.0,
+        latency_ms,
+        players_online_avg: row.players_online_avg,
+        players_online_peak: row.players_online_peak,
+        players_max: row.players_max,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(pings: u64, successful_pings: u64) -> query::ServerPingRow {
+        let answered = successful_pings > 0;
+        query::ServerPingRow {
+            bucket: 1,
+            project_id: DBProjectId(123),
+            edition: "java".to_string(),
+            pings,
+            successful_pings,
+            latency_p50: answered.then_some(20.0),
+            latency_p90: answered.then_some(45.0),
+            latency_p99: answered.then_some(80.0),
+            players_online_avg: answered.then_some(12.5),
+            players_online_peak: answered.then_some(20),
+            players_max: answered.then_some(100),
+        }
+    }
+
+    #[test]
+    fn computes_uptime_percentage() {
+        let analytics = row_to_analytics(row(4, 3)).unwrap();
+        assert_eq!(analytics.edition, ServerEdition::Java);
+        assert_eq!(analytics.uptime, 75.0);
+        assert_eq!(
+            analytics.latency_ms,
+            Some(LatencyPercentiles {
+                p50: 20.0,
+                p90: 45.0,
+                p99: 80.0,
+            })
+        );
+        assert_eq!(analytics.players_online_peak, Some(20));
+    }
+
+    #[test]
+    fn offline_server_has_no_latency() {
+        let analytics = row_to_analytics(row(2, 0)).unwrap();
+        assert_eq!(analytics.uptime, 0.0);
+        assert_eq!(analytics.latency_ms, None);
+        assert_eq!(analytics.players_online_avg, None);
+    }
+
+    #[test]
+    fn skips_unknown_editions() {
+        let mut unknown = row(1, 1);
+        unknown.edition = "pocket".to_string();
+        assert_eq!(row_to_analytics(unknown), None);
+    }
+}
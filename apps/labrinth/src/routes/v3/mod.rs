@@ -5,6 +5,7 @@ use serde_json::json;
 
 pub mod analytics_event;
 pub mod analytics_get;
+pub mod analytics_server;
 pub mod collections;
 pub mod friends;
 pub mod images;
@@ -15,6 +16,7 @@ pub mod payouts;
 pub mod project_creation;
 pub mod projects;
 pub mod reports;
+pub mod server_verification;
 pub mod shared_instance_version_creation;
 pub mod shared_instances;
 pub mod statistics;
@@ -58,7 +60,8 @@ pub fn utoipa_config(
     cfg.service(
         utoipa_actix_web::scope("/v3/analytics")
             .wrap(default_cors())
-            .configure(analytics_get::config),
+            .configure(analytics_get::config)
+            .configure(analytics_server::config),
     );
     cfg.service(
         utoipa_actix_web::scope("/v3/analytics-event")
@@ -74,7 +77,8 @@ pub fn utoipa_config(
         utoipa_actix_web::scope("/v3/project")
             .wrap(default_cors())
             .configure(projects::utoipa_config)
-            .configure(project_creation::config),
+            .configure(project_creation::config)
+            .configure(server_verification::config),
     );
 }
 
@@ -0,0 +1,222 @@
+use actix_web::{HttpRequest, get, post, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::get_user_from_headers;
+use crate::database::models::minecraft_server_verification_item::DBMinecraftServerVerification;
+use crate::database::models::{self as db_models, DBProjectId};
+use crate::database::{PgPool, redis::RedisPool};
+use crate::models::exp::minecraft::ServerVerificationMethod;
+use crate::models::pats::Scopes;
+use crate::models::teams::ProjectPermissions;
+use crate::queue::server_verification;
+use crate::queue::session::AuthQueue;
+use crate::routes::ApiError;
+use crate::util::error::Context;
+
+pub fn config(cfg: &mut utoipa_actix_web::service_config::ServiceConfig) {
+    cfg.service(server_verification_get)
+        .service(server_verification_create)
+        .service(server_verification_check);
+}
+
+/// Ownership verification of a Minecraft server project's address.
+///
+/// To verify, the owners put `token` in their server's MOTD, or in a DNS TXT
+/// record on the host of `address`, then request a check.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ServerVerificationStatus {
+    /// Address the token was issued for.
+    pub address: String,
+    pub token: String,
+    /// When the address was first verified, if it's currently verified.
+    pub verified: Option<DateTime<Utc>>,
+    /// How the owners proved they control the address, if it's currently
+    /// verified.
+    pub method: Option<ServerVerificationMethod>,
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+impl From<DBMinecraftServerVerification> for ServerVerificationStatus {
+    fn from(verification: DBMinecraftServerVerification) -> Self {
+        Self {
+            address: verification.address,
+            token: verification.token,
+            verified: verification.verified,
+            method: verification.method,
+            last_checked: verification.last_checked,
+        }
+    }
+}
+
+/// Get the ownership verification of a server project.
+#[utoipa::path(responses((status = OK, body = ServerVerificationStatus)))]
+#[get("/{id}/server-verification")]
+async fn server_verification_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<web::Json<ServerVerificationStatus>, ApiError> {
+    let (project_id, _) =
+        get_server_project(&req, &info.0, &pool, &redis, &session_queue)
+            .await?;
+
+    let verification = DBMinecraftServerVerification::get(project_id, &**pool)
+        .await
+        .wrap_internal_err("failed to fetch verification")?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(web::Json(verification.into()))
+}
+
+/// Issue a new verification token for a server project's current address.
+///
+/// This replaces any previous token, and removes any existing verification.
+#[utoipa::path(responses((status = OK, body = ServerVerificationStatus)))]
+#[post("/{id}/server-verification")]
+async fn server_verification_create(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<web::Json<ServerVerificationStatus>, ApiError> {
+    let (project_id, address) =
+        get_server_project(&req, &info.0, &pool, &redis, &session_queue)
+            .await?;
+
+    let verification = DBMinecraftServerVerification::new(project_id, address);
+    verification
+        .upsert(&**pool)
+        .await
+        .wrap_internal_err("failed to insert verification")?;
+    server_verification::cache_verification(&verification, &redis)
+        .await
+        .wrap_internal_err("failed to clear cached verification")?;
+
+    Ok(web::Json(verification.into()))
+}
+
+/// Check for a server project's verification token now, instead of waiting
+/// for the next periodic check.
+#[utoipa::path(responses((status = OK, body = ServerVerificationStatus)))]
+#[post("/{id}/server-verification/check")]
+async fn server_verification_check(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<web::Json<ServerVerificationStatus>, ApiError> {
+    let (project_id, address) =
+        get_server_project(&req, &info.0, &pool, &redis, &session_queue)
+            .await?;
+
+    let mut verification =
+        DBMinecraftServerVerification::get(project_id, &**pool)
+            .await
+            .wrap_internal_err("failed to fetch verification")?
+            .ok_or_else(|| {
+                ApiError::InvalidInput(
+                    "No verification token has been issued for this project!"
+                        .to_string(),
+                )
+            })?;
+
+    if !verification.address.eq_ignore_ascii_case(&address) {
+        return Err(ApiError::InvalidInput(
+            "The server's address has changed since the verification token was issued!"
+                .to_string(),
+        ));
+    }
+
+    let method =
+        server_verification::find_token(&address, &verification.token).await;
+    let changed = server_verification::apply_check(&mut verification, method);
+    verification
+        .upsert(&**pool)
+        .await
+        .wrap_internal_err("failed to update verification")?;
+    if changed {
+        server_verification::cache_verification(&verification, &redis)
+            .await
+            .wrap_internal_err("failed to cache verification")?;
+    }
+
+    Ok(web::Json(verification.into()))
+}
+
+/// Gets the ID and Java server address of the project `id`, if the user can
+/// edit its details.
+async fn get_server_project(
+    req: &HttpRequest,
+    id: &str,
+    pool: &PgPool,
+    redis: &RedisPool,
+    session_queue: &AuthQueue,
+) -> Result<(DBProjectId, String), ApiError> {
+    let user = get_user_from_headers(
+        req,
+        pool,
+        redis,
+        session_queue,
+        Scopes::PROJECT_WRITE,
+    )
+    .await?
+    .1;
+
+    let project_item = db_models::DBProject::get(id, pool, redis)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput(
+                "The specified project does not exist!".to_string(),
+            )
+        })?;
+
+    if !user.role.is_mod() {
+        let (team_member, organization_team_member) =
+            db_models::DBTeamMember::get_for_project_permissions(
+                &project_item.inner,
+                user.id.into(),
+                pool,
+            )
+            .await?;
+
+        // Hide the project
+        if team_member.is_none() && organization_team_member.is_none() {
+            return Err(ApiError::CustomAuthentication(
+                "The specified project does not exist!".to_string(),
+            ));
+        }
+
+        let permissions = ProjectPermissions::get_permissions_by_role(
+            &user.role,
+            &team_member,
+            &organization_team_member,
+        )
+        .unwrap_or_default();
+
+        if !permissions.contains(ProjectPermissions::EDIT_DETAILS) {
+            return Err(ApiError::CustomAuthentication(
+                "You don't have permission to verify this server.".to_string(),
+            ));
+        }
+    }
+
+    let address = project_item
+        .inner
+        .components
+        .minecraft_java_server
+        .as_ref()
+        .map(|server| server.address.clone())
+        .ok_or_else(|| {
+            ApiError::InvalidInput(
+                "The specified project is not a Minecraft Java server!"
+                    .to_string(),
+            )
+        })?;
+
+    Ok((project_item.inner.id, address))
+}
@@ -674,17 +674,52 @@ export namespace Labrinth {
 				verified_plays_4w?: number | null
 				verified_plays_2w?: number | null
 				ping: Projects.v3.MinecraftJavaServerPing | null
+				verification?: Projects.v3.ServerVerification | null
+			}
+
+			export type ServerVerificationMethod = 'motd' | 'dns'
+
+			export interface ServerVerification {
+				address: string
+				verified: string
+				method: ServerVerificationMethod
+			}
+
+			export interface ServerVerificationStatus {
+				address: string
+				token: string
+				verified: string | null
+				method: ServerVerificationMethod | null
+				last_checked: string | null
+			}
+
+			export interface MinecraftBedrockServerPing {
+				address: string
+				data?: {
+					description: string
+					game_mode?: string | null
+					latency: {
+						nanos: number
+						secs: number
+					}
+					players_max: number
+					players_online: number
+					version_name: string
+					version_protocol: number
+				}
+				when: string
 			}
 
 			export interface MinecraftBedrockServer {
 				address?: string
+				ping?: Projects.v3.MinecraftBedrockServerPing | null
 			}
 
 			export interface CreateServerProjectRequest {
 				base: CreateProjectBase
 				minecraft_server?: MinecraftServer
-				minecraft_java_server?: Omit<MinecraftJavaServer, 'ping'>
-				minecraft_bedrock_server?: MinecraftBedrockServer
+				minecraft_java_server?: Omit<MinecraftJavaServer, 'ping' | 'verification'>
+				minecraft_bedrock_server?: Omit<MinecraftBedrockServer, 'ping'>
 			}
 
 			export type EditProjectRequest = {
@@ -16,6 +16,8 @@ fn main() {
 }
 
 fn set_env() {
+    let mut meta_keys = None;
+    let mut meta_unsigned = None;
     for (var_name, var_value) in
         dotenvy::dotenv_iter().into_iter().flatten().flatten()
     {
@@ -23,9 +25,42 @@ fn set_env() {
             // The sqlx database URL is a build-time detail that should not be exposed to the crate
             continue;
         }
+        if var_name == "MODRINTH_LAUNCHER_META_KEYS" {
+            meta_keys = Some(var_value.clone());
+        }
+        if var_name == "MODRINTH_LAUNCHER_META_UNSIGNED" {
+            meta_unsigned = Some(var_value.clone());
+        }
 
         println!("cargo::rustc-env={var_name}={var_value}");
     }
+
+    // Without trusted keys, the app can't verify any metadata from the
+    // default metadata server, so it must be explicitly allowed unsigned
+    println!("cargo::rerun-if-env-changed=MODRINTH_LAUNCHER_META_KEYS");
+    println!("cargo::rerun-if-env-changed=MODRINTH_LAUNCHER_META_UNSIGNED");
+    let meta_keys =
+        meta_keys.or_else(|| env::var("MODRINTH_LAUNCHER_META_KEYS").ok());
+    let meta_unsigned = meta_unsigned.or_else(|| {
+        let value = env::var("MODRINTH_LAUNCHER_META_UNSIGNED").ok()?;
+        println!("cargo::rustc-env=MODRINTH_LAUNCHER_META_UNSIGNED={value}");
+        Some(value)
+    });
+    if let Err(err) = daedalus::signing::TrustedKey::parse_list(
+        meta_keys.as_deref().unwrap_or(""),
+    ) {
+        println!("cargo::error=MODRINTH_LAUNCHER_META_KEYS is invalid: {err}");
+        exit(1);
+    }
+    if env::var("PROFILE").as_deref() == Ok("release")
+        && meta_keys.as_deref().is_none_or(|x| x.trim().is_empty())
+        && meta_unsigned.as_deref() != Some("true")
+    {
+        println!(
+            "cargo::error=MODRINTH_LAUNCHER_META_KEYS must be set for release builds, unless MODRINTH_LAUNCHER_META_UNSIGNED is true"
+        );
+        exit(1);
+    }
 }
 
 fn build_java_jars() {
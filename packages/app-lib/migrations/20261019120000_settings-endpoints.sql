@@ -0,0 +1 @@
+ALTER TABLE settings ADD COLUMN endpoints JSONB NULL;
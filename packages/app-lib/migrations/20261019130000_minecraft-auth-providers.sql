@@ -0,0 +1 @@
+ALTER TABLE minecraft_users ADD COLUMN auth_provider JSONB NULL;
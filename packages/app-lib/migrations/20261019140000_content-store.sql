@@ -0,0 +1,10 @@
+ALTER TABLE settings ADD COLUMN shared_content_store INTEGER NOT NULL DEFAULT FALSE;
+
+CREATE TABLE content_store_refs (
+    profile_path TEXT NOT NULL,
+    path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+
+    PRIMARY KEY (profile_path, path)
+);
+CREATE INDEX content_store_refs_hash ON content_store_refs(hash);
@@ -0,0 +1 @@
+ALTER TABLE settings ADD COLUMN status_privacy JSONB NULL;
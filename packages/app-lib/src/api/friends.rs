@@ -1,5 +1,13 @@
-use crate::state::{FriendsSocket, UserFriend};
-use ariadne::users::UserStatus;
+use crate::ErrorKind;
+use crate::profile::QuickPlayType;
+use crate::server_address::ServerAddress;
+use crate::state::{
+    FriendTunnel, FriendsSocket, ProcessMetadata, Profile, TunnelInfo,
+    UserFriend,
+};
+use ariadne::ids::UserId;
+use ariadne::users::{UserActivity, UserStatus};
+use uuid::Uuid;
 
 #[tracing::instrument]
 pub async fn friends() -> crate::Result<Vec<UserFriend>> {
@@ -34,3 +42,196 @@ pub async fn remove_friend(user_id: &str) -> crate::Result<()> {
 
     Ok(())
 }
+
+/// Hosts a world that is open to LAN on `port` for friends, relayed through
+/// the friends socket. If `allowed_users` is set, only those friends can join
+#[tracing::instrument]
+pub async fn host_world(
+    port: u16,
+    allowed_users: Option<Vec<UserId>>,
+) -> crate::Result<TunnelInfo> {
+    let state = crate::State::get().await?;
+    state.friends_socket.host_world(port, allowed_users).await
+}
+
+/// Joins a world a friend is hosting. The game should connect to the local
+/// address of the returned tunnel
+#[tracing::instrument]
+pub async fn join_world(socket: Uuid) -> crate::Result<TunnelInfo> {
+    let state = crate::State::get().await?;
+    state.friends_socket.join_world(socket).await
+}
+
+#[tracing::instrument]
+pub async fn close_tunnel(id: Uuid) -> crate::Result<()> {
+    let state = crate::State::get().await?;
+    state.friends_socket.close_tunnel(id).await
+}
+
+/// Gets the worlds this user is hosting or has joined, with their
+/// connections and the traffic relayed through each
+pub async fn tunnels() -> crate::Result<Vec<TunnelInfo>> {
+    let state = crate::State::get().await?;
+    Ok(state.friends_socket.tunnels().await)
+}
+
+/// Gets the worlds friends are hosting that this user can join
+pub async fn friend_tunnels() -> crate::Result<Vec<FriendTunnel>> {
+    let state = crate::State::get().await?;
+    Ok(state.friends_socket.friend_tunnels())
+}
+
+/// Joins the server a friend is playing on with quick play. The instance used
+/// is one installed from the same project as the friend's, preferring the
+/// same version, or else one on the same game version and loader
+#[tracing::instrument]
+pub async fn join_friend_server(
+    user: UserId,
+) -> crate::Result<ProcessMetadata> {
+    let state = crate::State::get().await?;
+    let activity = state
+        .friends_socket
+        .friend_statuses()
+        .into_iter()
+        .find(|x| x.user_id == user)
+        .and_then(|x| x.activity);
+    let Some((activity, server)) =
+        activity.and_then(|x| x.server.clone().map(|server| (x, server)))
+    else {
+        return Err(ErrorKind::InputError(
+            "This friend isn't sharing a server they are playing on"
+                .to_string(),
+        )
+        .into());
+    };
+
+    let profile = Profile::get_all(&state.pool)
+        .await?
+        .into_iter()
+        .filter_map(|x| instance_match(&x, &activity).map(|score| (score, x)))
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, x)| x)
+        .ok_or_else(|| {
+            ErrorKind::InputError(
+                "No instance matches the one your friend is playing"
+                    .to_string(),
+            )
+        })?;
+
+    crate::profile::run(
+        &profile.path,
+        QuickPlayType::Server(ServerAddress::Unresolved(server)),
+    )
+    .await
+}
+
+/// How closely an instance matches what a friend is playing, if it can be
+/// used to join them at all
+fn instance_match(profile: &Profile, activity: &UserActivity) -> Option<u8> {
+    if let Some(project_id) = &activity.project_id {
+        let linked = profile.linked_data.as_ref()?;
+        if &linked.project_id != project_id {
+            return None;
+        }
+
+        return Some(
+            if activity.version_id.as_ref() == Some(&linked.version_id) {
+                3
+            } else {
+                2
+            },
+        );
+    }
+
+    let game_version = activity.game_version.as_ref()?;
+    if &profile.game_version != game_version
+        || activity
+            .loader
+            .as_ref()
+            .is_some_and(|x| x != profile.loader.as_str())
+    {
+        return None;
+    }
+
+    Some(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{LinkedData, ModLoader};
+
+    fn modpack(project_id: &str, version_id: &str) -> Profile {
+        Profile {
+            linked_data: Some(LinkedData {
+                project_id: project_id.to_string(),
+                version_id: version_id.to_string(),
+                locked: true,
+            }),
+            ..Profile::for_tests("1.20.1", ModLoader::Fabric, Some("0.15.0"))
+        }
+    }
+
+    #[test]
+    fn matches_modpack_instances_by_project_and_version() {
+        let activity = UserActivity {
+            project_id: Some("pack".to_string()),
+            version_id: Some("v2".to_string()),
+            game_version: Some("1.20.1".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(instance_match(&modpack("pack", "v2"), &activity), Some(3));
+        assert_eq!(instance_match(&modpack("pack", "v1"), &activity), Some(2));
+        assert_eq!(instance_match(&modpack("other", "v2"), &activity), None);
+
+        // Unlinked instances can't join a modpack, even on the same version
+        let unlinked =
+            Profile::for_tests("1.20.1", ModLoader::Fabric, Some("0.15.0"));
+        assert_eq!(instance_match(&unlinked, &activity), None);
+    }
+
+    #[test]
+    fn matches_other_instances_by_game_version_and_loader() {
+        let activity = UserActivity {
+            game_version: Some("1.20.1".to_string()),
+            loader: Some("fabric".to_string()),
+            ..Default::default()
+        };
+
+        let fabric =
+            Profile::for_tests("1.20.1", ModLoader::Fabric, Some("0.15.0"));
+        assert_eq!(instance_match(&fabric, &activity), Some(1));
+        assert_eq!(instance_match(&modpack("pack", "v1"), &activity), Some(1));
+        assert_eq!(
+            instance_match(
+                &Profile::for_tests("1.20.1", ModLoader::Vanilla, None),
+                &activity
+            ),
+            None
+        );
+        assert_eq!(
+            instance_match(
+                &Profile::for_tests("1.20.4", ModLoader::Fabric, None),
+                &activity
+            ),
+            None
+        );
+
+        // Any loader matches when the friend doesn't share theirs
+        let activity = UserActivity {
+            loader: None,
+            ..activity
+        };
+        assert_eq!(
+            instance_match(
+                &Profile::for_tests("1.20.1", ModLoader::Vanilla, None),
+                &activity
+            ),
+            Some(1)
+        );
+
+        // Nothing matches without a game version
+        assert_eq!(instance_match(&fabric, &UserActivity::default()), None);
+    }
+}
@@ -1,20 +1,25 @@
 //! Authentication flow interface
+use crate::event::LoadingBarId;
 use crate::event::emit::{emit_loading, init_loading};
 use crate::state::JavaVersion;
-use crate::util::fetch::{fetch_advanced, fetch_json};
 use dashmap::DashMap;
-use reqwest::Method;
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
 use sysinfo::{MemoryRefreshKind, RefreshKind};
+use tokio::sync::Mutex;
 
-use crate::util::io;
+use crate::util::io::{self, IOError};
 use crate::util::jre::extract_java_version;
 use crate::{
     LoadingBarType, State,
     util::jre::{self},
 };
 
+pub mod providers;
+
+use self::providers::{JavaProviderKind, JavaRuntime};
+
 pub async fn get_java_versions() -> crate::Result<DashMap<u32, JavaVersion>> {
     let state = State::get().await?;
 
@@ -51,7 +56,50 @@ pub async fn find_filtered_jres(
     })
 }
 
-pub async fn auto_install_java(java_version: u32) -> crate::Result<PathBuf> {
+/// A Java runtime installed by the app. Installs are tracked per major
+/// version, so updating a runtime keeps its path
+#[derive(Serialize, Debug, Clone)]
+pub struct ManagedJavaInstall {
+    pub major_version: u32,
+    pub provider: JavaProviderKind,
+    /// Full version of the installed runtime
+    pub version: String,
+    /// Path to the Java executable
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JavaUpdate {
+    pub major_version: u32,
+    pub provider: JavaProviderKind,
+    pub installed_version: String,
+    pub latest_version: String,
+}
+
+/// Stored in the directory of every managed install
+#[derive(Serialize, Deserialize)]
+struct InstallRecord {
+    major_version: u32,
+    provider: JavaProviderKind,
+    version: String,
+}
+
+const INSTALL_RECORD_FILE: &str = "modrinth_install.json";
+
+fn managed_install_dir(state: &State, major_version: u32) -> PathBuf {
+    state
+        .directories
+        .java_versions_dir()
+        .join(format!("java-{major_version}"))
+}
+
+/// Installs the newest runtime for a major Java version, trying `provider`
+/// first and falling back to the other providers if it fails.
+/// Returns the path to the Java executable
+pub async fn auto_install_java(
+    java_version: u32,
+    provider: Option<JavaProviderKind>,
+) -> crate::Result<PathBuf> {
     let state = State::get().await?;
 
     let loading_bar = init_loading(
@@ -63,97 +111,282 @@ pub async fn auto_install_java(java_version: u32) -> crate::Result<PathBuf> {
     )
     .await?;
 
-    #[derive(Deserialize)]
-    struct Package {
-        pub download_url: String,
-        pub name: PathBuf,
+    let mut last_error = None;
+    for provider in JavaProviderKind::by_preference(provider) {
+        emit_loading(&loading_bar, 0.0, Some("Fetching java version"))?;
+        let result = match provider.find_runtime(java_version, &state).await {
+            Ok(Some(runtime)) => {
+                emit_loading(
+                    &loading_bar,
+                    10.0,
+                    Some("Downloading java version"),
+                )?;
+                install_runtime(&runtime, &state, &loading_bar).await
+            }
+            Ok(None) => continue,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(path) => return Ok(path),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to install Java {java_version} from {}: {e}",
+                    provider.as_str()
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        crate::ErrorKind::LauncherError(format!(
+            "No Java Version found for Java version {}, OS {}, and Architecture {}",
+            java_version,
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+        ))
+        .into()
+    }))
+}
+
+/// Locks held while installing a major version, so concurrent installs
+/// don't remove each other's staging directory
+static INSTALL_LOCKS: LazyLock<DashMap<u32, Arc<Mutex<()>>>> =
+    LazyLock::new(DashMap::new);
+
+/// Downloads a runtime into a staging directory and swaps it with the
+/// current install of its major version, so a failed install never leaves
+/// the major version without a working runtime
+async fn install_runtime(
+    runtime: &JavaRuntime,
+    state: &State,
+    loading_bar: &LoadingBarId,
+) -> crate::Result<PathBuf> {
+    let lock = INSTALL_LOCKS
+        .entry(runtime.major_version)
+        .or_default()
+        .clone();
+    let _guard = lock.lock().await;
+
+    let java_versions_dir = state.directories.java_versions_dir();
+    let install_dir = managed_install_dir(state, runtime.major_version);
+    let staging_dir = java_versions_dir
+        .join(format!(".staging-java-{}", runtime.major_version));
+    let backup_dir =
+        java_versions_dir.join(format!(".old-java-{}", runtime.major_version));
+
+    if staging_dir.exists() {
+        io::remove_dir_all(&staging_dir).await?;
     }
+    io::create_dir_all(&staging_dir).await?;
+
+    let result: crate::Result<PathBuf> = async {
+        runtime
+            .provider
+            .download(runtime, &staging_dir, state, loading_bar)
+            .await?;
+
+        // Archives usually contain a single top level directory
+        let mut entries = Vec::new();
+        let mut read_dir = io::read_dir(&staging_dir).await?;
+        while let Some(entry) = read_dir
+            .next_entry()
+            .await
+            .map_err(|e| IOError::with_path(e, &staging_dir))?
+        {
+            entries.push(entry.path());
+        }
+        let content_dir = if entries.len() == 1 && entries[0].is_dir() {
+            entries.remove(0)
+        } else {
+            staging_dir.clone()
+        };
 
-    emit_loading(&loading_bar, 0.0, Some("Fetching java version"))?;
-    let packages = fetch_json::<Vec<Package>>(
-                Method::GET,
-                &format!(
-                    "https://api.azul.com/metadata/v1/zulu/packages?arch={}&java_version={}&os={}&archive_type=zip&javafx_bundled=false&java_package_type=jre&page_size=1",
-                    std::env::consts::ARCH, java_version, std::env::consts::OS
-                ),
-                None,
-                None,
-                &state.fetch_semaphore,
-                &state.pool,
-            ).await?;
-    emit_loading(&loading_bar, 10.0, Some("Downloading java version"))?;
-
-    if let Some(download) = packages.first() {
-        let file = fetch_advanced(
-            Method::GET,
-            &download.download_url,
-            None,
-            None,
-            None,
-            None,
-            Some((&loading_bar, 80.0)),
-            &state.fetch_semaphore,
-            &state.pool,
+        let Some(java_path) = find_java_executable(&content_dir).await? else {
+            return Err(crate::ErrorKind::LauncherError(format!(
+                "No Java executable found in the Java {} runtime from {}",
+                runtime.major_version,
+                runtime.provider.as_str()
+            ))
+            .into());
+        };
+        let java_path = java_path.strip_prefix(&content_dir)?.to_path_buf();
+
+        io::write(
+            content_dir.join(INSTALL_RECORD_FILE),
+            serde_json::to_vec(&InstallRecord {
+                major_version: runtime.major_version,
+                provider: runtime.provider,
+                version: runtime.version.clone(),
+            })?,
         )
         .await?;
 
-        let path = state.directories.java_versions_dir();
+        if backup_dir.exists() {
+            io::remove_dir_all(&backup_dir).await?;
+        }
+        if install_dir.exists() {
+            io::rename_or_move(&install_dir, &backup_dir).await?;
+        }
+        if let Err(e) = io::rename_or_move(&content_dir, &install_dir).await {
+            if backup_dir.exists() {
+                io::rename_or_move(&backup_dir, &install_dir).await?;
+            }
+            return Err(e.into());
+        }
 
-        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(file))
-            .map_err(|_| {
-                crate::Error::from(crate::ErrorKind::InputError(
-                    "Failed to read java zip".to_string(),
-                ))
-            })?;
+        Ok(install_dir.join(java_path))
+    }
+    .await;
 
-        // removes the old installation of java
-        if let Some(file) = archive.file_names().next()
-            && let Some(dir) = file.split('/').next()
-        {
-            let path = path.join(dir);
+    if staging_dir.exists() {
+        let _ = io::remove_dir_all(&staging_dir).await;
+    }
+    if result.is_ok() && backup_dir.exists() {
+        let _ = io::remove_dir_all(&backup_dir).await;
+    }
+
+    result
+}
+
+/// Finds the Java executable of a runtime, which is nested in
+/// `Contents/Home` in macOS bundles
+async fn find_java_executable(dir: &Path) -> crate::Result<Option<PathBuf>> {
+    fn search(dir: &Path, depth: usize) -> Option<PathBuf> {
+        let java = dir.join("bin").join(jre::JAVA_BIN);
+        if java.is_file() {
+            return Some(java);
+        }
+        if depth == 0 {
+            return None;
+        }
+
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|x| x.path())
+            .filter(|x| x.is_dir())
+            .find_map(|x| search(&x, depth - 1))
+    }
+
+    // Only search a few levels deep, e.g. `jre.bundle/Contents/Home`
+    let dir = dir.to_path_buf();
+    Ok(tokio::task::spawn_blocking(move || search(&dir, 3)).await?)
+}
 
-            if path.exists() {
-                io::remove_dir_all(path).await?;
+/// Lists the Java runtimes installed by the app
+pub async fn get_managed_java_installs()
+-> crate::Result<Vec<ManagedJavaInstall>> {
+    let state = State::get().await?;
+    let java_versions_dir = state.directories.java_versions_dir();
+    if !java_versions_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut installs = Vec::new();
+    let mut entries = io::read_dir(&java_versions_dir).await?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| IOError::with_path(e, &java_versions_dir))?
+    {
+        let dir = entry.path();
+        let record_path = dir.join(INSTALL_RECORD_FILE);
+        if !record_path.exists() {
+            continue;
+        }
+
+        let record = match serde_json::from_slice::<InstallRecord>(
+            &io::read(&record_path).await?,
+        ) {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::warn!(
+                    "Invalid Java install record at {}: {e}",
+                    record_path.display()
+                );
+                continue;
             }
+        };
+
+        if let Some(path) = find_java_executable(&dir).await? {
+            installs.push(ManagedJavaInstall {
+                major_version: record.major_version,
+                provider: record.provider,
+                version: record.version,
+                path,
+            });
         }
+    }
+    installs.sort_by_key(|x| x.major_version);
 
-        emit_loading(&loading_bar, 0.0, Some("Extracting java"))?;
-        archive.extract(&path).map_err(|_| {
-            crate::Error::from(crate::ErrorKind::InputError(
-                "Failed to extract java zip".to_string(),
-            ))
-        })?;
-        emit_loading(&loading_bar, 10.0, Some("Done extracting java"))?;
-        let mut base_path = path.join(
-            download
-                .name
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-        );
-
-        #[cfg(target_os = "macos")]
+    Ok(installs)
+}
+
+/// Checks the providers of the Java runtimes installed by the app for newer
+/// versions
+pub async fn check_java_updates() -> crate::Result<Vec<JavaUpdate>> {
+    let state = State::get().await?;
+
+    let mut updates = Vec::new();
+    for install in get_managed_java_installs().await? {
+        let runtime = match install
+            .provider
+            .find_runtime(install.major_version, &state)
+            .await
         {
-            base_path = base_path
-                .join("Contents")
-                .join("Home")
-                .join("bin")
-                .join("java")
-        }
+            Ok(runtime) => runtime,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to check for Java {} updates: {e}",
+                    install.major_version
+                );
+                continue;
+            }
+        };
 
-        #[cfg(not(target_os = "macos"))]
+        if let Some(runtime) = runtime
+            && runtime.version != install.version
         {
-            base_path = base_path.join("bin").join(jre::JAVA_BIN)
+            updates.push(JavaUpdate {
+                major_version: install.major_version,
+                provider: install.provider,
+                installed_version: install.version,
+                latest_version: runtime.version,
+            });
         }
+    }
 
-        Ok(base_path)
-    } else {
-        Err(crate::ErrorKind::LauncherError(format!(
-                    "No Java Version found for Java version {}, OS {}, and Architecture {}",
-                    java_version, std::env::consts::OS, std::env::consts::ARCH,
-                )).into())
+    Ok(updates)
+}
+
+/// Updates the Java runtime installed by the app for a major version, using
+/// the provider it was installed from
+pub async fn update_java(major_version: u32) -> crate::Result<PathBuf> {
+    let state = State::get().await?;
+    let install = get_managed_java_installs()
+        .await?
+        .into_iter()
+        .find(|x| x.major_version == major_version)
+        .ok_or_else(|| {
+            crate::ErrorKind::InputError(format!(
+                "Java {major_version} was not installed by the app"
+            ))
+        })?;
+
+    let path = auto_install_java(major_version, Some(install.provider)).await?;
+
+    // Refresh the stored version if it points to this install
+    if let Some(java_version) =
+        JavaVersion::get(major_version, &state.pool).await?
+        && Path::new(&java_version.path)
+            .starts_with(managed_install_dir(&state, major_version))
+    {
+        check_jre(path.clone()).await?.upsert(&state.pool).await?;
     }
+
+    Ok(path)
 }
 
 // Validates JRE at a given at a given path
@@ -0,0 +1,585 @@
+//! Java runtime providers used to automatically install Java
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::Path;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use path_util::SafeRelativeUtf8UnixPathBuf;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::State;
+use crate::event::LoadingBarId;
+use crate::event::emit::{emit_loading, loading_try_for_each_concurrent};
+use crate::util::fetch::{
+    fetch, fetch_advanced, fetch_json, sha1_async, write,
+};
+use crate::util::io::{self, IOError};
+use crate::util::jre::{JREError, extract_java_version};
+
+const MOJANG_RUNTIMES_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JavaProviderKind {
+    /// Azul Zulu
+    Zulu,
+    /// Eclipse Temurin, from Adoptium
+    Adoptium,
+    /// The runtimes used by the official Minecraft launcher
+    Mojang,
+}
+
+impl JavaProviderKind {
+    /// Providers in the order they are tried when installing Java
+    pub const DEFAULT_ORDER: [Self; 3] =
+        [Self::Zulu, Self::Adoptium, Self::Mojang];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Zulu => "zulu",
+            Self::Adoptium => "adoptium",
+            Self::Mojang => "mojang",
+        }
+    }
+
+    /// Providers to try when installing Java, starting with `preferred`
+    pub fn by_preference(preferred: Option<Self>) -> Vec<Self> {
+        preferred
+            .into_iter()
+            .chain(
+                Self::DEFAULT_ORDER
+                    .into_iter()
+                    .filter(move |x| Some(*x) != preferred),
+            )
+            .collect()
+    }
+
+    pub(crate) async fn find_runtime(
+        self,
+        major_version: u32,
+        state: &State,
+    ) -> crate::Result<Option<JavaRuntime>> {
+        match self {
+            Self::Zulu => Zulu.find_runtime(major_version, state).await,
+            Self::Adoptium => Adoptium.find_runtime(major_version, state).await,
+            Self::Mojang => Mojang.find_runtime(major_version, state).await,
+        }
+    }
+
+    pub(crate) async fn download(
+        self,
+        runtime: &JavaRuntime,
+        dir: &Path,
+        state: &State,
+        loading_bar: &LoadingBarId,
+    ) -> crate::Result<()> {
+        match self {
+            Self::Zulu => Zulu.download(runtime, dir, state, loading_bar).await,
+            Self::Adoptium => {
+                Adoptium.download(runtime, dir, state, loading_bar).await
+            }
+            Self::Mojang => {
+                Mojang.download(runtime, dir, state, loading_bar).await
+            }
+        }
+    }
+}
+
+/// A checksum published by a provider for a download
+#[derive(Debug, Clone)]
+pub enum Checksum {
+    Sha1(String),
+    Sha256(String),
+}
+
+impl Checksum {
+    pub async fn verify(&self, bytes: Bytes) -> crate::Result<()> {
+        let (expected, actual) = match self {
+            Self::Sha1(expected) => (expected, sha1_async(bytes).await?),
+            Self::Sha256(expected) => (
+                expected,
+                tokio::task::spawn_blocking(move || {
+                    format!("{:x}", sha2::Sha256::digest(&bytes))
+                })
+                .await?,
+            ),
+        };
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(JREError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+/// A Java runtime available from a provider
+#[derive(Debug, Clone)]
+pub struct JavaRuntime {
+    pub provider: JavaProviderKind,
+    pub major_version: u32,
+    /// Full version of the runtime, e.g. `21.0.5+11`
+    pub version: String,
+    pub url: String,
+    pub checksum: Checksum,
+}
+
+/// A source of Java runtimes
+pub(crate) trait JavaProvider {
+    /// Find the newest runtime of a major Java version for this platform
+    async fn find_runtime(
+        &self,
+        major_version: u32,
+        state: &State,
+    ) -> crate::Result<Option<JavaRuntime>>;
+
+    /// Download a runtime, verifying its checksum, and unpack it into `dir`
+    async fn download(
+        &self,
+        runtime: &JavaRuntime,
+        dir: &Path,
+        state: &State,
+        loading_bar: &LoadingBarId,
+    ) -> crate::Result<()> {
+        let bytes = fetch_advanced(
+            Method::GET,
+            &runtime.url,
+            None,
+            None,
+            None,
+            None,
+            Some((loading_bar, 80.0)),
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await?;
+        runtime.checksum.verify(bytes.clone()).await?;
+
+        emit_loading(loading_bar, 0.0, Some("Extracting java"))?;
+        let is_zip = runtime.url.ends_with(".zip");
+        let dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            extract_archive(bytes, &dir, is_zip)
+        })
+        .await??;
+        emit_loading(loading_bar, 10.0, Some("Done extracting java"))?;
+
+        Ok(())
+    }
+}
+
+fn extract_archive(
+    bytes: Bytes,
+    dir: &Path,
+    is_zip: bool,
+) -> crate::Result<()> {
+    if is_zip {
+        zip::ZipArchive::new(Cursor::new(bytes))
+            .and_then(|mut archive| archive.extract(dir))
+            .map_err(|_| {
+                crate::ErrorKind::InputError(
+                    "Failed to extract java zip".to_string(),
+                )
+            })?;
+    } else {
+        tar::Archive::new(flate2::read::GzDecoder::new(Cursor::new(bytes)))
+            .unpack(dir)
+            .map_err(|e| IOError::with_path(e, dir))?;
+    }
+
+    Ok(())
+}
+
+pub(crate) struct Zulu;
+
+impl JavaProvider for Zulu {
+    async fn find_runtime(
+        &self,
+        major_version: u32,
+        state: &State,
+    ) -> crate::Result<Option<JavaRuntime>> {
+        #[derive(Deserialize)]
+        struct Package {
+            package_uuid: String,
+            download_url: String,
+            java_version: Vec<u32>,
+        }
+
+        #[derive(Deserialize)]
+        struct PackageDetails {
+            sha256_hash: String,
+        }
+
+        let packages = fetch_json::<Vec<Package>>(
+            Method::GET,
+            &format!(
+                "https://api.azul.com/metadata/v1/zulu/packages?arch={}&java_version={}&os={}&archive_type=zip&javafx_bundled=false&java_package_type=jre&latest=true&page_size=1",
+                std::env::consts::ARCH,
+                major_version,
+                std::env::consts::OS
+            ),
+            None,
+            None,
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await?;
+        let Some(package) = packages.into_iter().next() else {
+            return Ok(None);
+        };
+
+        // Checksums are only included in the details of a package
+        let details = fetch_json::<PackageDetails>(
+            Method::GET,
+            &format!(
+                "https://api.azul.com/metadata/v1/zulu/packages/{}",
+                package.package_uuid
+            ),
+            None,
+            None,
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await?;
+
+        Ok(Some(JavaRuntime {
+            provider: JavaProviderKind::Zulu,
+            major_version,
+            version: package
+                .java_version
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join("."),
+            url: package.download_url,
+            checksum: Checksum::Sha256(details.sha256_hash),
+        }))
+    }
+}
+
+pub(crate) struct Adoptium;
+
+impl JavaProvider for Adoptium {
+    async fn find_runtime(
+        &self,
+        major_version: u32,
+        state: &State,
+    ) -> crate::Result<Option<JavaRuntime>> {
+        #[derive(Deserialize)]
+        struct Release {
+            binary: Binary,
+            release_name: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Binary {
+            package: Package,
+        }
+
+        #[derive(Deserialize)]
+        struct Package {
+            link: String,
+            checksum: String,
+        }
+
+        let os = match std::env::consts::OS {
+            "linux" => "linux",
+            "macos" => "mac",
+            "windows" => "windows",
+            _ => return Ok(None),
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "x32",
+            "aarch64" => "aarch64",
+            "arm" => "arm",
+            _ => return Ok(None),
+        };
+
+        let releases = fetch_json::<Vec<Release>>(
+            Method::GET,
+            &format!(
+                "https://api.adoptium.net/v3/assets/latest/{major_version}/hotspot?architecture={arch}&image_type=jre&os={os}&vendor=eclipse"
+            ),
+            None,
+            None,
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await?;
+
+        Ok(releases.into_iter().next().map(|release| JavaRuntime {
+            provider: JavaProviderKind::Adoptium,
+            major_version,
+            version: release
+                .release_name
+                .trim_start_matches("jdk-")
+                .trim_start_matches("jdk")
+                .to_string(),
+            url: release.binary.package.link,
+            checksum: Checksum::Sha256(release.binary.package.checksum),
+        }))
+    }
+}
+
+/// The runtimes distributed by Mojang. These are not archives, but
+/// manifests listing every file of the runtime, and only have SHA-1 hashes.
+pub(crate) struct Mojang;
+
+#[derive(Deserialize)]
+struct MojangDownload {
+    sha1: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct MojangRuntimeManifest {
+    files: BTreeMap<String, MojangRuntimeFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MojangRuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: MojangFileDownloads,
+    },
+    Directory,
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct MojangFileDownloads {
+    raw: MojangDownload,
+}
+
+impl JavaProvider for Mojang {
+    async fn find_runtime(
+        &self,
+        major_version: u32,
+        state: &State,
+    ) -> crate::Result<Option<JavaRuntime>> {
+        #[derive(Deserialize)]
+        struct Runtime {
+            manifest: MojangDownload,
+            version: RuntimeVersion,
+        }
+
+        #[derive(Deserialize)]
+        struct RuntimeVersion {
+            name: String,
+            released: DateTime<Utc>,
+        }
+
+        let platform = match (std::env::consts::OS, std::env::consts::ARCH) {
+            ("linux", "x86_64") => "linux",
+            ("linux", "x86") => "linux-i386",
+            ("macos", "x86_64") => "mac-os",
+            ("macos", "aarch64") => "mac-os-arm64",
+            ("windows", "x86_64") => "windows-x64",
+            ("windows", "x86") => "windows-x86",
+            ("windows", "aarch64") => "windows-arm64",
+            _ => return Ok(None),
+        };
+
+        // Platform -> component (e.g. `java-runtime-delta`) -> runtimes
+        let mut runtimes =
+            fetch_json::<HashMap<String, HashMap<String, Vec<Runtime>>>>(
+                Method::GET,
+                MOJANG_RUNTIMES_URL,
+                None,
+                None,
+                &state.fetch_semaphore,
+                &state.pool,
+            )
+            .await?;
+
+        let runtime = runtimes
+            .remove(platform)
+            .into_iter()
+            .flatten()
+            .filter(|(component, _)| !component.ends_with("snapshot"))
+            .flat_map(|(_, runtimes)| runtimes)
+            .filter(|x| {
+                mojang_major_version(&x.version.name) == Some(major_version)
+            })
+            .max_by_key(|x| x.version.released);
+
+        Ok(runtime.map(|runtime| JavaRuntime {
+            provider: JavaProviderKind::Mojang,
+            major_version,
+            version: runtime.version.name,
+            url: runtime.manifest.url,
+            checksum: Checksum::Sha1(runtime.manifest.sha1),
+        }))
+    }
+
+    async fn download(
+        &self,
+        runtime: &JavaRuntime,
+        dir: &Path,
+        state: &State,
+        loading_bar: &LoadingBarId,
+    ) -> crate::Result<()> {
+        let bytes = fetch(
+            &runtime.url,
+            None,
+            None,
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await?;
+        runtime.checksum.verify(bytes.clone()).await?;
+        let manifest: MojangRuntimeManifest = serde_json::from_slice(&bytes)?;
+
+        let mut files = Vec::new();
+        #[cfg(unix)]
+        let mut links = Vec::new();
+        for (relative_path, file) in manifest.files {
+            let path = dir.join(
+                SafeRelativeUtf8UnixPathBuf::try_from(relative_path.clone())?
+                    .as_str(),
+            );
+            match file {
+                MojangRuntimeFile::File {
+                    executable,
+                    downloads,
+                } => files.push((path, executable, downloads.raw)),
+                MojangRuntimeFile::Directory => {
+                    io::create_dir_all(&path).await?
+                }
+                MojangRuntimeFile::Link { target } => {
+                    if !is_contained_link(&relative_path, &target) {
+                        return Err(crate::ErrorKind::InputError(format!(
+                            "Runtime link {relative_path} points outside of the runtime: {target}"
+                        ))
+                        .into());
+                    }
+
+                    #[cfg(unix)]
+                    links.push((path, target));
+                }
+            }
+        }
+
+        let num_files = files.len();
+        loading_try_for_each_concurrent(
+            futures::stream::iter(files).map(Ok::<_, crate::Error>),
+            None,
+            Some(loading_bar),
+            90.0,
+            num_files,
+            None,
+            |(path, executable, download)| async move {
+                let bytes = fetch(
+                    &download.url,
+                    Some(&download.sha1),
+                    None,
+                    &state.fetch_semaphore,
+                    &state.pool,
+                )
+                .await?;
+                write(&path, &bytes, &state.io_semaphore).await?;
+
+                #[cfg(unix)]
+                if executable {
+                    use std::os::unix::fs::PermissionsExt;
+
+                    tokio::fs::set_permissions(
+                        &path,
+                        std::fs::Permissions::from_mode(0o755),
+                    )
+                    .await
+                    .map_err(|e| IOError::with_path(e, &path))?;
+                }
+                #[cfg(not(unix))]
+                let _ = executable;
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        // Links are only used by the macOS and Linux runtimes
+        #[cfg(unix)]
+        for (path, target) in links {
+            std::os::unix::fs::symlink(&target, &path)
+                .map_err(|e| IOError::with_path(e, &path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the target of a runtime link at `link` (both relative to the
+/// runtime directory) resolves to a path inside the runtime directory
+fn is_contained_link(link: &str, target: &str) -> bool {
+    if target.starts_with('/')
+        || target.starts_with('\\')
+        || target.contains(':')
+    {
+        return false;
+    }
+
+    // targets are relative to the directory containing the link
+    let mut depth = link
+        .split('/')
+        .filter(|x| !x.is_empty())
+        .count()
+        .saturating_sub(1);
+    for component in target.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return false,
+            },
+            _ => depth += 1,
+        }
+    }
+    true
+}
+
+/// Major version of a Mojang runtime name, e.g. `8u51` or `17.0.8`
+fn mojang_major_version(name: &str) -> Option<u32> {
+    extract_java_version(name).ok().or_else(|| {
+        let digits = name
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(name, |end| &name[..end]);
+        digits.parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_links_inside_runtime() {
+        assert!(is_contained_link(
+            "jre.bundle/Contents/Home/legal/java.base/LICENSE",
+            "../../../../../legal/LICENSE"
+        ));
+        assert!(is_contained_link("bin/java", "../lib/jspawnhelper"));
+        assert!(is_contained_link("lib/libjli.dylib", "./jli/libjli.dylib"));
+    }
+
+    #[test]
+    fn rejects_links_escaping_runtime() {
+        assert!(!is_contained_link("bin/java", "/usr/bin/java"));
+        assert!(!is_contained_link("bin/java", "../../outside"));
+        assert!(!is_contained_link("LICENSE", "../LICENSE"));
+        assert!(!is_contained_link("bin/java", "C:\\Windows\\java.exe"));
+        assert!(!is_contained_link("bin/java", "..\\..\\outside"));
+    }
+}
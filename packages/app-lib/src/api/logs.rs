@@ -15,6 +15,8 @@ use crate::{
     util::io::{self, IOError},
 };
 
+pub mod query;
+
 #[derive(Serialize, Debug)]
 pub struct Logs {
     pub log_type: LogType,
@@ -69,6 +71,10 @@ impl CensoredString {
 
         Self(s)
     }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -385,6 +391,15 @@ pub async fn get_output_by_filename(
     .into())
 }
 
+/// Analyze the latest crash of a profile, using its newest crash report and
+/// the tail of its game log
+#[tracing::instrument]
+pub async fn analyze_crash(
+    profile_path: &str,
+) -> crate::Result<crate::state::CrashAnalysis> {
+    crate::state::analyze_crash(profile_path, None, None, None).await
+}
+
 #[tracing::instrument]
 pub async fn delete_logs(profile_path_id: &str) -> crate::Result<()> {
     let state = State::get().await?;
@@ -0,0 +1,609 @@
+//! Structured search over the game logs of a profile, including archived
+//! (`.log.gz`) logs.
+//!
+//! Log lines are grouped into entries: a line with a log4j style header
+//! (`[time] [thread/LEVEL]: message`) followed by any lines without one,
+//! such as stack traces. Filters are applied to whole entries.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    State, api::logs::CensoredString, prelude::Credentials, util::io::IOError,
+};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_CONTEXT_LINES: usize = 50;
+
+static HEADER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\[(?P<time>[^\]]+)\] \[(?P<first>[^\]]*)\](?: \[(?P<second>[^\]]*)\])?(?: \((?P<paren>[^)]*)\))?:? ",
+    )
+    .unwrap()
+});
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s.trim().to_ascii_uppercase().as_str() {
+            "TRACE" => Self::Trace,
+            "DEBUG" => Self::Debug,
+            "INFO" => Self::Info,
+            "WARN" | "WARNING" => Self::Warn,
+            "ERROR" => Self::Error,
+            "FATAL" => Self::Fatal,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LogQuery {
+    /// Names of the log files to search. Searches every log file of the
+    /// profile, newest first, if empty
+    #[serde(default)]
+    pub filenames: Vec<String>,
+    /// Only match entries with one of these levels. Matches every entry,
+    /// including ones without a level, if empty
+    #[serde(default)]
+    pub levels: Vec<LogLevel>,
+    /// Case-insensitive substring of the logger name
+    pub logger: Option<String>,
+    /// Case-insensitive substring of the thread name
+    pub thread: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Case-insensitive regex matched against every line of an entry
+    pub regex: Option<String>,
+    /// Number of lines to include before and after every match
+    #[serde(default)]
+    pub context_lines: usize,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LogQueryResult {
+    pub matches: Vec<LogMatch>,
+    /// Number of matching entries across all searched files
+    pub total_matches: usize,
+    /// Number of entries matching every filter but the level filter, by level
+    pub level_counts: BTreeMap<LogLevel, usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LogMatch {
+    pub filename: String,
+    /// 1-based line number of the first line of the entry
+    pub line_number: usize,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: Option<LogLevel>,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    pub lines: Vec<CensoredString>,
+    pub context_before: Vec<CensoredString>,
+    pub context_after: Vec<CensoredString>,
+}
+
+#[derive(Debug, PartialEq)]
+enum HeaderTime {
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+}
+
+#[derive(Debug, PartialEq)]
+struct LogHeader {
+    time: Option<HeaderTime>,
+    level: Option<LogLevel>,
+    thread: Option<String>,
+    logger: Option<String>,
+}
+
+struct LogEntry {
+    /// Index of the first line of the entry
+    start: usize,
+    /// Index after the last line of the entry
+    end: usize,
+    timestamp: Option<DateTime<Utc>>,
+    level: Option<LogLevel>,
+    thread: Option<String>,
+    logger: Option<String>,
+}
+
+/// Search the logs of a profile, returning a page of matching entries along
+/// with the total number of matches and counts by level
+#[tracing::instrument]
+pub async fn query_logs(
+    profile_path: &str,
+    query: LogQuery,
+) -> crate::Result<LogQueryResult> {
+    let state = State::get().await?;
+    let logs_folder = state.directories.profile_logs_dir(profile_path);
+
+    let files = if query.filenames.is_empty() {
+        list_log_files(&logs_folder)?
+    } else {
+        query
+            .filenames
+            .iter()
+            .map(|filename| {
+                // Only allow files directly inside the logs folder
+                if Path::new(filename).file_name().and_then(|x| x.to_str())
+                    != Some(filename)
+                {
+                    return Err(crate::ErrorKind::InputError(format!(
+                        "Invalid log file name: {filename}"
+                    ))
+                    .into());
+                }
+                Ok(logs_folder.join(filename))
+            })
+            .collect::<crate::Result<Vec<_>>>()?
+    };
+
+    let credentials = Credentials::get_all(&state.pool)
+        .await?
+        .into_iter()
+        .map(|x| x.1)
+        .collect::<Vec<_>>();
+
+    tokio::task::spawn_blocking(move || search(&files, &query, &credentials))
+        .await?
+}
+
+fn search(
+    files: &[PathBuf],
+    query: &LogQuery,
+    credentials: &[Credentials],
+) -> crate::Result<LogQueryResult> {
+    let regex = query
+        .regex
+        .as_deref()
+        .map(|x| RegexBuilder::new(x).case_insensitive(true).build())
+        .transpose()?;
+    let logger = query.logger.as_deref().map(str::to_lowercase);
+    let thread = query.thread.as_deref().map(str::to_lowercase);
+    let context_lines = query.context_lines.min(MAX_CONTEXT_LINES);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+
+    let contains =
+        |value: &Option<String>, filter: &Option<String>| match (value, filter)
+        {
+            (_, None) => true,
+            (Some(value), Some(filter)) => {
+                value.to_lowercase().contains(filter)
+            }
+            (None, Some(_)) => false,
+        };
+    let censor =
+        |line: &str| CensoredString::censor(line.to_string(), credentials);
+
+    let mut result = LogQueryResult {
+        matches: Vec::new(),
+        total_matches: 0,
+        level_counts: BTreeMap::new(),
+    };
+
+    for path in files {
+        let filename = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let lines = read_log_lines(path)?;
+        let entries = parse_entries(&lines, file_date(path));
+
+        for entry in entries {
+            if !contains(&entry.logger, &logger)
+                || !contains(&entry.thread, &thread)
+            {
+                continue;
+            }
+            if (query.since.is_some() || query.until.is_some())
+                && !entry.timestamp.is_some_and(|x| {
+                    query.since.is_none_or(|since| x >= since)
+                        && query.until.is_none_or(|until| x <= until)
+                })
+            {
+                continue;
+            }
+            if let Some(regex) = &regex
+                && !lines[entry.start..entry.end]
+                    .iter()
+                    .any(|x| regex.is_match(x))
+            {
+                continue;
+            }
+
+            if let Some(level) = entry.level {
+                *result.level_counts.entry(level).or_default() += 1;
+            }
+            if !query.levels.is_empty()
+                && !entry.level.is_some_and(|x| query.levels.contains(&x))
+            {
+                continue;
+            }
+
+            result.total_matches += 1;
+            if result.total_matches <= query.offset
+                || result.matches.len() >= limit
+            {
+                continue;
+            }
+
+            let before = entry.start.saturating_sub(context_lines);
+            let after = (entry.end + context_lines).min(lines.len());
+            result.matches.push(LogMatch {
+                filename: filename.clone(),
+                line_number: entry.start + 1,
+                timestamp: entry.timestamp,
+                level: entry.level,
+                thread: entry.thread,
+                logger: entry.logger,
+                lines: lines[entry.start..entry.end]
+                    .iter()
+                    .map(|x| censor(x))
+                    .collect(),
+                context_before: lines[before..entry.start]
+                    .iter()
+                    .map(|x| censor(x))
+                    .collect(),
+                context_after: lines[entry.end..after]
+                    .iter()
+                    .map(|x| censor(x))
+                    .collect(),
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Log files in the logs folder of a profile, newest first
+fn list_log_files(logs_folder: &Path) -> crate::Result<Vec<PathBuf>> {
+    if !logs_folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(logs_folder)
+        .map_err(|e| IOError::with_path(e, logs_folder))?
+    {
+        let entry = entry.map_err(|e| IOError::with_path(e, logs_folder))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !path.is_file()
+            || !(name.ends_with(".log")
+                || name.ends_with(".log.gz")
+                || name.ends_with(".txt"))
+        {
+            continue;
+        }
+        let modified = entry.metadata().and_then(|x| x.modified()).ok();
+        files.push((modified, name, path));
+    }
+    files.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+
+    Ok(files.into_iter().map(|x| x.2).collect())
+}
+
+fn read_log_lines(path: &Path) -> crate::Result<Vec<String>> {
+    let file =
+        std::fs::File::open(path).map_err(|e| IOError::with_path(e, path))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|x| x == "gz") {
+        Box::new(flate2::read::GzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(file)
+    };
+
+    let mut reader = BufReader::new(reader);
+    let mut lines = Vec::new();
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        let bytes_read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|e| IOError::with_path(e, path))?;
+        if bytes_read == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    Ok(lines)
+}
+
+/// The date of the first or last entry of a log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogDate {
+    Start(NaiveDate),
+    /// Only the date of the last write is known, so the start date has to be
+    /// worked out from the times of the entries
+    End(NaiveDate),
+}
+
+/// The date a log file was started on. Archived logs are named after it
+/// (`2024-03-12-1.log.gz`), otherwise the creation date is used. Where
+/// creation dates aren't supported, the modification date is used as the
+/// date the log ended on
+fn file_date(path: &Path) -> Option<LogDate> {
+    let name = path.file_name()?.to_string_lossy();
+    if let Some(date) = name
+        .get(..10)
+        .and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok())
+    {
+        return Some(LogDate::Start(date));
+    }
+
+    let metadata = std::fs::metadata(path).ok()?;
+    let local_date = |time| DateTime::<Local>::from(time).date_naive();
+    match metadata.created() {
+        Ok(created) => Some(LogDate::Start(local_date(created))),
+        Err(_) => Some(LogDate::End(local_date(metadata.modified().ok()?))),
+    }
+}
+
+/// Number of times the entries of a log wrap past midnight
+fn midnight_rollovers(lines: &[String]) -> u64 {
+    let mut rollovers = 0;
+    let mut last_time = None;
+    for header in lines.iter().filter_map(|x| parse_header(x)) {
+        if let Some(HeaderTime::Time(time)) = header.time {
+            if last_time.is_some_and(|last| time < last) {
+                rollovers += 1;
+            }
+            last_time = Some(time);
+        }
+    }
+    rollovers
+}
+
+fn parse_entries(lines: &[String], date: Option<LogDate>) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    let mut date = date.and_then(|date| match date {
+        LogDate::Start(date) => Some(date),
+        LogDate::End(date) => {
+            date.checked_sub_days(Days::new(midnight_rollovers(lines)))
+        }
+    });
+    let mut last_time = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(header) = parse_header(line) else {
+            // Continuation of the previous entry, e.g. a stack trace
+            match entries.last_mut() {
+                Some(entry) => entry.end = index + 1,
+                None => entries.push(LogEntry {
+                    start: index,
+                    end: index + 1,
+                    timestamp: None,
+                    level: None,
+                    thread: None,
+                    logger: None,
+                }),
+            }
+            continue;
+        };
+
+        let timestamp = match header.time {
+            Some(HeaderTime::DateTime(x)) => Some(x),
+            Some(HeaderTime::Time(time)) => {
+                // Logs only containing times can span past midnight
+                if last_time.is_some_and(|last| time < last) {
+                    date = date.and_then(|x| x.succ_opt());
+                }
+                last_time = Some(time);
+                date.map(|x| x.and_time(time))
+            }
+            None => None,
+        };
+
+        entries.push(LogEntry {
+            start: index,
+            end: index + 1,
+            timestamp: timestamp
+                .and_then(|x| x.and_local_timezone(Local).earliest())
+                .map(|x| x.with_timezone(&Utc)),
+            level: header.level,
+            thread: header.thread,
+            logger: header.logger,
+        });
+    }
+
+    entries
+}
+
+/// Parse the header of a log line in one of the formats used by the game
+/// and the launcher:
+///
+/// - `[12:34:56] [Render thread/INFO]: message` (vanilla)
+/// - `[12:34:56] [main/INFO] (FabricLoader) message` (Fabric)
+/// - `[12Mar2024 12:34:56.789] [main/INFO] [logger/MARKER]: message` (Forge)
+/// - `[12:34:56] [thread] [logger/INFO]: message` (launcher)
+fn parse_header(line: &str) -> Option<LogHeader> {
+    let captures = HEADER_REGEX.captures(line)?;
+    let time = parse_time(&captures["time"]);
+    let first = &captures["first"];
+    let second = captures.name("second").map(|x| x.as_str());
+    let paren = captures.name("paren").map(|x| x.as_str());
+
+    let non_empty = |x: &str| (!x.is_empty()).then(|| x.to_string());
+    let strip_marker =
+        |x: &str| x.rsplit_once('/').map_or(x, |(name, _)| name).to_string();
+
+    let (thread, logger, level) =
+        if let Some((thread, level)) = split_level(first) {
+            (
+                non_empty(thread),
+                second
+                    .map(strip_marker)
+                    .or_else(|| paren.map(str::to_string)),
+                Some(level),
+            )
+        } else if let Some(second) = second {
+            let (logger, level) = match split_level(second) {
+                Some((logger, level)) => (non_empty(logger), Some(level)),
+                None => (None, LogLevel::parse(second)),
+            };
+            (non_empty(first), logger, level)
+        } else {
+            (non_empty(first), paren.map(str::to_string), None)
+        };
+
+    if time.is_none() && level.is_none() {
+        return None;
+    }
+
+    Some(LogHeader {
+        time,
+        level,
+        thread,
+        logger: logger.filter(|x| !x.is_empty()),
+    })
+}
+
+fn split_level(s: &str) -> Option<(&str, LogLevel)> {
+    let (name, level) = s.rsplit_once('/')?;
+    Some((name, LogLevel::parse(level)?))
+}
+
+fn parse_time(s: &str) -> Option<HeaderTime> {
+    NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+        .map(HeaderTime::Time)
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(s, "%d%b%Y %H:%M:%S%.f")
+                .map(HeaderTime::DateTime)
+        })
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_log_headers() {
+        let vanilla =
+            parse_header("[12:34:56] [Render thread/INFO]: Loading").unwrap();
+        assert_eq!(vanilla.level, Some(LogLevel::Info));
+        assert_eq!(vanilla.thread.as_deref(), Some("Render thread"));
+        assert_eq!(vanilla.logger, None);
+
+        let fabric =
+            parse_header("[12:34:56] [main/WARN] (FabricLoader) Warning")
+                .unwrap();
+        assert_eq!(fabric.level, Some(LogLevel::Warn));
+        assert_eq!(fabric.logger.as_deref(), Some("FabricLoader"));
+
+        let forge = parse_header(
+            "[12Mar2024 12:34:56.789] [main/ERROR] [net.minecraftforge.fml.loading.ModSorter/LOADING]: Failed",
+        )
+        .unwrap();
+        assert_eq!(forge.level, Some(LogLevel::Error));
+        assert_eq!(forge.thread.as_deref(), Some("main"));
+        assert_eq!(
+            forge.logger.as_deref(),
+            Some("net.minecraftforge.fml.loading.ModSorter")
+        );
+        assert!(matches!(forge.time, Some(HeaderTime::DateTime(_))));
+
+        let launcher =
+            parse_header("[12:34:56] [main] [com.example/DEBUG]: message")
+                .unwrap();
+        assert_eq!(launcher.level, Some(LogLevel::Debug));
+        assert_eq!(launcher.thread.as_deref(), Some("main"));
+        assert_eq!(launcher.logger.as_deref(), Some("com.example"));
+
+        assert_eq!(
+            parse_header("\tat java.lang.Thread.run(Thread.java)"),
+            None
+        );
+        assert_eq!(parse_header("[not a header] text"), None);
+    }
+
+    #[test]
+    fn groups_continuation_lines() {
+        let lines = [
+            "Preamble",
+            "[23:59:59] [main/INFO]: Starting",
+            "[00:00:01] [main/ERROR]: Crashed",
+            "java.lang.RuntimeException: oops",
+            "\tat Main.main(Main.java:1)",
+        ]
+        .map(str::to_string);
+        let date = NaiveDate::from_ymd_opt(2024, 3, 12).map(LogDate::Start);
+        let entries = parse_entries(&lines, date);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[0].start, entries[0].end), (0, 1));
+        assert_eq!(entries[0].level, None);
+        assert_eq!((entries[2].start, entries[2].end), (2, 5));
+        assert_eq!(entries[2].level, Some(LogLevel::Error));
+        assert!(entries[2].timestamp > entries[1].timestamp);
+    }
+
+    #[test]
+    fn dates_sessions_crossing_midnight() {
+        let lines = [
+            "[23:58:00] [main/INFO]: Starting",
+            "[23:59:59] [main/INFO]: Loading",
+            "[00:00:01] [main/INFO]: Loaded",
+            "[00:10:00] [main/INFO]: Stopping",
+        ]
+        .map(str::to_string);
+        let at = |day, time| {
+            NaiveDate::from_ymd_opt(2024, 3, day)
+                .unwrap()
+                .and_time(NaiveTime::parse_from_str(time, "%H:%M:%S").unwrap())
+                .and_local_timezone(Local)
+                .earliest()
+                .map(|x| x.with_timezone(&Utc))
+        };
+
+        for date in [
+            LogDate::Start(NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()),
+            // the log was last written to after midnight
+            LogDate::End(NaiveDate::from_ymd_opt(2024, 3, 13).unwrap()),
+        ] {
+            let entries = parse_entries(&lines, Some(date));
+            assert_eq!(
+                entries.iter().map(|x| x.timestamp).collect::<Vec<_>>(),
+                vec![
+                    at(12, "23:58:00"),
+                    at(12, "23:59:59"),
+                    at(13, "00:00:01"),
+                    at(13, "00:10:00"),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn dates_archived_logs_by_name() {
+        assert_eq!(
+            file_date(Path::new("logs/2024-03-12-1.log.gz")),
+            Some(LogDate::Start(
+                NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()
+            ))
+        );
+    }
+}
@@ -3,7 +3,9 @@
 use reqwest::StatusCode;
 
 use crate::State;
-use crate::state::{Credentials, MinecraftLoginFlow};
+use crate::state::{
+    CredentialProvider, Credentials, MinecraftLoginFlow, yggdrasil,
+};
 use crate::util::fetch::INSECURE_REQWEST_CLIENT;
 
 #[tracing::instrument]
@@ -36,6 +38,21 @@ pub async fn finish_login(
     crate::state::login_finish(code, flow, &state.pool).await
 }
 
+/// Sign into a Yggdrasil-compatible authentication server, such as one
+/// used with authlib-injector. The server URL may be the API root, or any
+/// URL pointing to it through the API location indication header
+#[tracing::instrument(skip(password))]
+pub async fn login_yggdrasil(
+    server_url: &str,
+    username: &str,
+    password: &str,
+) -> crate::Result<Credentials> {
+    let state = State::get().await?;
+
+    crate::state::login_yggdrasil(server_url, username, password, &state.pool)
+        .await
+}
+
 #[tracing::instrument]
 pub async fn get_default_user() -> crate::Result<Option<uuid::Uuid>> {
     let state = State::get().await?;
@@ -70,6 +87,23 @@ pub async fn remove_user(uuid: uuid::Uuid) -> crate::Result<()> {
     if let Some((uuid, user)) = users.remove(&uuid) {
         Credentials::remove(uuid, &state.pool).await?;
 
+        if let CredentialProvider::Yggdrasil {
+            api_root,
+            client_token,
+            ..
+        } = &user.provider
+            && let Err(err) = yggdrasil::invalidate(
+                api_root,
+                &user.access_token,
+                client_token,
+            )
+            .await
+        {
+            tracing::warn!(
+                "Failed to invalidate Yggdrasil access token: {err}"
+            );
+        }
+
         if user.active
             && let Some((_, mut user)) = users.into_iter().next()
         {
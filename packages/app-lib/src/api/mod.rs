@@ -12,21 +12,27 @@ pub mod pack;
 pub mod process;
 pub mod profile;
 pub mod server_address;
+pub mod server_project;
 pub mod settings;
+pub mod storage;
 pub mod tags;
 pub mod worlds;
 
 pub mod data {
     pub use crate::state::{
-        CacheBehaviour, CacheValueType, ContentItem, ContentItemOwner,
-        ContentItemProject, ContentItemVersion, Credentials, Dependency,
-        DirectoryInfo, Hooks, JavaVersion, LinkedData, LinkedModpackInfo,
-        MemorySettings, ModLoader, ModrinthCredentials, Organization,
-        OwnerType, ProcessMetadata, ProfileFile, Project, ProjectType,
-        ProjectV3, SearchResult, SearchResults, SearchResultsV3, Settings,
-        TeamMember, Theme, User, UserFriend, Version, WindowSize,
+        CacheBehaviour, CacheValueType, ContentDiagnostic, ContentFix,
+        ContentIssue, ContentItem, ContentItemOwner, ContentItemProject,
+        ContentItemVersion, CrashAnalysis, CrashEvidence, CrashEvidenceKind,
+        CrashSuspect, Credentials, Dependency, DirectoryInfo, FriendTunnel,
+        Hooks, JavaVersion, LinkedData, LinkedModpackInfo, MemorySettings,
+        ModLoader, ModrinthCredentials, Organization, OwnerType,
+        ProcessMetadata, ProfileFile, Project, ProjectType, ProjectV3,
+        ReportedMod, SearchResult, SearchResults, SearchResultsV3, Settings,
+        StatusPrivacy, TeamMember, Theme, TunnelConnection, TunnelInfo,
+        TunnelKind, User, UserFriend, Version, WindowSize,
     };
-    pub use ariadne::users::UserStatus;
+    pub use ariadne::ids::UserId;
+    pub use ariadne::users::{UserActivity, UserStatus};
 }
 
 pub mod prelude {
@@ -36,7 +42,7 @@ pub mod prelude {
         event::CommandPayload,
         jre, metadata, minecraft_auth, mr_auth, pack, process,
         profile::{self, Profile, create},
-        settings,
+        settings, storage,
         util::{
             io::{IOError, canonicalize},
             network::{is_network_metered, tcp_listen_any_loopback},
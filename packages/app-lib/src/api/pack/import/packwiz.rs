@@ -0,0 +1,277 @@
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use path_util::SafeRelativeUtf8UnixPathBuf;
+
+use crate::{
+    State,
+    event::{
+        LoadingBarType,
+        emit::{emit_loading, init_loading, loading_try_for_each_concurrent},
+    },
+    pack::packwiz::{
+        PackwizHashFormat, PackwizIndex, PackwizIndexFile, PackwizMetafile,
+        PackwizPack, PackwizSide,
+    },
+    prelude::{ModLoader, ProjectType},
+    state::{ProfileInstallStage, cache_file_hash},
+    util::{
+        fetch::{DownloadMeta, DownloadReason, fetch, write},
+        io,
+    },
+};
+
+// Check if folder has a pack.toml that parses
+pub async fn is_valid_packwiz(pack_folder: PathBuf) -> bool {
+    read_pack(&pack_folder).await.is_ok()
+}
+
+/// Imports a packwiz pack from a local directory (containing `pack.toml`)
+/// into the given profile, verifying the hashes of the index, every indexed
+/// file and every downloaded file.
+/// Note: this *deletes* the submitted empty profile if the import fails
+pub async fn import_packwiz(
+    pack_folder: PathBuf, // folder containing pack.toml
+    profile_path: &str,   // path to profile
+) -> crate::Result<()> {
+    tracing::debug!("Importing packwiz pack from {}", pack_folder.display());
+
+    match import_packwiz_inner(&pack_folder, profile_path).await {
+        Ok(()) => {
+            tracing::debug!("Completed import.");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::warn!("Import failed: {:?}", e);
+            let _ = crate::api::profile::remove(profile_path).await;
+            Err(e)
+        }
+    }
+}
+
+async fn import_packwiz_inner(
+    pack_folder: &Path,
+    profile_path: &str,
+) -> crate::Result<()> {
+    let state = State::get().await?;
+    let pack = read_pack(pack_folder).await?;
+
+    // Verify the index against the hash in pack.toml
+    let index_path = pack_folder.join(pack.index.file.as_str());
+    let index_bytes = bytes::Bytes::from(io::read(&index_path).await?);
+    pack.index
+        .hash_format
+        .verify(index_bytes.clone(), &pack.index.hash)
+        .await?;
+    let index: PackwizIndex =
+        toml::from_str(&String::from_utf8_lossy(&index_bytes))?;
+
+    // Index file paths are relative to the directory containing the index
+    let index_folder = index_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| pack_folder.to_path_buf());
+
+    let Some(game_version) = pack.versions.get("minecraft").cloned() else {
+        return Err(crate::ErrorKind::InputError(
+            "Pack did not specify Minecraft version".to_string(),
+        )
+        .into());
+    };
+
+    let mut mod_loader = ModLoader::Vanilla;
+    let mut loader_version = None;
+    for (key, loader) in [
+        ("forge", ModLoader::Forge),
+        ("neoforge", ModLoader::NeoForge),
+        ("fabric", ModLoader::Fabric),
+        ("quilt", ModLoader::Quilt),
+    ] {
+        if let Some(version) = pack.versions.get(key) {
+            mod_loader = loader;
+            loader_version = Some(version.clone());
+        }
+    }
+
+    let loader_version = if mod_loader != ModLoader::Vanilla {
+        crate::launcher::get_loader_version_from_profile(
+            &game_version,
+            mod_loader,
+            loader_version.as_deref(),
+        )
+        .await?
+    } else {
+        None
+    };
+
+    // Set profile data to created default profile
+    crate::api::profile::edit(profile_path, |prof| {
+        prof.name.clone_from(&pack.name);
+        prof.install_stage = ProfileInstallStage::PackInstalling;
+        prof.game_version.clone_from(&game_version);
+        prof.loader_version = loader_version.clone().map(|x| x.id);
+        prof.loader = mod_loader;
+
+        async { Ok(()) }
+    })
+    .await?;
+
+    let loading_bar = init_loading(
+        LoadingBarType::CopyProfile {
+            import_location: pack_folder.to_path_buf(),
+            profile_name: profile_path.to_string(),
+        },
+        100.0,
+        "Importing packwiz pack",
+    )
+    .await?;
+
+    let download_meta = DownloadMeta {
+        reason: DownloadReason::Modpack,
+        game_version: game_version.clone(),
+        loader: mod_loader.as_str().to_string(),
+    };
+    let default_hash_format = index.hash_format;
+    let profile_full_path =
+        crate::api::profile::get_full_path(profile_path).await?;
+
+    let num_files = index.files.len();
+    loading_try_for_each_concurrent(
+        futures::stream::iter(index.files)
+            .map(Ok::<PackwizIndexFile, crate::Error>),
+        None,
+        Some(&loading_bar),
+        100.0,
+        num_files,
+        None,
+        |file| {
+            let state = &state;
+            let index_folder = &index_folder;
+            let profile_full_path = &profile_full_path;
+            let download_meta = &download_meta;
+            async move {
+                let source_path = index_folder.join(file.file.as_str());
+                let bytes = bytes::Bytes::from(io::read(&source_path).await?);
+                file.hash_format
+                    .unwrap_or(default_hash_format)
+                    .verify(bytes.clone(), &file.hash)
+                    .await?;
+
+                let destination = file.alias.as_ref().unwrap_or(&file.file);
+
+                let (relative_path, bytes, sha1) = if file.is_metafile() {
+                    let metafile: PackwizMetafile =
+                        toml::from_str(&String::from_utf8_lossy(&bytes))?;
+
+                    let Some(download) =
+                        download_metafile(&metafile, download_meta, state)
+                            .await?
+                    else {
+                        return Ok(());
+                    };
+
+                    let relative_path = SafeRelativeUtf8UnixPathBuf::try_from(
+                        match destination.parent().map(|x| x.as_str()) {
+                            Some(parent) if !parent.is_empty() => {
+                                format!("{parent}/{}", metafile.filename)
+                            }
+                            _ => metafile.filename.clone(),
+                        },
+                    )?;
+
+                    (relative_path, download.0, download.1)
+                } else {
+                    (destination.clone(), bytes, None)
+                };
+
+                let path = profile_full_path.join(relative_path.as_str());
+                cache_file_hash(
+                    bytes.clone(),
+                    profile_path,
+                    relative_path.as_str(),
+                    sha1.as_deref(),
+                    ProjectType::get_from_parent_folder(&path),
+                    &state.pool,
+                )
+                .await?;
+
+                write(&path, &bytes, &state.io_semaphore).await?;
+
+                Ok(())
+            }
+        },
+    )
+    .await?;
+
+    emit_loading(&loading_bar, 0.0, Some("Installing Minecraft"))?;
+
+    if let Some(profile_val) = crate::api::profile::get(profile_path).await? {
+        crate::launcher::install_minecraft(
+            &profile_val,
+            Some(loading_bar),
+            false,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn read_pack(pack_folder: &Path) -> crate::Result<PackwizPack> {
+    let (pack, _) =
+        io::read_any_encoding_to_string(&pack_folder.join("pack.toml")).await?;
+    Ok(toml::from_str(&pack)?)
+}
+
+/// Downloads the file referenced by a metafile, verifying its hash.
+/// Returns the file bytes and its SHA-1 hash (when known), or `None` if the
+/// file should not be installed on the client
+async fn download_metafile(
+    metafile: &PackwizMetafile,
+    download_meta: &DownloadMeta,
+    state: &State,
+) -> crate::Result<Option<(bytes::Bytes, Option<String>)>> {
+    if metafile.side == PackwizSide::Server {
+        return Ok(None);
+    }
+
+    if let Some(option) = &metafile.option
+        && option.optional
+        && !option.default
+    {
+        return Ok(None);
+    }
+
+    let Some(url) = &metafile.download.url else {
+        // Files with `mode = "metadata:curseforge"` can only be resolved
+        // through the CurseForge API, which requires an API key
+        tracing::warn!(
+            "Skipping packwiz file {} without a download URL (mode: {:?})",
+            metafile.filename,
+            metafile.download.mode
+        );
+        return Ok(None);
+    };
+
+    let sha1 = (metafile.download.hash_format == PackwizHashFormat::Sha1)
+        .then(|| metafile.download.hash.to_lowercase());
+
+    let bytes = fetch(
+        url,
+        sha1.as_deref(),
+        Some(download_meta),
+        &state.fetch_semaphore,
+        &state.pool,
+    )
+    .await?;
+
+    if sha1.is_none() {
+        metafile
+            .download
+            .hash_format
+            .verify(bytes.clone(), &metafile.download.hash)
+            .await?;
+    }
+
+    Ok(Some((bytes, sha1)))
+}
@@ -6,8 +6,8 @@ use crate::pack::install_from::{
     EnvType, PackFile, PackFileHash, set_profile_information,
 };
 use crate::state::{
-    CacheBehaviour, CachedEntry, Profile, ProfileInstallStage, SideType,
-    cache_file_hash,
+    CacheBehaviour, CachedEntry, Profile, ProfileInstallStage, Settings,
+    SideType, cache_file_hash, content_store,
 };
 use crate::util::fetch::{
     DownloadMeta, DownloadReason, fetch_mirrors, sha1_async, write,
@@ -233,6 +233,9 @@ pub async fn install_zipped_mrpack_files(
         loader: profile.loader.as_str().to_string(),
     };
 
+    let shared_content_store =
+        Settings::get(&state.pool).await?.shared_content_store;
+
     let num_files = pack.files.len();
     loading_try_for_each_concurrent(
         futures::stream::iter(pack.files).map(Ok::<PackFile, crate::Error>),
@@ -270,18 +273,32 @@ pub async fn install_zipped_mrpack_files(
                 let path = profile::get_full_path(&profile_path)
                     .await?
                     .join(project.path.as_str());
+                let project_type = ProjectType::get_from_parent_folder(&path);
 
                 cache_file_hash(
                     file.clone(),
                     &profile_path,
                     project.path.as_str(),
                     project.hashes.get(&PackFileHash::Sha1).map(|x| &**x),
-                    ProjectType::get_from_parent_folder(&path),
+                    project_type,
                     &state.pool,
                 )
                 .await?;
 
-                write(&path, &file, &state.io_semaphore).await?;
+                if project_type.is_some() {
+                    content_store::write_profile_file(
+                        &profile_path,
+                        project.path.as_str(),
+                        &file,
+                        project.hashes.get(&PackFileHash::Sha1).map(|x| &**x),
+                        shared_content_store,
+                        &state.io_semaphore,
+                        &state.pool,
+                    )
+                    .await?;
+                } else {
+                    write(&path, &file, &state.io_semaphore).await?;
+                }
 
                 Ok(())
             }
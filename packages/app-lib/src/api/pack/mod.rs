@@ -1,3 +1,5 @@
 pub mod import;
 pub mod install_from;
 pub mod install_mrpack;
+pub mod offline_bundle;
+pub mod packwiz;
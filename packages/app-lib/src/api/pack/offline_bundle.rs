@@ -0,0 +1,955 @@
+//! Offline bundles: a profile together with everything needed to install
+//! and launch it without network access.
+//!
+//! A bundle is a zip archive containing:
+//! - `offline_bundle.json`: the [`OfflineBundleManifest`]
+//! - `overrides/`: the files of the profile
+//! - `meta/`: the version, libraries, natives, assets, log config and
+//!   optionally the Java runtime, relative to the metadata directory
+//! - `icon/`: the icon of the profile, if it has one
+//!
+//! On import, every entry is checked against its CRC, and files with a known
+//! SHA-1 hash (the client, libraries, assets and log config) are verified
+//! against the bundled version info and asset index. Files are streamed to a
+//! temporary path first, so an interrupted import never leaves partial files.
+//! Installed metadata files are only replaced when they don't match their
+//! known hash, so files without one are never overwritten.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use async_zip::tokio::read::fs::ZipFileReader;
+use async_zip::tokio::write::ZipFileWriter;
+use async_zip::{Compression, ZipEntryBuilder};
+use daedalus::minecraft::{
+    AssetsIndex, DownloadType, LoggingConfiguration, LoggingSide, VersionInfo,
+    VersionManifest,
+};
+use daedalus::modded::Manifest;
+use futures::AsyncReadExt;
+use path_util::SafeRelativeUtf8UnixPathBuf;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio_util::compat::FuturesAsyncWriteCompatExt;
+
+use crate::State;
+use crate::api::storage::gc::get_version_libraries;
+use crate::event::LoadingBarType;
+use crate::event::emit::{emit_loading, init_loading};
+use crate::event::{ProfilePayloadType, emit::emit_profile};
+use crate::launcher::{self, download};
+use crate::prelude::ModLoader;
+use crate::profile::{
+    self, add_all_recursive_folder_paths, pack_get_relative_path,
+    verify::hash_file,
+};
+use crate::state::{
+    CacheValue, CachedEntry, CachedLoaderManifest, JavaVersion,
+    ProfileInstallStage,
+};
+use crate::util::io::{self, IOError};
+
+pub const OFFLINE_BUNDLE_FORMAT_VERSION: u32 = 1;
+pub const OFFLINE_BUNDLE_MANIFEST: &str = "offline_bundle.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OfflineBundleManifest {
+    pub format_version: u32,
+    pub name: String,
+    pub game_version: String,
+    pub loader: ModLoader,
+    /// The resolved ID of the loader version
+    pub loader_version: Option<String>,
+    /// The ID of the version directory the game is launched from
+    pub version_id: String,
+    pub java: Option<BundledJava>,
+    pub icon: Option<String>,
+    /// Metadata the launcher needs to resolve the game and loader versions
+    pub minecraft_manifest: VersionManifest,
+    /// The loader manifest, only containing the bundled game version
+    pub loader_manifest: Option<Manifest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundledJava {
+    pub major_version: u32,
+    /// Path of the Java executable, relative to the Java versions directory
+    pub path: SafeRelativeUtf8UnixPathBuf,
+}
+
+/// Exports a profile with its game files, and optionally its Java runtime,
+/// so it can be imported without network access
+#[tracing::instrument]
+pub async fn export_offline_bundle(
+    profile_path: &str,
+    export_path: PathBuf,
+    included_export_candidates: Vec<String>,
+    include_java: bool,
+) -> crate::Result<()> {
+    let state = State::get().await?;
+    let profile = profile::get(profile_path).await?.ok_or_else(|| {
+        crate::ErrorKind::UnmanagedProfileError(profile_path.to_string())
+            .as_error()
+    })?;
+
+    if profile.install_stage != ProfileInstallStage::Installed {
+        return Err(crate::ErrorKind::InputError(
+            "Only installed profiles can be exported as an offline bundle"
+                .to_string(),
+        )
+        .into());
+    }
+
+    let (minecraft, version_index) =
+        launcher::resolve_minecraft_manifest(&profile.game_version, &state)
+            .await?;
+    let loader_version = launcher::get_loader_version_from_profile(
+        &profile.game_version,
+        profile.loader,
+        profile.loader_version.as_deref(),
+    )
+    .await?;
+    let version_info = download::download_version_info(
+        &state,
+        &minecraft.versions[version_index],
+        loader_version.as_ref(),
+        None,
+        None,
+    )
+    .await?;
+    let assets_index =
+        download::download_assets_index(&state, &version_info, None, false)
+            .await?;
+
+    let loader_manifest = if let Some(loader_version) = &loader_version {
+        let mut manifest = crate::api::metadata::get_loader_versions(
+            profile.loader.as_meta_str(),
+        )
+        .await?;
+        manifest.game_versions.retain(|x| {
+            x.id.replace(
+                daedalus::modded::DUMMY_REPLACE_STRING,
+                &profile.game_version,
+            ) == profile.game_version
+        });
+        for game_version in &mut manifest.game_versions {
+            game_version.loaders.retain(|x| x.id == loader_version.id);
+        }
+        Some(manifest)
+    } else {
+        None
+    };
+
+    let directories = &state.directories;
+    let metadata_dir = directories.metadata_dir();
+    let mut meta_paths = vec![
+        directories.version_dir(&version_info.id),
+        directories.version_natives_dir(&version_info.id),
+        directories
+            .assets_index_dir()
+            .join(format!("{}.json", version_info.asset_index.id)),
+    ];
+    meta_paths.extend(
+        get_version_libraries(&version_info)
+            .into_iter()
+            .map(|x| directories.libraries_dir().join(x)),
+    );
+    meta_paths.extend(
+        assets_index
+            .objects
+            .values()
+            .map(|x| directories.object_dir(&x.hash)),
+    );
+    if version_info.assets == "legacy" {
+        meta_paths.extend(assets_index.objects.keys().map(|name| {
+            directories.legacy_assets_dir().join(
+                name.replace('/', &String::from(std::path::MAIN_SEPARATOR)),
+            )
+        }));
+    }
+    if let Some(LoggingConfiguration::Log4j2Xml { file, .. }) = version_info
+        .logging
+        .as_ref()
+        .and_then(|x| x.get(&LoggingSide::Client))
+    {
+        meta_paths.push(directories.log_configs_dir().join(&file.id));
+    }
+
+    let java = if include_java {
+        let java_version =
+            launcher::get_java_version_from_profile(&profile, &version_info)
+                .await?
+                .ok_or_else(|| {
+                    crate::ErrorKind::LauncherError(
+                        "Missing correct java installation".to_string(),
+                    )
+                })?;
+        let java_versions_dir = directories.java_versions_dir();
+        let java_path = PathBuf::from(&java_version.path);
+        let relative = java_path.strip_prefix(&java_versions_dir).map_err(|_| {
+            crate::ErrorKind::InputError(
+                "Only Java installed by the app can be included in an offline bundle"
+                    .to_string(),
+            )
+        })?;
+        if let Some(dir) = relative.components().next() {
+            meta_paths.push(java_versions_dir.join(dir));
+        }
+
+        Some(BundledJava {
+            major_version: java_version.parsed_version,
+            path: pack_get_relative_path(&java_versions_dir, &java_path)?,
+        })
+    } else {
+        None
+    };
+
+    let mut meta_files = Vec::new();
+    for path in meta_paths {
+        if path.is_dir() {
+            add_all_recursive_folder_paths(&path, &mut meta_files).await?;
+        } else if path.is_file() {
+            meta_files.push(path);
+        }
+    }
+
+    let profile_base_path = profile::get_full_path(profile_path).await?;
+    let included_candidates = included_export_candidates
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let mut profile_files = Vec::new();
+    add_all_recursive_folder_paths(&profile_base_path, &mut profile_files)
+        .await?;
+    let mut override_files = Vec::new();
+    for path in profile_files {
+        let relative_path = pack_get_relative_path(&profile_base_path, &path)?;
+        if included_candidates
+            .iter()
+            .any(|x| relative_path.starts_with(&**x))
+        {
+            override_files.push((path, relative_path));
+        }
+    }
+
+    let icon = profile
+        .icon_path
+        .as_ref()
+        .map(PathBuf::from)
+        .filter(|x| x.is_file());
+    let manifest = OfflineBundleManifest {
+        format_version: OFFLINE_BUNDLE_FORMAT_VERSION,
+        name: profile.name.clone(),
+        game_version: profile.game_version.clone(),
+        loader: profile.loader,
+        loader_version: loader_version.map(|x| x.id),
+        version_id: version_info.id.clone(),
+        java,
+        icon: icon
+            .as_ref()
+            .and_then(|x| x.file_name())
+            .map(|x| x.to_string_lossy().into_owned()),
+        minecraft_manifest: minecraft,
+        loader_manifest,
+    };
+    let loading_bar = init_loading(
+        LoadingBarType::ZipExtract {
+            profile_path: profile.path.clone(),
+            profile_name: profile.name.clone(),
+        },
+        (meta_files.len() + override_files.len()) as f64,
+        "Exporting offline bundle",
+    )
+    .await?;
+
+    let file = File::create(&export_path)
+        .await
+        .map_err(|e| IOError::with_path(e, &export_path))?;
+    let mut writer = ZipFileWriter::with_tokio(file);
+
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new(
+                OFFLINE_BUNDLE_MANIFEST.to_string().into(),
+                Compression::Deflate,
+            ),
+            &serde_json::to_vec(&manifest)?,
+        )
+        .await?;
+
+    for path in meta_files {
+        let relative_path = pack_get_relative_path(&metadata_dir, &path)?;
+        write_file_entry(&mut writer, &path, &format!("meta/{relative_path}"))
+            .await?;
+        emit_loading(&loading_bar, 1.0, None)?;
+    }
+
+    for (path, relative_path) in override_files {
+        write_file_entry(
+            &mut writer,
+            &path,
+            &format!("overrides/{relative_path}"),
+        )
+        .await?;
+        emit_loading(&loading_bar, 1.0, None)?;
+    }
+
+    if let (Some(icon), Some(name)) = (&icon, &manifest.icon) {
+        write_file_entry(&mut writer, icon, &format!("icon/{name}")).await?;
+    }
+
+    writer.close().await?;
+
+    Ok(())
+}
+
+/// Imports an offline bundle as a new profile without network access.
+/// Returns the path of the created profile
+#[tracing::instrument]
+pub async fn import_offline_bundle(
+    bundle_path: PathBuf,
+) -> crate::Result<String> {
+    let state = State::get().await?;
+    let zip = ZipFileReader::new(&bundle_path).await.map_err(|_| {
+        crate::Error::from(crate::ErrorKind::InputError(
+            "Failed to read offline bundle".to_string(),
+        ))
+    })?;
+
+    let manifest_idx = zip
+        .file()
+        .entries()
+        .iter()
+        .position(|x| {
+            matches!(x.filename().as_str(), Ok(OFFLINE_BUNDLE_MANIFEST))
+        })
+        .ok_or_else(|| {
+            crate::ErrorKind::InputError(
+                "No offline bundle manifest found".to_string(),
+            )
+        })?;
+    let mut data = Vec::new();
+    zip.reader_with_entry(manifest_idx)
+        .await?
+        .read_to_end_checked(&mut data)
+        .await?;
+    let manifest: OfflineBundleManifest = serde_json::from_slice(&data)?;
+
+    if manifest.format_version > OFFLINE_BUNDLE_FORMAT_VERSION {
+        return Err(crate::ErrorKind::InputError(format!(
+            "Offline bundle format {} is not supported, please update the app",
+            manifest.format_version
+        ))
+        .into());
+    }
+
+    seed_metadata(&state, &manifest).await?;
+
+    let profile_path = profile::create::profile_create(
+        manifest.name.clone(),
+        manifest.game_version.clone(),
+        manifest.loader,
+        manifest.loader_version.clone(),
+        None,
+        None,
+        Some(true),
+    )
+    .await?;
+
+    let result = async {
+        install_bundle_files(&state, &zip, &manifest, &profile_path).await?;
+
+        crate::api::profile::edit(&profile_path, |prof| {
+            prof.install_stage = ProfileInstallStage::Installed;
+
+            async { Ok(()) }
+        })
+        .await?;
+        emit_profile(&profile_path, ProfilePayloadType::Edited).await?;
+
+        Ok::<_, crate::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Ok(profile_path),
+        Err(err) => {
+            let _ = profile::remove(&profile_path).await;
+
+            Err(err)
+        }
+    }
+}
+
+/// Adds the bundled manifests to the metadata cache, unless the cached
+/// manifests already contain the bundled versions
+async fn seed_metadata(
+    state: &State,
+    manifest: &OfflineBundleManifest,
+) -> crate::Result<()> {
+    let cached_minecraft = CachedEntry::get_minecraft_manifest(
+        None,
+        &state.pool,
+        &state.api_semaphore,
+    )
+    .await?;
+    if !cached_minecraft.is_some_and(|x| {
+        x.versions.iter().any(|x| x.id == manifest.game_version)
+    }) {
+        CachedEntry::insert_expired(
+            CacheValue::MinecraftManifest(manifest.minecraft_manifest.clone()),
+            &state.pool,
+        )
+        .await?;
+    }
+
+    if let (Some(loader_manifest), Some(loader_version)) =
+        (&manifest.loader_manifest, &manifest.loader_version)
+    {
+        let loader = manifest.loader.as_meta_str();
+        let cached_loader = CachedEntry::get_loader_manifest(
+            loader,
+            None,
+            &state.pool,
+            &state.api_semaphore,
+        )
+        .await?;
+
+        let has_loader_version = cached_loader.as_ref().is_some_and(|x| {
+            x.manifest.game_versions.iter().any(|x| {
+                x.id.replace(
+                    daedalus::modded::DUMMY_REPLACE_STRING,
+                    &manifest.game_version,
+                ) == manifest.game_version
+                    && x.loaders.iter().any(|x| x.id == *loader_version)
+            })
+        });
+
+        if !has_loader_version {
+            // Bundled versions go first, as loader versions are looked up in
+            // the first matching game version
+            let mut game_versions = loader_manifest.game_versions.clone();
+            if let Some(cached_loader) = cached_loader {
+                game_versions.extend(cached_loader.manifest.game_versions);
+            }
+
+            CachedEntry::insert_expired(
+                CacheValue::LoaderManifest(CachedLoaderManifest {
+                    loader: loader.to_string(),
+                    manifest: Manifest { game_versions },
+                }),
+                &state.pool,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn install_bundle_files(
+    state: &State,
+    zip: &ZipFileReader,
+    manifest: &OfflineBundleManifest,
+    profile_path: &str,
+) -> crate::Result<()> {
+    let profile = profile::get(profile_path).await?.ok_or_else(|| {
+        crate::ErrorKind::UnmanagedProfileError(profile_path.to_string())
+            .as_error()
+    })?;
+    let profile_dir = profile::get_full_path(profile_path).await?;
+    let metadata_dir = state.directories.metadata_dir();
+    let java_versions_dir = state.directories.java_versions_dir();
+
+    // A Java runtime that already exists is kept as is, so runtimes are
+    // never mixed
+    let bundled_java_dir = manifest.java.as_ref().and_then(|java| {
+        java.path
+            .components()
+            .next()
+            .map(|x| java_versions_dir.join(x.as_str()))
+    });
+    let skip_java = bundled_java_dir.as_ref().is_some_and(|x| x.exists());
+
+    // The bundled version info and asset index list the hashes of the
+    // metadata files
+    let version_info = read_named_entry(
+        zip,
+        &format!("meta/versions/{id}/{id}.json", id = manifest.version_id),
+    )
+    .await?
+    .ok_or_else(|| {
+        crate::ErrorKind::InputError(
+            "Offline bundle is missing the version info".to_string(),
+        )
+    })?;
+    let version_info: VersionInfo = serde_json::from_slice(&version_info)?;
+    let assets_index = read_named_entry(
+        zip,
+        &format!("meta/assets/indexes/{}.json", version_info.asset_index.id),
+    )
+    .await?
+    .map(|x| serde_json::from_slice::<AssetsIndex>(&x))
+    .transpose()?;
+    let hashes = bundle_hashes(&version_info, assets_index.as_ref());
+
+    let entries = zip.file().entries();
+    let loading_bar = init_loading(
+        LoadingBarType::ZipExtract {
+            profile_path: profile.path.clone(),
+            profile_name: profile.name.clone(),
+        },
+        entries.len() as f64,
+        "Importing offline bundle",
+    )
+    .await?;
+
+    let mut icon = None;
+    for (index, entry) in entries.iter().enumerate() {
+        emit_loading(&loading_bar, 1.0, None)?;
+
+        let Ok(filename) = entry.filename().as_str() else {
+            continue;
+        };
+        if filename.ends_with('/') {
+            continue;
+        }
+
+        let (target, hash) = if let Some(path) = filename.strip_prefix("meta/")
+        {
+            let path = safe_path(path)?;
+            let target = metadata_dir.join(path.as_str());
+            let is_java = bundled_java_dir
+                .as_ref()
+                .is_some_and(|dir| target.starts_with(dir));
+            if is_java && skip_java {
+                continue;
+            }
+
+            // Metadata may be shared with other profiles, so installed
+            // files are kept unless they don't match their known hash
+            let hash = hashes.get(path.as_str()).map(|x| &**x);
+            if target.exists() {
+                let Some(hash) = hash else {
+                    continue;
+                };
+                let installed = hash_file(&target, &state.io_semaphore).await?;
+                if installed.as_deref() == Some(hash) {
+                    continue;
+                }
+            }
+            (target, hash)
+        } else if let Some(path) = filename.strip_prefix("overrides/") {
+            (profile_dir.join(safe_path(path)?.as_str()), None)
+        } else if let Some(name) = filename.strip_prefix("icon/") {
+            icon = Some((name.to_string(), read_entry(zip, index).await?));
+            continue;
+        } else {
+            continue;
+        };
+
+        extract_entry(zip, index, &target, hash, entry.unix_permissions())
+            .await?;
+    }
+
+    if let Some((name, data)) = icon {
+        let mut profile = profile;
+        profile
+            .set_icon(
+                &state.directories.caches_dir(),
+                &state.io_semaphore,
+                bytes::Bytes::from(data),
+                &name,
+            )
+            .await?;
+        profile.upsert(&state.pool).await?;
+    }
+
+    if let Some(java) = &manifest.java {
+        let existing = JavaVersion::get(java.major_version, &state.pool)
+            .await?
+            .filter(|x| Path::new(&x.path).exists());
+        if existing.is_none() {
+            let java = crate::api::jre::check_jre(
+                java_versions_dir.join(java.path.as_str()),
+            )
+            .await?;
+            java.upsert(&state.pool).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the SHA-1 hashes of the metadata files listed by the version info
+/// and asset index, keyed by their path relative to the metadata directory
+fn bundle_hashes(
+    version_info: &VersionInfo,
+    assets_index: Option<&AssetsIndex>,
+) -> HashMap<String, String> {
+    let mut hashes = HashMap::new();
+
+    if let Some(client) = version_info.downloads.get(&DownloadType::Client) {
+        hashes.insert(
+            format!("versions/{id}/{id}.jar", id = version_info.id),
+            client.sha1.clone(),
+        );
+    }
+
+    for library in &version_info.libraries {
+        if let Some(artifact) =
+            library.downloads.as_ref().and_then(|x| x.artifact.as_ref())
+            && let Ok(path) = daedalus::get_path_from_artifact(&library.name)
+        {
+            hashes.insert(format!("libraries/{path}"), artifact.sha1.clone());
+        }
+    }
+
+    if let Some(assets_index) = assets_index {
+        for (name, asset) in &assets_index.objects {
+            if asset.hash.len() < 2 {
+                continue;
+            }
+            hashes.insert(
+                format!("assets/objects/{}/{}", &asset.hash[..2], asset.hash),
+                asset.hash.clone(),
+            );
+            if version_info.assets == "legacy" {
+                hashes.insert(format!("resources/{name}"), asset.hash.clone());
+            }
+        }
+    }
+
+    if let Some(LoggingConfiguration::Log4j2Xml { file, .. }) = version_info
+        .logging
+        .as_ref()
+        .and_then(|x| x.get(&LoggingSide::Client))
+    {
+        hashes.insert(format!("log_configs/{}", file.id), file.sha1.clone());
+    }
+
+    hashes
+}
+
+/// Reads an entry, checking it against its CRC
+async fn read_entry(
+    zip: &ZipFileReader,
+    index: usize,
+) -> crate::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    zip.reader_with_entry(index)
+        .await?
+        .read_to_end_checked(&mut data)
+        .await?;
+
+    Ok(data)
+}
+
+async fn read_named_entry(
+    zip: &ZipFileReader,
+    name: &str,
+) -> crate::Result<Option<Vec<u8>>> {
+    let index = zip
+        .file()
+        .entries()
+        .iter()
+        .position(|x| matches!(x.filename().as_str(), Ok(x) if x == name));
+
+    match index {
+        Some(index) => Ok(Some(read_entry(zip, index).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Streams an entry to a file next to `target`, checking it against its CRC
+/// and SHA-1 hash if it has one, and renames it over `target`, so an
+/// interrupted import never leaves a partial file behind
+async fn extract_entry(
+    zip: &ZipFileReader,
+    index: usize,
+    target: &Path,
+    hash: Option<&str>,
+    #[allow(unused_variables)] unix_permissions: Option<u16>,
+) -> crate::Result<()> {
+    if let Some(parent) = target.parent() {
+        io::create_dir_all(parent).await?;
+    }
+
+    let mut file_name = target.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".import.tmp");
+    let temp = target.with_file_name(file_name);
+
+    let result = async {
+        let mut reader = zip.reader_with_entry(index).await?;
+        let mut file = File::create(&temp)
+            .await
+            .map_err(|e| IOError::with_path(e, &temp))?;
+        let mut hasher = sha1_smol::Sha1::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .await
+                .map_err(|e| IOError::with_path(e, &temp))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read])
+                .await
+                .map_err(|e| IOError::with_path(e, &temp))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| IOError::with_path(e, &temp))?;
+        drop(file);
+
+        if reader.compute_hash() != reader.entry().crc32() {
+            return Err(async_zip::error::ZipError::CRC32CheckError.into());
+        }
+        if let Some(hash) = hash
+            && hasher.digest().to_string() != hash
+        {
+            return Err(crate::ErrorKind::InputError(format!(
+                "Offline bundle file {} is corrupted",
+                target.display()
+            ))
+            .into());
+        }
+
+        #[cfg(unix)]
+        if let Some(mode) = unix_permissions {
+            use std::os::unix::fs::PermissionsExt;
+
+            std::fs::set_permissions(
+                &temp,
+                std::fs::Permissions::from_mode(u32::from(mode) & 0o777),
+            )
+            .map_err(|e| IOError::with_path(e, &temp))?;
+        }
+
+        io::rename_or_move(&temp, target).await?;
+
+        Ok::<_, crate::Error>(())
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = io::remove_file(&temp).await;
+    }
+
+    result
+}
+
+fn safe_path(path: &str) -> crate::Result<SafeRelativeUtf8UnixPathBuf> {
+    Ok(SafeRelativeUtf8UnixPathBuf::try_from(path.to_string())?)
+}
+
+async fn write_file_entry(
+    writer: &mut ZipFileWriter<File>,
+    path: &Path,
+    name: &str,
+) -> crate::Result<()> {
+    let mut source = File::open(path)
+        .await
+        .map_err(|e| IOError::with_path(e, path))?;
+
+    #[allow(unused_mut)]
+    let mut builder =
+        ZipEntryBuilder::new(name.to_string().into(), Compression::Deflate);
+
+    // Keeps the Java executables executable
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = source
+            .metadata()
+            .await
+            .map_err(|e| IOError::with_path(e, path))?;
+        builder = builder
+            .unix_permissions((metadata.permissions().mode() & 0o777) as u16);
+    }
+
+    let mut stream = writer
+        .write_entry_stream(builder.build())
+        .await?
+        .compat_write();
+    tokio::io::copy(&mut source, &mut stream)
+        .await
+        .map_err(|e| IOError::with_path(e, path))?;
+    stream.into_inner().close().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_info() -> VersionInfo {
+        serde_json::from_value(serde_json::json!({
+            "assetIndex": {
+                "id": "legacy",
+                "sha1": "index",
+                "size": 0,
+                "totalSize": 0,
+                "url": "",
+            },
+            "assets": "legacy",
+            "downloads": {
+                "client": { "sha1": "client", "size": 0, "url": "" },
+            },
+            "id": "1.5.2",
+            "libraries": [
+                {
+                    "name": "com.example:library:1.0",
+                    "downloads": {
+                        "artifact": { "sha1": "library", "size": 0, "url": "" },
+                    },
+                },
+                { "name": "com.example:unhashed:1.0" },
+            ],
+            "logging": {
+                "client": {
+                    "type": "log4j2-xml",
+                    "argument": "",
+                    "file": {
+                        "id": "client.xml",
+                        "sha1": "log",
+                        "size": 0,
+                        "url": "",
+                    },
+                },
+            },
+            "mainClass": "",
+            "minimumLauncherVersion": 0,
+            "releaseTime": "2013-04-25T15:45:00Z",
+            "time": "2013-04-25T15:45:00Z",
+            "type": "release",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn hashes_known_metadata_files() {
+        let assets_index: AssetsIndex =
+            serde_json::from_value(serde_json::json!({
+                "objects": {
+                    "sounds/click.ogg": { "hash": "abcdef", "size": 0 },
+                },
+            }))
+            .unwrap();
+
+        let hashes = bundle_hashes(&version_info(), Some(&assets_index));
+        let hash = |path: &str| hashes.get(path).map(|x| &**x);
+
+        assert_eq!(hash("versions/1.5.2/1.5.2.jar"), Some("client"));
+        assert_eq!(
+            hash("libraries/com/example/library/1.0/library-1.0.jar"),
+            Some("library")
+        );
+        assert_eq!(hash("assets/objects/ab/abcdef"), Some("abcdef"));
+        assert_eq!(hash("resources/sounds/click.ogg"), Some("abcdef"));
+        assert_eq!(hash("log_configs/client.xml"), Some("log"));
+        assert_eq!(hashes.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupt_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.zip");
+        let data = b"bundled library contents";
+
+        write_zip(&path, "meta/libraries/library.jar", data).await;
+
+        let zip = ZipFileReader::new(&path).await.unwrap();
+        assert_eq!(read_entry(&zip, 0).await.unwrap(), data);
+        assert_eq!(
+            read_named_entry(&zip, "meta/libraries/library.jar")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(&data[..])
+        );
+        assert!(read_named_entry(&zip, "missing").await.unwrap().is_none());
+
+        // Flip a byte of the stored contents, so the CRC no longer matches
+        let mut bytes = std::fs::read(&path).unwrap();
+        let offset = bytes.windows(data.len()).position(|x| x == data).unwrap();
+        bytes[offset] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        let zip = ZipFileReader::new(&path).await.unwrap();
+        assert!(read_entry(&zip, 0).await.is_err());
+        let target = dir.path().join("library.jar");
+        assert!(extract_entry(&zip, 0, &target, None, None).await.is_err());
+        assert!(!target.exists());
+    }
+
+    async fn write_zip(path: &Path, name: &str, data: &[u8]) {
+        let mut writer =
+            ZipFileWriter::with_tokio(File::create(path).await.unwrap());
+        writer
+            .write_entry_whole(
+                ZipEntryBuilder::new(
+                    name.to_string().into(),
+                    Compression::Stored,
+                ),
+                data,
+            )
+            .await
+            .unwrap();
+        writer.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_files_not_matching_their_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.zip");
+        let data = b"bundled file";
+        write_zip(&path, "meta/libraries/library.jar", data).await;
+        let zip = ZipFileReader::new(&path).await.unwrap();
+
+        let target = dir.path().join("libraries").join("library.jar");
+        let hash = sha1_smol::Sha1::from(data).hexdigest();
+        extract_entry(&zip, 0, &target, Some(&hash), None)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), data);
+
+        let target = dir.path().join("libraries").join("corrupt.jar");
+        assert!(
+            extract_entry(&zip, 0, &target, Some("corrupt"), None)
+                .await
+                .is_err()
+        );
+        assert!(!target.exists());
+        assert_eq!(
+            std::fs::read_dir(target.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn replaces_files_left_by_an_interrupted_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.zip");
+        write_zip(&path, "overrides/library.jar", b"complete").await;
+        let zip = ZipFileReader::new(&path).await.unwrap();
+
+        let target = dir.path().join("libraries").join("library.jar");
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&target, b"partial").unwrap();
+        std::fs::write(target.with_file_name("library.jar.import.tmp"), b"")
+            .unwrap();
+
+        extract_entry(&zip, 0, &target, None, None).await.unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), b"complete");
+        assert_eq!(
+            std::fs::read_dir(target.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Data structures for the packwiz modpack format
+//! (`pack.toml`, `index.toml` and `.pw.toml` metadata files)
+//! See <https://packwiz.infra.link/reference/pack-format/>
+
+use path_util::SafeRelativeUtf8UnixPathBuf;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::collections::BTreeMap;
+
+pub const PACKWIZ_PACK_FORMAT: &str = "packwiz:1.1.0";
+pub const PACKWIZ_METAFILE_EXTENSION: &str = ".pw.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizPack {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack_format: Option<String>,
+    pub index: PackwizIndexReference,
+    /// Maps `minecraft` and loader names (`forge`, `neoforge`, `fabric`, `quilt`) to versions
+    pub versions: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizIndexReference {
+    pub file: SafeRelativeUtf8UnixPathBuf,
+    pub hash_format: PackwizHashFormat,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizIndex {
+    pub hash_format: PackwizHashFormat,
+    #[serde(default)]
+    pub files: Vec<PackwizIndexFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizIndexFile {
+    pub file: SafeRelativeUtf8UnixPathBuf,
+    pub hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash_format: Option<PackwizHashFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<SafeRelativeUtf8UnixPathBuf>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metafile: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preserve: bool,
+}
+
+impl PackwizIndexFile {
+    /// Older packwiz versions did not always write the `metafile` flag, so
+    /// fall back to the file extension
+    pub fn is_metafile(&self) -> bool {
+        self.metafile
+            || self.file.as_str().ends_with(PACKWIZ_METAFILE_EXTENSION)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizMetafile {
+    pub name: String,
+    pub filename: String,
+    #[serde(default)]
+    pub side: PackwizSide,
+    pub download: PackwizDownload,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub option: Option<PackwizOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<PackwizUpdate>,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PackwizSide {
+    #[default]
+    Both,
+    Client,
+    Server,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizDownload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub hash_format: PackwizHashFormat,
+    pub hash: String,
+    /// `metadata:curseforge` for files that must be resolved through the CurseForge API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizOption {
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<PackwizModrinthUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<PackwizCurseForgeUpdate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizModrinthUpdate {
+    pub mod_id: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackwizCurseForgeUpdate {
+    pub file_id: u32,
+    pub project_id: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackwizHashFormat {
+    Sha1,
+    Sha256,
+    Sha512,
+    Murmur2,
+}
+
+impl PackwizHashFormat {
+    /// Hashes the given bytes, returning the hash in the string representation packwiz uses
+    pub async fn hash(self, bytes: bytes::Bytes) -> crate::Result<String> {
+        let hash = tokio::task::spawn_blocking(move || match self {
+            PackwizHashFormat::Sha1 => {
+                sha1_smol::Sha1::from(&bytes).hexdigest()
+            }
+            PackwizHashFormat::Sha256 => {
+                hex_string(&sha2::Sha256::digest(&bytes))
+            }
+            PackwizHashFormat::Sha512 => {
+                hex_string(&sha2::Sha512::digest(&bytes))
+            }
+            PackwizHashFormat::Murmur2 => {
+                curseforge_fingerprint(&bytes).to_string()
+            }
+        })
+        .await?;
+
+        Ok(hash)
+    }
+
+    /// Hashes the given bytes and checks them against the expected hash
+    pub async fn verify(
+        self,
+        bytes: bytes::Bytes,
+        expected: &str,
+    ) -> crate::Result<()> {
+        let hash = self.hash(bytes).await?;
+        if !hash.eq_ignore_ascii_case(expected) {
+            return Err(crate::ErrorKind::HashError(
+                expected.to_string(),
+                hash,
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Computes the CurseForge file fingerprint: a MurmurHash2 of the file,
+/// with all whitespace bytes removed, seeded with 1
+pub fn curseforge_fingerprint(bytes: &[u8]) -> u32 {
+    murmur2::murmur2(
+        &bytes
+            .iter()
+            .copied()
+            .filter(|x| *x != 9 && *x != 10 && *x != 13 && *x != 32)
+            .collect::<Vec<u8>>(),
+        1,
+    )
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut acc, byte| {
+        let _ = write!(acc, "{byte:02x}");
+        acc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"Hello, world!";
+
+    #[test]
+    fn curseforge_fingerprint_skips_whitespace() {
+        // MurmurHash2 of `Hello,world!` seeded with 1
+        assert_eq!(curseforge_fingerprint(DATA), 2173663876);
+        assert_eq!(curseforge_fingerprint(b"Hello,\tworld!\r\n"), 2173663876);
+        assert_ne!(curseforge_fingerprint(b"Hello, world?"), 2173663876);
+    }
+
+    #[tokio::test]
+    async fn hashes_in_every_format() {
+        for (format, hash) in [
+            (
+                PackwizHashFormat::Sha1,
+                "943a702d06f34599aee1f8da8ef9f7296031d699",
+            ),
+            (
+                PackwizHashFormat::Sha256,
+                "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3",
+            ),
+            (
+                PackwizHashFormat::Sha512,
+                "c1527cd893c124773d811911970c8fe6e857d6df5dc9226bd8a160614c0cd963a4ddea2b94bb7d36021ef9d865d5cea294a82dd49a0bb269f51f6e7a57f79421",
+            ),
+            (PackwizHashFormat::Murmur2, "2173663876"),
+        ] {
+            let bytes = bytes::Bytes::from_static(DATA);
+            assert_eq!(format.hash(bytes.clone()).await.unwrap(), hash);
+            format
+                .verify(bytes.clone(), &hash.to_uppercase())
+                .await
+                .unwrap();
+            format.verify(bytes, "0").await.unwrap_err();
+        }
+    }
+
+    #[test]
+    fn parses_index_and_metafiles() {
+        let index: PackwizIndex = toml::from_str(
+            r#"
+            hash-format = "sha256"
+
+            [[files]]
+            file = "config/example.json"
+            hash = "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"
+
+            [[files]]
+            file = "mods/sodium.pw.toml"
+            hash = "943a702d06f34599aee1f8da8ef9f7296031d699"
+            hash-format = "sha1"
+            metafile = true
+
+            [[files]]
+            file = "mods/legacy.pw.toml"
+            hash = "2173663876"
+            hash-format = "murmur2"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(index.hash_format, PackwizHashFormat::Sha256);
+        let formats = index
+            .files
+            .iter()
+            .map(|file| (file.hash_format, file.is_metafile()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            formats,
+            [
+                (None, false),
+                (Some(PackwizHashFormat::Sha1), true),
+                (Some(PackwizHashFormat::Murmur2), true),
+            ]
+        );
+
+        let metafile: PackwizMetafile = toml::from_str(
+            r#"
+            name = "Sodium"
+            filename = "sodium.jar"
+            side = "client"
+
+            [download]
+            url = "https://cdn.modrinth.com/data/AANobbMI/versions/sodium.jar"
+            hash-format = "sha512"
+            hash = "c1527cd893c124773d811911970c8fe6e857d6df5dc9226bd8a160614c0cd963a4ddea2b94bb7d36021ef9d865d5cea294a82dd49a0bb269f51f6e7a57f79421"
+
+            [update.modrinth]
+            mod-id = "AANobbMI"
+            version = "yaoBL9D9"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(metafile.side, PackwizSide::Client);
+        assert_eq!(metafile.download.hash_format, PackwizHashFormat::Sha512);
+        assert_eq!(
+            metafile
+                .update
+                .and_then(|update| update.modrinth)
+                .unwrap()
+                .mod_id,
+            "AANobbMI"
+        );
+
+        let curseforge: PackwizMetafile = toml::from_str(
+            r#"
+            name = "JEI"
+            filename = "jei.jar"
+
+            [download]
+            hash-format = "murmur2"
+            hash = "2173663876"
+            mode = "metadata:curseforge"
+
+            [update.curseforge]
+            file-id = 4593548
+            project-id = 238222
+            "#,
+        )
+        .unwrap();
+        assert_eq!(curseforge.side, PackwizSide::Both);
+        assert!(curseforge.download.url.is_none());
+        assert_eq!(
+            curseforge.download.mode.as_deref(),
+            Some("metadata:curseforge")
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_hash_formats() {
+        toml::from_str::<PackwizIndex>(r#"hash-format = "md5""#).unwrap_err();
+    }
+}
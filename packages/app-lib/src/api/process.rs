@@ -1,6 +1,6 @@
 //! Theseus process management interface
 
-use crate::state::ProcessMetadata;
+use crate::state::{ProcessMetadata, ReportedMod};
 pub use crate::{
     State,
     state::{Hooks, MemorySettings, Profile, Settings, WindowSize},
@@ -30,6 +30,15 @@ pub async fn get_by_profile_path(
     Ok(processes)
 }
 
+// Gets the mods the game running in a profile reported as loaded, if it has
+// reported them through the launcher RPC
+#[tracing::instrument]
+pub async fn get_reported_mods(
+    profile_path: &str,
+) -> crate::Result<Option<Vec<ReportedMod>>> {
+    Ok(crate::state::get_reported_mods(profile_path))
+}
+
 // Kill a child process stored in the state by UUID, as a string
 #[tracing::instrument]
 pub async fn kill(uuid: Uuid) -> crate::Result<()> {
@@ -0,0 +1,647 @@
+//! Exporting profiles to third-party modpack formats (packwiz and CurseForge)
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use async_zip::tokio::write::ZipFileWriter;
+use async_zip::{Compression, ZipEntryBuilder};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::fs::File;
+
+use super::{
+    add_all_recursive_folder_paths, filter_export_candidates, get,
+    get_full_path, pack_get_relative_path,
+};
+use crate::State;
+use crate::event::LoadingBarType;
+use crate::event::emit::{emit_loading, init_loading};
+use crate::pack::packwiz::{
+    PACKWIZ_METAFILE_EXTENSION, PACKWIZ_PACK_FORMAT, PackwizDownload,
+    PackwizHashFormat, PackwizIndex, PackwizIndexFile, PackwizIndexReference,
+    PackwizMetafile, PackwizModrinthUpdate, PackwizOption, PackwizPack,
+    PackwizSide, PackwizUpdate, curseforge_fingerprint,
+};
+use crate::prelude::ModLoader;
+use crate::state::{
+    CacheBehaviour, CachedEntry, Profile, Project, Version, VersionFile,
+};
+use crate::util::fetch::fetch_advanced;
+use crate::util::io::{self, IOError};
+
+const CURSEFORGE_API_URL: &str = "https://api.curseforge.com/v1";
+const CURSEFORGE_MINECRAFT_GAME_ID: u32 = 432;
+
+/// Exports a profile as a packwiz pack (`pack.toml`, `index.toml`, and a
+/// `.pw.toml` metadata file for every Modrinth project) into `export_dir`.
+/// Files that are not known Modrinth projects are copied as-is.
+/// Disabled projects are exported as optional files that are off by default.
+#[tracing::instrument]
+pub async fn export_packwiz(
+    profile_path: &str,
+    export_dir: PathBuf,
+    included_export_candidates: Vec<String>, // which folders/files to include in the export
+    version: Option<String>,
+    author: Option<String>,
+) -> crate::Result<()> {
+    let profile = get_export_profile(profile_path).await?;
+    let profile_base_path = get_full_path(profile_path).await?;
+
+    let path_list = get_included_paths(
+        &profile_base_path,
+        filter_export_candidates(included_export_candidates),
+    )
+    .await?;
+    let modrinth_files = get_modrinth_files(&profile).await?;
+
+    let loading_bar = init_loading(
+        LoadingBarType::ZipExtract {
+            profile_path: profile.path.clone(),
+            profile_name: profile.name.clone(),
+        },
+        path_list.len() as f64,
+        "Exporting profile to packwiz",
+    )
+    .await?;
+
+    io::create_dir_all(&export_dir).await?;
+
+    let mut index_files = Vec::new();
+    for (path, relative_path) in path_list {
+        emit_loading(&loading_bar, 1.0, None)?;
+
+        let (file_path, bytes, metafile) = if let Some(modrinth_file) =
+            modrinth_files.get(&relative_path)
+        {
+            let enabled_path =
+                relative_path.trim_end_matches(".disabled").to_string();
+            let folder = enabled_path
+                .rsplit_once('/')
+                .map(|(folder, _)| format!("{folder}/"))
+                .unwrap_or_default();
+            let slug = modrinth_file
+                .project
+                .slug
+                .as_deref()
+                .unwrap_or(&modrinth_file.project.id);
+
+            let (hash_format, hash) = if let Some(hash) =
+                modrinth_file.file.hashes.get("sha512")
+            {
+                (PackwizHashFormat::Sha512, hash.clone())
+            } else if let Some(hash) = modrinth_file.file.hashes.get("sha1") {
+                (PackwizHashFormat::Sha1, hash.clone())
+            } else {
+                return Err(crate::ErrorKind::OtherError(format!(
+                    "No supported hash found for mod at: {relative_path}"
+                ))
+                .into());
+            };
+
+            let metafile = PackwizMetafile {
+                name: modrinth_file.project.title.clone(),
+                filename: modrinth_file.file.filename.clone(),
+                side: PackwizSide::Both,
+                download: PackwizDownload {
+                    url: Some(modrinth_file.file.url.clone()),
+                    hash_format,
+                    hash,
+                    mode: None,
+                },
+                option: modrinth_file.disabled.then_some(PackwizOption {
+                    optional: true,
+                    default: false,
+                    description: None,
+                }),
+                update: Some(PackwizUpdate {
+                    modrinth: Some(PackwizModrinthUpdate {
+                        mod_id: modrinth_file.project.id.clone(),
+                        version: modrinth_file.version.id.clone(),
+                    }),
+                    curseforge: None,
+                }),
+            };
+
+            (
+                format!("{folder}{slug}{PACKWIZ_METAFILE_EXTENSION}"),
+                bytes::Bytes::from(toml::to_string(&metafile)?),
+                true,
+            )
+        } else {
+            (
+                relative_path,
+                bytes::Bytes::from(io::read(&path).await?),
+                false,
+            )
+        };
+
+        let destination = export_dir.join(&file_path);
+        if let Some(parent) = destination.parent() {
+            io::create_dir_all(parent).await?;
+        }
+        io::write(&destination, &bytes).await?;
+
+        index_files.push(PackwizIndexFile {
+            file: file_path.try_into()?,
+            hash: PackwizHashFormat::Sha256.hash(bytes).await?,
+            hash_format: None,
+            alias: None,
+            metafile,
+            preserve: false,
+        });
+    }
+
+    index_files.sort_by(|a, b| a.file.as_str().cmp(b.file.as_str()));
+    let index = toml::to_string(&PackwizIndex {
+        hash_format: PackwizHashFormat::Sha256,
+        files: index_files,
+    })?;
+    io::write(export_dir.join("index.toml"), index.as_bytes()).await?;
+
+    let mut versions = BTreeMap::new();
+    versions.insert("minecraft".to_string(), profile.game_version.clone());
+    if profile.loader != ModLoader::Vanilla
+        && let Some(loader_version) = &profile.loader_version
+    {
+        versions.insert(
+            profile.loader.as_str().to_string(),
+            loader_version.clone(),
+        );
+    }
+
+    let pack = PackwizPack {
+        name: profile.name.clone(),
+        author,
+        version,
+        description: None,
+        pack_format: Some(PACKWIZ_PACK_FORMAT.to_string()),
+        index: PackwizIndexReference {
+            file: "index.toml".to_string().try_into()?,
+            hash_format: PackwizHashFormat::Sha256,
+            hash: PackwizHashFormat::Sha256
+                .hash(bytes::Bytes::from(index))
+                .await?,
+        },
+        versions,
+    };
+    io::write(
+        export_dir.join("pack.toml"),
+        toml::to_string(&pack)?.as_bytes(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Exports a profile as a CurseForge modpack zip (`manifest.json` and
+/// `overrides/`) at `export_path`.
+/// Content files are matched to CurseForge files by fingerprint, which needs a
+/// CurseForge API key, and referenced in the manifest. Files CurseForge
+/// doesn't know are put in the overrides.
+#[tracing::instrument(skip(curseforge_api_key))]
+pub async fn export_curseforge_manifest(
+    profile_path: &str,
+    export_path: PathBuf,
+    included_export_candidates: Vec<String>, // which folders/files to include in the export
+    version: Option<String>,
+    author: Option<String>,
+    curseforge_api_key: Option<String>,
+) -> crate::Result<()> {
+    // without a key, every file would silently end up in the overrides
+    let Some(api_key) =
+        curseforge_api_key.filter(|api_key| !api_key.trim().is_empty())
+    else {
+        return Err(crate::ErrorKind::InputError(
+            "A CurseForge API key is required to export a CurseForge modpack"
+                .to_string(),
+        )
+        .into());
+    };
+
+    let state = State::get().await?;
+    let profile = get_export_profile(profile_path).await?;
+    let profile_base_path = get_full_path(profile_path).await?;
+
+    let path_list = get_included_paths(
+        &profile_base_path,
+        filter_export_candidates(included_export_candidates),
+    )
+    .await?;
+
+    let loading_bar = init_loading(
+        LoadingBarType::ZipExtract {
+            profile_path: profile.path.clone(),
+            profile_name: profile.name.clone(),
+        },
+        path_list.len() as f64,
+        "Exporting profile to CurseForge modpack",
+    )
+    .await?;
+
+    // Match every content file of the profile, as identified by its Modrinth
+    // hash lookup, against CurseForge files by fingerprint, so they can be
+    // referenced in the manifest
+    let projects = profile
+        .get_projects(None, &state.pool, &state.api_semaphore)
+        .await?;
+
+    let mut fingerprints = HashMap::new();
+    for (path, relative_path) in &path_list {
+        if projects.contains_key(relative_path) {
+            let bytes = io::read(path).await?;
+            let fingerprint = tokio::task::spawn_blocking(move || {
+                curseforge_fingerprint(&bytes)
+            })
+            .await?;
+            fingerprints.insert(relative_path.clone(), fingerprint);
+        }
+    }
+
+    let matched = if fingerprints.is_empty() {
+        HashMap::new()
+    } else {
+        let matches = get_curseforge_fingerprint_matches(
+            &fingerprints.values().copied().collect::<Vec<_>>(),
+            &api_key,
+            &state,
+        )
+        .await?;
+
+        fingerprints
+            .into_iter()
+            .filter_map(|(relative_path, fingerprint)| {
+                Some((relative_path, *matches.get(&fingerprint)?))
+            })
+            .collect()
+    };
+
+    write_curseforge_zip(
+        &profile,
+        &export_path,
+        path_list,
+        matched,
+        version,
+        author,
+        &loading_bar,
+    )
+    .await
+}
+
+async fn write_curseforge_zip(
+    profile: &Profile,
+    export_path: &Path,
+    path_list: Vec<(PathBuf, String)>,
+    matched: HashMap<String, CurseForgeFileReference>,
+    version: Option<String>,
+    author: Option<String>,
+    loading_bar: &crate::event::LoadingBarId,
+) -> crate::Result<()> {
+    let mut file = File::create(export_path)
+        .await
+        .map_err(|e| IOError::with_path(e, export_path))?;
+    let mut writer = ZipFileWriter::with_tokio(&mut file);
+
+    let (files, overrides) = split_curseforge_files(path_list, &matched);
+    emit_loading(loading_bar, files.len() as f64, None)?;
+    for (path, relative_path) in overrides {
+        emit_loading(loading_bar, 1.0, None)?;
+
+        let data = io::read(&path).await?;
+        let builder = ZipEntryBuilder::new(
+            format!("overrides/{relative_path}").into(),
+            Compression::Deflate,
+        );
+        writer.write_entry_whole(builder, &data).await?;
+    }
+
+    let manifest = curseforge_manifest(profile, files, version, author);
+    let data = serde_json::to_vec_pretty(&manifest)?;
+    let builder = ZipEntryBuilder::new(
+        "manifest.json".to_string().into(),
+        Compression::Deflate,
+    );
+    writer.write_entry_whole(builder, &data).await?;
+
+    writer.close().await?;
+
+    Ok(())
+}
+
+/// Splits the exported files into manifest entries for the files matched on
+/// CurseForge, and the files to put in the overrides
+fn split_curseforge_files(
+    path_list: Vec<(PathBuf, String)>,
+    matched: &HashMap<String, CurseForgeFileReference>,
+) -> (Vec<serde_json::Value>, Vec<(PathBuf, String)>) {
+    let mut files = Vec::new();
+    let mut overrides = Vec::new();
+    for (path, relative_path) in path_list {
+        match matched.get(&relative_path) {
+            Some(file) => files.push(json!({
+                "projectID": file.project_id,
+                "fileID": file.file_id,
+                "required": !relative_path.ends_with(".disabled"),
+            })),
+            None => overrides.push((path, relative_path)),
+        }
+    }
+    (files, overrides)
+}
+
+fn curseforge_manifest(
+    profile: &Profile,
+    files: Vec<serde_json::Value>,
+    version: Option<String>,
+    author: Option<String>,
+) -> serde_json::Value {
+    json!({
+        "minecraft": {
+            "version": profile.game_version,
+            "modLoaders": curseforge_mod_loaders(
+                profile.loader,
+                profile.loader_version.as_deref(),
+            ),
+        },
+        "manifestType": "minecraftModpack",
+        "manifestVersion": 1,
+        "name": profile.name,
+        "version": version.unwrap_or_else(|| "1.0.0".to_string()),
+        "author": author.unwrap_or_default(),
+        "files": files,
+        "overrides": "overrides",
+    })
+}
+
+fn curseforge_mod_loaders(
+    loader: ModLoader,
+    loader_version: Option<&str>,
+) -> Vec<serde_json::Value> {
+    match (loader, loader_version) {
+        (ModLoader::Vanilla, _) | (_, None) => Vec::new(),
+        (loader, Some(loader_version)) => vec![json!({
+            "id": format!("{}-{loader_version}", loader.as_str()),
+            "primary": true,
+        })],
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CurseForgeFileReference {
+    project_id: u32,
+    file_id: u32,
+}
+
+#[derive(Deserialize)]
+struct CurseForgeFingerprintResponse {
+    data: CurseForgeFingerprintMatches,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseForgeFingerprintMatches {
+    exact_matches: Vec<CurseForgeFingerprintMatch>,
+}
+
+#[derive(Deserialize)]
+struct CurseForgeFingerprintMatch {
+    id: u32,
+    file: CurseForgeMatchedFile,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseForgeMatchedFile {
+    id: u32,
+    file_fingerprint: u32,
+}
+
+/// Looks up files on CurseForge by fingerprint, returning the matched
+/// project and file IDs keyed by fingerprint
+async fn get_curseforge_fingerprint_matches(
+    fingerprints: &[u32],
+    api_key: &str,
+    state: &State,
+) -> crate::Result<HashMap<u32, CurseForgeFileReference>> {
+    let response = fetch_advanced(
+        Method::POST,
+        &format!(
+            "{CURSEFORGE_API_URL}/fingerprints/{CURSEFORGE_MINECRAFT_GAME_ID}"
+        ),
+        None,
+        Some(json!({ "fingerprints": fingerprints })),
+        Some(("x-api-key", api_key)),
+        None,
+        None,
+        &state.fetch_semaphore,
+        &state.pool,
+    )
+    .await?;
+
+    let response: CurseForgeFingerprintResponse =
+        serde_json::from_slice(&response)?;
+
+    Ok(response
+        .data
+        .exact_matches
+        .into_iter()
+        .map(|x| {
+            (
+                x.file.file_fingerprint,
+                CurseForgeFileReference {
+                    project_id: x.id,
+                    file_id: x.file.id,
+                },
+            )
+        })
+        .collect())
+}
+
+struct ModrinthExportFile {
+    project: Project,
+    version: Version,
+    file: VersionFile,
+    disabled: bool,
+}
+
+/// Resolves the Modrinth project, version and file of every content file in
+/// the profile that is known to Modrinth, keyed by the profile relative path
+async fn get_modrinth_files(
+    profile: &Profile,
+) -> crate::Result<HashMap<String, ModrinthExportFile>> {
+    let state = State::get().await?;
+    let projects = profile
+        .get_projects(
+            Some(CacheBehaviour::MustRevalidate),
+            &state.pool,
+            &state.api_semaphore,
+        )
+        .await?
+        .into_iter()
+        .filter_map(|(path, file)| {
+            file.metadata.map(|metadata| (path, file.hash, metadata))
+        })
+        .collect::<Vec<_>>();
+
+    if projects.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let version_ids = projects
+        .iter()
+        .map(|x| &*x.2.version_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let project_ids = projects
+        .iter()
+        .map(|x| &*x.2.project_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let (versions, modrinth_projects) = tokio::try_join!(
+        CachedEntry::get_version_many(
+            &version_ids,
+            None,
+            &state.pool,
+            &state.api_semaphore,
+        ),
+        CachedEntry::get_project_many(
+            &project_ids,
+            None,
+            &state.pool,
+            &state.api_semaphore,
+        )
+    )?;
+
+    Ok(projects
+        .into_iter()
+        .filter_map(|(path, hash, metadata)| {
+            let version =
+                versions.iter().find(|x| x.id == metadata.version_id)?;
+            let project = modrinth_projects
+                .iter()
+                .find(|x| x.id == metadata.project_id)?;
+
+            // Prefer the file that is actually installed, then the primary file
+            let file = version
+                .files
+                .iter()
+                .find(|x| x.hashes.get("sha1") == Some(&hash))
+                .or_else(|| version.files.iter().find(|x| x.primary))
+                .or_else(|| version.files.first())?;
+
+            Some((
+                path.clone(),
+                ModrinthExportFile {
+                    project: project.clone(),
+                    version: version.clone(),
+                    file: file.clone(),
+                    disabled: path.ends_with(".disabled"),
+                },
+            ))
+        })
+        .collect())
+}
+
+async fn get_export_profile(profile_path: &str) -> crate::Result<Profile> {
+    get(profile_path).await?.ok_or_else(|| {
+        crate::ErrorKind::OtherError(format!(
+            "Tried to export a nonexistent or unloaded profile at path {profile_path}!"
+        ))
+        .into()
+    })
+}
+
+/// Returns every file in the profile that is covered by the included export
+/// candidates, along with its profile relative path
+async fn get_included_paths(
+    profile_base_path: &Path,
+    included_export_candidates: Vec<String>,
+) -> crate::Result<Vec<(PathBuf, String)>> {
+    let mut path_list = Vec::new();
+    add_all_recursive_folder_paths(profile_base_path, &mut path_list).await?;
+
+    let profile_base_path = profile_base_path.to_path_buf();
+    let mut included = Vec::new();
+    for path in path_list {
+        let relative_path = pack_get_relative_path(&profile_base_path, &path)?;
+        if path.is_file()
+            && included_export_candidates
+                .iter()
+                .any(|x| relative_path.starts_with(x.as_str()))
+        {
+            included.push((path, relative_path.to_string()));
+        }
+    }
+
+    Ok(included)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(relative_path: &str) -> (PathBuf, String) {
+        (
+            PathBuf::from("/profile").join(relative_path),
+            relative_path.to_string(),
+        )
+    }
+
+    #[test]
+    fn splits_matched_files_from_overrides() {
+        let matched = HashMap::from([
+            (
+                "mods/sodium.jar".to_string(),
+                CurseForgeFileReference {
+                    project_id: 394468,
+                    file_id: 5000001,
+                },
+            ),
+            (
+                "mods/iris.jar.disabled".to_string(),
+                CurseForgeFileReference {
+                    project_id: 455508,
+                    file_id: 5000002,
+                },
+            ),
+        ]);
+
+        let (files, overrides) = split_curseforge_files(
+            vec![
+                path("mods/sodium.jar"),
+                path("mods/iris.jar.disabled"),
+                path("mods/unknown.jar"),
+                path("config/sodium-options.json"),
+            ],
+            &matched,
+        );
+
+        assert_eq!(
+            files,
+            vec![
+                json!({ "projectID": 394468, "fileID": 5000001, "required": true }),
+                json!({ "projectID": 455508, "fileID": 5000002, "required": false }),
+            ]
+        );
+        assert_eq!(
+            overrides,
+            vec![path("mods/unknown.jar"), path("config/sodium-options.json")]
+        );
+    }
+
+    #[test]
+    fn lists_mod_loader_with_version() {
+        assert_eq!(
+            curseforge_mod_loaders(ModLoader::Fabric, Some("0.16.10")),
+            vec![json!({ "id": "fabric-0.16.10", "primary": true })]
+        );
+        assert!(curseforge_mod_loaders(ModLoader::Forge, None).is_empty());
+        assert!(
+            curseforge_mod_loaders(ModLoader::Vanilla, Some("1.21.4"))
+                .is_empty()
+        );
+    }
+}
@@ -8,9 +8,9 @@ use crate::pack::install_from::{
     EnvType, PackDependency, PackFile, PackFileHash, PackFormat,
 };
 use crate::state::{
-    CacheBehaviour, CachedEntry, ContentItem, Credentials, Dependency,
-    JavaVersion, LinkedModpackInfo, ProcessMetadata, ProfileFile,
-    ProfileInstallStage, ProjectType, SideType,
+    CacheBehaviour, CachedEntry, ContentDiagnostic, ContentFix, ContentItem,
+    Credentials, Dependency, Endpoints, JavaVersion, LinkedModpackInfo,
+    ProcessMetadata, ProfileFile, ProfileInstallStage, ProjectType, SideType,
 };
 
 use crate::event::{ProfilePayloadType, emit::emit_profile};
@@ -39,7 +39,9 @@ use tokio::io::AsyncReadExt;
 use tokio::{fs::File, process::Command, sync::RwLock};
 
 pub mod create;
+pub mod export;
 pub mod update;
+pub mod verify;
 
 #[derive(Debug, Clone)]
 pub enum QuickPlayType {
@@ -181,6 +183,80 @@ pub async fn get_dependencies_as_content_items(
     Ok(items)
 }
 
+/// Check a profile's installed content for problems
+///
+/// Returns missing or disabled required dependencies, duplicate projects and
+/// mods built for another loader or game version, each with a suggested fix
+/// that can be applied with [`apply_content_fix`].
+#[tracing::instrument]
+pub async fn get_content_diagnostics(
+    path: &str,
+    cache_behaviour: Option<CacheBehaviour>,
+) -> crate::Result<Vec<ContentDiagnostic>> {
+    let state = State::get().await?;
+
+    if let Some(profile) = get(path).await? {
+        let diagnostics = crate::state::get_content_diagnostics(
+            &profile,
+            cache_behaviour,
+            &state.pool,
+            &state.api_semaphore,
+        )
+        .await?;
+        Ok(diagnostics)
+    } else {
+        Err(crate::ErrorKind::UnmanagedProfileError(path.to_string())
+            .as_error())
+    }
+}
+
+/// Apply a fix suggested by [`get_content_diagnostics`]
+/// Enables the fix's disabled files and installs its versions, then removes
+/// the files they replace, so a failed download doesn't lose any files
+#[tracing::instrument]
+pub async fn apply_content_fix(
+    path: &str,
+    fix: ContentFix,
+) -> crate::Result<()> {
+    for project in &fix.enable {
+        if project.ends_with(".disabled") {
+            toggle_disable_project(path, project).await?;
+        }
+    }
+
+    // Versions replacing removed files are updates, otherwise they are
+    // missing dependencies
+    let reason = if fix.remove.is_empty() {
+        fetch::DownloadReason::Dependency
+    } else {
+        fetch::DownloadReason::Update
+    };
+    let mut installed = Vec::new();
+    for version_id in &fix.add {
+        installed
+            .push(add_project_from_version(path, version_id, reason).await?);
+    }
+
+    for project in files_to_remove(&fix, &installed) {
+        remove_project(path, project).await?;
+    }
+
+    Ok(())
+}
+
+/// Files of a [`ContentFix`] to remove once its versions were installed.
+/// A replacement may be installed at the same path as the file it replaces,
+/// which must then be kept.
+fn files_to_remove<'a>(
+    fix: &'a ContentFix,
+    installed: &[String],
+) -> impl Iterator<Item = &'a str> {
+    fix.remove
+        .iter()
+        .filter(|project| !installed.contains(project))
+        .map(String::as_str)
+}
+
 /// Get linked modpack info for a profile
 ///
 /// Returns project, version, and owner information for the linked modpack,
@@ -544,6 +620,7 @@ pub async fn add_project_from_path(
         bytes::Bytes::from(file),
         None,
         project_type,
+        Settings::get(&state.pool).await?.shared_content_store,
         &state.io_semaphore,
         &state.pool,
     )
@@ -601,18 +678,8 @@ pub async fn export_mrpack(
         ))
     })?;
 
-    // remove .DS_Store files from included_export_candidates
-    let included_export_candidates = included_export_candidates
-        .into_iter()
-        .filter(|x| {
-            if let Some(f) = PathBuf::from(x).file_name()
-                && f.to_string_lossy().starts_with(".DS_Store")
-            {
-                return false;
-            }
-            true
-        })
-        .collect::<Vec<_>>();
+    let included_export_candidates =
+        filter_export_candidates(included_export_candidates);
 
     let profile_base_path = get_full_path(profile_path).await?;
 
@@ -736,7 +803,24 @@ pub async fn get_pack_export_candidates(
     Ok(path_list)
 }
 
-fn pack_get_relative_path(
+// remove .DS_Store files from included_export_candidates
+fn filter_export_candidates(
+    included_export_candidates: Vec<String>,
+) -> Vec<String> {
+    included_export_candidates
+        .into_iter()
+        .filter(|x| {
+            if let Some(f) = PathBuf::from(x).file_name()
+                && f.to_string_lossy().starts_with(".DS_Store")
+            {
+                return false;
+            }
+            true
+        })
+        .collect()
+}
+
+pub(crate) fn pack_get_relative_path(
     profile_path: &PathBuf,
     path: &PathBuf,
 ) -> crate::Result<SafeRelativeUtf8UnixPathBuf> {
@@ -881,9 +965,9 @@ async fn run_credentials(
             match join_result {
                 Ok(resp) if resp.status().is_success() => {
                     let result = fetch::post_json(
-                        concat!(
-                            env!("MODRINTH_API_BASE_URL"),
-                            "analytics/minecraft-server-play"
+                        &format!(
+                            "{}analytics/minecraft-server-play",
+                            Endpoints::current().api_base_url
                         ),
                         json!({
                             "project_id": &linked_data.project_id,
@@ -979,7 +1063,7 @@ pub async fn try_update_playtime(path: &str) -> crate::Result<()> {
         }
 
         fetch::post_json(
-            concat!(env!("MODRINTH_API_BASE_URL"), "analytics/playtime"),
+            &format!("{}analytics/playtime", Endpoints::current().api_base_url),
             serde_json::to_value(hashmap)?,
             &state.api_semaphore,
             &state.pool,
@@ -1027,6 +1111,19 @@ pub async fn create_mrpack_json(
             dependencies.insert(PackDependency::QuiltLoader, v)
         }
         (crate::prelude::ModLoader::Vanilla, _) => None,
+        (
+            loader @ (crate::prelude::ModLoader::LiteLoader
+            | crate::prelude::ModLoader::Rift
+            | crate::prelude::ModLoader::LegacyFabric
+            | crate::prelude::ModLoader::Babric),
+            Some(_),
+        ) => {
+            return Err(crate::ErrorKind::OtherError(format!(
+                "Modpacks can't depend on {}",
+                loader.as_str()
+            ))
+            .into());
+        }
         _ => {
             return Err(crate::ErrorKind::OtherError(
                 "Loader version mismatch".to_string(),
@@ -1148,3 +1245,32 @@ pub fn sanitize_profile_name(input: &str) -> String {
         "_",
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_replaced_files_after_install() {
+        let fix = ContentFix {
+            remove: vec![
+                "mods/sodium-0.5.jar".to_string(),
+                "mods/lithium.jar".to_string(),
+            ],
+            add: vec!["version".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            files_to_remove(&fix, &["mods/sodium-0.6.jar".to_string()])
+                .collect::<Vec<_>>(),
+            vec!["mods/sodium-0.5.jar", "mods/lithium.jar"]
+        );
+        // the replacement was written over the file it replaces
+        assert_eq!(
+            files_to_remove(&fix, &["mods/lithium.jar".to_string()])
+                .collect::<Vec<_>>(),
+            vec!["mods/sodium-0.5.jar"]
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! Verification and repair of installed profiles.
+//!
+//! Game files are checked against the SHA-1 hashes in the version info and
+//! asset index of the profile's version, and content files against the
+//! hashes recorded for them. Repairing only downloads the files that are
+//! missing or do not match again.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use daedalus::minecraft::{
+    AssetsIndex, DownloadType, LibraryDownloads, LoggingConfiguration,
+    LoggingSide, VersionInfo as GameVersionInfo,
+};
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::event::LoadingBarType;
+use crate::event::emit::{
+    emit_loading, init_loading, loading_try_for_each_concurrent,
+};
+use crate::launcher::{self, download, parse_rules};
+use crate::profile::{QuickPlayType, get};
+use crate::state::{CachedEntry, ProfileInstallStage, content_store};
+use crate::util::fetch::{self, IoSemaphore};
+use crate::{State, state::Profile};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifiedFileKind {
+    Client,
+    Library,
+    Natives,
+    AssetIndex,
+    Asset,
+    LogConfig,
+    Content,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VerificationProblem {
+    Missing,
+    HashMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// The file exists but could not be parsed
+    Corrupted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerificationIssue {
+    pub kind: VerifiedFileKind,
+    pub path: PathBuf,
+    pub problem: VerificationProblem,
+    /// Whether the file was downloaded again
+    pub repaired: bool,
+    /// Why the file could not be repaired
+    pub repair_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerificationReport {
+    /// Number of files that were checked
+    pub checked: usize,
+    pub issues: Vec<VerificationIssue>,
+}
+
+/// Where a file can be downloaded from again
+#[derive(Debug, Clone)]
+enum FileSource {
+    Url(String),
+    /// A content file, which can be downloaded from its Modrinth version
+    Content {
+        project_path: String,
+        version_id: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct ExpectedFile {
+    kind: VerifiedFileKind,
+    path: PathBuf,
+    sha1: String,
+    source: FileSource,
+}
+
+/// Checks the game files and content files of a profile, and downloads the
+/// files that are missing or corrupted again if `repair` is set
+#[tracing::instrument]
+pub async fn verify(
+    profile_path: &str,
+    repair: bool,
+) -> crate::Result<VerificationReport> {
+    let state = State::get().await?;
+    let profile = get(profile_path).await?.ok_or_else(|| {
+        crate::ErrorKind::UnmanagedProfileError(profile_path.to_string())
+            .as_error()
+    })?;
+
+    if profile.install_stage != ProfileInstallStage::Installed {
+        return Err(crate::ErrorKind::InputError(
+            "Only installed profiles can be verified".to_string(),
+        )
+        .into());
+    }
+
+    let loading_bar = init_loading(
+        LoadingBarType::ProfileVerify {
+            profile_path: profile.path.clone(),
+            profile_name: profile.name.clone(),
+        },
+        100.0,
+        if repair {
+            "Repairing profile"
+        } else {
+            "Verifying profile"
+        },
+    )
+    .await?;
+
+    let (minecraft, version_index) =
+        launcher::resolve_minecraft_manifest(&profile.game_version, &state)
+            .await?;
+    let version = &minecraft.versions[version_index];
+    let minecraft_updated = version_index
+        <= minecraft
+            .versions
+            .iter()
+            .position(|x| x.id == "22w16a")
+            .unwrap_or(0);
+
+    let loader_version = launcher::get_loader_version_from_profile(
+        &profile.game_version,
+        profile.loader,
+        profile.loader_version.as_deref(),
+    )
+    .await?;
+
+    let version_info = download::download_version_info(
+        &state,
+        version,
+        loader_version.as_ref(),
+        None,
+        None,
+    )
+    .await?;
+
+    let java_arch =
+        launcher::get_java_version_from_profile(&profile, &version_info)
+            .await?
+            .map_or_else(
+                || std::env::consts::ARCH.to_string(),
+                |x| x.architecture,
+            );
+
+    let mut issues = Vec::new();
+    let mut checked = 0;
+
+    // The asset index is stored re-serialized, so it is checked by parsing
+    // it instead of by its hash
+    checked += 1;
+    let index_path = state
+        .directories
+        .assets_index_dir()
+        .join(format!("{}.json", version_info.asset_index.id));
+    let mut assets_index = read_assets_index(&index_path).await;
+    if assets_index.is_none() {
+        let mut issue = VerificationIssue {
+            kind: VerifiedFileKind::AssetIndex,
+            problem: if index_path.exists() {
+                VerificationProblem::Corrupted
+            } else {
+                VerificationProblem::Missing
+            },
+            path: index_path,
+            repaired: false,
+            repair_error: None,
+        };
+
+        if repair {
+            match download::download_assets_index(
+                &state,
+                &version_info,
+                None,
+                true,
+            )
+            .await
+            {
+                Ok(index) => {
+                    assets_index = Some(index);
+                    issue.repaired = true;
+                }
+                Err(err) => issue.repair_error = Some(err.to_string()),
+            }
+        }
+
+        issues.push(issue);
+    }
+    emit_loading(&loading_bar, 5.0, None)?;
+
+    let mut expected = get_game_files(
+        &state,
+        &version_info,
+        assets_index.as_ref(),
+        &java_arch,
+        minecraft_updated,
+    );
+    expected.extend(get_content_files(&state, &profile).await?);
+
+    // Natives are extracted from archives, so there is nothing to hash and
+    // only their presence is checked
+    let has_natives = version_info.libraries.iter().any(|x| {
+        x.natives_os_key_and_classifiers(&java_arch).is_some()
+            && x.rules.as_ref().is_none_or(|rules| {
+                parse_rules(
+                    rules,
+                    &java_arch,
+                    &QuickPlayType::None,
+                    minecraft_updated,
+                )
+            })
+    });
+    let natives_dir = state.directories.version_natives_dir(&version_info.id);
+    if has_natives {
+        checked += 1;
+        let empty = std::fs::read_dir(&natives_dir)
+            .map(|mut x| x.next().is_none())
+            .unwrap_or(true);
+        if empty {
+            issues.push(VerificationIssue {
+                kind: VerifiedFileKind::Natives,
+                path: natives_dir,
+                problem: VerificationProblem::Missing,
+                repaired: false,
+                repair_error: None,
+            });
+        }
+    }
+
+    checked += expected.len();
+    let found = Mutex::new(Vec::new());
+    let num_files = expected.len();
+    loading_try_for_each_concurrent(
+        stream::iter(expected).map(Ok::<ExpectedFile, crate::Error>),
+        None,
+        Some(&loading_bar),
+        if repair { 45.0 } else { 90.0 },
+        num_files,
+        None,
+        |file| {
+            let found = &found;
+            let io_semaphore = &state.io_semaphore;
+            async move {
+                let problem = match hash_file(&file.path, io_semaphore).await? {
+                    None => Some(VerificationProblem::Missing),
+                    Some(actual) if actual != file.sha1 => {
+                        Some(VerificationProblem::HashMismatch {
+                            expected: file.sha1.clone(),
+                            actual,
+                        })
+                    }
+                    Some(_) => None,
+                };
+
+                if let Some(problem) = problem {
+                    found.lock().unwrap().push((file, problem));
+                }
+
+                Ok(())
+            }
+        },
+    )
+    .await?;
+    let found = found.into_inner().unwrap();
+
+    if repair {
+        let repaired = Mutex::new(Vec::new());
+        let num_files = found.len();
+        loading_try_for_each_concurrent(
+            stream::iter(found).map(Ok::<_, crate::Error>),
+            None,
+            Some(&loading_bar),
+            45.0,
+            num_files,
+            None,
+            |(file, problem)| {
+                let repaired = &repaired;
+                let state = &state;
+                let profile = &profile;
+                async move {
+                    let result = repair_file(state, profile, &file).await.err();
+                    repaired.lock().unwrap().push(VerificationIssue {
+                        kind: file.kind,
+                        path: file.path,
+                        problem,
+                        repaired: result.is_none(),
+                        repair_error: result.map(|x| x.to_string()),
+                    });
+                    Ok(())
+                }
+            },
+        )
+        .await?;
+        issues.extend(repaired.into_inner().unwrap());
+
+        if let Some(issue) = issues
+            .iter_mut()
+            .find(|x| x.kind == VerifiedFileKind::Natives)
+        {
+            let natives = version_info
+                .libraries
+                .iter()
+                .filter(|x| {
+                    x.natives_os_key_and_classifiers(&java_arch).is_some()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            match download::download_libraries(
+                &state,
+                &natives,
+                &version_info.id,
+                None,
+                0.0,
+                &java_arch,
+                true,
+                minecraft_updated,
+            )
+            .await
+            {
+                Ok(()) => issue.repaired = true,
+                Err(err) => issue.repair_error = Some(err.to_string()),
+            }
+        }
+    } else {
+        issues.extend(found.into_iter().map(|(file, problem)| {
+            VerificationIssue {
+                kind: file.kind,
+                path: file.path,
+                problem,
+                repaired: false,
+                repair_error: None,
+            }
+        }));
+    }
+
+    emit_loading(&loading_bar, 5.0, None)?;
+
+    if issues.is_empty() {
+        tracing::info!("Verified {checked} files of profile {profile_path}");
+    } else {
+        tracing::warn!(
+            "Found {} problems in {checked} files of profile {profile_path}",
+            issues.len()
+        );
+    }
+
+    Ok(VerificationReport { checked, issues })
+}
+
+/// Gets the client, libraries, asset objects and log config of a version,
+/// the same way they are downloaded on install
+fn get_game_files(
+    state: &State,
+    version_info: &GameVersionInfo,
+    assets_index: Option<&AssetsIndex>,
+    java_arch: &str,
+    minecraft_updated: bool,
+) -> Vec<ExpectedFile> {
+    let directories = &state.directories;
+    let mut files = Vec::new();
+
+    if let Some(client) = version_info.downloads.get(&DownloadType::Client) {
+        files.push(ExpectedFile {
+            kind: VerifiedFileKind::Client,
+            path: directories
+                .version_dir(&version_info.id)
+                .join(format!("{}.jar", version_info.id)),
+            sha1: client.sha1.clone(),
+            source: FileSource::Url(client.url.clone()),
+        });
+    }
+
+    if let Some(LoggingConfiguration::Log4j2Xml { file, .. }) = version_info
+        .logging
+        .as_ref()
+        .and_then(|x| x.get(&LoggingSide::Client))
+    {
+        files.push(ExpectedFile {
+            kind: VerifiedFileKind::LogConfig,
+            path: directories.log_configs_dir().join(&file.id),
+            sha1: file.sha1.clone(),
+            source: FileSource::Url(file.url.clone()),
+        });
+    }
+
+    for library in &version_info.libraries {
+        if !library.downloadable
+            || library.natives_os_key_and_classifiers(java_arch).is_some()
+            || library.rules.as_ref().is_some_and(|rules| {
+                !parse_rules(
+                    rules,
+                    java_arch,
+                    &QuickPlayType::None,
+                    minecraft_updated,
+                )
+            })
+        {
+            continue;
+        }
+
+        // Libraries without an artifact are optional, see `download_libraries`
+        let Some(LibraryDownloads {
+            artifact: Some(ref artifact),
+            ..
+        }) = library.downloads
+        else {
+            continue;
+        };
+        let Ok(artifact_path) = daedalus::get_path_from_artifact(&library.name)
+        else {
+            continue;
+        };
+
+        if !artifact.url.is_empty() {
+            files.push(ExpectedFile {
+                kind: VerifiedFileKind::Library,
+                path: directories.libraries_dir().join(artifact_path),
+                sha1: artifact.sha1.clone(),
+                source: FileSource::Url(artifact.url.clone()),
+            });
+        }
+    }
+
+    if let Some(index) = assets_index {
+        let legacy = version_info.assets == "legacy";
+        for (name, asset) in &index.objects {
+            let url = format!(
+                "https://resources.download.minecraft.net/{}/{}",
+                &asset.hash[..2],
+                asset.hash
+            );
+
+            files.push(ExpectedFile {
+                kind: VerifiedFileKind::Asset,
+                path: directories.object_dir(&asset.hash),
+                sha1: asset.hash.clone(),
+                source: FileSource::Url(url.clone()),
+            });
+
+            if legacy {
+                files.push(ExpectedFile {
+                    kind: VerifiedFileKind::Asset,
+                    path: directories.legacy_assets_dir().join(name.replace(
+                        '/',
+                        &String::from(std::path::MAIN_SEPARATOR),
+                    )),
+                    sha1: asset.hash.clone(),
+                    source: FileSource::Url(url),
+                });
+            }
+        }
+    }
+
+    files
+}
+
+/// Gets the content files of a profile with their recorded hashes
+async fn get_content_files(
+    state: &State,
+    profile: &Profile,
+) -> crate::Result<Vec<ExpectedFile>> {
+    let profile_dir = state.directories.profiles_dir().join(&profile.path);
+
+    Ok(profile
+        .get_projects(None, &state.pool, &state.fetch_semaphore)
+        .await?
+        .into_iter()
+        .map(|(project_path, file)| ExpectedFile {
+            kind: VerifiedFileKind::Content,
+            path: profile_dir.join(&project_path),
+            sha1: file.hash,
+            source: FileSource::Content {
+                project_path,
+                version_id: file.metadata.map(|x| x.version_id),
+            },
+        })
+        .collect())
+}
+
+async fn repair_file(
+    state: &State,
+    profile: &Profile,
+    file: &ExpectedFile,
+) -> crate::Result<()> {
+    let url = match &file.source {
+        FileSource::Url(url) => url.clone(),
+        FileSource::Content {
+            version_id: Some(version_id),
+            ..
+        } => {
+            let version = CachedEntry::get_version(
+                version_id,
+                None,
+                &state.pool,
+                &state.fetch_semaphore,
+            )
+            .await?;
+
+            version
+                .and_then(|version| {
+                    version
+                        .files
+                        .into_iter()
+                        .find(|x| x.hashes.get("sha1") == Some(&file.sha1))
+                })
+                .map(|x| x.url)
+                .ok_or_else(|| {
+                    crate::ErrorKind::InputError(
+                        "This file is no longer available for download"
+                            .to_string(),
+                    )
+                    .as_error()
+                })?
+        }
+        FileSource::Content {
+            version_id: None, ..
+        } => {
+            return Err(crate::ErrorKind::InputError(
+                "This file was not downloaded from Modrinth".to_string(),
+            )
+            .into());
+        }
+    };
+
+    let bytes = fetch::fetch(
+        &url,
+        Some(&file.sha1),
+        None,
+        &state.fetch_semaphore,
+        &state.pool,
+    )
+    .await?;
+    fetch::write(&file.path, &bytes, &state.io_semaphore).await?;
+
+    // The repaired file is no longer linked to the content store
+    if let FileSource::Content { project_path, .. } = &file.source {
+        content_store::release(
+            &profile.path,
+            project_path,
+            &state.directories,
+            &state.pool,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn read_assets_index(path: &Path) -> Option<AssetsIndex> {
+    let bytes = crate::util::io::read(path).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Gets the SHA-1 hash of a file, or `None` if it does not exist
+pub(crate) async fn hash_file(
+    path: &Path,
+    io_semaphore: &IoSemaphore,
+) -> crate::Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let _permit = io_semaphore.0.acquire().await?;
+    let path = path.to_path_buf();
+    let hash = tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)
+            .map_err(|e| crate::util::io::IOError::with_path(e, &path))?;
+        let mut hasher = sha1_smol::Sha1::new();
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| crate::util::io::IOError::with_path(e, &path))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok::<_, crate::Error>(hasher.digest().to_string())
+    })
+    .await??;
+
+    Ok(Some(hash))
+}
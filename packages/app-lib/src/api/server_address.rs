@@ -1,8 +1,13 @@
+use crate::util::ttl_cache::TtlCache;
 use crate::{Error, ErrorKind, Result};
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::LookupIpStrategy;
 use std::fmt::Display;
 use std::mem;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use tokio::sync::Semaphore;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, Semaphore};
 
 #[derive(Debug, Clone)]
 pub enum ServerAddress {
@@ -131,12 +136,104 @@ fn parse_server_address_inner(
     Ok((host, port.unwrap_or(25565)))
 }
 
+/// Shortest time a resolved address is cached, even if its DNS records say
+/// otherwise.
+const MIN_RESOLVE_TTL: Duration = Duration::from_secs(30);
+/// Longest time a resolved address is cached, so moved servers are picked up.
+const MAX_RESOLVE_TTL: Duration = Duration::from_secs(10 * 60);
+
+static SIMULTANEOUS_DNS_QUERIES: Semaphore = Semaphore::const_new(24);
+static RESOLVER: OnceCell<TokioResolver> = OnceCell::const_new();
+static SRV_CACHE: LazyLock<TtlCache<(String, u16), (String, u16)>> =
+    LazyLock::new(TtlCache::new);
+static SOCKET_ADDR_CACHE: LazyLock<TtlCache<(String, u16), Vec<SocketAddr>>> =
+    LazyLock::new(TtlCache::new);
+
+/// A server address resolved through its SRV record, along with the IPv4 and
+/// IPv6 addresses to connect to.
+#[derive(Debug, Clone)]
+pub struct ResolvedServer {
+    pub host: String,
+    pub port: u16,
+    pub socket_addrs: Vec<SocketAddr>,
+}
+
+async fn resolver() -> Result<&'static TokioResolver> {
+    RESOLVER
+        .get_or_try_init(|| async {
+            let mut builder = TokioResolver::builder_tokio()?;
+            builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+            Ok::<_, Error>(builder.build())
+        })
+        .await
+}
+
+/// The DNS lookups needed to resolve a server, so resolution and caching can
+/// be tested without a network.
+trait ServerLookup {
+    /// Looks up the target of an SRV record, along with when the answer
+    /// expires. Missing records aren't an error.
+    async fn srv(&self, name: &str)
+    -> Result<(Option<(String, u16)>, Instant)>;
+
+    /// Looks up the IPv4 and IPv6 addresses of a host, along with when the
+    /// answer expires.
+    async fn ip(&self, host: &str) -> Result<(Vec<IpAddr>, Instant)>;
+}
+
+struct DnsLookup;
+
+impl ServerLookup for DnsLookup {
+    async fn srv(
+        &self,
+        name: &str,
+    ) -> Result<(Option<(String, u16)>, Instant)> {
+        match resolver().await?.srv_lookup(name).await {
+            Err(e)
+                if e.proto()
+                    .as_ref()
+                    .is_some_and(|x| x.kind().is_no_records_found()) =>
+            {
+                Ok((None, Instant::now()))
+            }
+            Err(e) => Err(e.into()),
+            Ok(lookup) => Ok((
+                lookup
+                    .iter()
+                    .next()
+                    .map(|r| (r.target().to_string(), r.port())),
+                lookup.as_lookup().valid_until(),
+            )),
+        }
+    }
+
+    async fn ip(&self, host: &str) -> Result<(Vec<IpAddr>, Instant)> {
+        let lookup = resolver().await?.lookup_ip(host).await?;
+        Ok((lookup.iter().collect(), lookup.valid_until()))
+    }
+}
+
+fn expires_at(valid_until: Instant) -> Instant {
+    let now = Instant::now();
+    valid_until.clamp(now + MIN_RESOLVE_TTL, now + MAX_RESOLVE_TTL)
+}
+
+/// Resolves the host and port to connect to for a server, following its
+/// `_minecraft._tcp` SRV record like the game does.
+///
+/// Results are cached for the TTL of the record.
 pub async fn resolve_server_address(
     host: &str,
     port: u16,
 ) -> Result<(String, u16)> {
-    static SIMULTANEOUS_DNS_QUERIES: Semaphore = Semaphore::const_new(24);
+    resolve_server_address_with(&DnsLookup, host, port).await
+}
 
+async fn resolve_server_address_with(
+    lookup: &impl ServerLookup,
+    host: &str,
+    port: u16,
+) -> Result<(String, u16)> {
     if port != 25565
         || host.parse::<Ipv4Addr>().is_ok()
         || host.parse::<Ipv6Addr>().is_ok()
@@ -144,23 +241,184 @@ pub async fn resolve_server_address(
         return Ok((host.to_owned(), port));
     }
 
+    let key = (host.to_ascii_lowercase(), port);
+    if let Some(resolved) = SRV_CACHE.get(&key) {
+        return Ok(resolved);
+    }
+
     let _permit = SIMULTANEOUS_DNS_QUERIES.acquire().await?;
-    let resolver = hickory_resolver::TokioResolver::builder_tokio()?.build();
-    Ok(
-        match resolver.srv_lookup(format!("_minecraft._tcp.{host}")).await {
-            Err(e)
-                if e.proto()
-                    .as_ref()
-                    .is_some_and(|x| x.kind().is_no_records_found()) =>
-            {
-                None
-            }
-            Err(e) => return Err(e.into()),
-            Ok(lookup) => lookup
-                .into_iter()
-                .next()
-                .map(|r| (r.target().to_string(), r.port())),
+    let (resolved, valid_until) =
+        lookup.srv(&format!("_minecraft._tcp.{host}")).await?;
+    let resolved = resolved.unwrap_or_else(|| (host.to_owned(), port));
+
+    SRV_CACHE.insert_until(key, resolved.clone(), expires_at(valid_until));
+    Ok(resolved)
+}
+
+/// Resolves a server's SRV record, then the IPv4 and IPv6 addresses of the
+/// host it points to.
+///
+/// Results are cached for the TTL of the records.
+pub async fn resolve_server(host: &str, port: u16) -> Result<ResolvedServer> {
+    resolve_server_with(&DnsLookup, host, port).await
+}
+
+async fn resolve_server_with(
+    lookup: &impl ServerLookup,
+    host: &str,
+    port: u16,
+) -> Result<ResolvedServer> {
+    let (host, port) = resolve_server_address_with(lookup, host, port).await?;
+
+    let host_ip = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>();
+    if let Ok(ip) = host_ip {
+        return Ok(ResolvedServer {
+            socket_addrs: vec![SocketAddr::new(ip, port)],
+            host,
+            port,
+        });
+    }
+
+    let key = (host.to_ascii_lowercase(), port);
+    if let Some(socket_addrs) = SOCKET_ADDR_CACHE.get(&key) {
+        return Ok(ResolvedServer {
+            host,
+            port,
+            socket_addrs,
+        });
+    }
+
+    let _permit = SIMULTANEOUS_DNS_QUERIES.acquire().await?;
+    let (ips, valid_until) = lookup.ip(&host).await?;
+    // IPv4 first, since it's still the most likely to be reachable - connecting
+    // falls back to the next address if one fails
+    let mut socket_addrs = ips
+        .into_iter()
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect::<Vec<_>>();
+    socket_addrs.sort_by_key(SocketAddr::is_ipv6);
+
+    SOCKET_ADDR_CACHE.insert_until(
+        key,
+        socket_addrs.clone(),
+        expires_at(valid_until),
+    );
+    Ok(ResolvedServer {
+        host,
+        port,
+        socket_addrs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers lookups from fixed records, counting how often it's asked.
+    #[derive(Default)]
+    struct StubLookup {
+        srv: Option<(String, u16)>,
+        ips: Vec<IpAddr>,
+        srv_lookups: AtomicUsize,
+        ip_lookups: AtomicUsize,
+    }
+
+    impl ServerLookup for StubLookup {
+        async fn srv(
+            &self,
+            _name: &str,
+        ) -> Result<(Option<(String, u16)>, Instant)> {
+            self.srv_lookups.fetch_add(1, Ordering::SeqCst);
+            Ok((self.srv.clone(), Instant::now() + Duration::from_secs(60)))
+        }
+
+        async fn ip(&self, _host: &str) -> Result<(Vec<IpAddr>, Instant)> {
+            self.ip_lookups.fetch_add(1, Ordering::SeqCst);
+            Ok((self.ips.clone(), Instant::now() + Duration::from_secs(60)))
         }
-        .unwrap_or_else(|| (host.to_owned(), port)),
-    )
+    }
+
+    #[tokio::test]
+    async fn follows_and_caches_srv_records() {
+        let lookup = StubLookup {
+            srv: Some(("mc.srv-cached.test.".to_string(), 25570)),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let resolved =
+                resolve_server_address_with(&lookup, "SRV-Cached.test", 25565)
+                    .await
+                    .unwrap();
+            assert_eq!(resolved, ("mc.srv-cached.test.".to_string(), 25570));
+        }
+        assert_eq!(lookup.srv_lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_srv_record_keeps_the_address() {
+        let lookup = StubLookup::default();
+
+        let resolved =
+            resolve_server_address_with(&lookup, "no-srv.test", 25565)
+                .await
+                .unwrap();
+
+        assert_eq!(resolved, ("no-srv.test".to_string(), 25565));
+    }
+
+    #[tokio::test]
+    async fn custom_ports_and_ips_skip_srv_lookups() {
+        let lookup = StubLookup::default();
+
+        resolve_server_address_with(&lookup, "custom-port.test", 25566)
+            .await
+            .unwrap();
+        resolve_server_address_with(&lookup, "127.0.0.1", 25565)
+            .await
+            .unwrap();
+
+        assert_eq!(lookup.srv_lookups.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn caches_ips_with_ipv4_first() {
+        let lookup = StubLookup {
+            ips: vec![
+                "2001:db8::1".parse().unwrap(),
+                "192.0.2.1".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let resolved =
+                resolve_server_with(&lookup, "ip-cached.test", 25566)
+                    .await
+                    .unwrap();
+            assert_eq!(
+                resolved.socket_addrs,
+                vec![
+                    "192.0.2.1:25566".parse().unwrap(),
+                    "[2001:db8::1]:25566".parse().unwrap(),
+                ]
+            );
+        }
+        assert_eq!(lookup.ip_lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn ip_addresses_skip_ip_lookups() {
+        let lookup = StubLookup::default();
+
+        let resolved =
+            resolve_server_with(&lookup, "[::1]", 25566).await.unwrap();
+
+        assert_eq!(resolved.socket_addrs, vec!["[::1]:25566".parse().unwrap()]);
+        assert_eq!(lookup.ip_lookups.load(Ordering::SeqCst), 0);
+    }
 }
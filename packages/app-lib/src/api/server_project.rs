@@ -0,0 +1,607 @@
+//! One-click joining of server projects listed on Modrinth: installing the
+//! server's content into an instance, adding the server to its server list,
+//! and launching straight into the server.
+use crate::data::{CacheBehaviour, LinkedData, ModLoader, ProcessMetadata};
+use crate::pack::install_from::CreatePackLocation;
+use crate::pack::install_mrpack::install_zipped_mrpack;
+use crate::profile::{self, Profile, QuickPlayType};
+use crate::server_address::ServerAddress;
+use crate::worlds::{self, ServerPackStatus, WorldDetails};
+use crate::{ErrorKind, Result};
+use serde::{Deserialize, Serialize};
+
+/// The game content a server project's listing asks players to join with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ServerContent {
+    /// Join with a specific version of a modpack.
+    Modpack { version_id: String },
+    /// Join with the vanilla game.
+    Vanilla {
+        #[serde(default)]
+        supported_game_versions: Vec<String>,
+        #[serde(default)]
+        recommended_game_version: Option<String>,
+    },
+}
+
+impl ServerContent {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Modpack { .. } => "modpack",
+            Self::Vanilla { .. } => "vanilla",
+        }
+    }
+
+    fn vanilla_game_version(&self) -> Option<&str> {
+        match self {
+            Self::Modpack { .. } => None,
+            Self::Vanilla {
+                supported_game_versions,
+                recommended_game_version,
+            } => recommended_game_version
+                .as_deref()
+                .or(supported_game_versions.first().map(String::as_str)),
+        }
+    }
+
+    /// Whether `profile` already has the content the server asks for.
+    fn is_installed_in(&self, profile: &Profile) -> bool {
+        match self {
+            Self::Modpack { version_id } => profile
+                .linked_data
+                .as_ref()
+                .is_some_and(|data| &data.version_id == version_id),
+            Self::Vanilla {
+                supported_game_versions,
+                ..
+            } => {
+                profile.loader == ModLoader::Vanilla
+                    && (supported_game_versions.contains(&profile.game_version)
+                        || self.vanilla_game_version()
+                            == Some(profile.game_version.as_str()))
+            }
+        }
+    }
+
+    /// Whether the server's instance should be updated before joining, if
+    /// one is installed.
+    fn update_available(&self, profile: Option<&Profile>) -> bool {
+        profile.is_some_and(|profile| !self.is_installed_in(profile))
+    }
+}
+
+/// Whether an instance can join a server, based on the protocol version the
+/// server reports when pinged with the instance's protocol version.
+///
+/// Servers which support several versions (i.e. through ViaVersion) echo
+/// back the protocol they were pinged with, so a mismatch means the server
+/// won't accept the instance's version.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ServerCompatibility {
+    Compatible,
+    Incompatible {
+        profile_protocol: u32,
+        server_protocol: i32,
+    },
+    /// The instance isn't installed yet, or the server couldn't be pinged.
+    Unknown,
+}
+
+/// What joining a server project would involve.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerProjectStatus {
+    pub project_id: String,
+    pub name: String,
+    pub address: String,
+    pub content: ServerContent,
+    /// Path of the instance used to join this server, if one was installed.
+    pub profile_path: Option<String>,
+    /// Whether the server's content changed since its instance was installed,
+    /// so it should be updated before joining.
+    pub update_available: bool,
+    pub compatibility: ServerCompatibility,
+}
+
+#[derive(Deserialize, Debug)]
+struct ServerListing {
+    name: String,
+    #[serde(default)]
+    icon_url: Option<String>,
+    #[serde(default)]
+    minecraft_java_server: Option<JavaServerListing>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JavaServerListing {
+    address: String,
+    content: ServerContent,
+}
+
+async fn get_listing(project_id: &str) -> Result<(String, ServerListing)> {
+    let project =
+        crate::cache::get_project_v3(project_id, Some(CacheBehaviour::Bypass))
+            .await?
+            .ok_or_else(|| {
+                ErrorKind::InputError(format!(
+                    "Could not find project {project_id}"
+                ))
+            })?;
+
+    let listing: ServerListing = serde_json::from_value(project.extra)?;
+    if listing.minecraft_java_server.is_none() {
+        return Err(ErrorKind::InputError(format!(
+            "Project {project_id} is not a Minecraft Java server"
+        ))
+        .into());
+    }
+    Ok((project.id, listing))
+}
+
+fn java_server(listing: &ServerListing) -> &JavaServerListing {
+    listing
+        .minecraft_java_server
+        .as_ref()
+        .expect("listing should be checked for a Java server")
+}
+
+/// Finds the instance which was installed to join a server project
+async fn find_profile(project_id: &str) -> Result<Option<Profile>> {
+    Ok(profile::list().await?.into_iter().find(|profile| {
+        profile
+            .linked_data
+            .as_ref()
+            .is_some_and(|data| data.project_id == project_id)
+    }))
+}
+
+async fn get_compatibility(
+    profile: &Profile,
+    address: &str,
+) -> Result<ServerCompatibility> {
+    let Some(profile_protocol) =
+        worlds::get_profile_protocol_version(&profile.path).await?
+    else {
+        return Ok(ServerCompatibility::Unknown);
+    };
+
+    let status = match worlds::get_server_status(
+        address,
+        Some(profile_protocol),
+    )
+    .await
+    {
+        Ok(status) => status,
+        Err(err) => {
+            tracing::debug!("Failed to ping server {address}: {err}");
+            return Ok(ServerCompatibility::Unknown);
+        }
+    };
+
+    Ok(match status.version {
+        Some(version)
+            if version.protocol != profile_protocol.version as i32 =>
+        {
+            ServerCompatibility::Incompatible {
+                profile_protocol: profile_protocol.version,
+                server_protocol: version.protocol,
+            }
+        }
+        Some(_) => ServerCompatibility::Compatible,
+        None => ServerCompatibility::Unknown,
+    })
+}
+
+/// Checks what joining a server project would involve, so an update can be
+/// offered before joining.
+#[tracing::instrument]
+pub async fn get_server_project_status(
+    project_id: &str,
+) -> Result<ServerProjectStatus> {
+    let (project_id, listing) = get_listing(project_id).await?;
+    let server = java_server(&listing);
+    let profile = find_profile(&project_id).await?;
+
+    let compatibility = match &profile {
+        Some(profile) => get_compatibility(profile, &server.address).await?,
+        None => ServerCompatibility::Unknown,
+    };
+
+    Ok(ServerProjectStatus {
+        update_available: server.content.update_available(profile.as_ref()),
+        profile_path: profile.map(|profile| profile.path),
+        project_id,
+        name: listing.name.clone(),
+        address: server.address.clone(),
+        content: server.content.clone(),
+        compatibility,
+    })
+}
+
+/// Creates an instance with a server project's content, or updates the
+/// existing one if the server's content changed, and adds the server to its
+/// server list.
+///
+/// Returns the path of the instance.
+#[tracing::instrument]
+pub async fn install_server_project(project_id: &str) -> Result<String> {
+    let (project_id, listing) = get_listing(project_id).await?;
+
+    let profile_path = match find_profile(&project_id).await? {
+        Some(profile) => {
+            update_profile(&project_id, &listing, &profile).await?;
+            profile.path
+        }
+        None => create_profile(&project_id, &listing).await?,
+    };
+
+    add_server(&project_id, &listing, &profile_path).await?;
+    Ok(profile_path)
+}
+
+/// Joins a server project with quick play, creating an instance for it first
+/// if none is installed.
+///
+/// An existing instance is launched as is - use
+/// [`get_server_project_status`] to offer an update, and
+/// [`install_server_project`] to apply it.
+#[tracing::instrument]
+pub async fn join_server_project(project_id: &str) -> Result<ProcessMetadata> {
+    let (project_id, listing) = get_listing(project_id).await?;
+    let server = java_server(&listing);
+
+    let profile_path = match find_profile(&project_id).await? {
+        Some(profile) => profile.path,
+        None => create_profile(&project_id, &listing).await?,
+    };
+    add_server(&project_id, &listing, &profile_path).await?;
+
+    let profile = profile::get(&profile_path).await?.ok_or_else(|| {
+        ErrorKind::UnmanagedProfileError(profile_path.clone())
+    })?;
+    if let ServerCompatibility::Incompatible {
+        profile_protocol,
+        server_protocol,
+    } = get_compatibility(&profile, &server.address).await?
+    {
+        return Err(ErrorKind::InputError(format!(
+            "{} uses protocol version {server_protocol}, but {} uses {profile_protocol}",
+            listing.name, profile.name
+        ))
+        .into());
+    }
+
+    profile::run(
+        &profile_path,
+        QuickPlayType::Server(ServerAddress::Unresolved(
+            server.address.clone(),
+        )),
+    )
+    .await
+}
+
+async fn create_profile(
+    project_id: &str,
+    listing: &ServerListing,
+) -> Result<String> {
+    let content = &java_server(listing).content;
+    match content {
+        ServerContent::Modpack { version_id } => {
+            let version = crate::cache::get_version(
+                version_id,
+                Some(CacheBehaviour::Bypass),
+            )
+            .await?
+            .ok_or_else(|| {
+                ErrorKind::InputError(format!(
+                    "Could not find modpack version {version_id}"
+                ))
+            })?;
+
+            let profile_path = profile::create::profile_create(
+                listing.name.clone(),
+                version.game_versions.first().cloned().unwrap_or_default(),
+                ModLoader::Vanilla,
+                None,
+                listing.icon_url.clone(),
+                Some(server_linked_data(project_id, version_id)),
+                Some(true),
+            )
+            .await?;
+            install_modpack(
+                project_id,
+                listing,
+                &profile_path,
+                &version.project_id,
+                version_id,
+            )
+            .await?;
+            Ok(profile_path)
+        }
+        ServerContent::Vanilla { .. } => {
+            let game_version =
+                content.vanilla_game_version().ok_or_else(|| {
+                    ErrorKind::InputError(format!(
+                        "{} does not list a game version",
+                        listing.name
+                    ))
+                })?;
+
+            profile::create::profile_create(
+                listing.name.clone(),
+                game_version.to_string(),
+                ModLoader::Vanilla,
+                None,
+                listing.icon_url.clone(),
+                Some(server_linked_data(project_id, "")),
+                None,
+            )
+            .await
+        }
+    }
+}
+
+async fn update_profile(
+    project_id: &str,
+    listing: &ServerListing,
+    profile: &Profile,
+) -> Result<()> {
+    let content = &java_server(listing).content;
+    if content.is_installed_in(profile) {
+        return Ok(());
+    }
+
+    match content {
+        ServerContent::Modpack { version_id } => {
+            if profile.linked_data.is_some() {
+                profile::update::update_managed_modrinth_version(
+                    &profile.path,
+                    version_id,
+                )
+                .await?;
+            } else {
+                let version = crate::cache::get_version(
+                    version_id,
+                    Some(CacheBehaviour::Bypass),
+                )
+                .await?
+                .ok_or_else(|| {
+                    ErrorKind::InputError(format!(
+                        "Could not find modpack version {version_id}"
+                    ))
+                })?;
+                install_modpack(
+                    project_id,
+                    listing,
+                    &profile.path,
+                    &version.project_id,
+                    version_id,
+                )
+                .await?;
+            }
+
+            // the pack update links the instance to the modpack's project,
+            // so link it back to the server
+            let linked_data = server_linked_data(project_id, version_id);
+            profile::edit(&profile.path, |profile| {
+                profile.linked_data = Some(linked_data.clone());
+                async { Ok(()) }
+            })
+            .await?;
+        }
+        ServerContent::Vanilla { .. } => {
+            let game_version = content
+                .vanilla_game_version()
+                .unwrap_or(&profile.game_version)
+                .to_string();
+            profile::edit(&profile.path, |profile| {
+                if profile.game_version != game_version {
+                    profile.protocol_version = None;
+                }
+                profile.game_version = game_version.clone();
+                profile.loader = ModLoader::Vanilla;
+                profile.loader_version = None;
+                async { Ok(()) }
+            })
+            .await?;
+            profile::install(&profile.path, false).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Installs a modpack version into a server's instance, keeping the instance
+/// named and linked after the server rather than the modpack.
+async fn install_modpack(
+    project_id: &str,
+    listing: &ServerListing,
+    profile_path: &str,
+    modpack_project_id: &str,
+    modpack_version_id: &str,
+) -> Result<()> {
+    let icon_path = profile::get(profile_path)
+        .await?
+        .and_then(|profile| profile.icon_path);
+
+    install_zipped_mrpack(
+        CreatePackLocation::FromVersionId {
+            project_id: modpack_project_id.to_string(),
+            version_id: modpack_version_id.to_string(),
+            title: listing.name.clone(),
+            icon_url: None,
+        },
+        profile_path.to_string(),
+    )
+    .await?;
+
+    let linked_data = server_linked_data(project_id, modpack_version_id);
+    profile::edit(profile_path, |profile| {
+        profile.name = listing.name.clone();
+        profile.icon_path = icon_path.clone();
+        profile.linked_data = Some(linked_data.clone());
+        async { Ok(()) }
+    })
+    .await
+}
+
+fn server_linked_data(project_id: &str, version_id: &str) -> LinkedData {
+    LinkedData {
+        project_id: project_id.to_string(),
+        version_id: version_id.to_string(),
+        locked: true,
+    }
+}
+
+/// Adds the server to the instance's server list, unless it's already there.
+async fn add_server(
+    project_id: &str,
+    listing: &ServerListing,
+    profile_path: &str,
+) -> Result<()> {
+    let server = java_server(listing);
+    let address = normalize_address(&server.address);
+
+    let already_added = worlds::get_profile_worlds(profile_path)
+        .await?
+        .into_iter()
+        .any(|world| match world.details {
+            WorldDetails::Server { address: other, .. } => {
+                normalize_address(&other) == address
+            }
+            WorldDetails::Singleplayer { .. } => false,
+        });
+    if already_added {
+        return Ok(());
+    }
+
+    worlds::add_server_to_profile(
+        &profile::get_full_path(profile_path).await?,
+        profile_path,
+        listing.name.clone(),
+        server.address.clone(),
+        ServerPackStatus::Prompt,
+        Some(project_id.to_string()),
+        Some(server.content.kind().to_string()),
+    )
+    .await?;
+    Ok(())
+}
+
+fn normalize_address(address: &str) -> String {
+    let address = address.trim().to_lowercase();
+    match address.strip_suffix(":25565") {
+        Some(address) => address.to_string(),
+        None => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_listing_content() {
+        let modpack: ServerContent =
+            serde_json::from_value(serde_json::json!({
+                "kind": "modpack",
+                "version_id": "abcdefgh",
+                "project_id": "ijklmnop",
+                "project_name": "Some Pack",
+            }))
+            .unwrap();
+        assert_eq!(
+            modpack,
+            ServerContent::Modpack {
+                version_id: "abcdefgh".to_string()
+            }
+        );
+
+        let vanilla: ServerContent =
+            serde_json::from_value(serde_json::json!({
+                "kind": "vanilla",
+                "supported_game_versions": ["1.21.4", "1.21.5"],
+            }))
+            .unwrap();
+        assert_eq!(vanilla.vanilla_game_version(), Some("1.21.4"));
+    }
+
+    fn linked_profile(version_id: &str) -> Profile {
+        let mut profile =
+            Profile::for_tests("1.21.4", ModLoader::Vanilla, None);
+        profile.linked_data = Some(server_linked_data("server", version_id));
+        profile
+    }
+
+    fn vanilla(
+        supported_game_versions: &[&str],
+        recommended_game_version: Option<&str>,
+    ) -> ServerContent {
+        ServerContent::Vanilla {
+            supported_game_versions: supported_game_versions
+                .iter()
+                .map(|version| version.to_string())
+                .collect(),
+            recommended_game_version: recommended_game_version
+                .map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn modpack_is_installed_at_linked_version() {
+        let content = ServerContent::Modpack {
+            version_id: "abcdefgh".to_string(),
+        };
+
+        assert!(content.is_installed_in(&linked_profile("abcdefgh")));
+        assert!(!content.is_installed_in(&linked_profile("ijklmnop")));
+        assert!(!content.is_installed_in(&linked_profile("")));
+        assert!(!content.is_installed_in(&Profile::for_tests(
+            "1.21.4",
+            ModLoader::Vanilla,
+            None
+        )));
+    }
+
+    #[test]
+    fn vanilla_is_installed_at_supported_version() {
+        let profile = linked_profile("");
+
+        assert!(vanilla(&["1.21.4", "1.21.5"], None).is_installed_in(&profile));
+        assert!(vanilla(&[], Some("1.21.4")).is_installed_in(&profile));
+        assert!(
+            !vanilla(&["1.21.5"], Some("1.21.5")).is_installed_in(&profile)
+        );
+
+        let fabric =
+            Profile::for_tests("1.21.4", ModLoader::Fabric, Some("0.16.10"));
+        assert!(!vanilla(&["1.21.4"], None).is_installed_in(&fabric));
+    }
+
+    #[test]
+    fn detects_updates() {
+        let content = ServerContent::Modpack {
+            version_id: "abcdefgh".to_string(),
+        };
+        assert!(!content.update_available(None));
+        assert!(!content.update_available(Some(&linked_profile("abcdefgh"))));
+        assert!(content.update_available(Some(&linked_profile("ijklmnop"))));
+
+        let content = vanilla(&["1.21.5"], Some("1.21.5"));
+        assert!(!content.update_available(None));
+        assert!(content.update_available(Some(&linked_profile(""))));
+    }
+
+    #[test]
+    fn normalizes_addresses() {
+        assert_eq!(
+            normalize_address("Play.Example.com:25565"),
+            "play.example.com"
+        );
+        assert_eq!(
+            normalize_address("play.example.com:25566"),
+            "play.example.com:25566"
+        );
+    }
+}
@@ -2,7 +2,10 @@
 
 pub use crate::{
     State,
-    state::{Hooks, MemorySettings, Profile, Settings, WindowSize},
+    state::{
+        DownloadMirror, Endpoints, Hooks, MemorySettings, Profile, Settings,
+        StatusPrivacy, WindowSize,
+    },
 };
 
 /// Gets entire settings
@@ -17,7 +20,15 @@ pub async fn get() -> crate::Result<Settings> {
 #[tracing::instrument]
 pub async fn set(settings: Settings) -> crate::Result<()> {
     let state = State::get().await?;
+    let old_privacy = Settings::get(&state.pool).await?.status_privacy;
     settings.update(&state.pool).await?;
+    Endpoints::apply_settings(&settings.endpoints);
+
+    if settings.status_privacy != old_privacy
+        && let Err(e) = crate::state::presence::refresh().await
+    {
+        tracing::warn!("Failed to update status: {e}");
+    }
 
     Ok(())
 }
@@ -0,0 +1,188 @@
+//! Theseus storage management interface
+
+pub use crate::state::content_store::ContentStoreStats;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+use crate::state::{DirectoryInfo, Profile, content_store};
+
+pub mod gc;
+
+/// A kind of data the launcher keeps outside of profiles
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    Versions,
+    Libraries,
+    Assets,
+    Natives,
+    JavaVersions,
+    LogConfigs,
+    ContentStore,
+    Caches,
+    /// Anything else in the metadata directory
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfileDiskUsage {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+}
+
+/// Sizes of the launcher's directories, in bytes
+///
+/// Files reflinked from the content store are counted both in the store and
+/// in every profile using them, even though they share their data on disk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiskUsage {
+    pub total: u64,
+    pub profiles: Vec<ProfileDiskUsage>,
+    pub categories: HashMap<StorageCategory, u64>,
+}
+
+impl StorageCategory {
+    pub(crate) fn dirs(&self, directories: &DirectoryInfo) -> Vec<PathBuf> {
+        match self {
+            Self::Versions => vec![directories.versions_dir()],
+            Self::Libraries => vec![directories.libraries_dir()],
+            Self::Assets => {
+                vec![directories.assets_dir(), directories.legacy_assets_dir()]
+            }
+            Self::Natives => vec![directories.natives_dir()],
+            Self::JavaVersions => vec![directories.java_versions_dir()],
+            Self::LogConfigs => vec![directories.log_configs_dir()],
+            Self::ContentStore => vec![directories.content_store_dir()],
+            Self::Caches => vec![directories.caches_dir()],
+            Self::Other => vec![],
+        }
+    }
+}
+
+/// Gets the disk usage of every profile and of the data shared between them
+#[tracing::instrument]
+pub async fn get_disk_usage() -> crate::Result<DiskUsage> {
+    let state = State::get().await?;
+    let profiles_dir = state.directories.profiles_dir();
+
+    let mut profiles = Vec::new();
+    for profile in Profile::get_all(&state.pool).await? {
+        let size = get_size(profiles_dir.join(&profile.path)).await?;
+        profiles.push(ProfileDiskUsage {
+            path: profile.path,
+            name: profile.name,
+            size,
+        });
+    }
+    profiles.sort_by(|a, b| b.size.cmp(&a.size));
+
+    let metadata_dir = state.directories.metadata_dir();
+    let mut categories = HashMap::new();
+    let mut categorized_metadata = 0;
+    let mut outside_metadata = 0;
+    for category in [
+        StorageCategory::Versions,
+        StorageCategory::Libraries,
+        StorageCategory::Assets,
+        StorageCategory::Natives,
+        StorageCategory::JavaVersions,
+        StorageCategory::LogConfigs,
+        StorageCategory::ContentStore,
+        StorageCategory::Caches,
+    ] {
+        for dir in category.dirs(&state.directories) {
+            let size = get_size(dir.clone()).await?;
+            if dir.starts_with(&metadata_dir) {
+                categorized_metadata += size;
+            } else {
+                outside_metadata += size;
+            }
+            *categories.entry(category).or_insert(0) += size;
+        }
+    }
+
+    // Everything else in the metadata directory, such as cached metadata
+    let metadata_size = get_size(metadata_dir).await?;
+    categories.insert(
+        StorageCategory::Other,
+        metadata_size.saturating_sub(categorized_metadata),
+    );
+
+    let total = profiles.iter().map(|x| x.size).sum::<u64>()
+        + metadata_size
+        + outside_metadata;
+
+    Ok(DiskUsage {
+        total,
+        profiles,
+        categories,
+    })
+}
+
+/// Gets the total size of a file or directory, without following symlinks
+pub(crate) async fn get_size(path: PathBuf) -> crate::Result<u64> {
+    Ok(tokio::task::spawn_blocking(move || get_size_blocking(&path)).await?)
+}
+
+fn get_size_blocking(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| get_size_blocking(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Gets statistics about the content store shared between profiles
+#[tracing::instrument]
+pub async fn get_content_store_stats() -> crate::Result<ContentStoreStats> {
+    let state = State::get().await?;
+    content_store::get_stats(&state.directories, &state.pool).await
+}
+
+/// Moves the mods, resource packs and shaders of all profiles into the
+/// content store, so identical files are only stored once
+#[tracing::instrument]
+pub async fn deduplicate_content() -> crate::Result<ContentStoreStats> {
+    let state = State::get().await?;
+    content_store::deduplicate(
+        &state.directories,
+        &state.pool,
+        &state.fetch_semaphore,
+        &state.io_semaphore,
+    )
+    .await
+}
+
+/// Removes files from the content store that no profile uses anymore
+#[tracing::instrument]
+pub async fn prune_content_store() -> crate::Result<ContentStoreStats> {
+    let state = State::get().await?;
+    content_store::prune(&state.directories, &state.pool).await
+}
@@ -0,0 +1,611 @@
+//! Garbage collection of the game files shared between profiles.
+//!
+//! Versions, libraries, asset objects, natives, log configs and Java
+//! runtimes are kept when they are reachable from an installed profile:
+//! a profile uses the version directories of its game and loader version,
+//! and every version uses the files listed in its version info. Anything
+//! else in these directories can be deleted and is downloaded again when a
+//! profile needs it.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use daedalus::minecraft::{
+    AssetsIndex, LoggingConfiguration, LoggingSide,
+    VersionInfo as GameVersionInfo,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+use crate::api::storage::{StorageCategory, get_size};
+use crate::state::{
+    DirectoryInfo, JavaVersion, ModLoader, Profile, ProfileInstallStage,
+};
+use crate::util::io;
+
+/// Directory in the libraries folder used by authlib-injector, which is
+/// not part of any version
+const AUTHLIB_INJECTOR_DIR: &str = "authlib-injector";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GarbageEntry {
+    pub category: StorageCategory,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GarbageCollectionReport {
+    /// Whether the entries were only found, not deleted
+    pub dry_run: bool,
+    pub entries: Vec<GarbageEntry>,
+    /// Total size of the entries, in bytes
+    pub size: u64,
+    /// Categories that were skipped because the version info of a used
+    /// version could not be read
+    pub skipped: Vec<StorageCategory>,
+}
+
+/// Files reachable from the installed profiles
+#[derive(Default, Debug)]
+struct Reachable {
+    versions: HashSet<String>,
+    /// Paths relative to the libraries directory, separated by `/`
+    libraries: HashSet<String>,
+    asset_indexes: HashSet<String>,
+    asset_objects: HashSet<String>,
+    legacy_assets: bool,
+    log_configs: HashSet<String>,
+    java: Vec<PathBuf>,
+    /// Whether the version info of every used version could be read
+    versions_complete: bool,
+    /// Whether every used asset index could be read
+    assets_complete: bool,
+}
+
+/// Finds the versions, libraries, asset objects, natives, log configs and
+/// Java runtimes no installed profile uses anymore, and deletes them unless
+/// `dry_run` is set
+#[tracing::instrument]
+pub async fn collect_garbage(
+    dry_run: bool,
+) -> crate::Result<GarbageCollectionReport> {
+    let state = State::get().await?;
+
+    if !state.process_manager.get_all().is_empty() {
+        return Err(crate::ErrorKind::OtherError(
+            "Close all running instances before cleaning up storage"
+                .to_string(),
+        )
+        .into());
+    }
+
+    let profiles = Profile::get_all(&state.pool).await?;
+    if profiles.iter().any(|x| {
+        matches!(
+            x.install_stage,
+            ProfileInstallStage::MinecraftInstalling
+                | ProfileInstallStage::PackInstalling
+        )
+    }) {
+        return Err(crate::ErrorKind::OtherError(
+            "Wait for all instances to finish installing before cleaning up storage"
+                .to_string(),
+        )
+        .into());
+    }
+
+    let mut reachable = get_reachable(&state.directories, &profiles).await?;
+    reachable.java = JavaVersion::get_all(&state.pool)
+        .await?
+        .into_iter()
+        .map(|(_, x)| PathBuf::from(x.path))
+        .chain(
+            profiles
+                .iter()
+                .filter_map(|x| x.java_path.as_ref().map(PathBuf::from)),
+        )
+        .collect();
+
+    let mut entries = find_garbage(&state.directories, &reachable).await?;
+    entries.sort_by(|a, b| {
+        a.category
+            .cmp(&b.category)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    let mut skipped = Vec::new();
+    if !reachable.versions_complete {
+        skipped.extend([
+            StorageCategory::Libraries,
+            StorageCategory::Assets,
+            StorageCategory::LogConfigs,
+        ]);
+    } else if !reachable.assets_complete {
+        skipped.push(StorageCategory::Assets);
+    }
+
+    if !dry_run {
+        for entry in &entries {
+            if entry.path.is_dir() {
+                io::remove_dir_all(&entry.path).await?;
+            } else {
+                io::remove_file(&entry.path).await?;
+            }
+        }
+
+        remove_empty_dirs(state.directories.libraries_dir()).await?;
+        remove_empty_dirs(state.directories.objects_dir()).await?;
+
+        tracing::info!(
+            "Removed {} unused files and directories from storage",
+            entries.len()
+        );
+    }
+
+    Ok(GarbageCollectionReport {
+        dry_run,
+        size: entries.iter().map(|x| x.size).sum(),
+        entries,
+        skipped,
+    })
+}
+
+/// Whether the version directory `name` belongs to the game or loader
+/// version of `profile`
+fn is_version_used(name: &str, profile: &Profile) -> bool {
+    if name == profile.game_version {
+        return true;
+    }
+
+    if profile.loader == ModLoader::Vanilla {
+        return false;
+    }
+
+    match profile.loader_version.as_deref() {
+        Some(loader_version)
+            if loader_version != "stable" && loader_version != "latest" =>
+        {
+            name == format!("{}-{loader_version}", profile.game_version)
+        }
+        // The loader version is only resolved on install, so keep every
+        // loader version of the game version
+        _ => name.starts_with(&format!("{}-", profile.game_version)),
+    }
+}
+
+/// Gets the paths of the libraries a version uses, including the files
+/// used and produced by Forge processors
+pub(crate) fn get_version_libraries(info: &GameVersionInfo) -> HashSet<String> {
+    let mut artifacts = info
+        .libraries
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+
+    if let Some(processors) = &info.processors {
+        for processor in processors {
+            artifacts.push(&processor.jar);
+            artifacts.extend(processor.classpath.iter().map(|x| x.as_str()));
+        }
+    }
+
+    if let Some(data) = &info.data {
+        for entry in data.values() {
+            if let Some(artifact) = entry
+                .client
+                .strip_prefix('[')
+                .and_then(|x| x.strip_suffix(']'))
+            {
+                artifacts.push(artifact);
+            }
+        }
+    }
+
+    artifacts
+        .into_iter()
+        .filter_map(|x| daedalus::get_path_from_artifact(x).ok())
+        .collect()
+}
+
+/// Finds the game files reachable from the profiles. Java runtimes are
+/// added by the caller
+async fn get_reachable(
+    directories: &DirectoryInfo,
+    profiles: &[Profile],
+) -> crate::Result<Reachable> {
+    let mut reachable = Reachable {
+        versions_complete: true,
+        assets_complete: true,
+        ..Default::default()
+    };
+
+    for name in read_dir_names(&directories.versions_dir()).await? {
+        if profiles.iter().any(|x| is_version_used(&name, x)) {
+            reachable.versions.insert(name);
+        }
+    }
+
+    for version in &reachable.versions {
+        let path = directories
+            .version_dir(version)
+            .join(format!("{version}.json"));
+        let info =
+            match io::read(&path).await.map_err(crate::Error::from).and_then(
+                |bytes| Ok(serde_json::from_slice::<GameVersionInfo>(&bytes)?),
+            ) {
+                Ok(info) => info,
+                Err(err) => {
+                    tracing::warn!(
+                        "Unable to read version info of {version}, skipping \
+                    version files: {err}"
+                    );
+                    reachable.versions_complete = false;
+                    continue;
+                }
+            };
+
+        reachable.libraries.extend(get_version_libraries(&info));
+        reachable.legacy_assets |= info.assets == "legacy";
+        reachable.asset_indexes.insert(info.asset_index.id.clone());
+        if let Some(LoggingConfiguration::Log4j2Xml { file, .. }) = info
+            .logging
+            .as_ref()
+            .and_then(|x| x.get(&LoggingSide::Client))
+        {
+            reachable.log_configs.insert(file.id.clone());
+        }
+    }
+
+    for id in &reachable.asset_indexes {
+        let path = directories.assets_index_dir().join(format!("{id}.json"));
+        match io::read(&path).await.map_err(crate::Error::from).and_then(
+            |bytes| Ok(serde_json::from_slice::<AssetsIndex>(&bytes)?),
+        ) {
+            Ok(index) => reachable
+                .asset_objects
+                .extend(index.objects.into_values().map(|x| x.hash)),
+            Err(err) => {
+                tracing::warn!(
+                    "Unable to read asset index {id}, skipping asset \
+                    objects: {err}"
+                );
+                reachable.assets_complete = false;
+            }
+        }
+    }
+
+    Ok(reachable)
+}
+
+async fn find_garbage(
+    directories: &DirectoryInfo,
+    reachable: &Reachable,
+) -> crate::Result<Vec<GarbageEntry>> {
+    let mut garbage = Vec::new();
+
+    for name in read_dir_names(&directories.versions_dir()).await? {
+        if !reachable.versions.contains(&name) {
+            garbage.push((
+                StorageCategory::Versions,
+                directories.version_dir(&name),
+            ));
+        }
+    }
+
+    for name in read_dir_names(&directories.natives_dir()).await? {
+        if !reachable.versions.contains(&name) {
+            garbage.push((
+                StorageCategory::Natives,
+                directories.version_natives_dir(&name),
+            ));
+        }
+    }
+
+    for name in read_dir_names(&directories.java_versions_dir()).await? {
+        let path = directories.java_versions_dir().join(&name);
+        if !reachable.java.iter().any(|x| x.starts_with(&path)) {
+            garbage.push((StorageCategory::JavaVersions, path));
+        }
+    }
+
+    if reachable.versions_complete {
+        let libraries_dir = directories.libraries_dir();
+        for path in walk_files(libraries_dir.clone()).await? {
+            let Ok(relative) = path.strip_prefix(&libraries_dir) else {
+                continue;
+            };
+            let relative = relative
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if !relative.starts_with(&format!("{AUTHLIB_INJECTOR_DIR}/"))
+                && !reachable.libraries.contains(&relative)
+            {
+                garbage.push((StorageCategory::Libraries, path));
+            }
+        }
+
+        for name in read_dir_names(&directories.assets_index_dir()).await? {
+            let used = name
+                .strip_suffix(".json")
+                .is_some_and(|id| reachable.asset_indexes.contains(id));
+            if !used {
+                garbage.push((
+                    StorageCategory::Assets,
+                    directories.assets_index_dir().join(name),
+                ));
+            }
+        }
+
+        if !reachable.legacy_assets && directories.legacy_assets_dir().exists()
+        {
+            garbage.push((
+                StorageCategory::Assets,
+                directories.legacy_assets_dir(),
+            ));
+        }
+
+        for name in read_dir_names(&directories.log_configs_dir()).await? {
+            if !reachable.log_configs.contains(&name) {
+                garbage.push((
+                    StorageCategory::LogConfigs,
+                    directories.log_configs_dir().join(name),
+                ));
+            }
+        }
+
+        if reachable.assets_complete {
+            for path in walk_files(directories.objects_dir()).await? {
+                let used = path.file_name().is_some_and(|x| {
+                    reachable.asset_objects.contains(&*x.to_string_lossy())
+                });
+                if !used {
+                    garbage.push((StorageCategory::Assets, path));
+                }
+            }
+        }
+    }
+
+    let mut entries = Vec::with_capacity(garbage.len());
+    for (category, path) in garbage {
+        entries.push(GarbageEntry {
+            category,
+            size: get_size(path.clone()).await?,
+            path,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Gets the names of the entries of a directory, or nothing if it does not
+/// exist
+async fn read_dir_names(dir: &Path) -> crate::Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    let mut entries = io::read_dir(dir).await?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| io::IOError::with_path(e, dir))?
+    {
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+
+    Ok(names)
+}
+
+/// Recursively gets the paths of all files in a directory
+async fn walk_files(dir: PathBuf) -> crate::Result<Vec<PathBuf>> {
+    Ok(tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        let mut stack = vec![dir];
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(|x| x.ok()) {
+                match entry.file_type() {
+                    Ok(file_type) if file_type.is_dir() => {
+                        stack.push(entry.path())
+                    }
+                    Ok(_) => files.push(entry.path()),
+                    Err(_) => {}
+                }
+            }
+        }
+        files
+    })
+    .await?)
+}
+
+/// Removes the empty directories left behind in a directory, keeping the
+/// directory itself
+async fn remove_empty_dirs(dir: PathBuf) -> crate::Result<()> {
+    fn remove(dir: &Path) -> bool {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return false;
+        };
+
+        let mut empty = true;
+        for entry in entries.filter_map(|x| x.ok()) {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|x| x.is_dir()) && remove(&path) {
+                empty &= std::fs::remove_dir(&path).is_ok();
+            } else {
+                empty = false;
+            }
+        }
+        empty
+    }
+
+    tokio::task::spawn_blocking(move || {
+        remove(&dir);
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_used_by_profile() {
+        let vanilla = Profile::for_tests("1.20.1", ModLoader::Vanilla, None);
+        assert!(is_version_used("1.20.1", &vanilla));
+        assert!(!is_version_used("1.20.1-0.15.0", &vanilla));
+        assert!(!is_version_used("1.20", &vanilla));
+
+        let fabric =
+            Profile::for_tests("1.20.1", ModLoader::Fabric, Some("0.15.0"));
+        assert!(is_version_used("1.20.1-0.15.0", &fabric));
+        assert!(!is_version_used("1.20.1-0.14.0", &fabric));
+
+        let unresolved =
+            Profile::for_tests("1.20", ModLoader::Forge, Some("stable"));
+        assert!(is_version_used("1.20-1.20-46.0.1", &unresolved));
+        assert!(!is_version_used("1.20.1-1.20.1-47.2.0", &unresolved));
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn version_info(id: &str, library: &str, asset_index: &str) -> String {
+        serde_json::json!({
+            "assetIndex": {
+                "id": asset_index,
+                "sha1": "",
+                "size": 0,
+                "totalSize": 0,
+                "url": "",
+            },
+            "assets": asset_index,
+            "downloads": {},
+            "id": id,
+            "libraries": [{ "name": library }],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minimumLauncherVersion": 21,
+            "releaseTime": "2023-06-12T13:25:51Z",
+            "time": "2023-06-12T13:25:51Z",
+            "type": "release",
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn sweeps_unreachable_game_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let directories = DirectoryInfo {
+            settings_dir: dir.path().to_path_buf(),
+            config_dir: dir.path().to_path_buf(),
+            app_identifier: "test".to_string(),
+        };
+
+        let used_object = "ab".repeat(20);
+        let unused_object = "cd".repeat(20);
+
+        write(
+            directories.version_dir("1.20.1").join("1.20.1.json"),
+            &version_info("1.20.1", "com.example:used:1.0", "5"),
+        );
+        write(
+            directories.version_dir("1.19").join("1.19.json"),
+            &version_info("1.19", "com.example:unused:1.0", "3"),
+        );
+        write(directories.version_natives_dir("1.20.1").join("a.so"), "");
+        write(directories.version_natives_dir("1.19").join("a.so"), "");
+
+        let libraries_dir = directories.libraries_dir();
+        write(libraries_dir.join("com/example/used/1.0/used-1.0.jar"), "");
+        write(
+            libraries_dir.join("com/example/unused/1.0/unused-1.0.jar"),
+            "",
+        );
+        write(
+            libraries_dir.join(AUTHLIB_INJECTOR_DIR).join("authlib.jar"),
+            "",
+        );
+
+        write(
+            directories.assets_index_dir().join("5.json"),
+            &serde_json::json!({
+                "objects": {
+                    "icons/icon.png": { "hash": used_object, "size": 1 },
+                },
+            })
+            .to_string(),
+        );
+        write(
+            directories.assets_index_dir().join("3.json"),
+            r#"{ "objects": {} }"#,
+        );
+        write(directories.object_dir(&used_object), "used");
+        write(directories.object_dir(&unused_object), "unused");
+
+        let profiles = [Profile::for_tests("1.20.1", ModLoader::Vanilla, None)];
+        let reachable = get_reachable(&directories, &profiles).await.unwrap();
+        assert!(reachable.versions_complete);
+        assert!(reachable.assets_complete);
+
+        let mut garbage = find_garbage(&directories, &reachable)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.category, x.path))
+            .collect::<Vec<_>>();
+        garbage.sort();
+
+        let mut expected = vec![
+            (StorageCategory::Versions, directories.version_dir("1.19")),
+            (
+                StorageCategory::Natives,
+                directories.version_natives_dir("1.19"),
+            ),
+            (
+                StorageCategory::Libraries,
+                libraries_dir.join("com/example/unused/1.0/unused-1.0.jar"),
+            ),
+            (
+                StorageCategory::Assets,
+                directories.assets_index_dir().join("3.json"),
+            ),
+            (
+                StorageCategory::Assets,
+                directories.object_dir(&unused_object),
+            ),
+        ];
+        expected.sort();
+        assert_eq!(garbage, expected);
+    }
+
+    #[tokio::test]
+    async fn unreadable_version_info_keeps_version_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let directories = DirectoryInfo {
+            settings_dir: dir.path().to_path_buf(),
+            config_dir: dir.path().to_path_buf(),
+            app_identifier: "test".to_string(),
+        };
+
+        write(directories.version_dir("1.20.1").join("1.20.1.json"), "{");
+        write(directories.libraries_dir().join("a/b/1.0/b-1.0.jar"), "");
+        write(directories.object_dir(&"ab".repeat(20)), "");
+
+        let profiles = [Profile::for_tests("1.20.1", ModLoader::Vanilla, None)];
+        let reachable = get_reachable(&directories, &profiles).await.unwrap();
+        assert!(!reachable.versions_complete);
+
+        let garbage = find_garbage(&directories, &reachable).await.unwrap();
+        assert!(garbage.is_empty(), "{garbage:?}");
+    }
+}
@@ -1,7 +1,7 @@
 use crate::data::ModLoader;
 use crate::launcher::get_loader_version_from_profile;
 use crate::profile::get_full_path;
-use crate::server_address::{parse_server_address, resolve_server_address};
+use crate::server_address::{parse_server_address, resolve_server};
 use crate::state::attached_world_data::AttachedWorldData;
 use crate::state::{
     Profile, ProfileInstallStage, attached_world_data, server_join_log,
@@ -11,6 +11,7 @@ pub use crate::util::protocol_version::ProtocolVersion;
 pub use crate::util::server_ping::{
     ServerGameProfile, ServerPlayers, ServerStatus, ServerVersion,
 };
+use crate::util::ttl_cache::TtlCache;
 use crate::util::{io, server_ping};
 use crate::{Error, ErrorKind, Result, State, launcher};
 use async_minecraft_ping::ServerDescription;
@@ -26,11 +27,13 @@ use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 use tokio_util::compat::FuturesAsyncWriteCompatExt;
 use url::Url;
@@ -926,9 +929,163 @@ pub async fn get_profile_protocol_version(
     Ok(version.map(ProtocolVersion::modern))
 }
 
+/// Maximum number of servers [`get_profile_server_statuses`] pings at once.
+pub const MAX_CONCURRENT_SERVER_PINGS: usize = 8;
+/// Protocol version of the legacy (pre-1.7) server list ping.
+const LEGACY_PING_PROTOCOL: ProtocolVersion = ProtocolVersion::legacy(74);
+/// How long servers which only answered a legacy ping are pinged with it
+/// directly, rather than trying a modern ping first.
+const LEGACY_SERVER_TTL: Duration = Duration::from_secs(10 * 60);
+/// How long the results of [`get_profile_server_statuses`] are reused.
+const SERVER_STATUS_TTL: Duration = Duration::from_secs(30);
+
+/// Servers which only answered a legacy ping, by address.
+static LEGACY_SERVERS: LazyLock<TtlCache<String, ()>> =
+    LazyLock::new(TtlCache::new);
+/// Results of [`get_profile_server_statuses`], by address and the protocol
+/// version the server was pinged with. Failed pings are cached as [`None`].
+static SERVER_STATUSES: LazyLock<
+    TtlCache<(String, Option<u32>), Option<ServerStatus>>,
+> = LazyLock::new(TtlCache::new);
+
+/// Pings a server for its status.
+///
+/// If the server accepts the connection but doesn't answer a modern ping,
+/// it's pinged again with the legacy ping used before 1.7.
 pub async fn get_server_status(
     address: &str,
     protocol_version: Option<ProtocolVersion>,
+) -> Result<ServerStatus> {
+    let legacy_key = address.to_ascii_lowercase();
+    if protocol_version.is_some_and(|version| version.legacy)
+        || LEGACY_SERVERS.get(&legacy_key).is_some()
+    {
+        return ping_server(address, Some(LEGACY_PING_PROTOCOL)).await;
+    }
+
+    match ping_server(address, protocol_version).await {
+        Ok(status) => Ok(status),
+        Err(err) if is_protocol_error(&err) => {
+            tracing::debug!(
+                "Modern ping of {address} failed, trying a legacy ping: {err}"
+            );
+            let status = ping_server(address, Some(LEGACY_PING_PROTOCOL))
+                .await
+                // report why the modern ping failed, since most servers
+                // don't use the legacy ping
+                .map_err(|_| err)?;
+            LEGACY_SERVERS.insert(legacy_key, (), LEGACY_SERVER_TTL);
+            Ok(status)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether a ping failed while talking to the server, rather than while
+/// resolving or connecting to it.
+///
+/// Legacy servers only fail this way, so offline servers aren't pinged again
+/// and don't take twice as long to time out.
+fn is_protocol_error(err: &Error) -> bool {
+    match &*err.raw {
+        ErrorKind::InputError(_) => true,
+        ErrorKind::StdIOError(err) => matches!(
+            err.kind(),
+            std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::InvalidData
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe
+        ),
+        _ => false,
+    }
+}
+
+/// Groups the addresses of the visible servers in a server list by the
+/// address they're pinged at, so servers listed more than once are only
+/// pinged once.
+fn group_server_addresses(
+    servers: Vec<servers_data::ServerData>,
+) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for server in servers.into_iter().filter(|server| !server.hidden) {
+        let key = server.ip.to_ascii_lowercase();
+        match groups.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, addresses)) => {
+                if !addresses.contains(&server.ip) {
+                    addresses.push(server.ip);
+                }
+            }
+            None => groups.push((key, vec![server.ip])),
+        }
+    }
+    groups
+}
+
+/// Pings every server in a profile's server list with the profile's protocol
+/// version, so servers supporting several versions report the one the
+/// profile will join with.
+///
+/// At most [`MAX_CONCURRENT_SERVER_PINGS`] servers are pinged at once, and
+/// results are reused for [`SERVER_STATUS_TTL`] unless `force_refresh` is
+/// set. Servers which couldn't be pinged map to [`None`].
+pub async fn get_profile_server_statuses(
+    profile_path: &str,
+    force_refresh: bool,
+) -> Result<HashMap<String, Option<ServerStatus>>> {
+    static SIMULTANEOUS_PINGS: Semaphore =
+        Semaphore::const_new(MAX_CONCURRENT_SERVER_PINGS);
+
+    let protocol_version = get_profile_protocol_version(profile_path).await?;
+    let servers =
+        servers_data::read(&get_full_path(profile_path).await?).await?;
+
+    let mut statuses = HashMap::new();
+    let mut tasks = JoinSet::new();
+    for (address, addresses) in group_server_addresses(servers) {
+        let key = (address, protocol_version.map(|version| version.version));
+        if !force_refresh && let Some(status) = SERVER_STATUSES.get(&key) {
+            for address in addresses {
+                statuses.insert(address, status.clone());
+            }
+            continue;
+        }
+
+        for address in &addresses {
+            statuses.insert(address.clone(), None);
+        }
+        tasks.spawn(async move {
+            let _permit = SIMULTANEOUS_PINGS.acquire().await;
+            let status = get_server_status(&addresses[0], protocol_version)
+                .await
+                .inspect_err(|e| {
+                    tracing::debug!("Failed to ping {}: {e}", addresses[0])
+                })
+                .ok();
+            SERVER_STATUSES.insert(key, status.clone(), SERVER_STATUS_TTL);
+            (addresses, status)
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((addresses, status)) => {
+                for address in addresses {
+                    statuses.insert(address, status.clone());
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Server ping task panicked: {e}");
+            }
+        }
+    }
+
+    Ok(statuses)
+}
+
+async fn ping_server(
+    address: &str,
+    protocol_version: Option<ProtocolVersion>,
 ) -> Result<ServerStatus> {
     tracing::debug!(
         "Pinging {address} with protocol version {protocol_version:?}"
@@ -943,13 +1100,9 @@ async fn get_server_status_old(
     protocol_version: Option<ProtocolVersion>,
 ) -> Result<ServerStatus> {
     let (original_host, original_port) = parse_server_address(address)?;
-    let (host, port) =
-        resolve_server_address(original_host, original_port).await?;
-    tracing::debug!(
-        "Pinging {address} with protocol version {protocol_version:?}"
-    );
+    let resolved = resolve_server(original_host, original_port).await?;
     server_ping::get_server_status(
-        &(&host as &str, port),
+        &resolved.socket_addrs.as_slice(),
         (original_host, original_port),
         protocol_version,
     )
@@ -1039,3 +1192,74 @@ async fn _get_server_status_new(
         ping: Some(latency),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use servers_data::ServerData;
+
+    fn server(ip: &str, hidden: bool) -> ServerData {
+        ServerData {
+            hidden,
+            icon: None,
+            ip: ip.to_string(),
+            name: ip.to_string(),
+            accept_textures: None,
+        }
+    }
+
+    #[test]
+    fn groups_duplicate_servers() {
+        let groups = group_server_addresses(vec![
+            server("play.example.com", false),
+            server("other.example.com", false),
+            server("Play.Example.com", false),
+            server("play.example.com", false),
+            server("hidden.example.com", true),
+        ]);
+
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    "play.example.com".to_string(),
+                    vec![
+                        "play.example.com".to_string(),
+                        "Play.Example.com".to_string()
+                    ]
+                ),
+                (
+                    "other.example.com".to_string(),
+                    vec!["other.example.com".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_protocol_errors_fall_back_to_legacy_ping() {
+        let io_error =
+            |kind: std::io::ErrorKind| Error::from(std::io::Error::from(kind));
+
+        assert!(is_protocol_error(
+            &ErrorKind::InputError("Unexpected packet".to_string()).into()
+        ));
+        assert!(is_protocol_error(&io_error(
+            std::io::ErrorKind::UnexpectedEof
+        )));
+        assert!(is_protocol_error(&io_error(
+            std::io::ErrorKind::ConnectionReset
+        )));
+
+        assert!(!is_protocol_error(&io_error(
+            std::io::ErrorKind::ConnectionRefused
+        )));
+        assert!(!is_protocol_error(&io_error(std::io::ErrorKind::TimedOut)));
+        assert!(!is_protocol_error(&io_error(
+            std::io::ErrorKind::HostUnreachable
+        )));
+        assert!(!is_protocol_error(
+            &ErrorKind::OtherError("Ping timed out".to_string()).into()
+        ));
+    }
+}
@@ -28,6 +28,12 @@ pub enum ErrorKind {
     #[error("Serialization error (JSON): {0}")]
     JSONError(#[from] serde_json::Error),
 
+    #[error("Serialization error (TOML): {0}")]
+    TOMLError(#[from] toml::de::Error),
+
+    #[error("Serialization error (TOML): {0}")]
+    TOMLSerializationError(#[from] toml::ser::Error),
+
     #[error("Serialization error (NBT): {0}")]
     NBTError(#[from] quartz_nbt::io::NbtIoError),
 
@@ -5,8 +5,10 @@ use crate::event::{
 };
 #[cfg(feature = "tauri")]
 use crate::event::{
-    LoadingPayload, ProcessPayload, ProfilePayload, WarningPayload,
+    CrashAnalysisPayload, LoadingPayload, ProcessPayload, ProfilePayload,
+    WarningPayload,
 };
+use crate::state::CrashAnalysis;
 use futures::prelude::*;
 #[cfg(feature = "tauri")]
 use tauri::{Emitter, Manager};
@@ -266,6 +268,31 @@ pub async fn emit_process(
     Ok(())
 }
 
+// emit_crash_analysis(path, uuid, analysis)
+#[allow(unused_variables)]
+pub async fn emit_crash_analysis(
+    profile_path: &str,
+    uuid: Uuid,
+    analysis: CrashAnalysis,
+) -> crate::Result<()> {
+    #[cfg(feature = "tauri")]
+    {
+        let event_state = crate::EventState::get()?;
+        event_state
+            .app
+            .emit(
+                "crash_analysis",
+                CrashAnalysisPayload {
+                    profile_path_id: profile_path.to_string(),
+                    uuid,
+                    analysis,
+                },
+            )
+            .map_err(EventError::from)?;
+    }
+    Ok(())
+}
+
 // emit_profile(path, event)
 #[allow(unused_variables)]
 pub async fn emit_profile(
@@ -165,6 +165,10 @@ pub enum LoadingBarType {
         profile_path: String,
         profile_name: String,
     },
+    ProfileVerify {
+        profile_path: String,
+        profile_name: String,
+    },
     ZipExtract {
         profile_path: String,
         profile_name: String,
@@ -216,6 +220,12 @@ pub enum CommandPayload {
         // run or install .mrpack
         path: PathBuf,
     },
+    OpenUrl {
+        url: String,
+    },
+    OpenProfileFolder {
+        profile_path: String,
+    },
 }
 
 #[derive(Serialize, Clone)]
@@ -227,6 +237,15 @@ pub struct ProcessPayload {
     pub message: String,
 }
 
+#[derive(Serialize, Clone)]
+#[cfg(feature = "tauri")]
+pub struct CrashAnalysisPayload {
+    pub profile_path_id: String,
+    pub uuid: Uuid,
+    #[serde(flatten)]
+    pub analysis: crate::state::CrashAnalysis,
+}
+
 #[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessPayloadType {
@@ -268,6 +287,8 @@ pub enum FriendPayload {
     UserOffline { id: UserId },
     StatusUpdate { user_status: UserStatus },
     StatusSync,
+    TunnelOpened { user: UserId, socket: Uuid },
+    TunnelClosed { user: UserId, socket: Option<Uuid> },
 }
 
 #[cfg(feature = "tauri")]
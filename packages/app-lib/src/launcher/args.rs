@@ -3,6 +3,7 @@ use crate::launcher::quick_play_version::QuickPlayServerVersion;
 use crate::launcher::{QuickPlayVersion, parse_rules};
 use crate::profile::QuickPlayType;
 use crate::state::Credentials;
+use crate::state::yggdrasil::AuthlibInjector;
 use crate::{
     state::{MemorySettings, WindowSize},
     util::{io::IOError, platform::classpath_separator},
@@ -124,6 +125,7 @@ pub fn get_jvm_arguments(
     quick_play_version: QuickPlayVersion,
     log_config: Option<&LoggingConfiguration>,
     ipc_addr: SocketAddr,
+    authlib_injector: Option<&AuthlibInjector>,
 ) -> crate::Result<Vec<String>> {
     let mut parsed_arguments = Vec::new();
 
@@ -168,6 +170,27 @@ pub fn get_jvm_arguments(
         parsed_arguments.push(argument.replace("${path}", &full_path));
     }
 
+    if let Some(authlib_injector) = authlib_injector {
+        parsed_arguments.push(format!(
+            "-javaagent:{}={}",
+            canonicalize(&authlib_injector.jar_path)
+                .map_err(|_| {
+                    crate::ErrorKind::LauncherError(format!(
+                        "Specified authlib-injector path {} does not exist",
+                        authlib_injector.jar_path.to_string_lossy()
+                    ))
+                    .as_error()
+                })?
+                .to_string_lossy(),
+            authlib_injector.api_root
+        ));
+        if let Some(metadata) = &authlib_injector.prefetched_metadata {
+            parsed_arguments.push(format!(
+                "-Dauthlibinjector.yggdrasil.prefetched={metadata}"
+            ));
+        }
+    }
+
     parsed_arguments.push(format!(
         "-javaagent:{}",
         canonicalize(agent_path)
@@ -268,6 +291,7 @@ pub async fn get_minecraft_arguments(
     quick_play_version: QuickPlayVersion,
 ) -> crate::Result<Vec<String>> {
     let access_token = credentials.access_token.clone();
+    let user_type = credentials.provider.user_type();
     let profile = credentials.maybe_online_profile().await;
     let mut parsed_arguments = Vec::new();
 
@@ -279,6 +303,7 @@ pub async fn get_minecraft_arguments(
                 parse_minecraft_argument(
                     arg,
                     &access_token,
+                    user_type,
                     &profile.name,
                     profile.id,
                     version,
@@ -298,6 +323,7 @@ pub async fn get_minecraft_arguments(
             parsed_arguments.push(parse_minecraft_argument(
                 &x.replace(' ', TEMPORARY_REPLACE_CHAR),
                 &access_token,
+                user_type,
                 &profile.name,
                 profile.id,
                 version,
@@ -330,6 +356,7 @@ pub async fn get_minecraft_arguments(
 fn parse_minecraft_argument(
     argument: &str,
     access_token: &str,
+    user_type: &str,
     username: &str,
     uuid: Uuid,
     version: &str,
@@ -351,7 +378,7 @@ fn parse_minecraft_argument(
         .replace("${uuid}", &uuid.simple().to_string())
         .replace("${clientid}", "c4502edb-87c6-40cb-b595-64a280cf8906")
         .replace("${user_properties}", "{}")
-        .replace("${user_type}", "msa")
+        .replace("${user_type}", user_type)
         .replace("${version_name}", version)
         .replace("${assets_index_name}", asset_index_name)
         .replace(
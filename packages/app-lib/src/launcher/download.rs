@@ -83,26 +83,13 @@ pub async fn download_version_info(
             &version.id,
             version.url
         );
-        let mut info = fetch_json(
-            Method::GET,
-            &version.url,
-            None,
-            None,
-            &st.api_semaphore,
-            &st.pool,
-        )
-        .await?;
+        let mut info =
+            fetch_meta_json(&version.url, &st.api_semaphore, &st.pool).await?;
 
         if let Some(loader) = loader {
-            let partial: d::modded::PartialVersionInfo = fetch_json(
-                Method::GET,
-                &loader.url,
-                None,
-                None,
-                &st.api_semaphore,
-                &st.pool,
-            )
-            .await?;
+            let partial: d::modded::PartialVersionInfo =
+                fetch_meta_json(&loader.url, &st.api_semaphore, &st.pool)
+                    .await?;
             info = d::modded::merge_partial_version(partial, info);
         }
 
@@ -9,7 +9,9 @@ use crate::launcher::quick_play_version::{
 };
 use crate::profile::QuickPlayType;
 use crate::server_address::{ServerAddress, parse_server_address};
+use crate::state::presence;
 use crate::state::server_join_log::JoinLogEntry;
+use crate::state::yggdrasil::AuthlibInjector;
 use crate::state::{
     Credentials, JavaVersion, ProcessMetadata, ProfileInstallStage,
 };
@@ -28,6 +30,7 @@ use std::path::PathBuf;
 use tokio::process::Command;
 
 mod args;
+mod rpc;
 
 pub mod download;
 pub mod quick_play_version;
@@ -320,7 +323,7 @@ pub async fn install_minecraft(
     {
         (std::path::PathBuf::from(java_version.path), false)
     } else {
-        let path = crate::api::jre::auto_install_java(key).await?;
+        let path = crate::api::jre::auto_install_java(key, None).await?;
 
         (path, true)
     };
@@ -672,7 +675,17 @@ pub async fn launch_minecraft(
     let (main_class_keep_alive, main_class_path) =
         get_resource_file!(env "JAVA_JARS_DIR" / "theseus.jar")?;
 
-    let rpc_server = RpcServerBuilder::new().launch().await?;
+    let rpc_server =
+        rpc::register_handlers(RpcServerBuilder::new(), profile, credentials)
+            .launch()
+            .await?;
+
+    let authlib_injector = match &credentials.provider {
+        st::CredentialProvider::Yggdrasil { api_root, .. } => {
+            Some(AuthlibInjector::prepare(api_root).await?)
+        }
+        st::CredentialProvider::Microsoft => None,
+    };
 
     command.args(
         args::get_jvm_arguments(
@@ -700,6 +713,7 @@ pub async fn launch_minecraft(
                 .as_ref()
                 .and_then(|x| x.get(&LoggingSide::Client)),
             rpc_server.address(),
+            authlib_injector.as_ref(),
         )?
         .into_iter(),
     );
@@ -813,19 +827,9 @@ pub async fn launch_minecraft(
         }
     }
 
-    let _ = state
-        .discord_rpc
-        .set_activity(&format!("Playing {}", profile.name), true)
-        .await;
-
-    let _ = state
-        .friends_socket
-        .update_status(Some(profile.name.clone()))
-        .await;
-
     // Create Minecraft child by inserting it into the state
     // This also spawns the process and prepares the subsequent processes
-    state
+    let process = state
         .process_manager
         .insert_new_process(
             &profile.path,
@@ -866,5 +870,11 @@ pub async fn launch_minecraft(
                 Ok(())
             },
         )
-        .await
+        .await?;
+
+    if let Err(e) = presence::refresh().await {
+        tracing::warn!("Failed to update status: {e}");
+    }
+
+    Ok(process)
 }
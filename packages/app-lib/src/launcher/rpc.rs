@@ -0,0 +1,304 @@
+//! Methods the game can call on the launcher through the RPC connection
+//!
+//! Methods are namespaced by the version of the RPC surface they belong to
+//! (`launcher.v1.*`), so a method can change in a later version without
+//! breaking mods written against an earlier one. Mods should call
+//! `launcher.getRpcVersions` first and pick the newest version they support.
+use crate::data::ModLoader;
+use crate::event::CommandPayload;
+use crate::event::emit::emit_command;
+use crate::state::{
+    CachedEntry, CredentialProvider, Credentials, Profile, ReportedMod,
+};
+use crate::util::rpc::{RpcServerBuilder, parse_arg};
+use crate::{ErrorKind, profile, state};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// The versions of the RPC surface this launcher implements
+pub const RPC_VERSIONS: &[u32] = &[1];
+
+#[derive(Serialize)]
+struct InstanceInfo {
+    name: String,
+    path: String,
+    game_version: String,
+    loader: ModLoader,
+    loader_version: Option<String>,
+    linked_project_id: Option<String>,
+    linked_version_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PlayerInfo {
+    id: Uuid,
+    name: String,
+    provider: &'static str,
+}
+
+pub(super) fn register_handlers(
+    builder: RpcServerBuilder,
+    profile: &Profile,
+    credentials: &Credentials,
+) -> RpcServerBuilder {
+    state::clear_reported_mods(&profile.path);
+
+    let player = PlayerInfo {
+        id: credentials.offline_profile.id,
+        name: credentials.offline_profile.name.clone(),
+        provider: match credentials.provider {
+            CredentialProvider::Microsoft => "microsoft",
+            CredentialProvider::Yggdrasil { .. } => "yggdrasil",
+        },
+    };
+    let player = serde_json::to_value(player).unwrap_or_default();
+
+    let get_instance_path = profile.path.clone();
+    let open_folder_path = profile.path.clone();
+    let report_mods_path = profile.path.clone();
+
+    builder
+        .handler("launcher.getRpcVersions", |_| async { Ok(RPC_VERSIONS) })
+        .handler("launcher.v1.getInstance", move |_| {
+            let path = get_instance_path.clone();
+            async move { get_instance(&path).await }
+        })
+        .handler("launcher.v1.getPlayer", move |_| {
+            let player = player.clone();
+            async move { Ok(player) }
+        })
+        .handler("launcher.v1.openUrl", |args| async move {
+            let url: String = parse_arg(&args, 0)?;
+            open_url(url).await
+        })
+        .handler("launcher.v1.openInstanceFolder", move |_| {
+            let profile_path = open_folder_path.clone();
+            async move {
+                emit_command(CommandPayload::OpenProfileFolder { profile_path })
+                    .await
+            }
+        })
+        .handler("launcher.v1.requestInstall", |args| async move {
+            let project_id: String = parse_arg(&args, 0)?;
+            let version_id: Option<String> = parse_arg(&args, 1)?;
+            let payload =
+                install_command(project_id, version_id, &CachedProjects)
+                    .await?;
+            emit_command(payload).await
+        })
+        .handler("launcher.v1.reportMods", move |args| {
+            let path = report_mods_path.clone();
+            async move {
+                let mods: Vec<ReportedMod> = parse_arg(&args, 0)?;
+                state::set_reported_mods(&path, mods);
+                Ok(())
+            }
+        })
+}
+
+async fn get_instance(path: &str) -> crate::Result<InstanceInfo> {
+    let profile = profile::get(path).await?.ok_or_else(|| {
+        ErrorKind::RpcError(format!("Instance {path} no longer exists"))
+    })?;
+
+    Ok(InstanceInfo {
+        name: profile.name,
+        path: profile.path,
+        game_version: profile.game_version,
+        loader: profile.loader,
+        loader_version: profile.loader_version,
+        linked_project_id: profile
+            .linked_data
+            .as_ref()
+            .map(|x| x.project_id.clone()),
+        linked_version_id: profile.linked_data.map(|x| x.version_id),
+    })
+}
+
+/// Asks the app to open a web page. Only HTTP(S) URLs are accepted, so the
+/// game cannot use this to run local files or other URL handlers
+async fn open_url(url: String) -> crate::Result<()> {
+    let parsed = url::Url::parse(&url)
+        .map_err(|e| ErrorKind::RpcError(format!("Invalid URL: {e}")))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ErrorKind::RpcError(format!(
+            "Unsupported URL scheme: {}",
+            parsed.scheme()
+        ))
+        .into());
+    }
+
+    emit_command(CommandPayload::OpenUrl {
+        url: parsed.to_string(),
+    })
+    .await
+}
+
+/// Looks up the projects install requests refer to
+trait ProjectSource {
+    /// The type of a project, or `None` if it doesn't exist
+    async fn project_type(
+        &self,
+        project_id: &str,
+    ) -> crate::Result<Option<String>>;
+
+    /// The ID of the project a version belongs to, or `None` if it doesn't
+    /// exist
+    async fn version_project(
+        &self,
+        version_id: &str,
+    ) -> crate::Result<Option<String>>;
+}
+
+struct CachedProjects;
+
+impl ProjectSource for CachedProjects {
+    async fn project_type(
+        &self,
+        project_id: &str,
+    ) -> crate::Result<Option<String>> {
+        let state = crate::State::get().await?;
+        Ok(CachedEntry::get_project(
+            project_id,
+            None,
+            &state.pool,
+            &state.api_semaphore,
+        )
+        .await?
+        .map(|x| x.project_type))
+    }
+
+    async fn version_project(
+        &self,
+        version_id: &str,
+    ) -> crate::Result<Option<String>> {
+        let state = crate::State::get().await?;
+        Ok(CachedEntry::get_version(
+            version_id,
+            None,
+            &state.pool,
+            &state.api_semaphore,
+        )
+        .await?
+        .map(|x| x.project_id))
+    }
+}
+
+/// The command asking the app to install a project or a specific version of
+/// it. This goes through the same confirmation prompt as install links from
+/// the website, so nothing is installed unless the user accepts.
+///
+/// Modpacks are rejected, as the app installs them as a new instance
+/// without asking
+async fn install_command(
+    project_id: String,
+    version_id: Option<String>,
+    source: &impl ProjectSource,
+) -> crate::Result<CommandPayload> {
+    for id in std::iter::once(&project_id).chain(version_id.as_ref()) {
+        if id.is_empty()
+            || id.len() > 64
+            || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(ErrorKind::RpcError(format!(
+                "Invalid project or version ID: {id}"
+            ))
+            .into());
+        }
+    }
+
+    let project_id = match &version_id {
+        Some(id) => source.version_project(id).await?.ok_or_else(|| {
+            ErrorKind::RpcError(format!("Version {id} does not exist"))
+        })?,
+        None => project_id,
+    };
+    let project_type =
+        source.project_type(&project_id).await?.ok_or_else(|| {
+            ErrorKind::RpcError(format!("Project {project_id} does not exist"))
+        })?;
+    if project_type == "modpack" {
+        return Err(ErrorKind::RpcError(
+            "Modpacks can't be installed from the game".to_string(),
+        )
+        .into());
+    }
+
+    Ok(match version_id {
+        Some(id) => CommandPayload::InstallVersion { id },
+        None => CommandPayload::InstallMod { id: project_id },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Projects;
+
+    impl ProjectSource for Projects {
+        async fn project_type(
+            &self,
+            project_id: &str,
+        ) -> crate::Result<Option<String>> {
+            Ok(match project_id {
+                "sodium" => Some("mod".to_string()),
+                "fabulously-optimized" => Some("modpack".to_string()),
+                _ => None,
+            })
+        }
+
+        async fn version_project(
+            &self,
+            version_id: &str,
+        ) -> crate::Result<Option<String>> {
+            Ok(match version_id {
+                "mod-version" => Some("sodium".to_string()),
+                "pack-version" => Some("fabulously-optimized".to_string()),
+                _ => None,
+            })
+        }
+    }
+
+    async fn command(
+        project_id: &str,
+        version_id: Option<&str>,
+    ) -> crate::Result<serde_json::Value> {
+        let payload = install_command(
+            project_id.to_string(),
+            version_id.map(|x| x.to_string()),
+            &Projects,
+        )
+        .await?;
+        Ok(serde_json::to_value(payload)?)
+    }
+
+    #[tokio::test]
+    async fn requests_install_of_mods() {
+        assert_eq!(
+            command("sodium", None).await.unwrap(),
+            serde_json::json!({ "event": "InstallMod", "id": "sodium" })
+        );
+        assert_eq!(
+            command("sodium", Some("mod-version")).await.unwrap(),
+            serde_json::json!({ "event": "InstallVersion", "id": "mod-version" })
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_modpacks() {
+        assert!(command("fabulously-optimized", None).await.is_err());
+        // The project of the version is checked, not the given project
+        assert!(command("sodium", Some("pack-version")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_and_invalid_ids() {
+        assert!(command("missing", None).await.is_err());
+        assert!(command("sodium", Some("missing")).await.is_err());
+        assert!(command("", None).await.is_err());
+        assert!(command("../sodium", None).await.is_err());
+        assert!(command(&"a".repeat(65), None).await.is_err());
+        assert!(command("sodium", Some("mod version")).await.is_err());
+    }
+}
@@ -1,12 +1,14 @@
-use crate::state::ProjectType;
-use crate::util::fetch::{FetchSemaphore, fetch_json, sha1_async};
+use crate::state::{Endpoints, ProjectType};
+use crate::util::fetch::{
+    FetchSemaphore, fetch_json, fetch_meta_json, sha1_async,
+};
 use chrono::{DateTime, Utc};
 use dashmap::DashSet;
 use reqwest::Method;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fmt::Display;
 use std::hash::Hash;
@@ -67,7 +69,48 @@ impl CacheValueType {
         }
     }
 
+    /// The `data_type` the values are stored with in the cache. Values from
+    /// non-default endpoints are namespaced by endpoint, so switching
+    /// endpoints never returns values from another instance.
+    ///
+    /// Launcher metadata verified with trusted keys is also namespaced by the
+    /// IDs of the keys, so metadata cached unsigned, such as before signing
+    /// was enabled, is fetched and verified again
+    fn cache_key(&self) -> String {
+        let endpoints = Endpoints::current();
+        let mut namespaces = Vec::new();
+        match self {
+            CacheValueType::LoaderManifest
+            | CacheValueType::MinecraftManifest => {
+                namespaces.extend(
+                    endpoints.meta_cache_namespace().map(str::to_string),
+                );
+                if !endpoints.launcher_meta_keys.is_empty() {
+                    namespaces.push(format!(
+                        "signed:{}",
+                        endpoints
+                            .launcher_meta_keys
+                            .iter()
+                            .map(|x| &*x.key_id)
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ));
+                }
+            }
+            CacheValueType::FileHash => {}
+            _ => namespaces
+                .extend(endpoints.api_cache_namespace().map(str::to_string)),
+        }
+
+        std::iter::once(self.as_str())
+            .chain(namespaces.iter().map(|x| &**x))
+            .collect::<Vec<_>>()
+            .join("@")
+    }
+
     pub fn from_string(val: &str) -> CacheValueType {
+        // Strip the endpoint namespace of `cache_key`
+        let val = val.split_once('@').map_or(val, |(val, _)| val);
         match val {
             "project" => CacheValueType::Project,
             "project_v3" => CacheValueType::ProjectV3,
@@ -846,7 +889,7 @@ impl CachedEntry {
         let expired_keys = DashSet::new();
 
         if cache_behaviour != CacheBehaviour::Bypass {
-            let type_str = type_.as_str();
+            let type_str = type_.cache_key();
             let serialized_keys = serde_json::to_string(&keys)?;
             let alias_keys = if type_.case_sensitive_alias().unwrap_or(true) {
                 serialized_keys.clone()
@@ -1004,6 +1047,8 @@ impl CachedEntry {
         fetch_semaphore: &FetchSemaphore,
         pool: &SqlitePool,
     ) -> crate::Result<Vec<(Self, bool)>> {
+        let endpoints = Endpoints::current();
+
         async fn fetch_many_batched<T: DeserializeOwned>(
             method: Method,
             api_url: &str,
@@ -1123,7 +1168,7 @@ impl CachedEntry {
             CacheValueType::Project => {
                 fetch_original_values!(
                     Project,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "projects",
                     CacheValue::Project
                 )
@@ -1131,7 +1176,7 @@ impl CachedEntry {
             CacheValueType::ProjectV3 => {
                 fetch_original_values!(
                     ProjectV3,
-                    env!("MODRINTH_API_URL_V3"),
+                    &endpoints.api_url_v3,
                     "projects",
                     CacheValue::ProjectV3
                 )
@@ -1139,7 +1184,7 @@ impl CachedEntry {
             CacheValueType::Version => {
                 fetch_original_values!(
                     Version,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "versions",
                     CacheValue::Version
                 )
@@ -1147,7 +1192,7 @@ impl CachedEntry {
             CacheValueType::User => {
                 fetch_original_values!(
                     User,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "users",
                     CacheValue::User
                 )
@@ -1155,7 +1200,7 @@ impl CachedEntry {
             CacheValueType::Team => {
                 let mut teams = fetch_many_batched::<Vec<TeamMember>>(
                     Method::GET,
-                    env!("MODRINTH_API_URL_V3"),
+                    &endpoints.api_url_v3,
                     "teams?ids=",
                     &keys,
                     fetch_semaphore,
@@ -1194,7 +1239,7 @@ impl CachedEntry {
             CacheValueType::Organization => {
                 let mut orgs = fetch_many_batched::<Organization>(
                     Method::GET,
-                    env!("MODRINTH_API_URL_V3"),
+                    &endpoints.api_url_v3,
                     "organizations?ids=",
                     &keys,
                     fetch_semaphore,
@@ -1249,7 +1294,7 @@ impl CachedEntry {
             CacheValueType::File => {
                 let mut versions = fetch_json::<HashMap<String, Version>>(
                     Method::POST,
-                    concat!(env!("MODRINTH_API_URL"), "version_files"),
+                    &format!("{}version_files", endpoints.api_url),
                     None,
                     Some(serde_json::json!({
                         "algorithm": "sha1",
@@ -1307,7 +1352,7 @@ impl CachedEntry {
                             x.key().to_string(),
                             format!(
                                 "{}{}/v0/manifest.json",
-                                env!("MODRINTH_LAUNCHER_META_URL"),
+                                endpoints.launcher_meta_url,
                                 x.key()
                             ),
                         )
@@ -1315,16 +1360,7 @@ impl CachedEntry {
                     .collect::<Vec<_>>();
 
                 futures::future::try_join_all(fetch_urls.iter().map(
-                    |(_, url)| {
-                        fetch_json(
-                            Method::GET,
-                            url,
-                            None,
-                            None,
-                            fetch_semaphore,
-                            pool,
-                        )
-                    },
+                    |(_, url)| fetch_meta_json(url, fetch_semaphore, pool),
                 ))
                 .await?
                 .into_iter()
@@ -1342,20 +1378,27 @@ impl CachedEntry {
                 .collect()
             }
             CacheValueType::MinecraftManifest => {
-                fetch_original_value!(
-                    MinecraftManifest,
-                    env!("MODRINTH_LAUNCHER_META_URL"),
-                    format!(
-                        "minecraft/v{}/manifest.json",
-                        daedalus::minecraft::CURRENT_FORMAT_VERSION
-                    ),
-                    CacheValue::MinecraftManifest
-                )
+                vec![(
+                    CacheValue::MinecraftManifest(
+                        fetch_meta_json(
+                            &format!(
+                                "{}minecraft/v{}/manifest.json",
+                                endpoints.launcher_meta_url,
+                                daedalus::minecraft::CURRENT_FORMAT_VERSION
+                            ),
+                            fetch_semaphore,
+                            pool,
+                        )
+                        .await?,
+                    )
+                    .get_entry(),
+                    true,
+                )]
             }
             CacheValueType::Categories => {
                 fetch_original_value!(
                     Categories,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "tag/category",
                     CacheValue::Categories
                 )
@@ -1363,7 +1406,7 @@ impl CachedEntry {
             CacheValueType::ReportTypes => {
                 fetch_original_value!(
                     ReportTypes,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "tag/report_type",
                     CacheValue::ReportTypes
                 )
@@ -1371,7 +1414,7 @@ impl CachedEntry {
             CacheValueType::Loaders => {
                 fetch_original_value!(
                     Loaders,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "tag/loader",
                     CacheValue::Loaders
                 )
@@ -1379,7 +1422,7 @@ impl CachedEntry {
             CacheValueType::GameVersions => {
                 fetch_original_value!(
                     GameVersions,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "tag/game_version",
                     CacheValue::GameVersions
                 )
@@ -1387,7 +1430,7 @@ impl CachedEntry {
             CacheValueType::DonationPlatforms => {
                 fetch_original_value!(
                     DonationPlatforms,
-                    env!("MODRINTH_API_URL"),
+                    &endpoints.api_url,
                     "tag/donation_platform",
                     CacheValue::DonationPlatforms
                 )
@@ -1492,7 +1535,7 @@ impl CachedEntry {
                         |((loaders_key, game_version), hashes)| {
                             fetch_json::<HashMap<String, Vec<Version>>>(
                                 Method::POST,
-                                concat!(env!("MODRINTH_API_URL"), "version_files/update_many"),
+                                &format!("{}version_files/update_many", endpoints.api_url),
                                 None,
                                 Some(serde_json::json!({
                                     "algorithm": "sha1",
@@ -1558,11 +1601,7 @@ impl CachedEntry {
                     .map(|x| {
                         (
                             x.key().to_string(),
-                            format!(
-                                "{}search{}",
-                                env!("MODRINTH_API_URL"),
-                                x.key()
-                            ),
+                            format!("{}search{}", endpoints.api_url, x.key()),
                         )
                     })
                     .collect::<Vec<_>>();
@@ -1606,8 +1645,7 @@ impl CachedEntry {
                     let project_id = key.to_string();
                     let url = format!(
                         "{}project/{}/version?include_changelog=false",
-                        env!("MODRINTH_API_URL"),
-                        project_id
+                        endpoints.api_url, project_id
                     );
 
                     match fetch_json::<Vec<Version>>(
@@ -1652,7 +1690,7 @@ impl CachedEntry {
                             x.key().to_string(),
                             format!(
                                 "{}search{}",
-                                env!("MODRINTH_API_URL_V3"),
+                                endpoints.api_url_v3,
                                 x.key()
                             ),
                         )
@@ -1768,6 +1806,19 @@ impl CachedEntry {
         Ok(value)
     }
 
+    /// Stores a value that was not fetched from its origin, such as metadata
+    /// from an offline bundle. The value is stored as expired, so it is
+    /// replaced as soon as the launcher is online
+    pub(crate) async fn insert_expired(
+        value: CacheValue,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+    ) -> crate::Result<()> {
+        let mut entry = value.get_entry();
+        entry.expires = Utc::now().timestamp();
+
+        Self::upsert_many(&[entry], exec).await
+    }
+
     pub(crate) async fn upsert_many(
         items: &[Self],
         exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
@@ -1783,7 +1834,7 @@ impl CachedEntry {
 
                 Ok(serde_json::json!({
                     "id": item.id,
-                    "data_type": item.type_.as_str(),
+                    "data_type": item.type_.cache_key(),
                     "alias": item.alias,
                     "data": data,
                     "expires": item.expires,
@@ -1821,7 +1872,13 @@ impl CachedEntry {
         cache_types: &[CacheValueType],
         exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
     ) -> crate::Result<()> {
-        let cache_types = serde_json::to_string(&cache_types)?;
+        // Purge the values of the current endpoints along with the defaults
+        let cache_types = serde_json::to_string(
+            &cache_types
+                .iter()
+                .flat_map(|x| [x.as_str().to_string(), x.cache_key()])
+                .collect::<HashSet<_>>(),
+        )?;
 
         sqlx::query!(
             "
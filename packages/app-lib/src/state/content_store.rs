@@ -0,0 +1,532 @@
+//! Content-addressed storage for files shared between profiles
+//!
+//! When enabled in the settings, mods, resource packs and shaders installed
+//! into profiles are stored once by their SHA-1 hash, and linked into each
+//! profile. Files are reflinked where the filesystem supports copy-on-write,
+//! so editing a profile file never changes the stored copy or other
+//! profiles. Each reflinked profile file is tracked as a reference, so stored
+//! files are removed with their last reference. Where reflinks aren't
+//! supported, profile files are plain copies which don't reference the store
+//! and aren't counted as deduplicated.
+use crate::State;
+use crate::state::{DirectoryInfo, Profile};
+use crate::util::fetch::{FetchSemaphore, IoSemaphore, sha1_async, write};
+use crate::util::io::{self, IOError};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Statistics about the shared content store
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ContentStoreStats {
+    /// Number of files in the store
+    pub objects: usize,
+    /// Total size of the files in the store, in bytes
+    pub size: u64,
+    /// Number of profile files reflinked to a stored file
+    pub references: usize,
+    /// Size of the additional copies profiles would need without the
+    /// store, in bytes
+    pub deduplicated_size: u64,
+}
+
+/// How a profile file was created from its stored copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkKind {
+    Reflink,
+    Copy,
+}
+
+/// Writes a content file into a profile, linking it from the content store
+/// if `shared_content_store` is enabled.
+///
+/// Otherwise the file is written in place as usual, and any reference left
+/// from when the store was enabled is dropped.
+pub(crate) async fn write_profile_file(
+    profile_path: &str,
+    project_path: &str,
+    bytes: &bytes::Bytes,
+    hash: Option<&str>,
+    shared_content_store: bool,
+    io_semaphore: &IoSemaphore,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<()> {
+    let state = State::get().await?;
+    let path = crate::api::profile::get_full_path(profile_path)
+        .await?
+        .join(project_path);
+
+    if !shared_content_store {
+        write(&path, bytes, io_semaphore).await?;
+        release(profile_path, project_path, &state.directories, exec).await?;
+
+        return Ok(());
+    }
+
+    let hash = match hash {
+        Some(hash) => hash.to_string(),
+        None => sha1_async(bytes.clone()).await?,
+    };
+
+    let object = state.directories.content_store_object(&hash);
+    if !object.exists() {
+        replace(&object, bytes, io_semaphore).await?;
+    }
+
+    let kind = link_object(&object, &path, io_semaphore).await?;
+    track_link(
+        profile_path,
+        project_path,
+        &hash,
+        kind,
+        &state.directories,
+        exec,
+    )
+    .await
+}
+
+/// Drops the reference of a profile file to the content store, removing
+/// the stored file if nothing else references it
+pub(crate) async fn release(
+    profile_path: &str,
+    project_path: &str,
+    directories: &DirectoryInfo,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<()> {
+    let res = sqlx::query!(
+        "
+        DELETE FROM content_store_refs
+        WHERE profile_path = $1 AND path = $2
+        RETURNING hash
+        ",
+        profile_path,
+        project_path,
+    )
+    .fetch_optional(exec)
+    .await?;
+
+    if let Some(res) = res {
+        remove_if_unreferenced(&res.hash, directories, exec).await?;
+    }
+
+    Ok(())
+}
+
+/// Drops all references of a profile to the content store
+pub(crate) async fn release_profile(
+    profile_path: &str,
+    directories: &DirectoryInfo,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<()> {
+    let hashes = sqlx::query!(
+        "
+        DELETE FROM content_store_refs
+        WHERE profile_path = $1
+        RETURNING hash
+        ",
+        profile_path,
+    )
+    .fetch_all(exec)
+    .await?
+    .into_iter()
+    .map(|x| x.hash)
+    .collect::<HashSet<_>>();
+
+    for hash in hashes {
+        remove_if_unreferenced(&hash, directories, exec).await?;
+    }
+
+    Ok(())
+}
+
+/// Updates the reference of a profile file that was renamed, e.g. when it
+/// is disabled
+pub(crate) async fn rename_reference(
+    profile_path: &str,
+    old_project_path: &str,
+    new_project_path: &str,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+) -> crate::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE content_store_refs
+        SET path = $3
+        WHERE profile_path = $1 AND path = $2
+        ",
+        profile_path,
+        old_project_path,
+        new_project_path,
+    )
+    .execute(exec)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves the content files of all profiles into the content store, linking
+/// identical files together
+#[tracing::instrument(skip(fetch_semaphore, io_semaphore))]
+pub async fn deduplicate(
+    directories: &DirectoryInfo,
+    pool: &SqlitePool,
+    fetch_semaphore: &FetchSemaphore,
+    io_semaphore: &IoSemaphore,
+) -> crate::Result<ContentStoreStats> {
+    let references = get_references(pool).await?;
+
+    for profile in Profile::get_all(pool).await? {
+        let profile_dir =
+            crate::api::profile::get_full_path(&profile.path).await?;
+        let files = profile
+            .get_content_file_hashes(pool, fetch_semaphore)
+            .await?;
+
+        for (project_path, hash) in files {
+            if references
+                .get(&(profile.path.clone(), project_path.clone()))
+                .is_some_and(|x| *x == hash)
+            {
+                continue;
+            }
+
+            // Cached hashes are keyed by file size, so make sure the file
+            // did not change before replacing it
+            let path = profile_dir.join(&project_path);
+            let bytes = bytes::Bytes::from(io::read(&path).await?);
+            if sha1_async(bytes.clone()).await? != hash {
+                continue;
+            }
+
+            let object = directories.content_store_object(&hash);
+            if !object.exists() {
+                replace(&object, &bytes, io_semaphore).await?;
+            }
+
+            let kind = link_object(&object, &path, io_semaphore).await?;
+            track_link(
+                &profile.path,
+                &project_path,
+                &hash,
+                kind,
+                directories,
+                pool,
+            )
+            .await?;
+        }
+    }
+
+    get_stats(directories, pool).await
+}
+
+/// Removes references to profile files that no longer exist, and stored
+/// files that are no longer referenced
+#[tracing::instrument]
+pub async fn prune(
+    directories: &DirectoryInfo,
+    pool: &SqlitePool,
+) -> crate::Result<ContentStoreStats> {
+    let profiles_dir = directories.profiles_dir();
+
+    for ((profile_path, project_path), _) in get_references(pool).await? {
+        let path = profiles_dir.join(&profile_path).join(&project_path);
+        if !path.exists() {
+            release(&profile_path, &project_path, directories, pool).await?;
+        }
+    }
+
+    let referenced = get_references(pool)
+        .await?
+        .into_values()
+        .collect::<HashSet<_>>();
+
+    for (hash, path, _) in get_objects(directories).await? {
+        if !referenced.contains(&hash) {
+            io::remove_file(&path).await?;
+        }
+    }
+
+    get_stats(directories, pool).await
+}
+
+/// Gets statistics about the content store
+#[tracing::instrument]
+pub async fn get_stats(
+    directories: &DirectoryInfo,
+    pool: &SqlitePool,
+) -> crate::Result<ContentStoreStats> {
+    let mut reference_counts: HashMap<String, u64> = HashMap::new();
+    for hash in get_references(pool).await?.into_values() {
+        *reference_counts.entry(hash).or_default() += 1;
+    }
+
+    let mut stats = ContentStoreStats {
+        references: reference_counts.values().sum::<u64>() as usize,
+        ..ContentStoreStats::default()
+    };
+    for (hash, _, size) in get_objects(directories).await? {
+        stats.objects += 1;
+        stats.size += size;
+        stats.deduplicated_size += size
+            * reference_counts
+                .get(&hash)
+                .map_or(0, |x| x.saturating_sub(1));
+    }
+
+    Ok(stats)
+}
+
+/// Gets the hashes of all profile files referencing the content store,
+/// keyed by profile path and path relative to the profile
+async fn get_references(
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+) -> crate::Result<HashMap<(String, String), String>> {
+    let res = sqlx::query!(
+        "
+        SELECT profile_path, path, hash
+        FROM content_store_refs
+        "
+    )
+    .fetch_all(exec)
+    .await?;
+
+    Ok(res
+        .into_iter()
+        .map(|x| ((x.profile_path, x.path), x.hash))
+        .collect())
+}
+
+/// Gets the hash, path and size of all files in the content store
+async fn get_objects(
+    directories: &DirectoryInfo,
+) -> crate::Result<Vec<(String, PathBuf, u64)>> {
+    let store_dir = directories.content_store_dir();
+    let mut objects = Vec::new();
+
+    if !store_dir.exists() {
+        return Ok(objects);
+    }
+
+    let mut prefixes = io::read_dir(&store_dir).await?;
+    while let Some(prefix) =
+        prefixes.next_entry().await.map_err(IOError::from)?
+    {
+        if !prefix.file_type().await.map_err(IOError::from)?.is_dir() {
+            continue;
+        }
+
+        let mut entries = io::read_dir(prefix.path()).await?;
+        while let Some(entry) =
+            entries.next_entry().await.map_err(IOError::from)?
+        {
+            let metadata = entry.metadata().await.map_err(IOError::from)?;
+            if let Some(hash) = entry.file_name().to_str()
+                && metadata.is_file()
+                && !hash.ends_with(".tmp")
+            {
+                objects.push((hash.to_string(), entry.path(), metadata.len()));
+            }
+        }
+    }
+
+    Ok(objects)
+}
+
+/// References the stored file from a reflinked profile file. Copied files
+/// share nothing with the store, so they drop their reference instead, and
+/// the stored file is removed if nothing else references it
+async fn track_link(
+    profile_path: &str,
+    project_path: &str,
+    hash: &str,
+    kind: LinkKind,
+    directories: &DirectoryInfo,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<()> {
+    match kind {
+        LinkKind::Reflink => {
+            add_reference(profile_path, project_path, hash, directories, exec)
+                .await
+        }
+        LinkKind::Copy => {
+            release(profile_path, project_path, directories, exec).await?;
+            remove_if_unreferenced(hash, directories, exec).await
+        }
+    }
+}
+
+async fn add_reference(
+    profile_path: &str,
+    project_path: &str,
+    hash: &str,
+    directories: &DirectoryInfo,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<()> {
+    let previous = sqlx::query!(
+        "
+        SELECT hash
+        FROM content_store_refs
+        WHERE profile_path = $1 AND path = $2
+        ",
+        profile_path,
+        project_path,
+    )
+    .fetch_optional(exec)
+    .await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO content_store_refs (profile_path, path, hash)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (profile_path, path) DO UPDATE SET
+            hash = $3
+        ",
+        profile_path,
+        project_path,
+        hash,
+    )
+    .execute(exec)
+    .await?;
+
+    // The profile file may replace a different stored file
+    if let Some(previous) = previous
+        && previous.hash != hash
+    {
+        remove_if_unreferenced(&previous.hash, directories, exec).await?;
+    }
+
+    Ok(())
+}
+
+async fn remove_if_unreferenced(
+    hash: &str,
+    directories: &DirectoryInfo,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+) -> crate::Result<()> {
+    let referenced = sqlx::query!(
+        "
+        SELECT hash
+        FROM content_store_refs
+        WHERE hash = $1
+        LIMIT 1
+        ",
+        hash,
+    )
+    .fetch_optional(exec)
+    .await?
+    .is_some();
+
+    let object = directories.content_store_object(hash);
+    if !referenced && object.exists() {
+        io::remove_file(&object).await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the file at `path` with a reflink to a stored file, or a copy of
+/// it if the filesystem doesn't support reflinks
+async fn link_object(
+    object: &Path,
+    path: &Path,
+    io_semaphore: &IoSemaphore,
+) -> crate::Result<LinkKind> {
+    let _permit = io_semaphore.0.acquire().await?;
+
+    let object = object.to_path_buf();
+    let path = path.to_path_buf();
+    let kind = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || -> std::io::Result<LinkKind> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Link next to the destination first, so the profile file is
+            // replaced atomically
+            let temp = temp_path(&path);
+            let _ = std::fs::remove_file(&temp);
+
+            // Hardlinks would share the stored file, so fall back to a copy
+            let kind = if reflink_copy::reflink(&object, &temp).is_ok() {
+                LinkKind::Reflink
+            } else {
+                let _ = std::fs::remove_file(&temp);
+                std::fs::copy(&object, &temp)?;
+                LinkKind::Copy
+            };
+
+            if let Err(err) = std::fs::rename(&temp, &path) {
+                let _ = std::fs::remove_file(&temp);
+                return Err(err);
+            }
+
+            Ok(kind)
+        }
+    })
+    .await?
+    .map_err(|e| IOError::with_path(e, &path))?;
+
+    tracing::trace!(
+        "Linked {} from the content store ({kind:?})",
+        path.display()
+    );
+
+    Ok(kind)
+}
+
+/// Writes a file next to `path` and renames it over `path`, so an existing
+/// file is replaced instead of being modified in place
+async fn replace(
+    path: &Path,
+    bytes: &[u8],
+    io_semaphore: &IoSemaphore,
+) -> crate::Result<()> {
+    let temp = temp_path(path);
+    write(&temp, bytes, io_semaphore).await?;
+    if let Err(err) = io::rename_or_move(&temp, path).await {
+        let _ = io::remove_file(&temp).await;
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}.tmp", rand::random::<u32>()));
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Semaphore;
+
+    #[tokio::test]
+    async fn linked_files_do_not_share_the_stored_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let io_semaphore = IoSemaphore(Semaphore::new(1));
+        let object = dir.path().join("store").join("object");
+        let path = dir.path().join("profile").join("mods").join("mod.jar");
+
+        replace(&object, b"stored", &io_semaphore).await.unwrap();
+        link_object(&object, &path, &io_semaphore).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"stored");
+
+        // Writing to the profile file in place leaves the stored file intact
+        std::fs::write(&path, b"edited").unwrap();
+        assert_eq!(std::fs::read(&object).unwrap(), b"stored");
+
+        replace(&path, b"replaced", &io_semaphore).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"replaced");
+        assert_eq!(std::fs::read(&object).unwrap(), b"stored");
+    }
+
+    #[test]
+    fn temp_paths_are_skipped_as_objects() {
+        let temp = temp_path(Path::new("store/ab/abcdef"));
+        assert_eq!(temp.parent(), Some(Path::new("store/ab")));
+        assert!(temp.to_string_lossy().ends_with(".tmp"));
+    }
+}
@@ -0,0 +1,686 @@
+//! Crash analysis for instances whose game process exited abnormally.
+//!
+//! Reads the newest crash report and the tail of the game log, extracts
+//! clues pointing at mods (suspects named by the mod loader, failed mixins,
+//! mixin handler names and stack frames) and maps them back to the installed
+//! mod files of the profile.
+
+use crate::logs::CensoredString;
+use crate::state::{
+    Credentials, Profile, ProfileFile, ProjectType, get_log_buffer,
+};
+use crate::util::io;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Number of log lines from the end of the game log to analyze
+const LOG_TAIL_LINES: usize = 1000;
+/// Maximum number of evidence lines kept per suspect
+const MAX_EVIDENCE_PER_SUSPECT: usize = 5;
+
+/// Packages of the game, loaders and common libraries, which never identify
+/// a mod on their own
+const IGNORED_PACKAGES: &[&str] = &[
+    "java.",
+    "javax.",
+    "jdk.",
+    "sun.",
+    "com.sun.",
+    "net.minecraft.",
+    "com.mojang.",
+    "net.fabricmc.",
+    "org.quiltmc.",
+    "net.minecraftforge.",
+    "net.neoforged.",
+    "cpw.mods.",
+    "org.spongepowered.",
+    "com.llamalad7.mixinextras.",
+    "org.lwjgl.",
+    "io.netty.",
+    "com.google.",
+    "org.apache.",
+    "it.unimi.",
+    "org.slf4j.",
+    "kotlin.",
+];
+
+/// The result of analyzing a crash of a profile
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrashAnalysis {
+    /// Exit code of the game process, if it exited normally
+    pub exit_code: Option<i32>,
+    /// Signal which killed the game process, if it was killed by one (Unix
+    /// only)
+    pub signal: Option<i32>,
+    /// File name of the analyzed crash report in `crash-reports/`, if any
+    pub crash_report: Option<String>,
+    /// The crash report description, ie: "Rendering overlay"
+    pub description: Option<String>,
+    /// The first exception found, ie: "java.lang.NullPointerException: ..."
+    pub exception: Option<String>,
+    /// Installed mods suspected of causing the crash, most suspicious first
+    pub suspects: Vec<CrashSuspect>,
+}
+
+/// An installed mod suspected of causing a crash
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrashSuspect {
+    /// Relative path of the file within the profile
+    pub file_path: String,
+    pub file_name: String,
+    /// Modrinth project ID, if the file is recognized
+    pub project_id: Option<String>,
+    /// Modrinth version ID, if the file is recognized
+    pub version_id: Option<String>,
+    /// Mod IDs declared by the file
+    pub mod_ids: Vec<String>,
+    /// Lines of the crash report or log implicating the file
+    pub evidence: Vec<CrashEvidence>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CrashEvidence {
+    pub kind: CrashEvidenceKind,
+    pub line: String,
+}
+
+/// Kinds of evidence, from weakest to strongest
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashEvidenceKind {
+    /// A class of the mod appears in a stack trace
+    StackFrame,
+    /// A mixin of the mod failed, or a mixin handler of the mod appears in a
+    /// stack trace
+    Mixin,
+    /// The mod loader reported the mod as the cause
+    ReportedSuspect,
+}
+
+/// Something found in a crash report or log that can identify a mod file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClueTarget {
+    ModId(String),
+    MixinConfig(String),
+    JarName(String),
+    Class(String),
+}
+
+#[derive(Debug, Default)]
+struct CrashClues {
+    description: Option<String>,
+    exception: Option<String>,
+    clues: Vec<(ClueTarget, CrashEvidence)>,
+}
+
+impl CrashClues {
+    fn push(
+        &mut self,
+        target: ClueTarget,
+        kind: CrashEvidenceKind,
+        line: &str,
+    ) {
+        self.clues.push((
+            target,
+            CrashEvidence {
+                kind,
+                line: line.to_string(),
+            },
+        ));
+    }
+
+    fn parse(&mut self, text: &str) {
+        static STACK_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^at\s+(?:\S+/)?([\w$.]+)\.[\w$<>]+\(").unwrap()
+        });
+        static FRAME_JAR: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"\[([^\[\]:/\\]+\.jar)(?::[^\]]*)?\]").unwrap()
+        });
+        static FRAME_MODULE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"/([a-z][a-z0-9_]*)@[^/\s]+/").unwrap()
+        });
+        static EXCEPTION: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"^(?:Caused by: )?(?:[a-z_$][\w$]*\.)+[\w$]*(?:Exception|Error|Throwable)(?::.*)?$",
+            )
+            .unwrap()
+        });
+        static SUSPECTED_MODS: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^Suspected Mods?: (.*)$").unwrap());
+        static PARENTHESIZED_ID: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\(([a-z][a-z0-9_\-]*)\)").unwrap());
+        static MOD_LOADING_ISSUE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^-- Mod loading issue for: (\S+) --$").unwrap()
+        });
+        static MOD_FILE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^Mod File: (?:.*[/\\])?([^/\\]+\.jar)$").unwrap()
+        });
+        static FROM_MOD: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"from mod ([a-z][a-z0-9_\-]*)").unwrap()
+        });
+        static MIXIN_CONFIG: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"([\w.\-]+\.json)(?::[\w$.]+|\])").unwrap()
+        });
+        static MIXIN_HANDLER: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"(?:handler|redirect|modify\w*|wrap\w*|localvar)\$[a-z0-9]{6}\$([a-z][a-z0-9_]*)\$",
+            )
+            .unwrap()
+        });
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if self.description.is_none()
+                && let Some(description) = line.strip_prefix("Description: ")
+            {
+                self.description = Some(description.to_string());
+            }
+
+            if self.exception.is_none() && EXCEPTION.is_match(line) {
+                self.exception = Some(
+                    line.trim_start_matches("Caused by: ")
+                        .chars()
+                        .take(500)
+                        .collect(),
+                );
+            }
+
+            if let Some(suspects) = SUSPECTED_MODS.captures(line) {
+                for id in PARENTHESIZED_ID.captures_iter(&suspects[1]) {
+                    self.push(
+                        ClueTarget::ModId(id[1].to_string()),
+                        CrashEvidenceKind::ReportedSuspect,
+                        line,
+                    );
+                }
+            }
+
+            if let Some(id) = MOD_LOADING_ISSUE.captures(line) {
+                self.push(
+                    ClueTarget::ModId(id[1].to_string()),
+                    CrashEvidenceKind::ReportedSuspect,
+                    line,
+                );
+            }
+
+            if let Some(jar) = MOD_FILE.captures(line) {
+                self.push(
+                    ClueTarget::JarName(jar[1].to_string()),
+                    CrashEvidenceKind::ReportedSuspect,
+                    line,
+                );
+            }
+
+            if line.to_lowercase().contains("mixin") {
+                for id in FROM_MOD.captures_iter(line) {
+                    self.push(
+                        ClueTarget::ModId(id[1].to_string()),
+                        CrashEvidenceKind::Mixin,
+                        line,
+                    );
+                }
+                for config in MIXIN_CONFIG.captures_iter(line) {
+                    self.push(
+                        ClueTarget::MixinConfig(config[1].to_string()),
+                        CrashEvidenceKind::Mixin,
+                        line,
+                    );
+                }
+            }
+
+            if let Some(frame) = STACK_FRAME.captures(line) {
+                for id in MIXIN_HANDLER.captures_iter(line) {
+                    self.push(
+                        ClueTarget::ModId(id[1].to_string()),
+                        CrashEvidenceKind::Mixin,
+                        line,
+                    );
+                }
+
+                let class = &frame[1];
+                if !IGNORED_PACKAGES.iter().any(|x| class.starts_with(x)) {
+                    self.push(
+                        ClueTarget::Class(class.to_string()),
+                        CrashEvidenceKind::StackFrame,
+                        line,
+                    );
+                }
+                for jar in FRAME_JAR.captures_iter(line) {
+                    self.push(
+                        ClueTarget::JarName(jar[1].to_string()),
+                        CrashEvidenceKind::StackFrame,
+                        line,
+                    );
+                }
+                for id in FRAME_MODULE.captures_iter(line) {
+                    self.push(
+                        ClueTarget::ModId(id[1].to_string()),
+                        CrashEvidenceKind::StackFrame,
+                        line,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// What an installed mod file declares, used to match clues against it
+#[derive(Debug, Default)]
+struct ModFileIndex {
+    mod_ids: HashSet<String>,
+    mixin_configs: HashSet<String>,
+    packages: HashSet<String>,
+}
+
+impl ModFileIndex {
+    async fn read(path: PathBuf) -> crate::Result<Self> {
+        let zip = async_zip::tokio::read::fs::ZipFileReader::new(path).await?;
+        let mut index = Self::default();
+
+        for (entry_index, entry) in zip.file().entries().iter().enumerate() {
+            let Ok(name) = entry.filename().as_str() else {
+                continue;
+            };
+
+            if let Some(class) = name.strip_suffix(".class") {
+                if !name.starts_with("META-INF/")
+                    && let Some((package, _)) = class.rsplit_once('/')
+                {
+                    index.packages.insert(package.replace('/', "."));
+                }
+                continue;
+            }
+
+            if !name.contains('/')
+                && name.ends_with(".json")
+                && name.contains("mixin")
+                && !name.contains("refmap")
+            {
+                index.mixin_configs.insert(name.to_string());
+                continue;
+            }
+
+            if !matches!(
+                name,
+                "fabric.mod.json"
+                    | "quilt.mod.json"
+                    | "mcmod.info"
+                    | "META-INF/mods.toml"
+                    | "META-INF/neoforge.mods.toml"
+            ) {
+                continue;
+            }
+
+            let mut data = vec![];
+            zip.reader_with_entry(entry_index)
+                .await?
+                .read_to_end_checked(&mut data)
+                .await?;
+            let data = String::from_utf8_lossy(&data);
+            index.mod_ids.extend(read_mod_ids(name, &data));
+        }
+
+        Ok(index)
+    }
+
+    fn matches(&self, target: &ClueTarget, file_name: &str) -> bool {
+        match target {
+            ClueTarget::ModId(id) => self.mod_ids.contains(id),
+            ClueTarget::MixinConfig(config) => {
+                self.mixin_configs.contains(config)
+            }
+            ClueTarget::JarName(jar) => {
+                file_name.trim_end_matches(".disabled") == jar
+            }
+            ClueTarget::Class(class) => class
+                .rsplit_once('.')
+                .is_some_and(|(package, _)| self.packages.contains(package)),
+        }
+    }
+}
+
+/// Reads the mod IDs declared by a mod metadata file
+fn read_mod_ids(name: &str, data: &str) -> Vec<String> {
+    match name {
+        "fabric.mod.json" => serde_json::from_str::<serde_json::Value>(data)
+            .ok()
+            .and_then(|x| x.get("id")?.as_str().map(str::to_string))
+            .into_iter()
+            .collect(),
+        "quilt.mod.json" => serde_json::from_str::<serde_json::Value>(data)
+            .ok()
+            .and_then(|x| {
+                x.get("quilt_loader")?
+                    .get("id")?
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .into_iter()
+            .collect(),
+        "mcmod.info" => {
+            let value = serde_json::from_str::<serde_json::Value>(data)
+                .unwrap_or_default();
+            let mods = value
+                .get("modList")
+                .and_then(|x| x.as_array())
+                .or_else(|| value.as_array());
+            mods.into_iter()
+                .flatten()
+                .filter_map(|x| x.get("modid")?.as_str().map(str::to_string))
+                .collect()
+        }
+        _ => toml::from_str::<toml::Table>(data)
+            .ok()
+            .and_then(|x| x.get("mods")?.as_array().cloned())
+            .into_iter()
+            .flatten()
+            .filter_map(|x| x.get("modId")?.as_str().map(str::to_string))
+            .collect(),
+    }
+}
+
+/// Analyzes the crash of a profile, using the newest crash report (written
+/// after `since`, if given) and the tail of the game log
+#[tracing::instrument]
+pub async fn analyze_crash(
+    profile_path: &str,
+    since: Option<DateTime<Utc>>,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+) -> crate::Result<CrashAnalysis> {
+    let state = crate::State::get().await?;
+    let credentials = Credentials::get_all(&state.pool)
+        .await?
+        .into_iter()
+        .map(|x| x.1)
+        .collect::<Vec<_>>();
+    let censor =
+        |text: String| CensoredString::censor(text, &credentials).into_inner();
+
+    let mut clues = CrashClues::default();
+
+    let crash_report = find_crash_report(
+        &state.directories.crash_reports_dir(profile_path),
+        since,
+    )
+    .await?;
+    if let Some((_, path)) = &crash_report {
+        let (text, _) = io::read_any_encoding_to_string(path).await?;
+        clues.parse(&censor(text));
+    }
+
+    let mut log = get_log_buffer(profile_path);
+    if log.is_empty() {
+        let latest_log = state
+            .directories
+            .profile_logs_dir(profile_path)
+            .join("latest.log");
+        if latest_log.exists() {
+            let (text, _) =
+                io::read_any_encoding_to_string(&latest_log).await?;
+            log = text.lines().map(str::to_string).collect();
+        }
+    }
+    let log_tail = log[log.len().saturating_sub(LOG_TAIL_LINES)..].join("\n");
+    clues.parse(&censor(log_tail));
+
+    let suspects = if clues.clues.is_empty() {
+        Vec::new()
+    } else if let Some(profile) =
+        Profile::get(profile_path, &state.pool).await?
+    {
+        let files = profile
+            .get_projects(None, &state.pool, &state.api_semaphore)
+            .await?;
+        let profile_full_path =
+            crate::api::profile::get_full_path(profile_path).await?;
+
+        get_suspects(&clues, files.into_iter().collect(), &profile_full_path)
+            .await
+    } else {
+        Vec::new()
+    };
+
+    Ok(CrashAnalysis {
+        exit_code,
+        signal,
+        crash_report: crash_report.map(|(name, _)| name),
+        description: clues.description,
+        exception: clues.exception,
+        suspects,
+    })
+}
+
+/// Finds the newest crash report, returning its file name and path
+async fn find_crash_report(
+    crash_reports_dir: &Path,
+    since: Option<DateTime<Utc>>,
+) -> crate::Result<Option<(String, PathBuf)>> {
+    if !crash_reports_dir.exists() {
+        return Ok(None);
+    }
+
+    let mut newest = None;
+    let mut read_dir = io::read_dir(crash_reports_dir).await?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| io::IOError::with_path(e, crash_reports_dir))?
+    {
+        let path = entry.path();
+        let Some(file_name) = path.file_name().and_then(|x| x.to_str()) else {
+            continue;
+        };
+        if !file_name.ends_with(".txt") {
+            continue;
+        }
+
+        let Ok(modified) = entry.metadata().await.and_then(|x| x.modified())
+        else {
+            continue;
+        };
+        let modified = DateTime::<Utc>::from(modified);
+        if since.is_some_and(|since| modified < since)
+            || newest
+                .as_ref()
+                .is_some_and(|(time, _, _)| *time >= modified)
+        {
+            continue;
+        }
+
+        newest = Some((modified, file_name.to_string(), path));
+    }
+
+    Ok(newest.map(|(_, name, path)| (name, path)))
+}
+
+/// Matches clues against the enabled mod files of a profile
+async fn get_suspects(
+    clues: &CrashClues,
+    files: Vec<(String, ProfileFile)>,
+    profile_full_path: &Path,
+) -> Vec<CrashSuspect> {
+    let indexed = futures::stream::iter(
+        files
+            .into_iter()
+            .filter(|(path, file)| {
+                file.project_type == ProjectType::Mod
+                    && !path.ends_with(".disabled")
+            })
+            .map(|(path, file)| async move {
+                match ModFileIndex::read(profile_full_path.join(&path)).await
+                {
+                    Ok(index) => Some((path, file, index)),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to read mod file {path} for crash analysis: {e}"
+                        );
+                        None
+                    }
+                }
+            }),
+    )
+    .buffer_unordered(16)
+    .filter_map(|x| async move { x })
+    .collect::<Vec<_>>()
+    .await;
+
+    let mut evidence: HashMap<&str, Vec<CrashEvidence>> = HashMap::new();
+    for (target, clue) in &clues.clues {
+        for (path, file, index) in &indexed {
+            if index.matches(target, &file.file_name) {
+                let entry = evidence.entry(path.as_str()).or_default();
+                if !entry.contains(clue) {
+                    entry.push(clue.clone());
+                }
+            }
+        }
+    }
+
+    let mut suspects = indexed
+        .iter()
+        .filter_map(|(path, file, index)| {
+            let mut evidence = evidence.remove(path.as_str())?;
+            evidence.sort_by(|a, b| b.kind.cmp(&a.kind));
+            let mut mod_ids = index.mod_ids.iter().cloned().collect::<Vec<_>>();
+            mod_ids.sort();
+
+            Some(CrashSuspect {
+                file_path: path.clone(),
+                file_name: file.file_name.clone(),
+                project_id: file
+                    .metadata
+                    .as_ref()
+                    .map(|x| x.project_id.clone()),
+                version_id: file
+                    .metadata
+                    .as_ref()
+                    .map(|x| x.version_id.clone()),
+                mod_ids,
+                evidence,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Strongest evidence first, then the most evidence
+    suspects.sort_by(|a, b| {
+        let strength = |x: &CrashSuspect| {
+            (x.evidence.first().map(|e| e.kind), x.evidence.len())
+        };
+        strength(b).cmp(&strength(a))
+    });
+    for suspect in &mut suspects {
+        suspect.evidence.truncate(MAX_EVIDENCE_PER_SUSPECT);
+    }
+
+    suspects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> CrashClues {
+        let mut clues = CrashClues::default();
+        clues.parse(text);
+        clues
+    }
+
+    fn targets(clues: &CrashClues) -> Vec<(ClueTarget, CrashEvidenceKind)> {
+        clues
+            .clues
+            .iter()
+            .map(|(target, evidence)| (target.clone(), evidence.kind))
+            .collect()
+    }
+
+    #[test]
+    fn parses_forge_crash_report() {
+        let clues = parse(
+            "---- Minecraft Crash Report ----\n\
+             Description: Rendering overlay\n\
+             \n\
+             java.lang.NullPointerException: Cannot invoke \"Object.toString()\"\n\
+             \tat TRANSFORMER/examplemod@1.0.0/com.example.mod.Renderer.render(Renderer.java:42)\n\
+             \tat TRANSFORMER/minecraft@1.20.1/net.minecraft.client.Minecraft.run(Minecraft.java:1)\n\
+             -- Mod loading issue for: examplemod --\n\
+             Suspected Mods: Example Mod (examplemod), Version: 1.0.0\n",
+        );
+
+        assert_eq!(clues.description.as_deref(), Some("Rendering overlay"));
+        assert_eq!(
+            clues.exception.as_deref(),
+            Some(
+                "java.lang.NullPointerException: Cannot invoke \"Object.toString()\""
+            )
+        );
+
+        let targets = targets(&clues);
+        assert!(targets.contains(&(
+            ClueTarget::Class("com.example.mod.Renderer".to_string()),
+            CrashEvidenceKind::StackFrame
+        )));
+        assert!(targets.contains(&(
+            ClueTarget::ModId("examplemod".to_string()),
+            CrashEvidenceKind::ReportedSuspect
+        )));
+        assert!(
+            !targets
+                .iter()
+                .any(|(x, _)| matches!(x, ClueTarget::Class(c) if c.starts_with("net.minecraft")))
+        );
+    }
+
+    #[test]
+    fn parses_mixin_errors() {
+        let clues = parse(
+            "Mixin [example.mixins.json:MixinRenderer] from phase [DEFAULT] in config [example.mixins.json] FAILED during APPLY\n\
+             org.spongepowered.asm.mixin.injection.throwables.InjectionError: Critical injection failure in example.mixins.json:MixinRenderer from mod example\n\
+             \tat net.minecraft.class_757.handler$zza000$othermod$onRender(class_757.java:1) ~[client-intermediary.jar:?]\n",
+        );
+
+        let targets = targets(&clues);
+        assert!(targets.contains(&(
+            ClueTarget::MixinConfig("example.mixins.json".to_string()),
+            CrashEvidenceKind::Mixin
+        )));
+        assert!(targets.contains(&(
+            ClueTarget::ModId("example".to_string()),
+            CrashEvidenceKind::Mixin
+        )));
+        assert!(targets.contains(&(
+            ClueTarget::ModId("othermod".to_string()),
+            CrashEvidenceKind::Mixin
+        )));
+        assert!(targets.contains(&(
+            ClueTarget::JarName("client-intermediary.jar".to_string()),
+            CrashEvidenceKind::StackFrame
+        )));
+    }
+
+    #[test]
+    fn reads_mod_ids() {
+        assert_eq!(
+            read_mod_ids("fabric.mod.json", r#"{"id": "example"}"#),
+            vec!["example"]
+        );
+        assert_eq!(
+            read_mod_ids(
+                "META-INF/mods.toml",
+                "modLoader = \"javafml\"\n[[mods]]\nmodId = \"example\"\n"
+            ),
+            vec!["example"]
+        );
+    }
+}
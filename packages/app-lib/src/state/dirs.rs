@@ -13,6 +13,7 @@ pub const CACHES_FOLDER_NAME: &str = "caches";
 pub const LAUNCHER_LOGS_FOLDER_NAME: &str = "launcher_logs";
 pub const PROFILES_FOLDER_NAME: &str = "profiles";
 pub const METADATA_FOLDER_NAME: &str = "meta";
+pub const CONTENT_STORE_FOLDER_NAME: &str = "content_store";
 
 #[derive(Debug)]
 pub struct DirectoryInfo {
@@ -184,6 +185,19 @@ impl DirectoryInfo {
         self.config_dir.join(CACHES_FOLDER_NAME)
     }
 
+    /// Get the directory of the content store, where files shared between
+    /// profiles are kept
+    #[inline]
+    pub fn content_store_dir(&self) -> PathBuf {
+        self.config_dir.join(CONTENT_STORE_FOLDER_NAME)
+    }
+
+    /// Get the path of a file in the content store by its SHA-1 hash
+    #[inline]
+    pub fn content_store_object(&self, hash: &str) -> PathBuf {
+        self.content_store_dir().join(&hash[..2]).join(hash)
+    }
+
     /// Get path from environment variable
     #[inline]
     fn env_path(name: &str) -> Option<PathBuf> {
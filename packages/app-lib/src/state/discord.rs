@@ -1,13 +1,14 @@
 use std::sync::{Arc, atomic::AtomicBool};
 
+use chrono::{DateTime, Utc};
 use discord_rich_presence::{
     DiscordIpc, DiscordIpcClient,
-    activity::{Activity, Assets},
+    activity::{Activity, Assets, Timestamps},
 };
 use tokio::sync::RwLock;
 
 use crate::State;
-use crate::state::Profile;
+use crate::state::presence::Presence;
 
 pub struct DiscordGuard {
     client: Arc<RwLock<DiscordIpcClient>>,
@@ -55,15 +56,41 @@ impl DiscordGuard {
         if !settings.discord_rpc {
             Ok(self.clear_activity(true).await?)
         } else {
-            Ok(self.force_set_activity(msg, reconnect_if_fail).await?)
+            Ok(self
+                .force_set_activity(msg, None, None, reconnect_if_fail)
+                .await?)
         }
     }
 
+    /// Show what the user is playing, as far as they chose to share it
+    /// First checks if discord is disabled, and if so, clear the activity instead
+    pub async fn set_presence(
+        &self,
+        presence: &Presence,
+        reconnect_if_fail: bool,
+    ) -> crate::Result<()> {
+        let state = State::get().await?;
+        let settings = crate::state::Settings::get(&state.pool).await?;
+        if !settings.discord_rpc {
+            return self.clear_activity(true).await;
+        }
+
+        self.force_set_activity(
+            &presence.state_text(),
+            presence.details_text().as_deref(),
+            presence.activity.as_ref().and_then(|x| x.started),
+            reconnect_if_fail,
+        )
+        .await
+    }
+
     /// Sets the activity to the given message, regardless of if discord is disabled or offline
     /// Should not be used except for in the above method, or if it is already known that discord is enabled (specifically for state initialization) and we are connected to the internet
     pub async fn force_set_activity(
         &self,
         msg: &str,
+        details: Option<&str>,
+        started: Option<DateTime<Utc>>,
         reconnect_if_fail: bool,
     ) -> crate::Result<()> {
         // Attempt to connect if not connected. Do not continue if it fails, as the client.set_activity can panic if it never was connected
@@ -71,11 +98,18 @@ impl DiscordGuard {
             return Ok(());
         }
 
-        let activity = Activity::new().state(msg).assets(
+        let mut activity = Activity::new().state(msg).assets(
             Assets::new()
                 .large_image("modrinth_simple")
                 .large_text("Modrinth Logo"),
         );
+        if let Some(details) = details {
+            activity = activity.details(details);
+        }
+        if let Some(started) = started {
+            activity = activity
+                .timestamps(Timestamps::new().start(started.timestamp()));
+        }
 
         // Attempt to set the activity
         // If the existing connection fails, attempt to reconnect and try again
@@ -121,7 +155,7 @@ impl DiscordGuard {
         Ok(())
     }
 
-    /// Clear the activity, but if there is a running profile, set the activity to that instead
+    /// Clear the activity, but if there is a running profile, show what is being played instead
     pub async fn clear_to_default(
         &self,
         reconnect_if_fail: bool,
@@ -134,20 +168,8 @@ impl DiscordGuard {
             return self.clear_activity(true).await;
         }
 
-        let running_profiles = state.process_manager.get_all();
-        if let Some(existing_child) = running_profiles.first() {
-            let prof =
-                Profile::get(&existing_child.profile_path, &state.pool).await?;
-            if let Some(prof) = prof {
-                self.set_activity(
-                    &format!("Playing {}", prof.name),
-                    reconnect_if_fail,
-                )
-                .await?;
-            }
-        } else {
-            self.set_activity("Idling...", reconnect_if_fail).await?;
-        }
-        Ok(())
+        let presence =
+            Presence::current(&state.pool, &state.process_manager).await?;
+        self.set_presence(&presence, reconnect_if_fail).await
     }
 }
@@ -0,0 +1,164 @@
+//! URLs of the services used by the app
+use daedalus::signing::TrustedKey;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+
+static CURRENT_ENDPOINTS: LazyLock<RwLock<Arc<Endpoints>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Endpoints::default())));
+static ENDPOINTS_OVERRIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Endpoints of the Modrinth API and launcher metadata, for use with
+/// self-hosted instances. Defaults to the endpoints the app was built with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Endpoints {
+    /// Modrinth website URL, e.g. `https://modrinth.com/`
+    pub site_url: String,
+    /// Modrinth API v2 URL, e.g. `https://api.modrinth.com/v2/`
+    pub api_url: String,
+    /// Modrinth API v3 URL, e.g. `https://api.modrinth.com/v3/`
+    pub api_url_v3: String,
+    /// Unversioned Modrinth API URL, e.g. `https://api.modrinth.com/`
+    pub api_base_url: String,
+    /// Modrinth socket URL, e.g. `wss://api.modrinth.com/`
+    pub socket_url: String,
+    /// Launcher metadata URL, e.g. `https://launcher-meta.modrinth.com/`
+    pub launcher_meta_url: String,
+    /// Keys launcher metadata must be signed with. When this is empty,
+    /// metadata from the default metadata server is rejected unless the app
+    /// was built to allow it unsigned, and self-hosted metadata is used
+    /// unsigned
+    pub launcher_meta_keys: Vec<TrustedKey>,
+    /// Mirrors tried in order when a download fails, such as metadata or
+    /// library downloads
+    pub download_mirrors: Vec<DownloadMirror>,
+}
+
+/// Alternative sources for downloads starting with a URL prefix
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DownloadMirror {
+    /// e.g. `https://launcher-meta.modrinth.com/`
+    pub prefix: String,
+    /// URLs replacing the prefix, tried in order
+    pub mirrors: Vec<String>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            site_url: env!("MODRINTH_URL").to_string(),
+            api_url: env!("MODRINTH_API_URL").to_string(),
+            api_url_v3: env!("MODRINTH_API_URL_V3").to_string(),
+            api_base_url: env!("MODRINTH_API_BASE_URL").to_string(),
+            socket_url: env!("MODRINTH_SOCKET_URL").to_string(),
+            launcher_meta_url: env!("MODRINTH_LAUNCHER_META_URL").to_string(),
+            // Validated by the build script
+            launcher_meta_keys: TrustedKey::parse_list(env!(
+                "MODRINTH_LAUNCHER_META_KEYS"
+            ))
+            .unwrap_or_default(),
+            download_mirrors: Vec::new(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// The endpoints currently in use
+    pub fn current() -> Arc<Self> {
+        CURRENT_ENDPOINTS.read().clone()
+    }
+
+    /// Use the endpoints from the settings, unless they were overridden when
+    /// initializing the state
+    pub(crate) fn apply_settings(endpoints: &Self) {
+        if !ENDPOINTS_OVERRIDDEN.load(Ordering::Relaxed) {
+            *CURRENT_ENDPOINTS.write() = Arc::new(endpoints.clone());
+        }
+    }
+
+    /// Use the given endpoints for the lifetime of the app, ignoring the
+    /// settings
+    pub(crate) fn set_override(endpoints: Self) {
+        ENDPOINTS_OVERRIDDEN.store(true, Ordering::Relaxed);
+        *CURRENT_ENDPOINTS.write() = Arc::new(endpoints);
+    }
+
+    /// The fields which differ from the default endpoints, as a JSON object,
+    /// or `None` if there are none.
+    ///
+    /// Only these are stored in the settings, so changes to the defaults
+    /// apply to existing installs.
+    pub(crate) fn overrides(&self) -> crate::Result<Option<String>> {
+        let serde_json::Value::Object(defaults) =
+            serde_json::to_value(Self::default())?
+        else {
+            return Ok(None);
+        };
+        let serde_json::Value::Object(values) = serde_json::to_value(self)?
+        else {
+            return Ok(None);
+        };
+
+        let overrides = values
+            .into_iter()
+            .filter(|(key, value)| defaults.get(key) != Some(value))
+            .collect::<serde_json::Map<_, _>>();
+        if overrides.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_string(&overrides)?))
+    }
+
+    pub fn is_api_url(&self, url: &str) -> bool {
+        url.starts_with(&self.api_url) || url.starts_with(&self.api_url_v3)
+    }
+
+    /// The URL to download, followed by its mirrors
+    pub fn mirrored_urls(&self, url: &str) -> Vec<String> {
+        std::iter::once(url.to_string())
+            .chain(self.download_mirrors.iter().flat_map(|mirror| {
+                url.strip_prefix(&mirror.prefix)
+                    .into_iter()
+                    .flat_map(|path| {
+                        mirror.mirrors.iter().map(move |x| format!("{x}{path}"))
+                    })
+            }))
+            .collect()
+    }
+
+    /// Namespace for cached data fetched from the API, so data from
+    /// different instances is kept separate. `None` for the default API
+    pub(crate) fn api_cache_namespace(&self) -> Option<&str> {
+        (self.api_url != env!("MODRINTH_API_URL")).then_some(&*self.api_url)
+    }
+
+    /// Namespace for cached launcher metadata. `None` for the default
+    /// metadata server
+    pub(crate) fn meta_cache_namespace(&self) -> Option<&str> {
+        (self.launcher_meta_url != env!("MODRINTH_LAUNCHER_META_URL"))
+            .then_some(&*self.launcher_meta_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_stores_overridden_fields() {
+        assert_eq!(Endpoints::default().overrides().unwrap(), None);
+
+        let endpoints = Endpoints {
+            api_url: "https://api.example.com/v2/".to_string(),
+            ..Default::default()
+        };
+        let overrides = endpoints.overrides().unwrap().unwrap();
+        assert_eq!(overrides, r#"{"api_url":"https://api.example.com/v2/"}"#);
+
+        // fields which weren't stored come from the current defaults
+        let loaded: Endpoints = serde_json::from_str(&overrides).unwrap();
+        assert_eq!(loaded, endpoints);
+    }
+}
@@ -2,14 +2,22 @@ use crate::ErrorKind;
 use crate::data::ModrinthCredentials;
 use crate::event::FriendPayload;
 use crate::event::emit::emit_friend;
-use crate::state::tunnel::InternalTunnelSocket;
-use crate::state::{ProcessManager, Profile, TunnelSocket};
+use crate::state::presence::Presence;
+use crate::state::tunnel::{
+    InternalTunnelSocket, OpenTunnel, close_tunnel_connections,
+    tunnel_connections,
+};
+use crate::state::{
+    Endpoints, FriendTunnel, ProcessManager, TunnelInfo, TunnelJoin,
+    TunnelKind, TunnelSocket,
+};
 use crate::util::fetch::{FetchSemaphore, fetch_advanced, fetch_json};
+use crate::util::network::tcp_listen_any_loopback;
 use ariadne::ids::UserId;
 use ariadne::networking::message::{
-    ClientToServerMessage, ServerToClientMessage,
+    ClientToServerMessage, ServerToClientMessage, SocketCloseReason,
 };
-use ariadne::users::UserStatus;
+use ariadne::users::{UserActivity, UserStatus};
 use async_tungstenite::WebSocketSender;
 use async_tungstenite::tokio::{ConnectStream, connect_async};
 use async_tungstenite::tungstenite::Message;
@@ -25,10 +33,10 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncReadExt;
 use tokio::net::TcpStream;
 use tokio::net::tcp::OwnedReadHalf;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 pub(super) type WriteSocket =
@@ -39,6 +47,10 @@ pub struct FriendsSocket {
     write: WriteSocket,
     user_statuses: Arc<DashMap<UserId, UserStatus>>,
     tunnel_sockets: TunnelSockets,
+    /// Sockets friends are listening on, with the friend and whether their
+    /// client supports flow control
+    friend_sockets: Arc<DashMap<Uuid, (UserId, bool)>>,
+    tunnels: Arc<DashMap<Uuid, OpenTunnel>>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -61,6 +73,8 @@ impl FriendsSocket {
             write: Arc::new(RwLock::new(None)),
             user_statuses: Arc::new(DashMap::new()),
             tunnel_sockets: Arc::new(DashMap::new()),
+            friend_sockets: Arc::new(DashMap::new()),
+            tunnels: Arc::new(DashMap::new()),
         }
     }
 
@@ -75,149 +89,230 @@ impl FriendsSocket {
             ModrinthCredentials::get_and_refresh(exec, semaphore).await?;
 
         if let Some(credentials) = credentials {
-            let mut request = format!(
-                "{}_internal/launcher_socket?code={}",
-                env!("MODRINTH_SOCKET_URL"),
-                credentials.session
+            self.connect_to(
+                &Endpoints::current().socket_url,
+                &credentials.session,
             )
-            .into_client_request()?;
+            .await?;
 
-            request.headers_mut().insert(
-                "User-Agent",
-                HeaderValue::from_str(&crate::launcher_user_agent()).unwrap(),
-            );
+            let presence = Presence::current(exec, process_manager).await?;
+            if presence.playing {
+                let _ = self
+                    .update_status(presence.profile_name, presence.activity)
+                    .await;
+            }
+        }
 
-            let res = connect_async(request).await;
+        Ok(())
+    }
 
-            match res {
-                Ok((socket, _)) => {
-                    tracing::info!("Connected to friends socket");
-                    let (write, read) = socket.split();
+    /// Connects to the friends socket at `socket_url` with a Modrinth
+    /// session, without looking up the stored credentials
+    #[tracing::instrument(skip_all)]
+    pub async fn connect_to(
+        &self,
+        socket_url: &str,
+        session: &str,
+    ) -> crate::Result<()> {
+        let mut request =
+            format!("{socket_url}_internal/launcher_socket?code={session}")
+                .into_client_request()?;
 
-                    {
-                        let mut write_lock = self.write.write().await;
-                        *write_lock = Some(write);
-                    }
+        request.headers_mut().insert(
+            "User-Agent",
+            HeaderValue::from_str(&crate::launcher_user_agent()).unwrap(),
+        );
 
-                    if let Some(process) = process_manager.get_all().first() {
-                        let profile =
-                            Profile::get(&process.profile_path, exec).await?;
+        let res = connect_async(request).await;
 
-                        if let Some(profile) = profile {
-                            let _ =
-                                self.update_status(Some(profile.name)).await;
-                        }
-                    }
+        match res {
+            Ok((socket, _)) => {
+                tracing::info!("Connected to friends socket");
+                let (write, read) = socket.split();
 
-                    let write_handle = self.write.clone();
-                    let statuses = self.user_statuses.clone();
-                    let sockets = self.tunnel_sockets.clone();
-
-                    tokio::spawn(async move {
-                        let mut read_stream = read;
-                        while let Some(msg_result) = read_stream.next().await {
-                            match msg_result {
-                                Ok(msg) => {
-                                    let server_message = match msg {
-                                        Message::Text(text) => {
-                                            ServerToClientMessage::deserialize(
-                                                Either::Left(&text),
-                                            )
-                                            .ok()
-                                        }
-                                        Message::Binary(bytes) => {
-                                            ServerToClientMessage::deserialize(
-                                                Either::Right(&bytes),
-                                            )
-                                            .ok()
+                {
+                    let mut write_lock = self.write.write().await;
+                    *write_lock = Some(write);
+                }
+
+                // Hosted worlds are forgotten by the server when the
+                // connection drops, so they are announced again
+                let listening = self
+                    .tunnel_sockets
+                    .iter()
+                    .filter_map(|x| match &**x.value() {
+                        InternalTunnelSocket::Listening {
+                            allowed_users,
+                            ..
+                        } => Some((*x.key(), allowed_users.clone())),
+                        InternalTunnelSocket::Connected { .. } => None,
+                    })
+                    .collect::<Vec<_>>();
+                for (socket, allowed_users) in listening {
+                    Self::send_message(
+                        &self.write,
+                        ClientToServerMessage::SocketListen {
+                            socket,
+                            allowed_users,
+                            flow_control: true,
+                        },
+                    )
+                    .await?;
+                }
+
+                let write_handle = self.write.clone();
+                let statuses = self.user_statuses.clone();
+                let sockets = self.tunnel_sockets.clone();
+                let friend_sockets = self.friend_sockets.clone();
+
+                tokio::spawn(async move {
+                    let mut read_stream = read;
+                    while let Some(msg_result) = read_stream.next().await {
+                        match msg_result {
+                            Ok(msg) => {
+                                let server_message = match msg {
+                                    Message::Text(text) => {
+                                        ServerToClientMessage::deserialize(
+                                            Either::Left(&text),
+                                        )
+                                        .ok()
+                                    }
+                                    Message::Binary(bytes) => {
+                                        ServerToClientMessage::deserialize(
+                                            Either::Right(&bytes),
+                                        )
+                                        .ok()
+                                    }
+                                    Message::Ping(bytes) => {
+                                        if let Some(write) =
+                                            write_handle.write().await.as_mut()
+                                        {
+                                            let _ = write
+                                                .send(Message::Pong(bytes))
+                                                .await;
                                         }
-                                        Message::Ping(bytes) => {
-                                            if let Some(write) = write_handle
-                                                .write()
-                                                .await
-                                                .as_mut()
-                                            {
-                                                let _ = write
-                                                    .send(Message::Pong(bytes))
-                                                    .await;
-                                            }
 
-                                            continue;
+                                        continue;
+                                    }
+                                    Message::Pong(_) | Message::Frame(_) => {
+                                        continue;
+                                    }
+                                    Message::Close(_) => break,
+                                };
+
+                                if let Some(server_message) = server_message {
+                                    match server_message {
+                                        ServerToClientMessage::StatusUpdate { status } => {
+                                            statuses.insert(status.user_id, status.clone());
+                                            let _ = emit_friend(FriendPayload::StatusUpdate { user_status: status }).await;
+                                        },
+                                        ServerToClientMessage::UserOffline { id } => {
+                                            statuses.remove(&id);
+                                            friend_sockets.retain(|_, (user, _)| *user != id);
+                                            let _ = emit_friend(FriendPayload::UserOffline { id }).await;
+                                        }
+                                        ServerToClientMessage::FriendStatuses { statuses: new_statuses } => {
+                                            statuses.clear();
+                                            new_statuses.into_iter().for_each(|status| {
+                                                statuses.insert(status.user_id, status);
+                                            });
+                                            let _ = emit_friend(FriendPayload::StatusSync).await;
                                         }
-                                        Message::Pong(_)
-                                        | Message::Frame(_) => continue,
-                                        Message::Close(_) => break,
-                                    };
-
-                                    if let Some(server_message) = server_message
-                                    {
-                                        match server_message {
-                                            ServerToClientMessage::StatusUpdate { status } => {
-                                                statuses.insert(status.user_id, status.clone());
-                                                let _ = emit_friend(FriendPayload::StatusUpdate { user_status: status }).await;
-                                            },
-                                            ServerToClientMessage::UserOffline { id } => {
-                                                statuses.remove(&id);
-                                                let _ = emit_friend(FriendPayload::UserOffline { id }).await;
+                                        ServerToClientMessage::FriendRequest { from } => {
+                                            let _ = emit_friend(FriendPayload::FriendRequest { from }).await;
+                                        }
+                                        ServerToClientMessage::FriendRequestRejected { .. } => {}, // TODO
+
+                                        ServerToClientMessage::FriendSocketListening { user, socket, flow_control } => {
+                                            friend_sockets.insert(socket, (user, flow_control));
+                                            let _ = emit_friend(FriendPayload::TunnelOpened { user, socket }).await;
+                                        },
+                                        ServerToClientMessage::FriendSocketStoppedListening { user, socket } => {
+                                            match socket {
+                                                Some(socket) => {
+                                                    friend_sockets.remove(&socket);
+                                                }
+                                                None => friend_sockets.retain(|_, (x, _)| *x != user),
                                             }
-                                            ServerToClientMessage::FriendStatuses { statuses: new_statuses } => {
-                                                statuses.clear();
-                                                new_statuses.into_iter().for_each(|status| {
-                                                    statuses.insert(status.user_id, status);
-                                                });
-                                                let _ = emit_friend(FriendPayload::StatusSync).await;
+                                            let _ = emit_friend(FriendPayload::TunnelClosed { user, socket }).await;
+                                        },
+
+                                        ServerToClientMessage::SocketConnected { to_socket, new_socket, flow_control } => {
+                                            let local_addr = sockets.get(&to_socket).and_then(|x| match &**x.value() {
+                                                InternalTunnelSocket::Listening { address, .. } => Some(*address),
+                                                InternalTunnelSocket::Connected { .. } => None,
+                                            });
+                                            if let Some(local_addr) = local_addr
+                                                && let Ok(new_stream) = TcpStream::connect(local_addr).await {
+                                                    let (read, write) = new_stream.into_split();
+                                                    let internal = Arc::new(InternalTunnelSocket::connected(new_socket, write, to_socket, flow_control, write_handle.clone()));
+                                                    sockets.insert(new_socket, internal.clone());
+                                                    Self::socket_read_loop(write_handle.clone(), sockets.clone(), read, new_socket, internal);
+                                                    continue;
+                                                }
+                                            let _ = Self::send_message(&write_handle, ClientToServerMessage::SocketClose { socket: new_socket, reason: Some(SocketCloseReason::Refused) }).await;
+                                        },
+                                        ServerToClientMessage::SocketClosed { socket: socket_id, reason } => {
+                                            if let Some((_, socket)) = sockets.remove_if(&socket_id, |_, x| matches!(**x, InternalTunnelSocket::Connected { .. })) {
+                                                tracing::debug!("Tunnel socket {socket_id} closed: {reason:?}");
+                                                socket.shutdown();
                                             }
-                                            ServerToClientMessage::FriendRequest { from } => {
-                                                let _ = emit_friend(FriendPayload::FriendRequest { from }).await;
+                                        },
+                                        ServerToClientMessage::SocketData { socket: socket_id, data } => {
+                                            let socket = sockets.get(&socket_id).map(|x| x.value().clone());
+                                            if let Some(socket) = socket
+                                                && let Err(reason) = socket.push(data) {
+                                                    tracing::warn!("Closing tunnel socket {socket_id}: {reason:?}");
+                                                    sockets.remove(&socket_id);
+                                                    socket.shutdown();
+                                                    let _ = Self::send_message(&write_handle, ClientToServerMessage::SocketClose { socket: socket_id, reason: Some(reason) }).await;
+                                                }
+                                        },
+                                        ServerToClientMessage::SocketAck { socket, bytes } => {
+                                            if let Some(socket) = sockets.get(&socket) {
+                                                socket.acknowledge(bytes);
                                             }
-                                            ServerToClientMessage::FriendRequestRejected { .. } => {}, // TODO
-
-                                            ServerToClientMessage::FriendSocketListening { .. } => {}, // TODO
-                                            ServerToClientMessage::FriendSocketStoppedListening { .. } => {}, // TODO
-
-                                            ServerToClientMessage::SocketConnected { to_socket, new_socket } => {
-                                                if let Some(connected_to) = sockets.get(&to_socket)
-                                                    && let InternalTunnelSocket::Listening(local_addr) = *connected_to.value().clone()
-                                                        && let Ok(new_stream) = TcpStream::connect(local_addr).await {
-                                                            let (read, write) = new_stream.into_split();
-                                                            sockets.insert(new_socket, Arc::new(InternalTunnelSocket::Connected(Mutex::new(write))));
-                                                            Self::socket_read_loop(write_handle.clone(), read, new_socket);
-                                                            continue;
-                                                        }
-                                                let _ = Self::send_message(&write_handle, ClientToServerMessage::SocketClose { socket: new_socket }).await;
-                                            },
-                                            ServerToClientMessage::SocketClosed { socket } => {
-                                                sockets.remove_if(&socket, |_, x| matches!(*x.clone(), InternalTunnelSocket::Connected(_)));
-                                            },
-                                            ServerToClientMessage::SocketData { socket, data } => {
-                                                if let Some(mut socket) = sockets.get_mut(&socket)
-                                                    && let InternalTunnelSocket::Connected(ref stream) = *socket.value_mut().clone() {
-                                                        let _ = stream.lock().await.write_all(&data).await;
-                                                    }
-                                            },
-                                        }
+                                        },
                                     }
                                 }
-                                Err(e) => {
-                                    tracing::error!(
-                                        "Error handling message from websocket server: {:?}",
-                                        e
-                                    );
-                                }
+                            }
+                            Err(e) => {
+                                tracing::error!(
+                                    "Error handling message from websocket server: {:?}",
+                                    e
+                                );
                             }
                         }
+                    }
 
-                        let mut w = write_handle.write().await;
-                        *w = None;
-                    });
-                }
-                Err(e) => {
-                    tracing::error!(
-                        "Error connecting to friends socket: {e:?}"
-                    );
+                    let mut w = write_handle.write().await;
+                    *w = None;
+                    drop(w);
+
+                    // The server closes every relayed connection along with
+                    // the friends socket
+                    friend_sockets.clear();
+                    let connected = sockets
+                        .iter()
+                        .filter(|x| {
+                            matches!(
+                                **x.value(),
+                                InternalTunnelSocket::Connected { .. }
+                            )
+                        })
+                        .map(|x| *x.key())
+                        .collect::<Vec<_>>();
+                    for socket in connected {
+                        if let Some((_, socket)) = sockets.remove(&socket) {
+                            socket.shutdown();
+                        }
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("Error connecting to friends socket: {e:?}");
 
-                    return Err(crate::Error::from(e));
-                }
+                return Err(crate::Error::from(e));
             }
         }
 
@@ -281,10 +376,14 @@ impl FriendsSocket {
     pub async fn update_status(
         &self,
         profile_name: Option<String>,
+        activity: Option<UserActivity>,
     ) -> crate::Result<()> {
         Self::send_message(
             &self.write,
-            ClientToServerMessage::StatusUpdate { profile_name },
+            ClientToServerMessage::StatusUpdate {
+                profile_name,
+                activity,
+            },
         )
         .await
     }
@@ -296,7 +395,7 @@ impl FriendsSocket {
     ) -> crate::Result<Vec<UserFriend>> {
         fetch_json(
             Method::GET,
-            concat!(env!("MODRINTH_API_URL_V3"), "friends"),
+            &format!("{}friends", Endpoints::current().api_url_v3),
             None,
             None,
             semaphore,
@@ -321,7 +420,7 @@ impl FriendsSocket {
     ) -> crate::Result<()> {
         let result = fetch_advanced(
             Method::POST,
-            &format!("{}friend/{user_id}", env!("MODRINTH_API_URL_V3")),
+            &format!("{}friend/{user_id}", Endpoints::current().api_url_v3),
             None,
             None,
             None,
@@ -354,7 +453,7 @@ impl FriendsSocket {
     ) -> crate::Result<()> {
         fetch_advanced(
             Method::DELETE,
-            &format!("{}friend/{user_id}", env!("MODRINTH_API_URL_V3")),
+            &format!("{}friend/{user_id}", Endpoints::current().api_url_v3),
             None,
             None,
             None,
@@ -369,22 +468,216 @@ impl FriendsSocket {
     }
 
     #[tracing::instrument(skip(self))]
-    pub async fn open_port(&self, port: u16) -> crate::Result<TunnelSocket> {
+    pub async fn open_port(
+        &self,
+        port: u16,
+        allowed_users: Option<Vec<UserId>>,
+    ) -> crate::Result<TunnelSocket> {
         let socket_id = Uuid::new_v4();
         let socket = self.tunnel_sockets.entry(socket_id).insert(Arc::new(
-            InternalTunnelSocket::Listening(SocketAddr::new(
-                "127.0.0.1".parse().unwrap(),
-                port,
-            )),
+            InternalTunnelSocket::Listening {
+                address: SocketAddr::new("127.0.0.1".parse().unwrap(), port),
+                allowed_users: allowed_users.clone(),
+            },
         ));
         Self::send_message(
             &self.write,
-            ClientToServerMessage::SocketListen { socket: socket_id },
+            ClientToServerMessage::SocketListen {
+                socket: socket_id,
+                allowed_users,
+                flow_control: true,
+            },
         )
         .await?;
         self.create_tunnel_socket(socket_id, socket)
     }
 
+    /// Hosts a world that is open to LAN on `port` for friends. If
+    /// `allowed_users` is set, only those friends can see and join it
+    #[tracing::instrument(skip(self))]
+    pub async fn host_world(
+        &self,
+        port: u16,
+        allowed_users: Option<Vec<UserId>>,
+    ) -> crate::Result<TunnelInfo> {
+        self.ensure_connected().await?;
+
+        let socket = self.open_port(port, allowed_users).await?;
+        let id = socket.socket_id();
+        self.tunnels
+            .insert(id, OpenTunnel::Hosting { socket, port });
+
+        self.tunnel(id).await.ok_or_else(|| {
+            ErrorKind::OtherError("Hosted world was closed".to_string()).into()
+        })
+    }
+
+    /// Joins a world a friend is hosting. The game connects to the returned
+    /// local address, and every connection made to it is relayed to the host
+    #[tracing::instrument(skip(self))]
+    pub async fn join_world(
+        &self,
+        to_socket: Uuid,
+    ) -> crate::Result<TunnelInfo> {
+        self.ensure_connected().await?;
+
+        let (host, flow_control) =
+            match self.friend_sockets.get(&to_socket).map(|x| *x.value()) {
+                Some((host, flow_control)) => (Some(host), flow_control),
+                None => (None, false),
+            };
+        let listener = tcp_listen_any_loopback().await?;
+        let local_address = listener.local_addr()?;
+        let tunnel_id = Uuid::new_v4();
+
+        let accept_task = {
+            let write = self.write.clone();
+            let sockets = self.tunnel_sockets.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    if write.read().await.is_none() {
+                        continue;
+                    }
+
+                    let new_socket = Uuid::new_v4();
+                    let (read, write_half) = stream.into_split();
+                    let internal = Arc::new(InternalTunnelSocket::connected(
+                        new_socket,
+                        write_half,
+                        tunnel_id,
+                        flow_control,
+                        write.clone(),
+                    ));
+                    sockets.insert(new_socket, internal.clone());
+                    if Self::send_message(
+                        &write,
+                        ClientToServerMessage::SocketConnect {
+                            to_socket,
+                            new_socket,
+                            flow_control,
+                        },
+                    )
+                    .await
+                    .is_err()
+                    {
+                        sockets.remove(&new_socket);
+                        continue;
+                    }
+                    Self::socket_read_loop(
+                        write.clone(),
+                        sockets.clone(),
+                        read,
+                        new_socket,
+                        internal,
+                    );
+                }
+            })
+            .abort_handle()
+        };
+
+        self.tunnels.insert(
+            tunnel_id,
+            OpenTunnel::Joining {
+                join: TunnelJoin {
+                    tunnel_id,
+                    to_socket,
+                    local_address,
+                    accept_task,
+                    write: self.write.clone(),
+                    sockets: self.tunnel_sockets.clone(),
+                },
+                host,
+            },
+        );
+
+        self.tunnel(tunnel_id).await.ok_or_else(|| {
+            ErrorKind::OtherError("Joined world was closed".to_string()).into()
+        })
+    }
+
+    /// Stops hosting or joining a world, closing all of its connections
+    #[tracing::instrument(skip(self))]
+    pub async fn close_tunnel(&self, id: Uuid) -> crate::Result<()> {
+        if let Some((_, tunnel)) = self.tunnels.remove(&id) {
+            if let OpenTunnel::Hosting { socket, .. } = tunnel {
+                socket.shutdown().await?;
+            }
+            close_tunnel_connections(
+                self.write.clone(),
+                self.tunnel_sockets.clone(),
+                id,
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    pub async fn tunnel(&self, id: Uuid) -> Option<TunnelInfo> {
+        let relay_connected = self.is_connected().await;
+        let tunnel = self.tunnels.get(&id)?;
+        Some(self.tunnel_info(id, tunnel.value(), relay_connected))
+    }
+
+    pub async fn tunnels(&self) -> Vec<TunnelInfo> {
+        let relay_connected = self.is_connected().await;
+        self.tunnels
+            .iter()
+            .map(|x| self.tunnel_info(*x.key(), x.value(), relay_connected))
+            .collect()
+    }
+
+    /// Gets the worlds friends are hosting that this user can join
+    pub fn friend_tunnels(&self) -> Vec<FriendTunnel> {
+        self.friend_sockets
+            .iter()
+            .map(|x| FriendTunnel {
+                user: x.value().0,
+                socket: *x.key(),
+            })
+            .collect()
+    }
+
+    fn tunnel_info(
+        &self,
+        id: Uuid,
+        tunnel: &OpenTunnel,
+        relay_connected: bool,
+    ) -> TunnelInfo {
+        let kind = match tunnel {
+            OpenTunnel::Hosting { socket, port } => TunnelKind::Hosting {
+                port: *port,
+                allowed_users: match &*socket.internal {
+                    InternalTunnelSocket::Listening {
+                        allowed_users, ..
+                    } => allowed_users.clone(),
+                    InternalTunnelSocket::Connected { .. } => None,
+                },
+            },
+            OpenTunnel::Joining { join, host } => TunnelKind::Joining {
+                to_socket: join.to_socket,
+                host: *host,
+                local_address: join.local_address(),
+            },
+        };
+
+        TunnelInfo {
+            id,
+            kind,
+            relay_connected,
+            connections: tunnel_connections(&self.tunnel_sockets, id),
+        }
+    }
+
+    async fn ensure_connected(&self) -> crate::Result<()> {
+        if !self.is_connected().await {
+            return Err(ErrorKind::OtherError(
+                "Not connected to the friends socket".to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     pub async fn is_connected(&self) -> bool {
         self.write.read().await.is_some()
     }
@@ -404,8 +697,10 @@ impl FriendsSocket {
 
     fn socket_read_loop(
         write: WriteSocket,
+        sockets: TunnelSockets,
         mut read_half: OwnedReadHalf,
         socket_id: Uuid,
+        internal: Arc<InternalTunnelSocket>,
     ) {
         tokio::spawn(async move {
             let mut read_buffer = [0u8; 8192];
@@ -413,6 +708,11 @@ impl FriendsSocket {
                 match read_half.read(&mut read_buffer).await {
                     Ok(0) | Err(_) => break,
                     Ok(n) => {
+                        // Stop reading from the local connection until the
+                        // peer has room for the data
+                        if !internal.reserve_send(n).await {
+                            break;
+                        }
                         let _ = Self::send_message(
                             &write,
                             ClientToServerMessage::SocketSend {
@@ -424,6 +724,20 @@ impl FriendsSocket {
                     }
                 };
             }
+
+            // Tell the other end the connection was closed, unless it was
+            // the one that closed it
+            if sockets.remove(&socket_id).is_some() {
+                let _ = Self::send_message(
+                    &write,
+                    ClientToServerMessage::SocketClose {
+                        socket: socket_id,
+                        reason: Some(SocketCloseReason::Closed),
+                    },
+                )
+                .await;
+            }
+            internal.shutdown();
         });
     }
 
@@ -0,0 +1,595 @@
+//! # Content Diagnostics
+//!
+//! Checks the installed content of a profile for common problems:
+//!
+//! - required dependencies that are not installed
+//! - disabled files that are required dependencies of enabled files
+//! - the same project installed more than once
+//! - mods built for another mod loader or game version
+//!
+//! Every problem comes with a suggested [`ContentFix`]: a list of disabled
+//! files to enable, versions to install and files to remove, applied through
+//! `profile::toggle_disable_project`, `profile::add_project_from_version` and
+//! `profile::remove_project`.
+
+use crate::state::cache::DependencyType;
+use crate::state::profiles::{ModLoader, Profile, ProfileFile, ProjectType};
+use crate::state::{CacheBehaviour, CachedEntry, Version};
+use crate::util::fetch::FetchSemaphore;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A problem with the installed content of a profile
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContentDiagnostic {
+    pub issue: ContentIssue,
+    /// Suggested fix, if one could be found
+    pub fix: Option<ContentFix>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentIssue {
+    /// A project required by enabled files is not installed
+    MissingDependency {
+        project_id: String,
+        /// Relative paths of the files requiring the project
+        required_by: Vec<String>,
+    },
+    /// A disabled file is required by enabled files
+    DisabledDependency {
+        file_path: String,
+        /// Relative paths of the files requiring the disabled file
+        required_by: Vec<String>,
+    },
+    /// The same project is installed more than once
+    DuplicateProject {
+        project_id: String,
+        file_paths: Vec<String>,
+    },
+    /// A mod is not built for the profile's mod loader
+    LoaderMismatch {
+        file_path: String,
+        loader: ModLoader,
+        /// Loaders supported by the installed version
+        loaders: Vec<String>,
+    },
+    /// A mod is not built for the profile's game version
+    GameVersionMismatch {
+        file_path: String,
+        game_version: String,
+        /// Game versions supported by the installed version
+        game_versions: Vec<String>,
+    },
+}
+
+/// Files to enable, versions to install and files to remove to resolve a
+/// [`ContentIssue`].
+/// Removals should only be applied once the installs succeeded, so a failed
+/// download doesn't lose the file it was replacing.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ContentFix {
+    /// Relative paths of disabled files to enable
+    pub enable: Vec<String>,
+    /// Relative paths of files to remove
+    pub remove: Vec<String>,
+    /// IDs of versions to install
+    pub add: Vec<String>,
+}
+
+/// An installed file that is known to Modrinth
+struct IdentifiedFile<'a> {
+    path: &'a str,
+    enabled: bool,
+    project_id: &'a str,
+    version: &'a Version,
+}
+
+/// Where diagnostics look up versions, so they can be checked without the
+/// network
+trait VersionSource {
+    async fn versions(&self, ids: &[&str]) -> crate::Result<Vec<Version>>;
+
+    async fn project_versions(
+        &self,
+        project_id: &str,
+    ) -> crate::Result<Vec<Version>>;
+}
+
+struct CachedVersions<'a> {
+    cache_behaviour: Option<CacheBehaviour>,
+    pool: &'a SqlitePool,
+    fetch_semaphore: &'a FetchSemaphore,
+}
+
+impl VersionSource for CachedVersions<'_> {
+    async fn versions(&self, ids: &[&str]) -> crate::Result<Vec<Version>> {
+        CachedEntry::get_version_many(
+            ids,
+            self.cache_behaviour,
+            self.pool,
+            self.fetch_semaphore,
+        )
+        .await
+    }
+
+    async fn project_versions(
+        &self,
+        project_id: &str,
+    ) -> crate::Result<Vec<Version>> {
+        Ok(CachedEntry::get_project_versions(
+            project_id,
+            self.cache_behaviour,
+            self.pool,
+            self.fetch_semaphore,
+        )
+        .await?
+        .unwrap_or_default())
+    }
+}
+
+/// Check the installed content of a profile for missing or disabled
+/// dependencies, duplicate projects and loader/game version mismatches.
+pub async fn get_content_diagnostics(
+    profile: &Profile,
+    cache_behaviour: Option<CacheBehaviour>,
+    pool: &SqlitePool,
+    fetch_semaphore: &FetchSemaphore,
+) -> crate::Result<Vec<ContentDiagnostic>> {
+    let files: BTreeMap<String, ProfileFile> = profile
+        .get_projects(cache_behaviour, pool, fetch_semaphore)
+        .await?
+        .into_iter()
+        .collect();
+
+    diagnose(
+        profile,
+        &files,
+        &CachedVersions {
+            cache_behaviour,
+            pool,
+            fetch_semaphore,
+        },
+    )
+    .await
+}
+
+async fn diagnose(
+    profile: &Profile,
+    files: &BTreeMap<String, ProfileFile>,
+    source: &impl VersionSource,
+) -> crate::Result<Vec<ContentDiagnostic>> {
+    let version_ids = files
+        .values()
+        .filter_map(|f| f.metadata.as_ref().map(|m| &*m.version_id))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if version_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let versions = source.versions(&version_ids).await?;
+
+    let identified = files
+        .iter()
+        .filter_map(|(path, file)| {
+            let metadata = file.metadata.as_ref()?;
+            let version =
+                versions.iter().find(|v| v.id == metadata.version_id)?;
+            Some(IdentifiedFile {
+                path,
+                enabled: !path.ends_with(".disabled"),
+                project_id: &metadata.project_id,
+                version,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Dependencies may only specify a version, so resolve their projects
+    let dependency_version_ids = identified
+        .iter()
+        .filter(|f| f.enabled)
+        .flat_map(|f| &f.version.dependencies)
+        .filter(|d| d.project_id.is_none())
+        .filter_map(|d| d.version_id.as_deref())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let dependency_versions = if dependency_version_ids.is_empty() {
+        Vec::new()
+    } else {
+        source.versions(&dependency_version_ids).await?
+    };
+
+    // Required project ID -> (files requiring it, pinned version ID)
+    let mut required: BTreeMap<&str, (Vec<&str>, Option<&str>)> =
+        BTreeMap::new();
+    for file in identified.iter().filter(|f| f.enabled) {
+        for dependency in &file.version.dependencies {
+            if !matches!(dependency.dependency_type, DependencyType::Required) {
+                continue;
+            }
+
+            let Some(project_id) =
+                dependency.project_id.as_deref().or_else(|| {
+                    let version_id = dependency.version_id.as_deref()?;
+                    dependency_versions
+                        .iter()
+                        .find(|v| v.id == version_id)
+                        .map(|v| &*v.project_id)
+                })
+            else {
+                continue;
+            };
+            if project_id == file.project_id {
+                continue;
+            }
+
+            let entry = required.entry(project_id).or_default();
+            entry.0.push(file.path);
+            if entry.1.is_none() {
+                entry.1 = dependency.version_id.as_deref();
+            }
+        }
+    }
+
+    let mut by_project: BTreeMap<&str, Vec<&IdentifiedFile>> = BTreeMap::new();
+    for file in &identified {
+        by_project.entry(file.project_id).or_default().push(file);
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut missing = Vec::new();
+
+    for (project_id, (required_by, pinned_version)) in &required {
+        let installed = by_project.get(project_id);
+        if installed.is_some_and(|x| x.iter().any(|f| f.enabled)) {
+            continue;
+        }
+
+        if let Some(disabled) = installed.and_then(|x| x.first()) {
+            diagnostics.push(ContentDiagnostic {
+                issue: ContentIssue::DisabledDependency {
+                    file_path: disabled.path.to_string(),
+                    required_by: to_strings(required_by),
+                },
+                fix: Some(ContentFix {
+                    enable: vec![disabled.path.to_string()],
+                    ..Default::default()
+                }),
+            });
+        } else {
+            missing.push((*project_id, required_by, *pinned_version));
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for (project_id, installed) in &by_project {
+        if installed.len() < 2 {
+            continue;
+        }
+
+        // Keep the newest enabled file
+        let Some(keep) = installed
+            .iter()
+            .max_by_key(|f| (f.enabled, f.version.date_published, f.path))
+        else {
+            continue;
+        };
+
+        duplicates.push(ContentDiagnostic {
+            issue: ContentIssue::DuplicateProject {
+                project_id: project_id.to_string(),
+                file_paths: installed
+                    .iter()
+                    .map(|f| f.path.to_string())
+                    .collect(),
+            },
+            fix: Some(ContentFix {
+                remove: installed
+                    .iter()
+                    .filter(|f| f.path != keep.path)
+                    .map(|f| f.path.to_string())
+                    .collect(),
+                ..Default::default()
+            }),
+        });
+    }
+
+    let mismatched = identified
+        .iter()
+        .filter(|f| {
+            f.enabled
+                && files.get(f.path).map(|x| x.project_type)
+                    == Some(ProjectType::Mod)
+                && !is_compatible(f.version, profile)
+        })
+        .collect::<Vec<_>>();
+
+    // Fetch the versions of every project we need to find a replacement for
+    let mut project_versions = HashMap::new();
+    for project_id in missing
+        .iter()
+        .filter(|(_, _, pinned)| pinned.is_none())
+        .map(|(project_id, _, _)| *project_id)
+        .chain(mismatched.iter().map(|f| f.project_id))
+    {
+        if project_versions.contains_key(project_id) {
+            continue;
+        }
+        project_versions
+            .insert(project_id, source.project_versions(project_id).await?);
+    }
+    let newest_compatible = |project_id: &str| {
+        project_versions
+            .get(project_id)
+            .into_iter()
+            .flatten()
+            .filter(|v| is_compatible(v, profile))
+            .max_by_key(|v| v.date_published)
+    };
+
+    for (project_id, required_by, pinned_version) in missing {
+        let version_id = pinned_version
+            .map(str::to_string)
+            .or_else(|| newest_compatible(project_id).map(|v| v.id.clone()));
+
+        diagnostics.push(ContentDiagnostic {
+            issue: ContentIssue::MissingDependency {
+                project_id: project_id.to_string(),
+                required_by: to_strings(required_by),
+            },
+            fix: version_id.map(|version_id| ContentFix {
+                add: vec![version_id],
+                ..Default::default()
+            }),
+        });
+    }
+
+    diagnostics.extend(duplicates);
+
+    for file in mismatched {
+        let issue = if !supports_loader(&file.version.loaders, profile.loader) {
+            ContentIssue::LoaderMismatch {
+                file_path: file.path.to_string(),
+                loader: profile.loader,
+                loaders: file.version.loaders.clone(),
+            }
+        } else {
+            ContentIssue::GameVersionMismatch {
+                file_path: file.path.to_string(),
+                game_version: profile.game_version.clone(),
+                game_versions: file.version.game_versions.clone(),
+            }
+        };
+
+        // Replace the file with a compatible version, or remove it if the
+        // project has none
+        diagnostics.push(ContentDiagnostic {
+            issue,
+            fix: Some(ContentFix {
+                remove: vec![file.path.to_string()],
+                add: newest_compatible(file.project_id)
+                    .map(|v| v.id.clone())
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            }),
+        });
+    }
+
+    Ok(diagnostics)
+}
+
+/// Whether a version can be installed to the profile. Loaders are only
+/// checked for mods, as other content is loader independent.
+fn is_compatible(version: &Version, profile: &Profile) -> bool {
+    version.game_versions.contains(&profile.game_version)
+        && (ProjectType::get_from_loaders(version.loaders.clone())
+            != Some(ProjectType::Mod)
+            || supports_loader(&version.loaders, profile.loader))
+}
+
+fn supports_loader(loaders: &[String], loader: ModLoader) -> bool {
+    loaders.iter().any(|x| {
+        // Quilt can load Fabric mods
+        x == loader.as_str()
+            || (loader == ModLoader::Quilt && x == ModLoader::Fabric.as_str())
+    })
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|x| x.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::cache::Dependency;
+    use crate::state::profiles::FileMetadata;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    struct StubVersions(Vec<Version>);
+
+    impl VersionSource for StubVersions {
+        async fn versions(&self, ids: &[&str]) -> crate::Result<Vec<Version>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|v| ids.contains(&v.id.as_str()))
+                .cloned()
+                .collect())
+        }
+
+        async fn project_versions(
+            &self,
+            project_id: &str,
+        ) -> crate::Result<Vec<Version>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|v| v.project_id == project_id)
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn version(
+        id: &str,
+        project_id: &str,
+        loader: &str,
+        game_version: &str,
+        day: u32,
+        dependencies: &[(Option<&str>, Option<&str>)],
+    ) -> Version {
+        Version {
+            id: id.to_string(),
+            project_id: project_id.to_string(),
+            author_id: "author".to_string(),
+            featured: false,
+            name: id.to_string(),
+            version_number: id.to_string(),
+            changelog: None,
+            changelog_url: None,
+            date_published: Utc
+                .with_ymd_and_hms(2024, 1, day, 0, 0, 0)
+                .unwrap(),
+            downloads: 0,
+            version_type: "release".to_string(),
+            files: Vec::new(),
+            dependencies: dependencies
+                .iter()
+                .map(|(project_id, version_id)| Dependency {
+                    version_id: version_id.map(str::to_string),
+                    project_id: project_id.map(str::to_string),
+                    file_name: None,
+                    dependency_type: DependencyType::Required,
+                })
+                .collect(),
+            game_versions: vec![game_version.to_string()],
+            loaders: vec![loader.to_string()],
+        }
+    }
+
+    fn file(
+        path: &str,
+        project_id: &str,
+        version_id: &str,
+    ) -> (String, ProfileFile) {
+        (
+            path.to_string(),
+            ProfileFile {
+                hash: path.to_string(),
+                file_name: path.to_string(),
+                size: 0,
+                metadata: Some(FileMetadata {
+                    project_id: project_id.to_string(),
+                    version_id: version_id.to_string(),
+                }),
+                update_version_id: None,
+                project_type: ProjectType::Mod,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn diagnoses_installed_content() {
+        let profile = Profile::for_tests("1.20.1", ModLoader::Fabric, None);
+        let source = StubVersions(vec![
+            // requires Fabric API, which isn't installed
+            version(
+                "sodium-1",
+                "sodium",
+                "fabric",
+                "1.20.1",
+                1,
+                &[(Some("fabric-api"), None)],
+            ),
+            // requires a version of Indium, which is disabled
+            version(
+                "iris-1",
+                "iris",
+                "fabric",
+                "1.20.1",
+                1,
+                &[(None, Some("indium-1"))],
+            ),
+            version("indium-1", "indium", "fabric", "1.20.1", 1, &[]),
+            version("fapi-old", "fabric-api", "fabric", "1.19.2", 1, &[]),
+            version("fapi-new", "fabric-api", "fabric", "1.20.1", 2, &[]),
+            version("lithium-1", "lithium", "fabric", "1.20.1", 1, &[]),
+            version("lithium-2", "lithium", "fabric", "1.20.1", 2, &[]),
+            version("jei-forge", "jei", "forge", "1.20.1", 1, &[]),
+            version("jei-fabric", "jei", "fabric", "1.20.1", 2, &[]),
+        ]);
+        let files = BTreeMap::from([
+            file("mods/sodium.jar", "sodium", "sodium-1"),
+            file("mods/iris.jar", "iris", "iris-1"),
+            file("mods/indium.jar.disabled", "indium", "indium-1"),
+            file("mods/lithium-1.jar", "lithium", "lithium-1"),
+            file("mods/lithium-2.jar", "lithium", "lithium-2"),
+            file("mods/jei.jar", "jei", "jei-forge"),
+        ]);
+
+        let diagnostics = diagnose(&profile, &files, &source).await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&diagnostics).unwrap(),
+            json!([
+                {
+                    "issue": {
+                        "type": "disabled_dependency",
+                        "file_path": "mods/indium.jar.disabled",
+                        "required_by": ["mods/iris.jar"],
+                    },
+                    "fix": { "enable": ["mods/indium.jar.disabled"], "remove": [], "add": [] },
+                },
+                {
+                    "issue": {
+                        "type": "missing_dependency",
+                        "project_id": "fabric-api",
+                        "required_by": ["mods/sodium.jar"],
+                    },
+                    "fix": { "enable": [], "remove": [], "add": ["fapi-new"] },
+                },
+                {
+                    "issue": {
+                        "type": "duplicate_project",
+                        "project_id": "lithium",
+                        "file_paths": ["mods/lithium-1.jar", "mods/lithium-2.jar"],
+                    },
+                    "fix": { "enable": [], "remove": ["mods/lithium-1.jar"], "add": [] },
+                },
+                {
+                    "issue": {
+                        "type": "loader_mismatch",
+                        "file_path": "mods/jei.jar",
+                        "loader": "fabric",
+                        "loaders": ["forge"],
+                    },
+                    "fix": { "enable": [], "remove": ["mods/jei.jar"], "add": ["jei-fabric"] },
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn unidentified_content_has_no_diagnostics() {
+        let profile = Profile::for_tests("1.20.1", ModLoader::Fabric, None);
+        let (path, mut file) = file("mods/custom.jar", "", "");
+        file.metadata = None;
+
+        let diagnostics = diagnose(
+            &profile,
+            &BTreeMap::from([(path, file)]),
+            &StubVersions(Vec::new()),
+        )
+        .await
+        .unwrap();
+
+        assert!(diagnostics.is_empty());
+    }
+}
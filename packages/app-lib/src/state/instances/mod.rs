@@ -2,3 +2,6 @@
 
 mod content;
 pub use self::content::*;
+
+mod diagnostics;
+pub use self::diagnostics::*;
@@ -4,10 +4,10 @@ use crate::prelude::ModLoader;
 use crate::state;
 use crate::state::{
     CacheValue, CachedEntry, CachedFile, CachedFileHash, CachedFileUpdate,
-    Credentials, DefaultPage, DependencyType, DeviceToken, DeviceTokenKey,
-    DeviceTokenPair, FileType, Hooks, LauncherFeatureVersion, LinkedData,
-    MemorySettings, ModrinthCredentials, Profile, ProfileInstallStage,
-    TeamMember, Theme, VersionFile, WindowSize,
+    CredentialProvider, Credentials, DefaultPage, DependencyType, DeviceToken,
+    DeviceTokenKey, DeviceTokenPair, FileType, Hooks, LauncherFeatureVersion,
+    LinkedData, MemorySettings, ModrinthCredentials, Profile,
+    ProfileInstallStage, TeamMember, Theme, VersionFile, WindowSize,
 };
 use crate::util::fetch::{IoSemaphore, read_json};
 use chrono::{DateTime, Utc};
@@ -130,6 +130,7 @@ where
                     expires: legacy_credentials.expires,
                     active: minecraft_auth.default_user == Some(uuid)
                         || minecraft_users_len == 1,
+                    provider: CredentialProvider::Microsoft,
                 }
                 .upsert(exec)
                 .await?;
@@ -33,6 +33,8 @@ use tokio::task;
 use url::Url;
 use uuid::Uuid;
 
+pub mod yggdrasil;
+
 #[derive(Debug, Clone, Copy)]
 pub enum MinecraftAuthStep {
     GetDeviceToken,
@@ -44,6 +46,12 @@ pub enum MinecraftAuthStep {
     MinecraftToken,
     MinecraftEntitlements,
     MinecraftProfile,
+    YggdrasilMetadata,
+    YggdrasilAuthenticate,
+    YggdrasilRefresh,
+    YggdrasilValidate,
+    YggdrasilInvalidate,
+    YggdrasilProfile,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -78,6 +86,21 @@ pub enum MinecraftAuthenticationError {
     NoSessionId,
     #[error("Error reading user hash")]
     NoUserHash,
+    #[error("Invalid authentication server URL: {0}")]
+    InvalidYggdrasilUrl(String),
+    #[error("Authentication server returned no metadata")]
+    NoYggdrasilMetadata,
+    #[error("No Minecraft profile is available for this account")]
+    NoYggdrasilProfile,
+    #[error(
+        "Authentication server error during step {step:?}: {error}: {message}. Status Code: {status_code}"
+    )]
+    Yggdrasil {
+        step: MinecraftAuthStep,
+        status_code: StatusCode,
+        error: String,
+        message: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -156,6 +179,7 @@ pub async fn login_finish(
         expires: oauth_token.date
             + Duration::seconds(oauth_token.value.expires_in as i64),
         active: true,
+        provider: CredentialProvider::Microsoft,
     };
 
     // During login, we need to fetch the online profile at least once to get the
@@ -178,6 +202,104 @@ pub async fn login_finish(
     Ok(credentials)
 }
 
+/// How often Yggdrasil access tokens are validated, as these servers do not
+/// tell when their tokens expire
+const YGGDRASIL_VALIDATION_INTERVAL: Duration = Duration::minutes(15);
+
+/// Signs into a Yggdrasil-compatible authentication server. If the account
+/// has no profile selected yet, the one named like the username is picked,
+/// or else the first one
+#[tracing::instrument(skip(password))]
+pub async fn login_yggdrasil(
+    server_url: &str,
+    username: &str,
+    password: &str,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<Credentials> {
+    let api_root = yggdrasil::resolve_api_root(server_url).await?;
+    let metadata = yggdrasil::metadata(&api_root).await?;
+    let server_name = metadata["meta"]["serverName"]
+        .as_str()
+        .map(ToString::to_string);
+
+    let client_token = Uuid::new_v4().simple().to_string();
+    let mut session =
+        yggdrasil::authenticate(&api_root, username, password, &client_token)
+            .await?;
+
+    if session.selected_profile.is_none() {
+        let profile = session
+            .available_profiles
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(username))
+            .or_else(|| session.available_profiles.first())
+            .cloned()
+            .ok_or(MinecraftAuthenticationError::NoYggdrasilProfile)?;
+
+        session = yggdrasil::refresh(
+            &api_root,
+            &session.access_token,
+            &session.client_token,
+            Some(&profile),
+        )
+        .await?;
+    }
+
+    let profile = session
+        .selected_profile
+        .ok_or(MinecraftAuthenticationError::NoYggdrasilProfile)?;
+
+    let credentials = Credentials {
+        offline_profile: MinecraftProfile {
+            id: profile.id,
+            name: profile.name,
+            ..MinecraftProfile::default()
+        },
+        access_token: session.access_token,
+        refresh_token: String::new(),
+        expires: Utc::now() + YGGDRASIL_VALIDATION_INTERVAL,
+        active: true,
+        provider: CredentialProvider::Yggdrasil {
+            api_root,
+            server_name,
+            client_token: session.client_token,
+        },
+    };
+
+    credentials.upsert(exec).await?;
+
+    Ok(credentials)
+}
+
+/// The service a set of credentials was issued by
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CredentialProvider {
+    /// A Microsoft account, signed into through Xbox Live
+    #[default]
+    Microsoft,
+    /// An account on a Yggdrasil-compatible authentication server, which
+    /// the game uses through authlib-injector
+    Yggdrasil {
+        /// The API root of the server, ending with a slash
+        api_root: String,
+        server_name: Option<String>,
+        /// The client token the access token was issued to, required to
+        /// refresh it
+        client_token: String,
+    },
+}
+
+impl CredentialProvider {
+    /// The `${user_type}` passed to the game
+    pub fn user_type(&self) -> &'static str {
+        match self {
+            Self::Microsoft => "msa",
+            Self::Yggdrasil { .. } => "mojang",
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Credentials {
     /// The offline profile of the user these credentials are for.
@@ -191,6 +313,8 @@ pub struct Credentials {
     pub refresh_token: String,
     pub expires: DateTime<Utc>,
     pub active: bool,
+    #[serde(default)]
+    pub provider: CredentialProvider,
 }
 
 /// An entry in the player profile cache, keyed by player UUID.
@@ -231,6 +355,35 @@ impl Credentials {
             return Ok(());
         }
 
+        if let CredentialProvider::Yggdrasil {
+            api_root,
+            client_token,
+            ..
+        } = &self.provider
+        {
+            if !yggdrasil::validate(api_root, &self.access_token, client_token)
+                .await?
+            {
+                let session = yggdrasil::refresh(
+                    api_root,
+                    &self.access_token,
+                    client_token,
+                    None,
+                )
+                .await?;
+
+                self.access_token = session.access_token;
+                if let Some(profile) = session.selected_profile {
+                    self.offline_profile.name = profile.name;
+                }
+            }
+
+            self.expires = Utc::now() + YGGDRASIL_VALIDATION_INTERVAL;
+            self.upsert(exec).await?;
+
+            return Ok(());
+        }
+
         let oauth_token = oauth_refresh(&self.refresh_token).await?;
         let (pair, current_date) =
             DeviceTokenPair::refresh_and_get_device_token(
@@ -306,7 +459,20 @@ impl Credentials {
                     }
                 }
                 Entry::Vacant(entry) => {
-                    match minecraft_profile(&self.access_token).await {
+                    let profile = match &self.provider {
+                        CredentialProvider::Microsoft => {
+                            minecraft_profile(&self.access_token).await
+                        }
+                        CredentialProvider::Yggdrasil { api_root, .. } => {
+                            yggdrasil::profile(
+                                api_root,
+                                self.offline_profile.id,
+                            )
+                            .await
+                        }
+                    };
+
+                    match profile {
                         Ok(profile) => {
                             let profile = Arc::new(profile);
                             let cache_entry =
@@ -414,7 +580,8 @@ impl Credentials {
         let res = sqlx::query!(
             "
             SELECT
-                uuid, active, username, access_token, refresh_token, expires
+                uuid, active, username, access_token, refresh_token, expires,
+                json(auth_provider) auth_provider
             FROM minecraft_users
             WHERE active = TRUE
             "
@@ -437,6 +604,11 @@ impl Credentials {
                         .single()
                         .unwrap_or_else(Utc::now),
                     active: x.active == 1,
+                    provider: x
+                        .auth_provider
+                        .as_ref()
+                        .and_then(|x| serde_json::from_str(x).ok())
+                        .unwrap_or_default(),
                 };
                 credentials.refresh(exec).await.ok();
                 Some(credentials)
@@ -451,7 +623,8 @@ impl Credentials {
         let res = sqlx::query!(
             "
             SELECT
-                uuid, active, username, access_token, refresh_token, expires
+                uuid, active, username, access_token, refresh_token, expires,
+                json(auth_provider) auth_provider
             FROM minecraft_users
             "
         )
@@ -471,6 +644,11 @@ impl Credentials {
                     .single()
                     .unwrap_or_else(Utc::now),
                 active: x.active == 1,
+                provider: x
+                    .auth_provider
+                    .as_ref()
+                    .and_then(|x| serde_json::from_str(x).ok())
+                    .unwrap_or_default(),
             };
 
             async move {
@@ -492,6 +670,7 @@ impl Credentials {
         let profile = self.maybe_online_profile().await;
         let expires = self.expires.timestamp();
         let uuid = profile.id.as_hyphenated().to_string();
+        let auth_provider = serde_json::to_string(&self.provider)?;
 
         if self.active {
             sqlx::query!(
@@ -506,14 +685,15 @@ impl Credentials {
 
         sqlx::query!(
             "
-            INSERT INTO minecraft_users (uuid, active, username, access_token, refresh_token, expires)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO minecraft_users (uuid, active, username, access_token, refresh_token, expires, auth_provider)
+            VALUES ($1, $2, $3, $4, $5, $6, jsonb($7))
             ON CONFLICT (uuid) DO UPDATE SET
                 active = $2,
                 username = $3,
                 access_token = $4,
                 refresh_token = $5,
-                expires = $6
+                expires = $6,
+                auth_provider = jsonb($7)
             ",
             uuid,
             self.active,
@@ -521,6 +701,7 @@ impl Credentials {
             self.access_token,
             self.refresh_token,
             expires,
+            auth_provider,
         )
             .execute(exec)
             .await?;
@@ -577,12 +758,13 @@ impl Serialize for Credentials {
                 ),
         };
 
-        let mut ser = serializer.serialize_struct("Credentials", 5)?;
+        let mut ser = serializer.serialize_struct("Credentials", 6)?;
         ser.serialize_field("profile", &*profile)?;
         ser.serialize_field("access_token", &self.access_token)?;
         ser.serialize_field("refresh_token", &self.refresh_token)?;
         ser.serialize_field("expires", &self.expires)?;
         ser.serialize_field("active", &self.active)?;
+        ser.serialize_field("provider", &self.provider)?;
         ser.end()
     }
 }
@@ -0,0 +1,649 @@
+//! Yggdrasil-compatible authentication servers, used by the game through
+//! authlib-injector.
+//!
+//! See <https://github.com/yushijinhun/authlib-injector/wiki> for the
+//! specification of the API implemented by these servers.
+use super::{
+    MinecraftAuthStep, MinecraftAuthenticationError, MinecraftCape,
+    MinecraftCharacterExpressionState, MinecraftProfile, MinecraftSkin,
+    MinecraftSkinVariant, auth_retry,
+};
+use crate::State;
+use crate::util::fetch::{INSECURE_REQWEST_CLIENT, fetch, fetch_json, write};
+use crate::util::io;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Digest;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use url::Url;
+use uuid::Uuid;
+
+const AUTHLIB_INJECTOR_META_URL: &str =
+    "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+/// A game profile owned by a Yggdrasil account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct YggdrasilProfile {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// The tokens and profiles returned when authenticating or refreshing
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct YggdrasilSession {
+    pub access_token: String,
+    pub client_token: String,
+    #[serde(default)]
+    pub available_profiles: Vec<YggdrasilProfile>,
+    pub selected_profile: Option<YggdrasilProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+/// Resolves the API root of an authentication server from a URL entered by
+/// the user, following the API location indication (ALI) header if the
+/// server sends one. The returned URL always ends with a slash
+#[tracing::instrument]
+pub async fn resolve_api_root(
+    url: &str,
+) -> Result<String, MinecraftAuthenticationError> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{url}")
+    };
+    let url = Url::parse(&url)
+        .map_err(|_| MinecraftAuthenticationError::InvalidYggdrasilUrl(url))?;
+
+    let res = auth_retry(|| INSECURE_REQWEST_CLIENT.get(url.clone()).send())
+        .await
+        .map_err(|source| MinecraftAuthenticationError::Request {
+            source,
+            step: MinecraftAuthStep::YggdrasilMetadata,
+        })?;
+
+    let api_root = res
+        .headers()
+        .get("X-Authlib-Injector-API-Location")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|location| res.url().join(location).ok())
+        .unwrap_or(url);
+
+    let mut api_root = api_root.to_string();
+    if !api_root.ends_with('/') {
+        api_root.push('/');
+    }
+
+    Ok(api_root)
+}
+
+/// Fetches the metadata of an authentication server, such as its name and
+/// the domains it serves skins from
+#[tracing::instrument]
+pub async fn metadata(
+    api_root: &str,
+) -> Result<serde_json::Value, MinecraftAuthenticationError> {
+    request(
+        Method::GET,
+        api_root.to_string(),
+        None,
+        MinecraftAuthStep::YggdrasilMetadata,
+    )
+    .await?
+    .ok_or(MinecraftAuthenticationError::NoYggdrasilMetadata)
+}
+
+/// Signs into an authentication server with a username (usually an email)
+/// and password
+#[tracing::instrument(skip(password))]
+pub async fn authenticate(
+    api_root: &str,
+    username: &str,
+    password: &str,
+    client_token: &str,
+) -> Result<YggdrasilSession, MinecraftAuthenticationError> {
+    request(
+        Method::POST,
+        format!("{api_root}authserver/authenticate"),
+        Some(json!({
+            "username": username,
+            "password": password,
+            "clientToken": client_token,
+            "requestUser": false,
+            "agent": {
+                "name": "Minecraft",
+                "version": 1
+            }
+        })),
+        MinecraftAuthStep::YggdrasilAuthenticate,
+    )
+    .await?
+    .ok_or(MinecraftAuthenticationError::NoYggdrasilProfile)
+}
+
+/// Exchanges an access token for a new one, invalidating the old one. If a
+/// profile is given, the new token is bound to it
+#[tracing::instrument(skip(access_token))]
+pub async fn refresh(
+    api_root: &str,
+    access_token: &str,
+    client_token: &str,
+    selected_profile: Option<&YggdrasilProfile>,
+) -> Result<YggdrasilSession, MinecraftAuthenticationError> {
+    let mut body = json!({
+        "accessToken": access_token,
+        "clientToken": client_token,
+        "requestUser": false,
+    });
+    if let Some(profile) = selected_profile {
+        body["selectedProfile"] = json!({
+            "id": profile.id.simple().to_string(),
+            "name": profile.name,
+        });
+    }
+
+    request(
+        Method::POST,
+        format!("{api_root}authserver/refresh"),
+        Some(body),
+        MinecraftAuthStep::YggdrasilRefresh,
+    )
+    .await?
+    .ok_or(MinecraftAuthenticationError::NoYggdrasilProfile)
+}
+
+/// Checks whether an access token can still be used to join servers
+#[tracing::instrument(skip(access_token))]
+pub async fn validate(
+    api_root: &str,
+    access_token: &str,
+    client_token: &str,
+) -> Result<bool, MinecraftAuthenticationError> {
+    let res = request::<serde_json::Value>(
+        Method::POST,
+        format!("{api_root}authserver/validate"),
+        Some(json!({
+            "accessToken": access_token,
+            "clientToken": client_token,
+        })),
+        MinecraftAuthStep::YggdrasilValidate,
+    )
+    .await;
+
+    match res {
+        Ok(_) => Ok(true),
+        Err(MinecraftAuthenticationError::Yggdrasil {
+            status_code: StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED,
+            ..
+        }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Revokes an access token, e.g. when the account is removed
+#[tracing::instrument(skip(access_token))]
+pub async fn invalidate(
+    api_root: &str,
+    access_token: &str,
+    client_token: &str,
+) -> Result<(), MinecraftAuthenticationError> {
+    request::<serde_json::Value>(
+        Method::POST,
+        format!("{api_root}authserver/invalidate"),
+        Some(json!({
+            "accessToken": access_token,
+            "clientToken": client_token,
+        })),
+        MinecraftAuthStep::YggdrasilInvalidate,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the profile of a player, including their skin and cape, from the
+/// session server
+#[tracing::instrument]
+pub async fn profile(
+    api_root: &str,
+    id: Uuid,
+) -> Result<MinecraftProfile, MinecraftAuthenticationError> {
+    let profile = request::<SessionProfile>(
+        Method::GET,
+        format!(
+            "{api_root}sessionserver/session/minecraft/profile/{}?unsigned=true",
+            id.simple()
+        ),
+        None,
+        MinecraftAuthStep::YggdrasilProfile,
+    )
+    .await?
+    .ok_or(MinecraftAuthenticationError::NoYggdrasilProfile)?;
+
+    let mut profile = profile.into_minecraft_profile();
+    profile.fetch_time = Some(Instant::now());
+
+    Ok(profile)
+}
+
+async fn request<T: DeserializeOwned>(
+    method: Method,
+    url: String,
+    body: Option<serde_json::Value>,
+    step: MinecraftAuthStep,
+) -> Result<Option<T>, MinecraftAuthenticationError> {
+    let res = auth_retry(|| {
+        let mut req = INSECURE_REQWEST_CLIENT
+            .request(method.clone(), &url)
+            .header("Accept", "application/json")
+            .timeout(std::time::Duration::from_secs(15));
+        if let Some(body) = &body {
+            req = req.json(body);
+        }
+        req.send()
+    })
+    .await
+    .map_err(|source| MinecraftAuthenticationError::Request { source, step })?;
+
+    let status_code = res.status();
+    let raw = res.text().await.map_err(|source| {
+        MinecraftAuthenticationError::Request { source, step }
+    })?;
+
+    if !status_code.is_success() {
+        return Err(
+            match serde_json::from_str::<YggdrasilErrorResponse>(&raw) {
+                Ok(err) => MinecraftAuthenticationError::Yggdrasil {
+                    step,
+                    status_code,
+                    error: err.error,
+                    message: err.error_message,
+                },
+                Err(source) => {
+                    MinecraftAuthenticationError::DeserializeResponse {
+                        step,
+                        raw,
+                        source,
+                        status_code,
+                    }
+                }
+            },
+        );
+    }
+
+    if status_code == StatusCode::NO_CONTENT || raw.trim().is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::from_str(&raw).map(Some).map_err(|source| {
+        MinecraftAuthenticationError::DeserializeResponse {
+            step,
+            raw,
+            source,
+            status_code,
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct SessionProfile {
+    id: Uuid,
+    name: String,
+    #[serde(default)]
+    properties: Vec<SessionProfileProperty>,
+}
+
+#[derive(Deserialize)]
+struct SessionProfileProperty {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Default)]
+struct TexturesProperty {
+    #[serde(default)]
+    textures: Textures,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "UPPERCASE")]
+struct Textures {
+    skin: Option<Texture>,
+    cape: Option<Texture>,
+}
+
+#[derive(Deserialize)]
+struct Texture {
+    url: Url,
+    #[serde(default)]
+    metadata: Option<TextureMetadata>,
+}
+
+#[derive(Deserialize)]
+struct TextureMetadata {
+    model: Option<String>,
+}
+
+impl SessionProfile {
+    fn into_minecraft_profile(self) -> MinecraftProfile {
+        let textures = self
+            .properties
+            .iter()
+            .find(|x| x.name == "textures")
+            .and_then(|x| BASE64_STANDARD.decode(&x.value).ok())
+            .and_then(|x| serde_json::from_slice::<TexturesProperty>(&x).ok())
+            .unwrap_or_default()
+            .textures;
+
+        // Session servers do not give textures IDs, so the profile ID is
+        // used instead, as players have at most one skin and cape here
+        let skins = textures
+            .skin
+            .map(|skin| MinecraftSkin {
+                id: self.id,
+                state: MinecraftCharacterExpressionState::Active,
+                url: Arc::new(skin.url),
+                texture_key: None,
+                variant: match skin.metadata.and_then(|x| x.model).as_deref() {
+                    Some("slim") => MinecraftSkinVariant::Slim,
+                    _ => MinecraftSkinVariant::Classic,
+                },
+                name: None,
+            })
+            .into_iter()
+            .collect();
+        let capes = textures
+            .cape
+            .map(|cape| MinecraftCape {
+                id: self.id,
+                state: MinecraftCharacterExpressionState::Active,
+                url: Arc::new(cape.url),
+                name: "Cape".into(),
+            })
+            .into_iter()
+            .collect();
+
+        MinecraftProfile {
+            id: self.id,
+            name: self.name,
+            skins,
+            capes,
+            fetch_time: None,
+        }
+    }
+}
+
+/// What the game needs to authenticate against a Yggdrasil server through
+/// authlib-injector
+#[derive(Debug)]
+pub struct AuthlibInjector {
+    /// Path to the authlib-injector agent JAR
+    pub jar_path: PathBuf,
+    /// The API root of the authentication server
+    pub api_root: String,
+    /// The server metadata, base64 encoded, so the game doesn't have to
+    /// fetch it again on startup
+    pub prefetched_metadata: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AuthlibInjectorArtifact {
+    version: String,
+    download_url: String,
+    checksums: AuthlibInjectorChecksums,
+}
+
+#[derive(Deserialize)]
+struct AuthlibInjectorChecksums {
+    sha256: String,
+}
+
+impl AuthlibInjector {
+    /// Downloads the latest authlib-injector if it is not already, and
+    /// prefetches the metadata of the authentication server
+    #[tracing::instrument]
+    pub async fn prepare(api_root: &str) -> crate::Result<Self> {
+        let jar_path = Self::download().await?;
+
+        let prefetched_metadata = match metadata(api_root).await {
+            Ok(metadata) => {
+                Some(BASE64_STANDARD.encode(serde_json::to_string(&metadata)?))
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to prefetch authentication server metadata: {err}"
+                );
+                None
+            }
+        };
+
+        Ok(Self {
+            jar_path,
+            api_root: api_root.to_string(),
+            prefetched_metadata,
+        })
+    }
+
+    async fn download() -> crate::Result<PathBuf> {
+        let state = State::get().await?;
+        let dir = state.directories.libraries_dir().join("authlib-injector");
+
+        let artifact = fetch_json::<AuthlibInjectorArtifact>(
+            Method::GET,
+            AUTHLIB_INJECTOR_META_URL,
+            None,
+            None,
+            &state.fetch_semaphore,
+            &state.pool,
+        )
+        .await;
+
+        let artifact = match artifact {
+            Ok(artifact) => artifact,
+            Err(err) => {
+                // Fall back to the last downloaded version when offline
+                let mut latest = None;
+                if let Ok(mut entries) = io::read_dir(&dir).await {
+                    while let Some(entry) = entries.next_entry().await? {
+                        let path = entry.path();
+                        let modified = entry.metadata().await?.modified()?;
+                        if path.extension().is_some_and(|x| x == "jar")
+                            && latest
+                                .as_ref()
+                                .is_none_or(|(x, _)| modified > *x)
+                        {
+                            latest = Some((modified, path));
+                        }
+                    }
+                }
+
+                return latest.map(|(_, path)| path).ok_or(err);
+            }
+        };
+
+        let path =
+            dir.join(format!("authlib-injector-{}.jar", artifact.version));
+        if !path.exists() {
+            let bytes = fetch(
+                &artifact.download_url,
+                None,
+                None,
+                &state.fetch_semaphore,
+                &state.pool,
+            )
+            .await?;
+
+            let hash = format!("{:x}", sha2::Sha256::digest(&bytes));
+            if hash != artifact.checksums.sha256 {
+                return Err(crate::ErrorKind::LauncherError(format!(
+                    "Incorrect SHA-256 hash for authlib-injector: {} != {hash}",
+                    artifact.checksums.sha256
+                ))
+                .into());
+            }
+
+            write(&path, &bytes, &state.io_semaphore).await?;
+        }
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const PROFILE_ID: &str = "0d2c6e6e1c4a4e0b9d0b2a6c7a1e3f4b";
+
+    /// Serves a minimal Yggdrasil server, accepting the password `hunter2`
+    /// and the access tokens `first` and `second`
+    async fn mock_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = String::new();
+                let mut buf = vec![0; 8192];
+                let body = loop {
+                    let len = stream.read(&mut buf).await.unwrap();
+                    request.push_str(&String::from_utf8_lossy(&buf[..len]));
+
+                    if let Some((headers, body)) =
+                        request.split_once("\r\n\r\n")
+                    {
+                        let content_length = headers
+                            .lines()
+                            .find_map(|x| {
+                                x.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .and_then(|x| x.trim().parse().ok())
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= content_length || len == 0 {
+                            break body.to_string();
+                        }
+                    } else if len == 0 {
+                        break String::new();
+                    }
+                };
+                let path = request.split(' ').nth(1).unwrap_or_default();
+
+                let session = |token: &str| {
+                    json!({
+                        "accessToken": token,
+                        "clientToken": "client",
+                        "availableProfiles": [{ "id": PROFILE_ID, "name": "Steve" }],
+                        "selectedProfile": { "id": PROFILE_ID, "name": "Steve" },
+                    })
+                    .to_string()
+                };
+                let forbidden = json!({
+                    "error": "ForbiddenOperationException",
+                    "errorMessage": "Invalid token.",
+                })
+                .to_string();
+
+                let (status, response) = match path {
+                    "/authserver/authenticate" if body.contains("hunter2") => {
+                        ("200 OK", session("first"))
+                    }
+                    "/authserver/refresh" if body.contains("\"first\"") => {
+                        ("200 OK", session("second"))
+                    }
+                    "/authserver/validate" | "/authserver/invalidate"
+                        if body.contains("\"second\"") =>
+                    {
+                        ("204 No Content", String::new())
+                    }
+                    _ => ("403 Forbidden", forbidden),
+                };
+
+                let _ = stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                            response.len()
+                        )
+                        .as_bytes(),
+                    )
+                    .await;
+            }
+        });
+
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test]
+    async fn test_mock_server_flow() {
+        let api_root = mock_server().await;
+
+        let err = authenticate(&api_root, "steve", "wrong", "client")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MinecraftAuthenticationError::Yggdrasil {
+                status_code: StatusCode::FORBIDDEN,
+                ..
+            }
+        ));
+
+        let session = authenticate(&api_root, "steve", "hunter2", "client")
+            .await
+            .unwrap();
+        assert_eq!(session.access_token, "first");
+        assert_eq!(session.selected_profile.unwrap().name, "Steve");
+
+        assert!(!validate(&api_root, "first", "client").await.unwrap());
+        let session =
+            refresh(&api_root, "first", "client", None).await.unwrap();
+        assert_eq!(session.access_token, "second");
+        assert!(validate(&api_root, "second", "client").await.unwrap());
+
+        invalidate(&api_root, "second", "client").await.unwrap();
+    }
+
+    #[test]
+    fn test_session_profile_textures() {
+        let textures = BASE64_STANDARD.encode(
+            json!({
+                "textures": {
+                    "SKIN": {
+                        "url": "https://skins.example.com/textures/abc",
+                        "metadata": { "model": "slim" }
+                    }
+                }
+            })
+            .to_string(),
+        );
+        let profile: SessionProfile = serde_json::from_value(json!({
+            "id": PROFILE_ID,
+            "name": "Steve",
+            "properties": [{ "name": "textures", "value": textures }]
+        }))
+        .unwrap();
+
+        let profile = profile.into_minecraft_profile();
+        assert_eq!(profile.name, "Steve");
+        assert_eq!(profile.skins.len(), 1);
+        assert_eq!(profile.skins[0].variant, MinecraftSkinVariant::Slim);
+        assert_eq!(&*profile.skins[0].texture_key(), "abc");
+        assert!(profile.capes.is_empty());
+    }
+}
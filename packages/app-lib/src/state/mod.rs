@@ -20,9 +20,15 @@ pub use self::instances::*;
 mod settings;
 pub use self::settings::*;
 
+mod endpoints;
+pub use self::endpoints::*;
+
 mod process;
 pub use self::process::*;
 
+mod crash_analysis;
+pub use self::crash_analysis::*;
+
 mod java_globals;
 pub use self::java_globals::*;
 
@@ -52,6 +58,8 @@ pub use self::mr_auth::*;
 mod legacy_converter;
 
 pub mod attached_world_data;
+pub mod content_store;
+pub mod presence;
 pub mod server_join_log;
 
 // Global state
@@ -93,8 +101,19 @@ pub struct State {
 
 impl State {
     pub async fn init(app_identifier: String) -> crate::Result<()> {
+        Self::init_with_endpoints(app_identifier, None).await
+    }
+
+    /// Initializes the state, using `endpoints` instead of the endpoints in
+    /// the settings if given
+    pub async fn init_with_endpoints(
+        app_identifier: String,
+        endpoints: Option<Endpoints>,
+    ) -> crate::Result<()> {
         let state = LAUNCHER_STATE
-            .get_or_try_init(move || Self::initialize_state(app_identifier))
+            .get_or_try_init(move || {
+                Self::initialize_state(app_identifier, endpoints)
+            })
             .await?;
 
         tokio::task::spawn(async move {
@@ -156,6 +175,7 @@ impl State {
     #[tracing::instrument]
     async fn initialize_state(
         app_identifier: String,
+        endpoints: Option<Endpoints>,
     ) -> crate::Result<Arc<Self>> {
         tracing::info!("Connecting to app database");
         let pool = db::connect(&app_identifier).await?;
@@ -165,6 +185,11 @@ impl State {
         tracing::info!("Fetching app settings");
         let mut settings = Settings::get(&pool).await?;
 
+        match endpoints {
+            Some(endpoints) => Endpoints::set_override(endpoints),
+            None => Endpoints::apply_settings(&settings.endpoints),
+        }
+
         let fetch_semaphore =
             FetchSemaphore(Semaphore::new(settings.max_concurrent_downloads));
         let io_semaphore =
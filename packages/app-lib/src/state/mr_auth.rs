@@ -1,4 +1,4 @@
-use crate::state::{CacheBehaviour, CachedEntry};
+use crate::state::{CacheBehaviour, CachedEntry, Endpoints};
 use crate::util::fetch::{FetchSemaphore, fetch_advanced};
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use dashmap::DashMap;
@@ -30,7 +30,7 @@ impl ModrinthCredentials {
 
                 let resp = fetch_advanced(
                     Method::POST,
-                    concat!(env!("MODRINTH_API_URL"), "session/refresh"),
+                    &format!("{}session/refresh", Endpoints::current().api_url),
                     None,
                     None,
                     Some(("Authorization", &*creds.session)),
@@ -190,8 +190,8 @@ impl ModrinthCredentials {
     }
 }
 
-pub const fn get_login_url() -> &'static str {
-    concat!(env!("MODRINTH_URL"), "auth/sign-in")
+pub fn get_login_url() -> String {
+    format!("{}auth/sign-in", Endpoints::current().site_url)
 }
 
 pub async fn finish_login_flow(
@@ -222,7 +222,7 @@ async fn fetch_info(
 ) -> crate::Result<crate::state::cache::User> {
     let result = fetch_advanced(
         Method::GET,
-        concat!(env!("MODRINTH_API_URL"), "user"),
+        &format!("{}user", Endpoints::current().api_url),
         None,
         None,
         Some(("Authorization", token)),
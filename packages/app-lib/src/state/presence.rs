@@ -0,0 +1,230 @@
+//! What friends and Discord are told about the instance being played
+use crate::State;
+use crate::state::{ProcessManager, Profile, Settings};
+use ariadne::users::UserActivity;
+use dashmap::DashMap;
+use std::sync::LazyLock;
+
+/// Where in the game a running instance is, as read from its logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayLocation {
+    Server { host: String, port: u16 },
+    Singleplayer { world: String },
+}
+
+static LOCATIONS: LazyLock<DashMap<String, PlayLocation>> =
+    LazyLock::new(DashMap::new);
+
+/// Records where the game of a profile is, and shares it if it changed
+pub(crate) fn set_location(profile_path: &str, location: Option<PlayLocation>) {
+    let changed = match location {
+        Some(location) => LOCATIONS
+            .insert(profile_path.to_string(), location.clone())
+            .is_none_or(|old| old != location),
+        None => LOCATIONS.remove(profile_path).is_some(),
+    };
+
+    if changed {
+        tokio::spawn(async {
+            if let Err(e) = refresh().await {
+                tracing::warn!("Failed to update status: {e}");
+            }
+        });
+    }
+}
+
+/// Tracks singleplayer worlds being opened, and servers and worlds being
+/// left, from the game's logs
+pub(crate) fn handle_log_message(profile_path: &str, message: &str) {
+    match parse_log_message(message) {
+        Some(LocationChange::Enter(location)) => {
+            set_location(profile_path, Some(location))
+        }
+        Some(LocationChange::Leave) => set_location(profile_path, None),
+        None => {}
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LocationChange {
+    Enter(PlayLocation),
+    Leave,
+}
+
+fn parse_log_message(message: &str) -> Option<LocationChange> {
+    if let Some(world) = message
+        .strip_prefix("Preparing level \"")
+        .and_then(|x| x.strip_suffix('"'))
+    {
+        Some(LocationChange::Enter(PlayLocation::Singleplayer {
+            world: world.to_string(),
+        }))
+    } else if message.starts_with("Stopping singleplayer server")
+        // Leaving a server, being kicked or losing the connection
+        || message.starts_with("Client disconnected with reason:")
+        || message.starts_with("Couldn't connect to server")
+        // The game is closing, which older versions log without the
+        // messages above
+        || message == "Stopping!"
+    {
+        Some(LocationChange::Leave)
+    } else {
+        None
+    }
+}
+
+/// The status of the user, limited to what they chose to share in
+/// [`crate::state::StatusPrivacy`]
+#[derive(Debug, Clone, Default)]
+pub struct Presence {
+    /// Whether an instance is running at all
+    pub playing: bool,
+    pub profile_name: Option<String>,
+    pub activity: Option<UserActivity>,
+}
+
+impl Presence {
+    /// Builds the presence for the first running instance
+    pub async fn current(
+        exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+        process_manager: &ProcessManager,
+    ) -> crate::Result<Self> {
+        let Some(process) = process_manager.get_all().into_iter().next() else {
+            return Ok(Self::default());
+        };
+        let Some(profile) = Profile::get(&process.profile_path, exec).await?
+        else {
+            return Ok(Self::default());
+        };
+        let privacy = Settings::get(exec).await?.status_privacy;
+
+        let location = LOCATIONS
+            .get(&process.profile_path)
+            .map(|x| x.value().clone());
+        let (server, world) = match location {
+            Some(PlayLocation::Server { host, port }) => (
+                Some(if port == 25565 {
+                    host
+                } else {
+                    format!("{host}:{port}")
+                }),
+                None,
+            ),
+            Some(PlayLocation::Singleplayer { world }) => (None, Some(world)),
+            None => (None, None),
+        };
+
+        let activity = UserActivity {
+            project_id: profile
+                .linked_data
+                .as_ref()
+                .filter(|_| privacy.project)
+                .map(|x| x.project_id.clone()),
+            version_id: profile
+                .linked_data
+                .as_ref()
+                .filter(|_| privacy.project)
+                .map(|x| x.version_id.clone()),
+            game_version: Some(profile.game_version)
+                .filter(|_| privacy.game_version),
+            loader: Some(profile.loader.as_str().to_string())
+                .filter(|_| privacy.loader),
+            server: server.filter(|_| privacy.server),
+            world: world.filter(|_| privacy.world),
+            started: Some(process.start_time).filter(|_| privacy.play_time),
+        };
+
+        Ok(Self {
+            playing: true,
+            profile_name: Some(profile.name).filter(|_| privacy.instance_name),
+            activity: (activity != UserActivity::default()).then_some(activity),
+        })
+    }
+
+    /// The main line shown on Discord
+    pub fn state_text(&self) -> String {
+        match &self.profile_name {
+            Some(name) => format!("Playing {name}"),
+            None if self.playing => "Playing Minecraft".to_string(),
+            None => "Idling...".to_string(),
+        }
+    }
+
+    /// The second line shown on Discord, if there is anything to show
+    pub fn details_text(&self) -> Option<String> {
+        let activity = self.activity.as_ref()?;
+
+        if let Some(server) = &activity.server {
+            Some(format!("On {server}"))
+        } else if activity.world.is_some() {
+            Some("In singleplayer".to_string())
+        } else {
+            activity
+                .game_version
+                .as_ref()
+                .map(|x| format!("Minecraft {x}"))
+        }
+    }
+}
+
+/// Sends the current presence to friends and Discord
+pub(crate) async fn refresh() -> crate::Result<()> {
+    let state = State::get().await?;
+    let presence =
+        Presence::current(&state.pool, &state.process_manager).await?;
+
+    let _ = state.discord_rpc.set_presence(&presence, true).await;
+    state
+        .friends_socket
+        .update_status(presence.profile_name, presence.activity)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_singleplayer_worlds() {
+        assert_eq!(
+            parse_log_message("Preparing level \"New World\""),
+            Some(LocationChange::Enter(PlayLocation::Singleplayer {
+                world: "New World".to_string()
+            }))
+        );
+        assert_eq!(
+            parse_log_message(
+                "Stopping singleplayer server as player logged out"
+            ),
+            Some(LocationChange::Leave)
+        );
+    }
+
+    #[test]
+    fn clears_servers_on_disconnect() {
+        for message in [
+            "Client disconnected with reason: Disconnected",
+            "Client disconnected with reason: Timed out",
+            "Couldn't connect to server",
+            "Stopping!",
+        ] {
+            assert_eq!(
+                parse_log_message(message),
+                Some(LocationChange::Leave),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        for message in [
+            "Connecting to mc.example.com, 25565",
+            "Preparing level",
+            "Stopping the server",
+            "[CHAT] Client disconnected with reason: spoofed",
+        ] {
+            assert_eq!(parse_log_message(message), None, "{message}");
+        }
+    }
+}
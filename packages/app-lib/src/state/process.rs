@@ -1,8 +1,9 @@
-use crate::event::emit::{emit_process, emit_profile};
+use crate::event::emit::{emit_crash_analysis, emit_process, emit_profile};
 #[cfg(feature = "tauri")]
 use crate::event::{LogEvent, LogPayload};
 use crate::event::{ProcessPayloadType, ProfilePayloadType};
 use crate::profile;
+use crate::state::presence::{self, PlayLocation};
 use crate::util::io::IOError;
 use crate::util::rpc::RpcServer;
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
@@ -82,6 +83,29 @@ pub fn remove_log_buffer(profile_path: &str) {
     LOG_BUFFERS.remove(profile_path);
 }
 
+/// A mod the game reported as loaded through the launcher RPC
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportedMod {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+static REPORTED_MODS: LazyLock<DashMap<String, Vec<ReportedMod>>> =
+    LazyLock::new(DashMap::new);
+
+pub fn set_reported_mods(profile_path: &str, mods: Vec<ReportedMod>) {
+    REPORTED_MODS.insert(profile_path.to_string(), mods);
+}
+
+pub fn get_reported_mods(profile_path: &str) -> Option<Vec<ReportedMod>> {
+    REPORTED_MODS.get(profile_path).map(|mods| mods.clone())
+}
+
+pub fn clear_reported_mods(profile_path: &str) {
+    REPORTED_MODS.remove(profile_path);
+}
+
 pub struct ProcessManager {
     processes: DashMap<Uuid, Process>,
 }
@@ -132,6 +156,7 @@ impl ProcessManager {
             child: mc_proc,
             rpc_server,
             _main_class_keep_alive: main_class_keep_alive,
+            killed: false,
         };
 
         if let Err(e) =
@@ -259,12 +284,18 @@ impl ProcessManager {
 
     pub async fn kill(&self, id: Uuid) -> crate::Result<()> {
         if let Some(mut process) = self.processes.get_mut(&id) {
+            process.killed = true;
             process.child.kill().await?;
         }
 
         Ok(())
     }
 
+    /// Whether the process was killed through [`Self::kill`]
+    fn was_killed(&self, id: Uuid) -> bool {
+        self.processes.get(&id).is_some_and(|x| x.killed)
+    }
+
     fn remove(&self, id: Uuid) {
         self.processes.remove(&id);
     }
@@ -283,6 +314,8 @@ struct Process {
     child: Child,
     _main_class_keep_alive: TempDir,
     rpc_server: RpcServer,
+    /// Whether the launcher killed the process, so its exit isn't a crash
+    killed: bool,
 }
 
 #[derive(Debug, Default, Serialize, Clone)]
@@ -691,6 +724,8 @@ impl Process {
         message: &str,
         timestamp: DateTime<Utc>,
     ) -> crate::Result<()> {
+        presence::handle_log_message(profile_path, message);
+
         let Some(host_port_string) = message.strip_prefix("Connecting to ")
         else {
             return Ok(());
@@ -703,6 +738,14 @@ impl Process {
             return Ok(());
         };
 
+        presence::set_location(
+            profile_path,
+            Some(PlayLocation::Server {
+                host: host.to_string(),
+                port,
+            }),
+        );
+
         let state = crate::State::get().await?;
         crate::state::server_join_log::JoinLogEntry {
             profile_path: profile_path.to_owned(),
@@ -769,6 +812,10 @@ impl Process {
         let mut last_updated_playtime = Utc::now();
 
         let state = crate::State::get().await?;
+        let start_time = state
+            .process_manager
+            .get(uuid)
+            .map_or_else(Utc::now, |x| x.start_time);
         loop {
             if let Some(process) = state.process_manager.try_wait(uuid)? {
                 if let Some(t) = process {
@@ -788,7 +835,16 @@ impl Process {
                 .await;
         }
 
+        let killed = state.process_manager.was_killed(uuid);
         state.process_manager.remove(uuid);
+        if !state
+            .process_manager
+            .get_all()
+            .iter()
+            .any(|x| x.profile_path == profile_path)
+        {
+            clear_reported_mods(&profile_path);
+        }
         emit_process(
             &profile_path,
             uuid,
@@ -826,9 +882,41 @@ impl Process {
             tracing::warn!("Failed to write exit status to log file: {}", e);
         }
 
-        let _ = state.discord_rpc.clear_to_default(true).await;
+        // Look for the mods that caused the crash, if the game crashed
+        if is_crash(&mc_exit_status, killed) {
+            let profile_path = profile_path.clone();
+            tokio::spawn(async move {
+                let analysis = match crate::state::analyze_crash(
+                    &profile_path,
+                    Some(start_time),
+                    mc_exit_status.code(),
+                    exit_signal(&mc_exit_status),
+                )
+                .await
+                {
+                    Ok(analysis) => analysis,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to analyze crash of profile {}: {}",
+                            profile_path,
+                            e
+                        );
+                        return;
+                    }
+                };
 
-        let _ = state.friends_socket.update_status(None).await;
+                if let Err(e) =
+                    emit_crash_analysis(&profile_path, uuid, analysis).await
+                {
+                    tracing::warn!("Failed to emit crash analysis: {}", e);
+                }
+            });
+        }
+
+        presence::set_location(&profile_path, None);
+        if let Err(e) = presence::refresh().await {
+            tracing::warn!("Failed to update status: {e}");
+        }
 
         // If in tauri, window should show itself again after process exists if it was hidden
         #[cfg(feature = "tauri")]
@@ -865,3 +953,63 @@ impl Process {
         Ok(())
     }
 }
+
+/// Whether the game exited abnormally on its own, with a non-zero exit code
+/// or (on Unix) by a signal like `SIGSEGV`. Processes killed through the
+/// launcher didn't crash.
+fn is_crash(status: &ExitStatus, killed: bool) -> bool {
+    !killed && !status.success()
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(status)
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn signals_are_crashes() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // killed by SIGSEGV
+        let segfault = ExitStatus::from_raw(11);
+        assert!(is_crash(&segfault, false));
+        assert_eq!(segfault.code(), None);
+        assert_eq!(exit_signal(&segfault), Some(11));
+
+        let exit_code = ExitStatus::from_raw(1 << 8);
+        assert!(is_crash(&exit_code, false));
+        assert_eq!(exit_signal(&exit_code), None);
+
+        assert!(!is_crash(&ExitStatus::from_raw(0), false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn killed_processes_are_not_crashes() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // killed by SIGKILL
+        assert!(!is_crash(&ExitStatus::from_raw(9), true));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn killed_processes_are_not_crashes() {
+        use std::os::windows::process::ExitStatusExt;
+
+        // `TerminateProcess` exits with code 1
+        let status = ExitStatus::from_raw(1);
+        assert!(is_crash(&status, false));
+        assert!(!is_crash(&status, true));
+    }
+}
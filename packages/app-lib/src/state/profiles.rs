@@ -2,7 +2,8 @@ use super::settings::{Hooks, MemorySettings, WindowSize};
 use crate::profile::get_full_path;
 use crate::state::server_join_log::JoinLogEntry;
 use crate::state::{
-    CacheBehaviour, CachedEntry, CachedFile, CachedFileHash, cache_file_hash,
+    CacheBehaviour, CachedEntry, CachedFile, CachedFileHash, Settings,
+    cache_file_hash, content_store,
 };
 use crate::util;
 use crate::util::fetch::{FetchSemaphore, IoSemaphore, write_cached_icon};
@@ -147,6 +148,11 @@ pub enum ModLoader {
     Fabric,
     Quilt,
     NeoForge,
+    LiteLoader,
+    Rift,
+    #[serde(rename = "legacy-fabric")]
+    LegacyFabric,
+    Babric,
 }
 
 impl ModLoader {
@@ -157,6 +163,10 @@ impl ModLoader {
             Self::Fabric => "fabric",
             Self::Quilt => "quilt",
             Self::NeoForge => "neoforge",
+            Self::LiteLoader => "liteloader",
+            Self::Rift => "rift",
+            Self::LegacyFabric => "legacy-fabric",
+            Self::Babric => "babric",
         }
     }
 
@@ -167,6 +177,10 @@ impl ModLoader {
             Self::Fabric => "fabric",
             Self::Quilt => "quilt",
             Self::NeoForge => "neo",
+            Self::LiteLoader => "liteloader",
+            Self::Rift => "rift",
+            Self::LegacyFabric => "legacy-fabric",
+            Self::Babric => "babric",
         }
     }
 
@@ -177,6 +191,10 @@ impl ModLoader {
             "fabric" => Self::Fabric,
             "quilt" => Self::Quilt,
             "neoforge" => Self::NeoForge,
+            "liteloader" => Self::LiteLoader,
+            "rift" => Self::Rift,
+            "legacy-fabric" => Self::LegacyFabric,
+            "babric" => Self::Babric,
             _ => Self::Vanilla,
         }
     }
@@ -211,7 +229,7 @@ impl ProjectType {
     pub fn get_from_loaders(loaders: Vec<String>) -> Option<Self> {
         if loaders
             .iter()
-            .any(|x| ["fabric", "forge", "quilt", "neoforge"].contains(&&**x))
+            .any(|x| ProjectType::Mod.get_loaders().contains(&&**x))
         {
             Some(ProjectType::Mod)
         } else if loaders.iter().any(|x| x == "datapack") {
@@ -264,7 +282,16 @@ impl ProjectType {
 
     pub fn get_loaders(&self) -> &'static [&'static str] {
         match self {
-            ProjectType::Mod => &["fabric", "forge", "quilt", "neoforge"],
+            ProjectType::Mod => &[
+                "fabric",
+                "forge",
+                "quilt",
+                "neoforge",
+                "liteloader",
+                "rift",
+                "legacy-fabric",
+                "babric",
+            ],
             ProjectType::DataPack => &["datapack"],
             ProjectType::ResourcePack => &["vanilla", "canvas", "minecraft"],
             ProjectType::ShaderPack => &["iris", "optifine"],
@@ -625,6 +652,10 @@ impl Profile {
             io::remove_dir_all(&path).await?;
         }
 
+        let state = crate::State::get().await?;
+        content_store::release_profile(profile_path, &state.directories, pool)
+            .await?;
+
         Ok(())
     }
 
@@ -1151,6 +1182,37 @@ impl Profile {
         Ok(project_ids)
     }
 
+    /// Gets the SHA-1 hashes of the content files of this profile, keyed by
+    /// their path relative to the profile
+    pub(crate) async fn get_content_file_hashes(
+        &self,
+        pool: &SqlitePool,
+        fetch_semaphore: &FetchSemaphore,
+    ) -> crate::Result<HashMap<String, String>> {
+        let (keys, file_hashes) =
+            self.scan_and_hash(pool, fetch_semaphore).await?;
+
+        let mut keys_by_path: HashMap<String, InitialScanFile> =
+            keys.into_iter().map(|k| (k.path.clone(), k)).collect();
+
+        Ok(file_hashes
+            .into_iter()
+            .filter_map(|hash| {
+                let file = keys_by_path
+                    .remove(hash.path.trim_end_matches(".disabled"))?;
+
+                Some((
+                    format!(
+                        "{}/{}",
+                        file.project_type.get_folder(),
+                        file.file_name
+                    ),
+                    hash.hash,
+                ))
+            })
+            .collect())
+    }
+
     async fn scan_and_hash(
         &self,
         pool: &SqlitePool,
@@ -1246,6 +1308,8 @@ impl Profile {
             game_version: profile.game_version.clone(),
             loader: profile.loader.as_str().to_string(),
         };
+        let shared_content_store =
+            Settings::get(pool).await?.shared_content_store;
 
         let version =
             CachedEntry::get_version(version_id, None, pool, fetch_semaphore)
@@ -1284,6 +1348,7 @@ impl Profile {
             bytes,
             file.hashes.get("sha1").map(|x| &**x),
             ProjectType::get_from_loaders(version.loaders.clone()),
+            shared_content_store,
             io_semaphore,
             pool,
         )
@@ -1299,8 +1364,9 @@ impl Profile {
         bytes: bytes::Bytes,
         hash: Option<&str>,
         project_type: Option<ProjectType>,
+        shared_content_store: bool,
         io_semaphore: &IoSemaphore,
-        exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
     ) -> crate::Result<String> {
         let project_type = if let Some(project_type) = project_type {
             project_type
@@ -1336,7 +1402,6 @@ impl Profile {
             }
         };
 
-        let path = crate::api::profile::get_full_path(profile_path).await?;
         let project_path =
             format!("{}/{}", project_type.get_folder(), file_name);
 
@@ -1350,8 +1415,16 @@ impl Profile {
         )
         .await?;
 
-        util::fetch::write(&path.join(&project_path), &bytes, io_semaphore)
-            .await?;
+        content_store::write_profile_file(
+            profile_path,
+            &project_path,
+            &bytes,
+            hash,
+            shared_content_store,
+            io_semaphore,
+            exec,
+        )
+        .await?;
 
         Ok(project_path)
     }
@@ -1394,6 +1467,15 @@ impl Profile {
         io::rename_or_move(&base.join(&current_path), &base.join(&new_path))
             .await?;
 
+        let state = crate::State::get().await?;
+        content_store::rename_reference(
+            profile_path,
+            &current_path,
+            &new_path,
+            &state.pool,
+        )
+        .await?;
+
         Ok(new_path)
     }
 
@@ -1407,6 +1489,55 @@ impl Profile {
             io::remove_file(path.join(project_path)).await?;
         }
 
+        let state = crate::State::get().await?;
+        content_store::release(
+            profile_path,
+            project_path,
+            &state.directories,
+            &state.pool,
+        )
+        .await?;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+impl Profile {
+    /// An installed profile that is not linked to a modpack
+    pub(crate) fn for_tests(
+        game_version: &str,
+        loader: ModLoader,
+        loader_version: Option<&str>,
+    ) -> Self {
+        Self {
+            path: "test".to_string(),
+            install_stage: ProfileInstallStage::Installed,
+            launcher_feature_version: LauncherFeatureVersion::MOST_RECENT,
+            name: "Test".to_string(),
+            icon_path: None,
+            game_version: game_version.to_string(),
+            protocol_version: None,
+            loader,
+            loader_version: loader_version.map(|x| x.to_string()),
+            groups: Vec::new(),
+            linked_data: None,
+            created: Utc::now(),
+            modified: Utc::now(),
+            last_played: None,
+            submitted_time_played: 0,
+            recent_time_played: 0,
+            java_path: None,
+            extra_launch_args: None,
+            custom_env_vars: None,
+            memory: None,
+            force_fullscreen: None,
+            game_resolution: None,
+            hooks: Hooks {
+                pre_launch: None,
+                wrapper: None,
+                post_exit: None,
+            },
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Theseus settings file
 
+use crate::state::Endpoints;
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
 use std::collections::HashMap;
@@ -23,6 +24,9 @@ pub struct Settings {
     pub telemetry: bool,
     pub discord_rpc: bool,
     pub personalized_ads: bool,
+    /// What friends and Discord are told about the instance being played
+    #[serde(default)]
+    pub status_privacy: StatusPrivacy,
 
     pub onboarded: bool,
 
@@ -34,6 +38,11 @@ pub struct Settings {
     pub hide_on_process_start: bool,
     pub hooks: Hooks,
 
+    /// Whether content files are kept in a store shared between profiles
+    /// instead of being copied into each profile
+    #[serde(default)]
+    pub shared_content_store: bool,
+
     pub custom_dir: Option<String>,
     pub prev_custom_dir: Option<String>,
     pub migrated: bool,
@@ -45,9 +54,41 @@ pub struct Settings {
     pub pending_update_toast_for_version: Option<String>,
     pub auto_download_updates: Option<bool>,
 
+    #[serde(default)]
+    pub endpoints: Endpoints,
+
     pub version: usize,
 }
 
+/// Which details about the instance being played are shared with friends and
+/// shown on Discord. The server and world are only shared once the user opts
+/// in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(default)]
+pub struct StatusPrivacy {
+    pub instance_name: bool,
+    pub project: bool,
+    pub game_version: bool,
+    pub loader: bool,
+    pub server: bool,
+    pub world: bool,
+    pub play_time: bool,
+}
+
+impl Default for StatusPrivacy {
+    fn default() -> Self {
+        Self {
+            instance_name: true,
+            project: true,
+            game_version: true,
+            loader: true,
+            server: false,
+            world: false,
+            play_time: true,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, Hash, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum FeatureFlag {
@@ -81,6 +122,8 @@ impl Settings {
                 hook_pre_launch, hook_wrapper, hook_post_exit,
                 custom_dir, prev_custom_dir, migrated, json(feature_flags) feature_flags, toggle_sidebar,
                 skipped_update, pending_update_toast_for_version, auto_download_updates,
+                json(endpoints) endpoints, shared_content_store,
+                json(status_privacy) status_privacy,
                 version
             FROM settings
             "
@@ -140,6 +183,17 @@ impl Settings {
             pending_update_toast_for_version: res
                 .pending_update_toast_for_version,
             auto_download_updates: res.auto_download_updates.map(|x| x == 1),
+            endpoints: res
+                .endpoints
+                .as_ref()
+                .and_then(|x| serde_json::from_str(x).ok())
+                .unwrap_or_default(),
+            shared_content_store: res.shared_content_store == 1,
+            status_privacy: res
+                .status_privacy
+                .as_ref()
+                .and_then(|x| serde_json::from_str(x).ok())
+                .unwrap_or_default(),
             version: res.version as usize,
         })
     }
@@ -155,6 +209,8 @@ impl Settings {
         let extra_launch_args = serde_json::to_string(&self.extra_launch_args)?;
         let custom_env_vars = serde_json::to_string(&self.custom_env_vars)?;
         let feature_flags = serde_json::to_string(&self.feature_flags)?;
+        let endpoints = self.endpoints.overrides()?;
+        let status_privacy = serde_json::to_string(&self.status_privacy)?;
         let version = self.version as i64;
 
         sqlx::query!(
@@ -202,7 +258,11 @@ impl Settings {
                 pending_update_toast_for_version = $31,
                 auto_download_updates = $32,
 
-                version = $33
+                endpoints = jsonb($33),
+                shared_content_store = $34,
+                status_privacy = jsonb($35),
+
+                version = $36
             ",
             max_concurrent_writes,
             max_concurrent_downloads,
@@ -236,6 +296,9 @@ impl Settings {
             self.skipped_update,
             self.pending_update_toast_for_version,
             self.auto_download_updates,
+            endpoints,
+            self.shared_content_store,
+            status_privacy,
             version,
         )
         .execute(exec)
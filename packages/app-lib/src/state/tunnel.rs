@@ -1,16 +1,190 @@
 use crate::state::FriendsSocket;
 use crate::state::friends::{TunnelSockets, WriteSocket};
-use ariadne::networking::message::ClientToServerMessage;
+use ariadne::ids::UserId;
+use ariadne::networking::message::{
+    ClientToServerMessage, SOCKET_WINDOW_SIZE, SocketCloseReason,
+};
+use serde::Serialize;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::OwnedWriteHalf;
-use tokio::sync::Mutex;
+use tokio::sync::{Semaphore, mpsc};
+use tokio::task::AbortHandle;
 use uuid::Uuid;
 
+/// How much data received from a peer without flow control may wait to be
+/// written to the local connection before the connection is closed
+const UNACKNOWLEDGED_BUFFER_LIMIT: usize = 8 * 1024 * 1024;
+
+/// How many bytes are written to the local connection before they're
+/// acknowledged to the peer. Acknowledging in batches keeps the number of
+/// messages down, and a quarter of the window leaves the peer enough credit
+/// to keep sending in the meantime
+const ACK_THRESHOLD: u32 = SOCKET_WINDOW_SIZE / 4;
+
 pub(super) enum InternalTunnelSocket {
-    Listening(SocketAddr),
-    Connected(Mutex<OwnedWriteHalf>),
+    Listening {
+        address: SocketAddr,
+        allowed_users: Option<Vec<UserId>>,
+    },
+    Connected {
+        /// Data waiting to be written to the local connection. It's written
+        /// by a separate task, so a slow local connection can't hold up the
+        /// others relayed through the friends socket
+        writer: Mutex<Option<mpsc::UnboundedSender<Vec<u8>>>>,
+        buffered: Arc<AtomicUsize>,
+        /// The hosted or joined tunnel this connection belongs to
+        tunnel: Uuid,
+        traffic: TunnelTraffic,
+        /// How many more bytes the peer is ready to receive, if the
+        /// connection uses flow control
+        send_window: Option<Semaphore>,
+    },
+}
+
+impl InternalTunnelSocket {
+    pub(super) fn connected(
+        socket_id: Uuid,
+        mut stream: OwnedWriteHalf,
+        tunnel: Uuid,
+        flow_control: bool,
+        write: WriteSocket,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+        let buffered = Arc::new(AtomicUsize::new(0));
+
+        {
+            let buffered = buffered.clone();
+            tokio::spawn(async move {
+                let mut unacknowledged = 0;
+                while let Some(data) = receiver.recv().await {
+                    if stream.write_all(&data).await.is_err() {
+                        break;
+                    }
+                    buffered.fetch_sub(data.len(), Ordering::Relaxed);
+
+                    if flow_control {
+                        unacknowledged += data.len() as u32;
+                        if unacknowledged >= ACK_THRESHOLD {
+                            let _ = FriendsSocket::send_message(
+                                &write,
+                                ClientToServerMessage::SocketAck {
+                                    socket: socket_id,
+                                    bytes: unacknowledged,
+                                },
+                            )
+                            .await;
+                            unacknowledged = 0;
+                        }
+                    }
+                }
+                let _ = stream.shutdown().await;
+            });
+        }
+
+        Self::Connected {
+            writer: Mutex::new(Some(sender)),
+            buffered,
+            tunnel,
+            traffic: TunnelTraffic::default(),
+            send_window: flow_control
+                .then(|| Semaphore::new(SOCKET_WINDOW_SIZE as usize)),
+        }
+    }
+
+    /// Queues data received from the peer to be written to the local
+    /// connection. Fails if the peer sent more than the buffer allows
+    pub(super) fn push(&self, data: Vec<u8>) -> Result<(), SocketCloseReason> {
+        let Self::Connected {
+            writer,
+            buffered,
+            traffic,
+            send_window,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        let limit = if send_window.is_some() {
+            SOCKET_WINDOW_SIZE as usize
+        } else {
+            UNACKNOWLEDGED_BUFFER_LIMIT
+        };
+        let len = data.len();
+        if buffered.fetch_add(len, Ordering::Relaxed) + len > limit {
+            return Err(if send_window.is_some() {
+                SocketCloseReason::FlowControlViolation
+            } else {
+                SocketCloseReason::BufferFull
+            });
+        }
+
+        traffic.received.fetch_add(len as u64, Ordering::Relaxed);
+        if let Some(writer) = writer.lock().unwrap().as_ref() {
+            let _ = writer.send(data);
+        }
+        Ok(())
+    }
+
+    /// Waits until the peer is ready to receive `bytes` more bytes. Returns
+    /// false if the connection was closed in the meantime
+    pub(super) async fn reserve_send(&self, bytes: usize) -> bool {
+        let Self::Connected {
+            traffic,
+            send_window,
+            ..
+        } = self
+        else {
+            return false;
+        };
+
+        if let Some(window) = send_window {
+            match window.acquire_many(bytes as u32).await {
+                Ok(permit) => permit.forget(),
+                Err(_) => return false,
+            }
+        }
+        traffic.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        true
+    }
+
+    /// Gives the sender more credit after the peer acknowledged data
+    pub(super) fn acknowledge(&self, bytes: u32) {
+        if let Self::Connected {
+            send_window: Some(window),
+            ..
+        } = self
+        {
+            window.add_permits(bytes as usize);
+        }
+    }
+
+    /// Closes the local connection once the data already received from the
+    /// peer has been written to it
+    pub(super) fn shutdown(&self) {
+        if let Self::Connected {
+            writer,
+            send_window,
+            ..
+        } = self
+        {
+            writer.lock().unwrap().take();
+            if let Some(window) = send_window {
+                window.close();
+            }
+        }
+    }
+}
+
+/// Bytes relayed through a tunnel connection, counted from the point of view
+/// of this client
+#[derive(Default)]
+pub(super) struct TunnelTraffic {
+    pub(super) sent: AtomicU64,
+    pub(super) received: AtomicU64,
 }
 
 pub struct TunnelSocket {
@@ -31,14 +205,11 @@ impl TunnelSocket {
                 &self.write,
                 ClientToServerMessage::SocketClose {
                     socket: self.socket_id,
+                    reason: Some(SocketCloseReason::Closed),
                 },
             )
             .await?;
-            if let InternalTunnelSocket::Connected(ref stream) =
-                *self.internal.clone()
-            {
-                stream.lock().await.shutdown().await?
-            }
+            self.internal.shutdown();
         }
         Ok(())
     }
@@ -52,10 +223,222 @@ impl Drop for TunnelSocket {
             tokio::spawn(async move {
                 let _ = FriendsSocket::send_message(
                     &write,
-                    ClientToServerMessage::SocketClose { socket: socket_id },
+                    ClientToServerMessage::SocketClose {
+                        socket: socket_id,
+                        reason: Some(SocketCloseReason::Closed),
+                    },
                 )
                 .await;
             });
         }
     }
 }
+
+/// A loopback listener that forwards every connection made to it to a socket
+/// a friend is listening on, so the game can join a world a friend hosts
+pub struct TunnelJoin {
+    pub(super) tunnel_id: Uuid,
+    pub(super) to_socket: Uuid,
+    pub(super) local_address: SocketAddr,
+    pub(super) accept_task: AbortHandle,
+    pub(super) write: WriteSocket,
+    pub(super) sockets: TunnelSockets,
+}
+
+impl TunnelJoin {
+    pub fn local_address(&self) -> SocketAddr {
+        self.local_address
+    }
+}
+
+impl Drop for TunnelJoin {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        tokio::spawn(close_tunnel_connections(
+            self.write.clone(),
+            self.sockets.clone(),
+            self.tunnel_id,
+        ));
+    }
+}
+
+/// A world hosted for friends, or a friend's world joined through the
+/// friends socket
+pub(super) enum OpenTunnel {
+    Hosting {
+        socket: TunnelSocket,
+        port: u16,
+    },
+    Joining {
+        join: TunnelJoin,
+        host: Option<UserId>,
+    },
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TunnelKind {
+    Hosting {
+        port: u16,
+        allowed_users: Option<Vec<UserId>>,
+    },
+    Joining {
+        to_socket: Uuid,
+        host: Option<UserId>,
+        local_address: SocketAddr,
+    },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TunnelInfo {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub kind: TunnelKind,
+    /// Whether the friends socket that relays this tunnel is connected
+    pub relay_connected: bool,
+    pub connections: Vec<TunnelConnection>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TunnelConnection {
+    pub socket: Uuid,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// A socket a friend is listening on that this user is allowed to join
+#[derive(Serialize, Debug, Clone)]
+pub struct FriendTunnel {
+    pub user: UserId,
+    pub socket: Uuid,
+}
+
+pub(super) fn tunnel_connections(
+    sockets: &TunnelSockets,
+    tunnel_id: Uuid,
+) -> Vec<TunnelConnection> {
+    sockets
+        .iter()
+        .filter_map(|x| match &**x.value() {
+            InternalTunnelSocket::Connected {
+                tunnel, traffic, ..
+            } if *tunnel == tunnel_id => Some(TunnelConnection {
+                socket: *x.key(),
+                bytes_sent: traffic.sent.load(Ordering::Relaxed),
+                bytes_received: traffic.received.load(Ordering::Relaxed),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Closes every connection relayed for a tunnel, on both ends
+pub(super) async fn close_tunnel_connections(
+    write: WriteSocket,
+    sockets: TunnelSockets,
+    tunnel_id: Uuid,
+) {
+    let connections = tunnel_connections(&sockets, tunnel_id);
+    for connection in connections {
+        if let Some((_, socket)) = sockets.remove(&connection.socket) {
+            let _ = FriendsSocket::send_message(
+                &write,
+                ClientToServerMessage::SocketClose {
+                    socket: connection.socket,
+                    reason: Some(SocketCloseReason::Closed),
+                },
+            )
+            .await;
+            socket.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::time::timeout;
+
+    fn env(name: &str) -> String {
+        std::env::var(name).unwrap_or_else(|_| panic!("{name} must be set"))
+    }
+
+    /// Run against a local labrinth with
+    /// `FRIENDS_TEST_SOCKET_URL=ws://127.0.0.1:8000/` and the sessions of two
+    /// users who are friends in `FRIENDS_TEST_HOST_SESSION` and
+    /// `FRIENDS_TEST_GUEST_SESSION`
+    #[tokio::test]
+    #[ignore = "requires a local labrinth and two users who are friends"]
+    async fn relays_hosted_world_to_friend() {
+        let url = env("FRIENDS_TEST_SOCKET_URL");
+        let host = FriendsSocket::new();
+        host.connect_to(&url, &env("FRIENDS_TEST_HOST_SESSION"))
+            .await
+            .unwrap();
+        let guest = FriendsSocket::new();
+        guest
+            .connect_to(&url, &env("FRIENDS_TEST_GUEST_SESSION"))
+            .await
+            .unwrap();
+
+        // An echo server stands in for the world opened to LAN
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = server.accept().await {
+                tokio::spawn(async move {
+                    let (mut read, mut write) = stream.split();
+                    let _ = tokio::io::copy(&mut read, &mut write).await;
+                });
+            }
+        });
+
+        let hosted = host.host_world(port, None).await.unwrap();
+        timeout(Duration::from_secs(5), async {
+            while !guest.friend_tunnels().iter().any(|x| x.socket == hosted.id)
+            {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("guest was never told about the hosted world");
+
+        let joined = guest.join_world(hosted.id).await.unwrap();
+        let TunnelKind::Joining { local_address, .. } = joined.kind else {
+            panic!("joined tunnel is not joining");
+        };
+
+        let messages: [&[u8]; 2] = [b"first client", b"second client"];
+        let mut clients = Vec::new();
+        for message in messages {
+            let mut client = TcpStream::connect(local_address).await.unwrap();
+            client.write_all(message).await.unwrap();
+            clients.push((client, message));
+        }
+        for (client, message) in &mut clients {
+            let mut buffer = vec![0; message.len()];
+            timeout(Duration::from_secs(5), client.read_exact(&mut buffer))
+                .await
+                .expect("timed out waiting for the echo")
+                .unwrap();
+            assert_eq!(buffer, *message);
+        }
+
+        let joined = guest.tunnel(joined.id).await.unwrap();
+        assert!(joined.relay_connected);
+        assert_eq!(joined.connections.len(), 2);
+        assert!(
+            joined.connections.iter().all(|x| {
+                x.bytes_sent == x.bytes_received && x.bytes_sent > 0
+            })
+        );
+        let hosted = host.tunnel(hosted.id).await.unwrap();
+        assert_eq!(hosted.connections.len(), 2);
+
+        guest.close_tunnel(joined.id).await.unwrap();
+        host.close_tunnel(hosted.id).await.unwrap();
+    }
+}
@@ -3,6 +3,7 @@ use super::io::{self, IOError};
 use crate::ErrorKind;
 use crate::event::LoadingBarId;
 use crate::event::emit::emit_loading;
+use crate::state::Endpoints;
 use bytes::Bytes;
 use chrono::{DateTime, TimeDelta, Utc};
 use parking_lot::Mutex;
@@ -244,6 +245,64 @@ where
     Ok(value)
 }
 
+/// Fetches JSON from the launcher metadata server. The file must have a
+/// detached signature made with one of the keys the endpoints trust.
+///
+/// Without trusted keys, metadata is used unsigned from self-hosted metadata
+/// servers, and from the default one only if the app was built with
+/// `MODRINTH_LAUNCHER_META_UNSIGNED`
+#[tracing::instrument(skip(semaphore, exec))]
+pub async fn fetch_meta_json<T>(
+    url: &str,
+    semaphore: &FetchSemaphore,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Sqlite> + Copy,
+) -> crate::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let endpoints = Endpoints::current();
+    if endpoints.launcher_meta_keys.is_empty() {
+        if endpoints.meta_cache_namespace().is_none()
+            && option_env!("MODRINTH_LAUNCHER_META_UNSIGNED") != Some("true")
+        {
+            return Err(daedalus::Error::SignatureError(format!(
+                "No keys are trusted to verify {url}"
+            ))
+            .into());
+        }
+
+        let result = fetch(url, None, None, semaphore, exec).await?;
+        return Ok(serde_json::from_slice(&result)?);
+    }
+
+    let path =
+        url.strip_prefix(&endpoints.launcher_meta_url)
+            .ok_or_else(|| {
+                daedalus::Error::SignatureError(format!(
+                    "{url} is not on the metadata server"
+                ))
+            })?;
+    let result = fetch(url, None, None, semaphore, exec).await?;
+    let signature = fetch_json(
+        Method::GET,
+        &format!("{url}{}", daedalus::signing::SIGNATURE_SUFFIX),
+        None,
+        None,
+        semaphore,
+        exec,
+    )
+    .await?;
+
+    daedalus::signing::verify(
+        &endpoints.launcher_meta_keys,
+        path,
+        &result,
+        &signature,
+    )?;
+
+    Ok(serde_json::from_slice(&result)?)
+}
+
 /// Downloads a file with retry and checksum functionality, and a specific
 /// [`reqwest::Client`].
 #[tracing::instrument(skip(json_body, semaphore))]
@@ -274,7 +333,8 @@ pub async fn fetch_advanced(
     .await
 }
 
-/// Downloads a file with retry and checksum functionality
+/// Downloads a file with retry and checksum functionality. If the download
+/// fails, the mirrors configured for the URL are tried in order
 #[tracing::instrument(skip(json_body, semaphore))]
 #[allow(clippy::too_many_arguments)]
 pub async fn fetch_advanced_with_client(
@@ -291,13 +351,16 @@ pub async fn fetch_advanced_with_client(
 ) -> crate::Result<Bytes> {
     let _permit = semaphore.0.acquire().await?;
 
-    let is_api_url = url.starts_with(env!("MODRINTH_API_URL"))
-        || url.starts_with(env!("MODRINTH_API_URL_V3"));
+    let endpoints = Endpoints::current();
+    let urls = endpoints.mirrored_urls(url);
+    let sends_credentials = |url: &str| {
+        url.starts_with("https://cdn.modrinth.com") || endpoints.is_api_url(url)
+    };
 
     let creds = if header
         .as_ref()
         .is_none_or(|x| &*x.0.to_lowercase() != "authorization")
-        && (url.starts_with("https://cdn.modrinth.com") || is_api_url)
+        && urls.iter().any(|x| sends_credentials(x.as_str()))
     {
         crate::state::ModrinthCredentials::get_active(exec).await?
     } else {
@@ -307,6 +370,53 @@ pub async fn fetch_advanced_with_client(
     let download_meta_header = download_meta
         .map(|m| (DOWNLOAD_META_HEADER.to_string(), m.to_header_value()));
 
+    let mut result = Err(ErrorKind::NoValueFor(url.to_string()).into());
+    for (index, url) in urls.iter().enumerate() {
+        if index > 0 {
+            tracing::warn!(
+                "Download failed, trying mirror {url}: {}",
+                result
+                    .as_ref()
+                    .err()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            );
+        }
+
+        result = fetch_with_retries(
+            &method,
+            url,
+            sha1,
+            json_body.as_ref(),
+            header,
+            creds.as_ref().filter(|_| sends_credentials(url.as_str())),
+            download_meta_header.as_ref(),
+            loading_bar,
+            endpoints.is_api_url(url),
+            client,
+        )
+        .await;
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fetch_with_retries(
+    method: &Method,
+    url: &str,
+    sha1: Option<&str>,
+    json_body: Option<&serde_json::Value>,
+    header: Option<(&str, &str)>,
+    creds: Option<&crate::state::ModrinthCredentials>,
+    download_meta_header: Option<&(String, String)>,
+    loading_bar: Option<(&LoadingBarId, f64)>,
+    is_api_url: bool,
+    client: &reqwest::Client,
+) -> crate::Result<Bytes> {
     for attempt in 1..=(FETCH_ATTEMPTS + 1) {
         if is_api_url && GLOBAL_FETCH_FENCE.is_blocked() {
             return Err(ErrorKind::ApiIsDownError.into());
@@ -314,19 +424,19 @@ pub async fn fetch_advanced_with_client(
 
         let mut req = client.request(method.clone(), url);
 
-        if let Some(body) = json_body.clone() {
-            req = req.json(&body);
+        if let Some(body) = json_body {
+            req = req.json(body);
         }
 
         if let Some(header) = header {
             req = req.header(header.0, header.1);
         }
 
-        if let Some(ref creds) = creds {
+        if let Some(creds) = creds {
             req = req.header("Authorization", &creds.session);
         }
 
-        if let Some((name, value)) = &download_meta_header {
+        if let Some((name, value)) = download_meta_header {
             tracing::info!("Sending download analytics: {value}");
             req = req.header(name.as_str(), value.as_str());
         }
@@ -354,6 +354,11 @@ pub enum JREError {
     #[error("Join error: {0}")]
     JoinError(#[from] JoinError),
 
+    #[error(
+        "Checksum mismatch for Java download: expected {expected}, got {actual}"
+    )]
+    ChecksumMismatch { expected: String, actual: String },
+
     #[error("No stored tag for Minecraft version {0}")]
     NoMinecraftVersionFound(String),
 
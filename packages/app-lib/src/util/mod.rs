@@ -7,3 +7,4 @@ pub mod platform;
 pub mod protocol_version;
 pub mod rpc;
 pub mod server_ping;
+pub mod ttl_cache;
@@ -31,14 +31,25 @@ impl RpcServerBuilder {
         }
     }
 
-    // We'll use this function in the future. Please remove this #[allow] when we do.
-    #[allow(dead_code)]
-    pub fn handler(
+    pub fn handler<F, Fut, R>(
         mut self,
         function_name: &'static str,
-        handler: HandlerMethod,
-    ) -> Self {
-        self.handlers.insert(function_name, Box::new(handler));
+        handler: F,
+    ) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Vec<Value>) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<R>>,
+        R: Serialize,
+    {
+        self.handlers.insert(
+            function_name,
+            Box::new(move |args| {
+                let future = handler(args);
+                Box::pin(
+                    async move { Ok(serde_json::to_value(future.await?)?) },
+                )
+            }),
+        );
         self
     }
 
@@ -71,6 +82,18 @@ impl RpcServerBuilder {
     }
 }
 
+/// Deserializes the argument at `index` of an RPC call. Missing arguments are
+/// treated as `null`, so trailing optional arguments can be omitted
+pub fn parse_arg<T: DeserializeOwned>(
+    args: &[Value],
+    index: usize,
+) -> Result<T> {
+    let value = args.get(index).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| {
+        ErrorKind::RpcError(format!("Invalid argument {index}: {e}")).into()
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcServer {
     address: SocketAddr,
@@ -0,0 +1,61 @@
+//! Small in-memory cache whose entries expire after a time-to-live
+use dashmap::DashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+pub struct TtlCache<K, V> {
+    entries: DashMap<K, (V, Instant)>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Gets the value cached for `key`, if it hasn't expired yet
+    pub fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
+        let value = self
+            .entries
+            .get(key)
+            .filter(|entry| entry.1 > now)
+            .map(|entry| entry.0.clone());
+        if value.is_none() {
+            self.entries
+                .remove_if(key, |_, (_, expires)| *expires <= now);
+        }
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V, ttl: Duration) {
+        self.insert_until(key, value, Instant::now() + ttl);
+    }
+
+    pub fn insert_until(&self, key: K, value: V, expires: Instant) {
+        self.entries.insert(key, (value, expires));
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> Default for TtlCache<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire() {
+        let cache = TtlCache::new();
+        cache.insert("fresh", 1, Duration::from_secs(60));
+        cache.insert_until("stale", 2, Instant::now());
+
+        assert_eq!(cache.get(&"fresh"), Some(1));
+        assert_eq!(cache.get(&"stale"), None);
+        assert_eq!(cache.get(&"missing"), None);
+    }
+}
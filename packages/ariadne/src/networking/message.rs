@@ -1,21 +1,74 @@
 use crate::ids::UserId;
-use crate::users::UserStatus;
+use crate::users::{UserActivity, UserStatus};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// How many bytes of a tunnel connection may be sent before the receiver
+/// acknowledges them with a `SocketAck`, if both ends support flow control
+pub const SOCKET_WINDOW_SIZE: u32 = 256 * 1024;
+
+/// The largest chunk of data a single `SocketSend` may carry
+pub const MAX_SOCKET_DATA_SIZE: usize = 64 * 1024;
+
+/// Why a tunnel connection was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketCloseReason {
+    /// The other end closed the connection
+    Closed,
+    /// The socket isn't listening, or the user isn't allowed to connect to it
+    Refused,
+    /// The other end disconnected from the friends socket
+    Disconnected,
+    /// More data was sent than the receiver acknowledged
+    FlowControlViolation,
+    /// A message was larger than [`MAX_SOCKET_DATA_SIZE`]
+    MessageTooLarge,
+    /// The receiver could not keep up with the data sent to it
+    BufferFull,
+    /// A reason this version doesn't know about
+    #[serde(other)]
+    Other,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientToServerMessage {
     StatusUpdate {
         profile_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        activity: Option<UserActivity>,
     },
 
+    /// Starts listening on a tunnel socket that friends can connect to. If
+    /// `allowed_users` is set, only those friends are told about the socket
+    /// and allowed to connect to it
     SocketListen {
         socket: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        allowed_users: Option<Vec<UserId>>,
+        /// Whether this client acknowledges received data with `SocketAck`
+        #[serde(default)]
+        flow_control: bool,
+    },
+    /// Connects to a socket a friend is listening on. `new_socket` is the ID
+    /// picked by the client for its end of the connection. If the connection
+    /// is refused, the server responds with [`ServerToClientMessage::SocketClosed`]
+    /// for `new_socket` with [`SocketCloseReason::Refused`]
+    SocketConnect {
+        to_socket: Uuid,
+        new_socket: Uuid,
+        #[serde(default)]
+        flow_control: bool,
     },
     SocketClose {
         socket: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<SocketCloseReason>,
     },
+    /// Acknowledges that `bytes` bytes received on `socket` were consumed, so
+    /// the other end may send that many more
+    SocketAck { socket: Uuid, bytes: u32 },
     SocketSend {
         socket: Uuid,
         #[serde(with = "serde_bytes")]
@@ -45,17 +98,33 @@ pub enum ServerToClientMessage {
     FriendSocketListening {
         user: UserId,
         socket: Uuid,
+        #[serde(default)]
+        flow_control: bool,
     },
     FriendSocketStoppedListening {
         user: UserId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        socket: Option<Uuid>,
     },
 
+    /// A friend connected to `to_socket`. `flow_control` is set if both ends
+    /// support it, in which case data sent on `new_socket` must stay within
+    /// [`SOCKET_WINDOW_SIZE`] of what was acknowledged
     SocketConnected {
         to_socket: Uuid,
         new_socket: Uuid,
+        #[serde(default)]
+        flow_control: bool,
     },
     SocketClosed {
         socket: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<SocketCloseReason>,
+    },
+    /// The other end of `socket` consumed `bytes` more bytes
+    SocketAck {
+        socket: Uuid,
+        bytes: u32,
     },
     SocketData {
         socket: Uuid,
@@ -63,3 +132,56 @@ pub enum ServerToClientMessage {
         data: Vec<u8>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use either::Either;
+
+    #[test]
+    fn messages_from_clients_without_flow_control() {
+        let socket = Uuid::new_v4();
+
+        let message = ClientToServerMessage::deserialize(Either::Left(
+            format!(r#"{{"type":"socket_listen","socket":"{socket}"}}"#)
+                .as_str(),
+        ))
+        .unwrap();
+        assert!(matches!(
+            message,
+            ClientToServerMessage::SocketListen {
+                allowed_users: None,
+                flow_control: false,
+                ..
+            }
+        ));
+
+        let message = ClientToServerMessage::deserialize(Either::Left(
+            format!(r#"{{"type":"socket_close","socket":"{socket}"}}"#)
+                .as_str(),
+        ))
+        .unwrap();
+        assert!(matches!(
+            message,
+            ClientToServerMessage::SocketClose { reason: None, .. }
+        ));
+    }
+
+    #[test]
+    fn unknown_close_reason() {
+        let socket = Uuid::new_v4();
+        let message = ServerToClientMessage::deserialize(Either::Left(
+            format!(
+                r#"{{"type":"socket_closed","socket":"{socket}","reason":"something_new"}}"#
+            ).as_str(),
+        ))
+        .unwrap();
+        assert!(matches!(
+            message,
+            ServerToClientMessage::SocketClosed {
+                reason: Some(SocketCloseReason::Other),
+                ..
+            }
+        ));
+    }
+}
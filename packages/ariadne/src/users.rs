@@ -6,5 +6,54 @@ use serde::{Deserialize, Serialize};
 pub struct UserStatus {
     pub user_id: UserId,
     pub profile_name: Option<String>,
+    /// What the user is currently doing in the game, limited to what they
+    /// chose to share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<UserActivity>,
     pub last_update: DateTime<Utc>,
 }
+
+/// Details about the instance a user is playing. Every field is optional, as
+/// users can choose which of them are shared with friends
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct UserActivity {
+    /// The Modrinth project the instance was installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    /// The version of the linked project the instance is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    /// The address of the server the user joined, as `host` or `host:port`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// The name of the singleplayer world the user is playing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world: Option<String>,
+    /// When the game was started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<DateTime<Utc>>,
+}
+
+impl UserActivity {
+    /// The longest any of the text fields may be
+    pub const MAX_FIELD_LENGTH: usize = 255;
+
+    /// Whether every text field is within [`Self::MAX_FIELD_LENGTH`]
+    pub fn is_valid(&self) -> bool {
+        [
+            &self.project_id,
+            &self.version_id,
+            &self.game_version,
+            &self.loader,
+            &self.server,
+            &self.world,
+        ]
+        .into_iter()
+        .flatten()
+        .all(|x| x.len() <= Self::MAX_FIELD_LENGTH)
+    }
+}
@@ -0,0 +1,385 @@
+//! This module defines a client for the RakNet
+//! [unconnected ping](https://wiki.vg/Raknet_Protocol#Unconnected_Ping)
+//! used by Minecraft: Bedrock Edition servers to report their status
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::server::ServerError;
+use crate::udp::connect_udp;
+
+const UNCONNECTED_PING_ID: u8 = 0x01;
+const UNCONNECTED_PONG_ID: u8 = 0x1c;
+
+/// Magic bytes which every offline RakNet message carries.
+const OFFLINE_MESSAGE_MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+const DEFAULT_PORT: u16 = 19132;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pongs are a single UDP datagram, which never exceeds the RakNet MTU.
+const MAX_PONG_SIZE: usize = 1500;
+
+/// The decoded status of a Bedrock server, as advertised
+/// in its unconnected pong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedrockStatus {
+    /// The edition of the server, i.e. "MCPE", or "MCEE"
+    /// for Education Edition.
+    pub edition: String,
+
+    /// The first line of the server's MOTD.
+    pub motd: String,
+
+    /// The server's network protocol version.
+    pub protocol: i32,
+
+    /// The server's Minecraft version, i.e. "1.21.50".
+    pub version: String,
+
+    /// The number of players currently online.
+    pub players_online: i32,
+
+    /// The configured maximum number of players for the
+    /// server.
+    pub players_max: i32,
+
+    /// The server's unique ID, generated on startup.
+    pub server_guid: u64,
+
+    /// The second line of the server's MOTD, usually
+    /// the level name.
+    pub sub_motd: Option<String>,
+
+    /// The server's default game mode, i.e. "Survival".
+    pub game_mode: Option<String>,
+
+    /// The numeric ID of the server's default game mode.
+    pub game_mode_id: Option<i32>,
+
+    /// The port the server listens on over IPv4.
+    pub port_v4: Option<u16>,
+
+    /// The port the server listens on over IPv6.
+    pub port_v6: Option<u16>,
+}
+
+impl BedrockStatus {
+    /// Parses the semicolon-separated server ID string
+    /// sent in an unconnected pong.
+    fn parse(server_id: &str) -> Result<Self, ServerError> {
+        let invalid = || ServerError::InvalidBedrockStatus(server_id.to_string());
+
+        let mut fields = server_id.split(';');
+        let mut required = || fields.next().ok_or_else(invalid);
+
+        let edition = required()?.to_string();
+        let motd = required()?.to_string();
+        let protocol = required()?.parse().map_err(|_| invalid())?;
+        let version = required()?.to_string();
+        let players_online = required()?.parse().map_err(|_| invalid())?;
+        let players_max = required()?.parse().map_err(|_| invalid())?;
+
+        // Everything after the player counts was added in later versions,
+        // and third-party servers commonly leave it out
+        let mut optional = || fields.next().filter(|field| !field.is_empty());
+
+        let server_guid = optional().and_then(|x| x.parse().ok()).unwrap_or(0);
+        let sub_motd = optional().map(str::to_string);
+        let game_mode = optional().map(str::to_string);
+        let game_mode_id = optional().and_then(|x| x.parse().ok());
+        let port_v4 = optional().and_then(|x| x.parse().ok());
+        let port_v6 = optional().and_then(|x| x.parse().ok());
+
+        Ok(BedrockStatus {
+            edition,
+            motd,
+            protocol,
+            version,
+            players_online,
+            players_max,
+            server_guid,
+            sub_motd,
+            game_mode,
+            game_mode_id,
+            port_v4,
+            port_v6,
+        })
+    }
+}
+
+/// Builder for a Minecraft Bedrock
+/// unconnected ping.
+pub struct BedrockConfig {
+    address: String,
+    port: u16,
+    timeout: Duration,
+}
+
+impl BedrockConfig {
+    /// Initiates the Bedrock server
+    /// ping build process.
+    pub fn build<T: Into<String>>(address: T) -> Self {
+        BedrockConfig {
+            address: address.into(),
+            port: DEFAULT_PORT,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets a specific port for the
+    /// ping to use. If not specified, the
+    /// default port of 19132 will be used.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets a specific timeout for the
+    /// ping to use. If not specified, the
+    /// timeout defaults to two seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends an unconnected ping to the server and
+    /// consumes the builder.
+    pub async fn ping(self) -> Result<BedrockStatus, ServerError> {
+        tokio::time::timeout(self.timeout, self.ping_inner())
+            .await
+            .map_err(|_| ServerError::ConnectionTimedOut)?
+    }
+
+    async fn ping_inner(&self) -> Result<BedrockStatus, ServerError> {
+        let socket = connect_udp(&self.address, self.port).await?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_millis() as u64)
+            .unwrap_or_default();
+        let client_guid = (u64::from(std::process::id()) << 32) | (timestamp & 0xFFFF_FFFF);
+
+        socket
+            .send(&unconnected_ping(timestamp, client_guid))
+            .await
+            .map_err(|_| ServerError::ProtocolError)?;
+
+        // Ignore anything that isn't a pong to this ping, such as
+        // late replies to earlier pings from the same port
+        let mut buffer = [0; MAX_PONG_SIZE];
+        loop {
+            let len = socket
+                .recv(&mut buffer)
+                .await
+                .map_err(|_| ServerError::ProtocolError)?;
+
+            if let Some(server_id) = read_unconnected_pong(&buffer[..len], timestamp)? {
+                return BedrockStatus::parse(&server_id);
+            }
+        }
+    }
+}
+
+/// Convenience wrapper for easily pinging
+/// a Bedrock server on the default port.
+pub async fn ping_bedrock(address: String) -> Result<BedrockStatus, ServerError> {
+    BedrockConfig::build(address).ping().await
+}
+
+fn unconnected_ping(timestamp: u64, client_guid: u64) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(1 + 8 + OFFLINE_MESSAGE_MAGIC.len() + 8);
+    packet.push(UNCONNECTED_PING_ID);
+    packet.extend_from_slice(&timestamp.to_be_bytes());
+    packet.extend_from_slice(&OFFLINE_MESSAGE_MAGIC);
+    packet.extend_from_slice(&client_guid.to_be_bytes());
+    packet
+}
+
+/// Reads the server ID string out of an unconnected pong.
+///
+/// Returns `None` if the datagram isn't an unconnected pong,
+/// or is a pong to a different ping.
+fn read_unconnected_pong(packet: &[u8], timestamp: u64) -> Result<Option<String>, ServerError> {
+    const HEADER_LEN: usize = 1 + 8 + 8 + OFFLINE_MESSAGE_MAGIC.len() + 2;
+
+    if packet.len() < HEADER_LEN
+        || packet[0] != UNCONNECTED_PONG_ID
+        || packet[17..33] != OFFLINE_MESSAGE_MAGIC
+    {
+        return Ok(None);
+    }
+
+    let mut pong_timestamp = [0; 8];
+    pong_timestamp.copy_from_slice(&packet[1..9]);
+    if u64::from_be_bytes(pong_timestamp) != timestamp {
+        return Ok(None);
+    }
+
+    let len = u16::from_be_bytes([packet[33], packet[34]]) as usize;
+    let server_id = packet
+        .get(HEADER_LEN..HEADER_LEN + len)
+        .ok_or(ServerError::ProtocolError)?;
+
+    Ok(Some(String::from_utf8_lossy(server_id).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::UdpSocket;
+
+    use super::*;
+
+    const SERVER_ID: &str =
+        "MCPE;Dedicated Server;766;1.21.50;3;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;";
+
+    /// Binds a local stand-in server that answers a single
+    /// unconnected ping with the given server ID string, after
+    /// sending the given stray datagrams.
+    async fn stand_in_server(server_id: &'static str, stray: &'static [&'static [u8]]) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let mut buffer = [0; MAX_PONG_SIZE];
+            let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+            assert_eq!(len, 33);
+            assert_eq!(buffer[0], UNCONNECTED_PING_ID);
+            assert_eq!(buffer[9..25], OFFLINE_MESSAGE_MAGIC);
+
+            for datagram in stray {
+                socket.send_to(datagram, peer).await.unwrap();
+            }
+
+            let mut pong = vec![UNCONNECTED_PONG_ID];
+            pong.extend_from_slice(&buffer[1..9]);
+            pong.extend_from_slice(&0x1234u64.to_be_bytes());
+            pong.extend_from_slice(&OFFLINE_MESSAGE_MAGIC);
+            pong.extend_from_slice(&(server_id.len() as u16).to_be_bytes());
+            pong.extend_from_slice(server_id.as_bytes());
+            socket.send_to(&pong, peer).await.unwrap();
+        });
+
+        port
+    }
+
+    #[test]
+    fn test_parse_full_status() {
+        let status = BedrockStatus::parse(SERVER_ID).unwrap();
+        assert_eq!(status.edition, "MCPE");
+        assert_eq!(status.motd, "Dedicated Server");
+        assert_eq!(status.protocol, 766);
+        assert_eq!(status.version, "1.21.50");
+        assert_eq!(status.players_online, 3);
+        assert_eq!(status.players_max, 10);
+        assert_eq!(status.server_guid, 13253860892328930865);
+        assert_eq!(status.sub_motd.as_deref(), Some("Bedrock level"));
+        assert_eq!(status.game_mode.as_deref(), Some("Survival"));
+        assert_eq!(status.game_mode_id, Some(1));
+        assert_eq!(status.port_v4, Some(19132));
+        assert_eq!(status.port_v6, Some(19133));
+    }
+
+    #[test]
+    fn test_parse_minimal_status() {
+        let status = BedrockStatus::parse("MCPE;A Server;390;1.14.60;0;20").unwrap();
+        assert_eq!(status.players_max, 20);
+        assert_eq!(status.server_guid, 0);
+        assert!(status.sub_motd.is_none());
+        assert!(status.game_mode.is_none());
+        assert!(status.port_v4.is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_status() {
+        assert!(matches!(
+            BedrockStatus::parse("MCPE;A Server;not a number;1.21.50;0;20"),
+            Err(ServerError::InvalidBedrockStatus(_))
+        ));
+        assert!(matches!(
+            BedrockStatus::parse("MCPE;A Server"),
+            Err(ServerError::InvalidBedrockStatus(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_pong_for_other_ping() {
+        let mut pong = vec![UNCONNECTED_PONG_ID];
+        pong.extend_from_slice(&1u64.to_be_bytes());
+        pong.extend_from_slice(&0u64.to_be_bytes());
+        pong.extend_from_slice(&OFFLINE_MESSAGE_MAGIC);
+        pong.extend_from_slice(&0u16.to_be_bytes());
+
+        assert_eq!(
+            read_unconnected_pong(&pong, 1).unwrap(),
+            Some(String::new())
+        );
+        assert_eq!(read_unconnected_pong(&pong, 2).unwrap(), None);
+        assert_eq!(read_unconnected_pong(&pong[..20], 1).unwrap(), None);
+
+        // A pong to this ping with a truncated server ID
+        pong[34] = 1;
+        assert!(read_unconnected_pong(&pong, 1).is_err());
+    }
+
+    #[test]
+    fn test_read_other_packets() {
+        let ping = unconnected_ping(1, 2);
+        assert_eq!(read_unconnected_pong(&ping, 1).unwrap(), None);
+
+        let mut packet = vec![0x84];
+        packet.extend_from_slice(&[0; 40]);
+        assert_eq!(read_unconnected_pong(&packet, 1).unwrap(), None);
+        assert_eq!(read_unconnected_pong(&[], 1).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_ping_stand_in_server() {
+        let port = stand_in_server(SERVER_ID, &[]).await;
+
+        let status = BedrockConfig::build("127.0.0.1")
+            .with_port(port)
+            .ping()
+            .await
+            .unwrap();
+        assert_eq!(status.motd, "Dedicated Server");
+        assert_eq!(status.players_online, 3);
+        assert_eq!(status.game_mode.as_deref(), Some("Survival"));
+    }
+
+    #[tokio::test]
+    async fn test_ping_ignores_stray_datagrams() {
+        const STRAY: &[&[u8]] = &[b"not a pong", &[UNCONNECTED_PONG_ID; 40]];
+        let port = stand_in_server(SERVER_ID, STRAY).await;
+
+        let status = BedrockConfig::build("127.0.0.1")
+            .with_port(port)
+            .ping()
+            .await
+            .unwrap();
+        assert_eq!(status.motd, "Dedicated Server");
+    }
+
+    #[tokio::test]
+    async fn test_ping_timeout() {
+        // Bound but never answers
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        let result = BedrockConfig::build("127.0.0.1")
+            .with_port(port)
+            .with_timeout(Duration::from_millis(100))
+            .ping()
+            .await;
+        assert!(matches!(result, Err(ServerError::ConnectionTimedOut)));
+    }
+
+    #[test]
+    fn test_bedrock_config_defaults() {
+        let config = BedrockConfig::build("localhost");
+        assert_eq!(config.address, "localhost");
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.timeout, DEFAULT_TIMEOUT);
+    }
+}
@@ -0,0 +1,223 @@
+//! This module defines a client for the
+//! [legacy server list ping](https://wiki.vg/Server_List_Ping#1.6)
+//! answered by servers older than 1.7
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::server::ServerError;
+
+const LEGACY_PING_ID: u8 = 0xfe;
+const LEGACY_PING_PAYLOAD: u8 = 0x01;
+const PLUGIN_MESSAGE_ID: u8 = 0xfa;
+const KICK_ID: u8 = 0xff;
+
+const PING_CHANNEL: &str = "MC|PingHost";
+
+/// Protocol version sent in the 1.6 ping. Servers don't
+/// reject pings with a different version.
+const LEGACY_PROTOCOL_VERSION: u8 = 74;
+
+/// Prefix of the response sent by 1.4 to 1.6 servers.
+const RESPONSE_PREFIX: &str = "\u{a7}1\0";
+
+/// The kick message is a Minecraft string, so it's never
+/// longer than this many UTF-16 code units.
+const MAX_RESPONSE_LENGTH: usize = 32_767;
+
+/// The decoded response to a legacy ping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyStatus {
+    /// The server's protocol version. Not sent by servers
+    /// older than 1.4.
+    pub protocol: Option<i32>,
+
+    /// The server's Minecraft version, i.e. "1.6.4". Not sent
+    /// by servers older than 1.4.
+    pub version: Option<String>,
+
+    /// The server's MOTD.
+    pub motd: String,
+
+    /// The number of players currently online.
+    pub players_online: i32,
+
+    /// The configured maximum number of players for the
+    /// server.
+    pub players_max: i32,
+}
+
+impl LegacyStatus {
+    /// Parses the kick message servers answer a legacy
+    /// ping with.
+    fn parse(response: &str) -> Result<Self, ServerError> {
+        let invalid = || ServerError::InvalidLegacyStatus(response.to_string());
+
+        // 1.4 to 1.6: §1\0protocol\0version\0motd\0online\0max
+        if let Some(fields) = response.strip_prefix(RESPONSE_PREFIX) {
+            let fields = fields.split('\0').collect::<Vec<_>>();
+            let [protocol, version, motd, online, max] = fields[..] else {
+                return Err(invalid());
+            };
+
+            return Ok(LegacyStatus {
+                protocol: Some(protocol.parse().map_err(|_| invalid())?),
+                version: Some(version.to_string()),
+                motd: motd.to_string(),
+                players_online: online.parse().map_err(|_| invalid())?,
+                players_max: max.parse().map_err(|_| invalid())?,
+            });
+        }
+
+        // Beta 1.8 to 1.3: motd§online§max, where the MOTD
+        // may contain § itself
+        let mut fields = response.rsplitn(3, '\u{a7}');
+        let max = fields.next().ok_or_else(invalid)?;
+        let online = fields.next().ok_or_else(invalid)?;
+        let motd = fields.next().ok_or_else(invalid)?;
+
+        Ok(LegacyStatus {
+            protocol: None,
+            version: None,
+            motd: motd.to_string(),
+            players_online: online.parse().map_err(|_| invalid())?,
+            players_max: max.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Builds the 1.6 ping. Older servers stop reading after
+/// the first bytes they understand, so it's answered by
+/// every legacy version.
+fn legacy_ping(address: &str, port: u16) -> Vec<u8> {
+    let host = utf16_be(address);
+
+    let mut packet = vec![LEGACY_PING_ID, LEGACY_PING_PAYLOAD, PLUGIN_MESSAGE_ID];
+    packet.extend_from_slice(&(PING_CHANNEL.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&utf16_be(PING_CHANNEL));
+    packet.extend_from_slice(&(7 + host.len() as u16).to_be_bytes());
+    packet.push(LEGACY_PROTOCOL_VERSION);
+    packet.extend_from_slice(&((host.len() / 2) as u16).to_be_bytes());
+    packet.extend_from_slice(&host);
+    packet.extend_from_slice(&i32::from(port).to_be_bytes());
+    packet
+}
+
+fn utf16_be(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// Sends a legacy ping over the stream and reads the
+/// server's response.
+pub(crate) async fn legacy_status<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    address: &str,
+    port: u16,
+) -> Result<LegacyStatus, ServerError> {
+    stream
+        .write_all(&legacy_ping(address, port))
+        .await
+        .map_err(|_| ServerError::ProtocolError)?;
+
+    let id = stream
+        .read_u8()
+        .await
+        .map_err(|_| ServerError::ProtocolError)?;
+    if id != KICK_ID {
+        return Err(ServerError::ProtocolError);
+    }
+
+    let length = stream
+        .read_u16()
+        .await
+        .map_err(|_| ServerError::ProtocolError)? as usize;
+    if length > MAX_RESPONSE_LENGTH {
+        return Err(ServerError::ProtocolError);
+    }
+
+    let mut buffer = vec![0; length * 2];
+    stream
+        .read_exact(&mut buffer)
+        .await
+        .map_err(|_| ServerError::ProtocolError)?;
+
+    let units = buffer
+        .chunks_exact(2)
+        .map(|x| u16::from_be_bytes([x[0], x[1]]))
+        .collect::<Vec<_>>();
+
+    LegacyStatus::parse(&String::from_utf16_lossy(&units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kick_packet(message: &str) -> Vec<u8> {
+        let mut packet = vec![KICK_ID];
+        packet.extend_from_slice(&(message.encode_utf16().count() as u16).to_be_bytes());
+        packet.extend_from_slice(&utf16_be(message));
+        packet
+    }
+
+    #[test]
+    fn test_parse_1_6_status() {
+        let status =
+            LegacyStatus::parse("\u{a7}1\x0078\x001.6.4\x00A Minecraft Server\x003\x0020").unwrap();
+        assert_eq!(status.protocol, Some(78));
+        assert_eq!(status.version.as_deref(), Some("1.6.4"));
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert_eq!(status.players_online, 3);
+        assert_eq!(status.players_max, 20);
+    }
+
+    #[test]
+    fn test_parse_beta_status() {
+        let status = LegacyStatus::parse("\u{a7}cA \u{a7}lServer\u{a7}0\u{a7}10").unwrap();
+        assert_eq!(status.protocol, None);
+        assert_eq!(status.version, None);
+        assert_eq!(status.motd, "\u{a7}cA \u{a7}lServer");
+        assert_eq!(status.players_online, 0);
+        assert_eq!(status.players_max, 10);
+    }
+
+    #[test]
+    fn test_parse_invalid_status() {
+        assert!(LegacyStatus::parse("\u{a7}1\x0078\x001.6.4").is_err());
+        assert!(LegacyStatus::parse("A Minecraft Server").is_err());
+        assert!(LegacyStatus::parse("A Minecraft Server\u{a7}three\u{a7}20").is_err());
+    }
+
+    #[test]
+    fn test_legacy_ping_serialization() {
+        let packet = legacy_ping("localhost", 25565);
+        assert_eq!(packet[..3], [0xfe, 0x01, 0xfa]);
+        assert_eq!(packet[3..5], [0x00, 0x0b]);
+        assert_eq!(packet[5..27], utf16_be("MC|PingHost")[..]);
+        // protocol version, host and port
+        assert_eq!(packet[27..29], (7u16 + 18).to_be_bytes());
+        assert_eq!(packet[29], LEGACY_PROTOCOL_VERSION);
+        assert_eq!(packet[30..32], 9u16.to_be_bytes());
+        assert_eq!(packet[packet.len() - 4..], 25565i32.to_be_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_status_round_trip() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+
+        let response = "\u{a7}1\x0078\x001.6.4\x00Test\x001\x002";
+        let server_task = tokio::spawn(async move {
+            let mut buffer = [0; 3];
+            server.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(buffer, [0xfe, 0x01, 0xfa]);
+            server.write_all(&kick_packet(response)).await.unwrap();
+        });
+
+        let status = legacy_status(&mut client, "localhost", 25565)
+            .await
+            .unwrap();
+        assert_eq!(status.motd, "Test");
+        assert_eq!(status.players_max, 2);
+
+        server_task.await.unwrap();
+    }
+}
@@ -1,6 +1,13 @@
+mod bedrock;
+mod legacy;
 mod protocol;
+mod query;
 mod server;
+mod udp;
+pub use bedrock::{ping_bedrock, BedrockConfig, BedrockStatus};
+pub use legacy::LegacyStatus;
+pub use query::{QueryBasicStat, QueryConnection, QueryFullStat};
 pub use server::{
     connect, ConnectionConfig, ServerDescription, ServerError, ServerPlayer, ServerPlayers,
-    ServerVersion, StatusConnection, StatusResponse,
+    ServerStatus, ServerVersion, StatusConnection, StatusResponse,
 };
@@ -0,0 +1,425 @@
+//! This module defines a client for the UDP
+//! [Query](https://wiki.vg/Query) protocol, based on
+//! GameSpy4, which servers expose when `enable-query`
+//! is set
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::net::UdpSocket;
+
+use crate::server::ServerError;
+
+const QUERY_MAGIC: [u8; 2] = [0xfe, 0xfd];
+const HANDSHAKE_TYPE: u8 = 0x09;
+const STAT_TYPE: u8 = 0x00;
+
+/// Sent after the challenge token to request a full stat.
+const FULL_STAT_PADDING: [u8; 4] = [0x00; 4];
+
+/// Fixed padding before the key-value section of a full stat.
+const FULL_STAT_KV_PADDING: &[u8] = b"splitnum\x00\x80\x00";
+
+/// Fixed padding before the player section of a full stat.
+const FULL_STAT_PLAYERS_PADDING: &[u8] = b"\x01player_\x00\x00";
+
+/// Responses are a single UDP datagram.
+const MAX_RESPONSE_SIZE: usize = 65_535;
+
+/// The response to a basic stat request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryBasicStat {
+    /// The server's MOTD.
+    pub motd: String,
+
+    /// The game type, which is always "SMP".
+    pub game_type: String,
+
+    /// The name of the server's default world.
+    pub map: String,
+
+    /// The number of players currently online.
+    pub players_online: i32,
+
+    /// The configured maximum number of players for the
+    /// server.
+    pub players_max: i32,
+
+    /// The port the server listens on.
+    pub host_port: u16,
+
+    /// The IP the server listens on.
+    pub host_ip: String,
+}
+
+/// The response to a full stat request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryFullStat {
+    /// The server's MOTD.
+    pub motd: String,
+
+    /// The game type, which is always "SMP".
+    pub game_type: String,
+
+    /// The game ID, which is always "MINECRAFT".
+    pub game_id: String,
+
+    /// The server's Minecraft version, i.e. "1.21.4".
+    pub version: String,
+
+    /// The server software and its version, i.e.
+    /// "Paper on 1.21.4", if the server reports plugins.
+    pub server_mod: Option<String>,
+
+    /// The server's plugins, including their versions.
+    pub plugins: Vec<String>,
+
+    /// The name of the server's default world.
+    pub map: String,
+
+    /// The number of players currently online.
+    pub players_online: i32,
+
+    /// The configured maximum number of players for the
+    /// server.
+    pub players_max: i32,
+
+    /// The port the server listens on.
+    pub host_port: u16,
+
+    /// The IP the server listens on.
+    pub host_ip: String,
+
+    /// The names of the players currently online.
+    pub players: Vec<String>,
+}
+
+/// Wraps a Query session after the handshake.
+///
+/// Constructed by calling `query()` on a
+/// `ConnectionConfig` struct.
+pub struct QueryConnection {
+    socket: UdpSocket,
+    session_id: i32,
+    challenge_token: i32,
+    timeout: Duration,
+}
+
+impl QueryConnection {
+    /// Performs the handshake, which issues the
+    /// challenge token stat requests must carry.
+    pub(crate) async fn handshake(
+        socket: UdpSocket,
+        timeout: Duration,
+    ) -> Result<Self, ServerError> {
+        // Only the lower 4 bits of each byte are read by
+        // the server
+        let session_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.subsec_nanos() as i32)
+            .unwrap_or_default()
+            & 0x0F0F_0F0F;
+
+        let mut connection = QueryConnection {
+            socket,
+            session_id,
+            challenge_token: 0,
+            timeout,
+        };
+
+        let response = connection.request(HANDSHAKE_TYPE, &[]).await?;
+        let mut reader = ResponseReader::new(&response);
+        connection.challenge_token = reader.number()?;
+
+        Ok(connection)
+    }
+
+    /// Requests a basic stat from the server.
+    pub async fn basic_stat(&self) -> Result<QueryBasicStat, ServerError> {
+        let response = self
+            .request(STAT_TYPE, &self.challenge_token.to_be_bytes())
+            .await?;
+
+        let mut reader = ResponseReader::new(&response);
+        Ok(QueryBasicStat {
+            motd: reader.string()?,
+            game_type: reader.string()?,
+            map: reader.string()?,
+            players_online: reader.number()?,
+            players_max: reader.number()?,
+            host_port: reader.u16_le()?,
+            host_ip: reader.string()?,
+        })
+    }
+
+    /// Requests a full stat, which includes plugins
+    /// and the names of the online players, from the
+    /// server.
+    pub async fn full_stat(&self) -> Result<QueryFullStat, ServerError> {
+        let mut payload = self.challenge_token.to_be_bytes().to_vec();
+        payload.extend_from_slice(&FULL_STAT_PADDING);
+        let response = self.request(STAT_TYPE, &payload).await?;
+
+        let mut reader = ResponseReader::new(&response);
+        reader.expect(FULL_STAT_KV_PADDING)?;
+
+        let mut values = HashMap::new();
+        loop {
+            let key = reader.string()?;
+            if key.is_empty() {
+                break;
+            }
+            values.insert(key, reader.string()?);
+        }
+
+        reader.expect(FULL_STAT_PLAYERS_PADDING)?;
+
+        let mut players = Vec::new();
+        loop {
+            let player = reader.string()?;
+            if player.is_empty() {
+                break;
+            }
+            players.push(player);
+        }
+
+        let mut value = |key: &str| values.remove(key).unwrap_or_default();
+        let (server_mod, plugins) = parse_plugins(&value("plugins"));
+
+        Ok(QueryFullStat {
+            motd: value("hostname"),
+            game_type: value("gametype"),
+            game_id: value("game_id"),
+            version: value("version"),
+            server_mod,
+            plugins,
+            map: value("map"),
+            players_online: parse_number(value("numplayers"))?,
+            players_max: parse_number(value("maxplayers"))?,
+            host_port: parse_number(value("hostport"))?,
+            host_ip: value("hostip"),
+            players,
+        })
+    }
+
+    /// Sends a request and returns the response body
+    /// following its header.
+    async fn request(&self, request_type: u8, payload: &[u8]) -> Result<Vec<u8>, ServerError> {
+        let mut packet = QUERY_MAGIC.to_vec();
+        packet.push(request_type);
+        packet.extend_from_slice(&self.session_id.to_be_bytes());
+        packet.extend_from_slice(payload);
+
+        tokio::time::timeout(self.timeout, async {
+            self.socket
+                .send(&packet)
+                .await
+                .map_err(|_| ServerError::ProtocolError)?;
+
+            let mut buffer = vec![0; MAX_RESPONSE_SIZE];
+            loop {
+                let len = self
+                    .socket
+                    .recv(&mut buffer)
+                    .await
+                    .map_err(|_| ServerError::ProtocolError)?;
+
+                // Skip late responses to earlier requests
+                if len < 5
+                    || buffer[0] != request_type
+                    || buffer[1..5] != self.session_id.to_be_bytes()
+                {
+                    continue;
+                }
+
+                buffer.truncate(len);
+                return Ok(buffer.split_off(5));
+            }
+        })
+        .await
+        .map_err(|_| ServerError::ConnectionTimedOut)?
+    }
+}
+
+fn parse_number<T: FromStr>(value: String) -> Result<T, ServerError> {
+    value.parse().map_err(|_| ServerError::InvalidQueryResponse)
+}
+
+/// Splits the `plugins` value of a full stat, formatted as
+/// `<server mod>: <plugin>; <plugin>`, into its parts.
+fn parse_plugins(plugins: &str) -> (Option<String>, Vec<String>) {
+    if plugins.is_empty() {
+        return (None, Vec::new());
+    }
+
+    match plugins.split_once(": ") {
+        Some((server_mod, plugins)) => (
+            Some(server_mod.to_string()),
+            plugins
+                .split("; ")
+                .filter(|x| !x.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ),
+        None => (Some(plugins.to_string()), Vec::new()),
+    }
+}
+
+/// Reads the null-terminated fields of a response.
+struct ResponseReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ResponseReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ResponseReader { data }
+    }
+
+    fn string(&mut self) -> Result<String, ServerError> {
+        let end = self
+            .data
+            .iter()
+            .position(|x| *x == 0)
+            .ok_or(ServerError::InvalidQueryResponse)?;
+        let string = String::from_utf8_lossy(&self.data[..end]).into_owned();
+        self.data = &self.data[end + 1..];
+        Ok(string)
+    }
+
+    fn number(&mut self) -> Result<i32, ServerError> {
+        parse_number(self.string()?)
+    }
+
+    fn u16_le(&mut self) -> Result<u16, ServerError> {
+        let [a, b, rest @ ..] = self.data else {
+            return Err(ServerError::InvalidQueryResponse);
+        };
+        self.data = rest;
+        Ok(u16::from_le_bytes([*a, *b]))
+    }
+
+    fn expect(&mut self, bytes: &[u8]) -> Result<(), ServerError> {
+        self.data = self
+            .data
+            .strip_prefix(bytes)
+            .ok_or(ServerError::InvalidQueryResponse)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHALLENGE_TOKEN: i32 = 9_513_307;
+
+    /// Binds a local stand-in server that answers Query
+    /// handshakes and stat requests.
+    async fn stand_in_server() -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let mut buffer = [0; 1024];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+                let request = &buffer[..len];
+                assert_eq!(request[..2], QUERY_MAGIC);
+
+                let mut response = vec![request[2]];
+                response.extend_from_slice(&request[3..7]);
+                match (request[2], len) {
+                    (HANDSHAKE_TYPE, 7) => {
+                        response.extend_from_slice(format!("{CHALLENGE_TOKEN}\0").as_bytes());
+                    }
+                    (STAT_TYPE, 11) => {
+                        assert_eq!(request[7..11], CHALLENGE_TOKEN.to_be_bytes());
+                        response.extend_from_slice(b"A Minecraft Server\0SMP\0world\x002\x0020\0");
+                        response.extend_from_slice(&25565u16.to_le_bytes());
+                        response.extend_from_slice(b"127.0.0.1\0");
+                    }
+                    (STAT_TYPE, 15) => {
+                        assert_eq!(request[7..11], CHALLENGE_TOKEN.to_be_bytes());
+                        response.extend_from_slice(FULL_STAT_KV_PADDING);
+                        response.extend_from_slice(
+                            b"hostname\0A Minecraft Server\0gametype\0SMP\0game_id\0MINECRAFT\0\
+                            version\x001.21.4\0plugins\0Paper on 1.21.4: WorldEdit 7.3.9; LuckPerms\0\
+                            map\0world\0numplayers\x002\0maxplayers\x0020\0hostport\x0025565\0\
+                            hostip\x00127.0.0.1\0\0",
+                        );
+                        response.extend_from_slice(FULL_STAT_PLAYERS_PADDING);
+                        response.extend_from_slice(b"Player1\0Player2\0\0");
+                    }
+                    _ => panic!("unexpected request {request:?}"),
+                }
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+
+        port
+    }
+
+    async fn connect(port: u16) -> QueryConnection {
+        let socket = crate::udp::connect_udp("127.0.0.1", port).await.unwrap();
+        QueryConnection::handshake(socket, Duration::from_secs(2))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handshake() {
+        let connection = connect(stand_in_server().await).await;
+        assert_eq!(connection.challenge_token, CHALLENGE_TOKEN);
+        assert_eq!(connection.session_id & !0x0F0F_0F0F, 0);
+    }
+
+    #[tokio::test]
+    async fn test_basic_stat() {
+        let connection = connect(stand_in_server().await).await;
+        let stat = connection.basic_stat().await.unwrap();
+        assert_eq!(stat.motd, "A Minecraft Server");
+        assert_eq!(stat.game_type, "SMP");
+        assert_eq!(stat.map, "world");
+        assert_eq!(stat.players_online, 2);
+        assert_eq!(stat.players_max, 20);
+        assert_eq!(stat.host_port, 25565);
+        assert_eq!(stat.host_ip, "127.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_full_stat() {
+        let connection = connect(stand_in_server().await).await;
+        let stat = connection.full_stat().await.unwrap();
+        assert_eq!(stat.motd, "A Minecraft Server");
+        assert_eq!(stat.game_id, "MINECRAFT");
+        assert_eq!(stat.version, "1.21.4");
+        assert_eq!(stat.server_mod.as_deref(), Some("Paper on 1.21.4"));
+        assert_eq!(stat.plugins, vec!["WorldEdit 7.3.9", "LuckPerms"]);
+        assert_eq!(stat.players_online, 2);
+        assert_eq!(stat.host_port, 25565);
+        assert_eq!(stat.players, vec!["Player1", "Player2"]);
+    }
+
+    #[test]
+    fn test_parse_plugins() {
+        assert_eq!(parse_plugins(""), (None, vec![]));
+        assert_eq!(
+            parse_plugins("CraftBukkit on Bukkit 1.2.5-R4.0"),
+            (Some("CraftBukkit on Bukkit 1.2.5-R4.0".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn test_truncated_response() {
+        let mut reader = ResponseReader::new(b"m");
+        assert!(matches!(
+            reader.string(),
+            Err(ServerError::InvalidQueryResponse)
+        ));
+        assert!(matches!(
+            reader.u16_le(),
+            Err(ServerError::InvalidQueryResponse)
+        ));
+    }
+}
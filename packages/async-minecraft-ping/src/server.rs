@@ -7,7 +7,10 @@ use serde::Deserialize;
 use thiserror::Error;
 use tokio::net::TcpStream;
 
+use crate::legacy::{self, LegacyStatus};
 use crate::protocol::{self, AsyncReadRawPacket, AsyncWriteRawPacket};
+use crate::query::QueryConnection;
+use crate::udp::connect_udp;
 
 #[derive(Error, Debug)]
 pub enum ServerError {
@@ -25,6 +28,15 @@ pub enum ServerError {
 
     #[error("mismatched pong payload (expected \"{expected}\", got \"{actual}\")")]
     MismatchedPayload { expected: u64, actual: u64 },
+
+    #[error("invalid Bedrock server status: \"{0}\"")]
+    InvalidBedrockStatus(String),
+
+    #[error("invalid legacy server status: \"{0}\"")]
+    InvalidLegacyStatus(String),
+
+    #[error("invalid Query response")]
+    InvalidQueryResponse,
 }
 
 impl From<protocol::ProtocolError> for ServerError {
@@ -95,6 +107,44 @@ pub struct StatusResponse {
     pub favicon: Option<String>,
 }
 
+/// The status of a server, read with the modern
+/// ServerListPing if the server supports it or with
+/// the legacy ping otherwise.
+#[derive(Debug)]
+pub enum ServerStatus {
+    Modern(StatusResponse),
+    Legacy(LegacyStatus),
+}
+
+impl ServerStatus {
+    /// The server's MOTD.
+    pub fn motd(&self) -> &str {
+        match self {
+            ServerStatus::Modern(status) => match &status.description {
+                ServerDescription::Plain(text) | ServerDescription::Object { text } => text,
+            },
+            ServerStatus::Legacy(status) => &status.motd,
+        }
+    }
+
+    /// The number of players currently online.
+    pub fn players_online(&self) -> i32 {
+        match self {
+            ServerStatus::Modern(status) => status.players.online,
+            ServerStatus::Legacy(status) => status.players_online,
+        }
+    }
+
+    /// The configured maximum number of players for the
+    /// server.
+    pub fn players_max(&self) -> i32 {
+        match self {
+            ServerStatus::Modern(status) => status.players.max,
+            ServerStatus::Legacy(status) => status.players_max,
+        }
+    }
+}
+
 const LATEST_PROTOCOL_VERSION: usize = 578;
 const DEFAULT_PORT: u16 = 25565;
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
@@ -105,6 +155,7 @@ pub struct ConnectionConfig {
     protocol_version: usize,
     address: String,
     port: u16,
+    query_port: Option<u16>,
     timeout: Duration,
     #[cfg(feature = "srv")]
     srv_lookup: bool,
@@ -118,6 +169,7 @@ impl ConnectionConfig {
             protocol_version: LATEST_PROTOCOL_VERSION,
             address: address.into(),
             port: DEFAULT_PORT,
+            query_port: None,
             timeout: DEFAULT_TIMEOUT,
             #[cfg(feature = "srv")]
             srv_lookup: false,
@@ -141,6 +193,14 @@ impl ConnectionConfig {
         self
     }
 
+    /// Sets a specific port for Query
+    /// requests to use. If not specified, the
+    /// server's port will be used.
+    pub fn with_query_port(mut self, port: u16) -> Self {
+        self.query_port = Some(port);
+        self
+    }
+
     /// Sets a specific timeout for the
     /// connection to use. If not specified, the
     /// timeout defaults to two seconds.
@@ -165,6 +225,78 @@ impl ConnectionConfig {
 
     /// Connects to the server and consumes the builder.
     pub async fn connect(self) -> Result<StatusConnection, ServerError> {
+        let (stream, address, port) = self.open_stream().await?;
+
+        Ok(StatusConnection {
+            stream,
+            protocol_version: self.protocol_version,
+            address,
+            port,
+            timeout: self.timeout,
+        })
+    }
+
+    /// Reads the server's status with the legacy ping
+    /// answered by servers older than 1.7, and consumes
+    /// the builder.
+    pub async fn legacy_status(self) -> Result<LegacyStatus, ServerError> {
+        let (mut stream, address, port) = self.open_stream().await?;
+
+        tokio::time::timeout(
+            self.timeout,
+            legacy::legacy_status(&mut stream, &address, port),
+        )
+        .await
+        .map_err(|_| ServerError::ConnectionTimedOut)?
+    }
+
+    /// Reads the server's status and consumes the builder.
+    ///
+    /// Falls back to the legacy ping if the server doesn't
+    /// answer the modern ServerListPing, so servers older
+    /// than 1.7 are supported as well.
+    pub async fn status(self) -> Result<ServerStatus, ServerError> {
+        let modern = async {
+            let (stream, address, port) = self.open_stream().await?;
+            let connection = StatusConnection {
+                stream,
+                protocol_version: self.protocol_version,
+                address,
+                port,
+                timeout: self.timeout,
+            };
+            connection.status().await
+        };
+
+        let err = match modern.await {
+            Ok(connection) => return Ok(ServerStatus::Modern(connection.status)),
+            // The server isn't reachable at all, so there's
+            // nothing to fall back to
+            Err(err @ ServerError::FailedToConnect) => return Err(err),
+            Err(err) => err,
+        };
+
+        // Legacy servers close the connection on the modern
+        // handshake, so the legacy ping needs a new one
+        self.legacy_status()
+            .await
+            .map(ServerStatus::Legacy)
+            .map_err(|_| err)
+    }
+
+    /// Performs a Query handshake with the server and
+    /// consumes the builder.
+    ///
+    /// The server must have `enable-query` set. Query
+    /// requests are sent over UDP to the query port.
+    pub async fn query(self) -> Result<QueryConnection, ServerError> {
+        let (address, port) = self.resolve_address().await;
+        let socket = connect_udp(&address, self.query_port.unwrap_or(port)).await?;
+
+        QueryConnection::handshake(socket, self.timeout).await
+    }
+
+    async fn open_stream(&self) -> Result<(TcpStream, String, u16), ServerError> {
         let (address, port) = self.resolve_address().await;
 
         let stream = tokio::time::timeout(
@@ -175,13 +307,7 @@ impl ConnectionConfig {
         .map_err(|_| ServerError::ConnectionTimedOut)?
         .map_err(|_| ServerError::FailedToConnect)?;
 
-        Ok(StatusConnection {
-            stream,
-            protocol_version: self.protocol_version,
-            address,
-            port,
-            timeout: self.timeout,
-        })
+        Ok((stream, address, port))
     }
 
     #[cfg(feature = "srv")]
@@ -419,6 +545,55 @@ mod tests {
         assert_eq!(config.protocol_version, 47);
     }
 
+    #[test]
+    fn test_connection_config_with_query_port() {
+        let config = ConnectionConfig::build("localhost");
+        assert_eq!(config.query_port, None);
+
+        let config = config.with_query_port(25575);
+        assert_eq!(config.query_port, Some(25575));
+    }
+
+    #[tokio::test]
+    async fn test_status_falls_back_to_legacy() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            // Legacy servers don't understand the modern
+            // handshake and close the connection
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 1];
+            stream.read_exact(&mut buffer).await.unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 2];
+            stream.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(buffer, [0xfe, 0x01]);
+
+            let message = "\u{a7}1\x0061\x001.5.2\x00Old Server\x001\x0010";
+            let mut kick = vec![0xff];
+            kick.extend_from_slice(&(message.encode_utf16().count() as u16).to_be_bytes());
+            kick.extend(message.encode_utf16().flat_map(u16::to_be_bytes));
+            stream.write_all(&kick).await.unwrap();
+        });
+
+        let status = ConnectionConfig::build("127.0.0.1")
+            .with_port(port)
+            .status()
+            .await
+            .unwrap();
+        assert!(
+            matches!(&status, ServerStatus::Legacy(status) if status.version.as_deref() == Some("1.5.2"))
+        );
+        assert_eq!(status.motd(), "Old Server");
+        assert_eq!(status.players_max(), 10);
+    }
+
     #[cfg(feature = "srv")]
     #[test]
     fn test_connection_config_with_srv_lookup() {
@@ -0,0 +1,32 @@
+//! Helpers shared by the UDP based protocols
+
+use std::net::SocketAddr;
+
+use tokio::net::UdpSocket;
+
+use crate::server::ServerError;
+
+/// Binds a UDP socket on an ephemeral port and connects
+/// it to the first address the host resolves to.
+pub(crate) async fn connect_udp(address: &str, port: u16) -> Result<UdpSocket, ServerError> {
+    let target = tokio::net::lookup_host((address, port))
+        .await
+        .map_err(|_| ServerError::FailedToConnect)?
+        .next()
+        .ok_or(ServerError::FailedToConnect)?;
+
+    let bind_address: SocketAddr = if target.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind_address)
+        .await
+        .map_err(|_| ServerError::FailedToConnect)?;
+    socket
+        .connect(target)
+        .await
+        .map_err(|_| ServerError::FailedToConnect)?;
+
+    Ok(socket)
+}
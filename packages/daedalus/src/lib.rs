@@ -8,6 +8,8 @@
 pub mod minecraft;
 /// Models and methods for fetching metadata for Minecraft mod loaders
 pub mod modded;
+/// Signatures of metadata files, so launchers can tell they are authentic
+pub mod signing;
 
 #[derive(thiserror::Error, Debug)]
 /// An error type representing possible errors when fetching metadata
@@ -15,6 +17,9 @@ pub enum Error {
     /// Error while parsing input
     #[error("{0}")]
     ParseError(String),
+    /// A signature is missing, malformed or doesn't match
+    #[error("Invalid signature: {0}")]
+    SignatureError(String),
 }
 
 /// Converts a maven artifact to a path
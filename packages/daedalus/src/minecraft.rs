@@ -4,9 +4,15 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// The latest version of the format the model structs deserialize to
-pub const CURRENT_FORMAT_VERSION: usize = 0;
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// Format 1 added the [`VersionType::Experiment`] and
+/// [`VersionType::AprilFools`] version types. Launchers reading format 0 fail
+/// to parse a manifest containing types they don't know, so those versions
+/// can't be added to it. Manifests of format 0 are still published for them,
+/// with April Fools versions listed as snapshots and experiments left out
+pub const CURRENT_FORMAT_VERSION: usize = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 /// The version type
 pub enum VersionType {
@@ -18,6 +24,11 @@ pub enum VersionType {
     OldAlpha,
     /// Early versions of the game
     OldBeta,
+    /// A one-off build testing a single feature, such as the combat tests,
+    /// which was never part of the regular snapshot cycle
+    Experiment,
+    /// A joke version released on April Fools' Day
+    AprilFools,
 }
 
 impl VersionType {
@@ -28,6 +39,8 @@ impl VersionType {
             VersionType::Snapshot => "snapshot",
             VersionType::OldAlpha => "old_alpha",
             VersionType::OldBeta => "old_beta",
+            VersionType::Experiment => "experiment",
+            VersionType::AprilFools => "april_fools",
         }
     }
 }
@@ -1,5 +1,5 @@
 use crate::minecraft::{
-    Argument, ArgumentType, Library, VersionInfo, VersionType,
+    Argument, ArgumentType, ArgumentValue, Library, VersionInfo, VersionType,
 };
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
@@ -13,6 +13,14 @@ pub const CURRENT_FORGE_FORMAT_VERSION: usize = 0;
 pub const CURRENT_QUILT_FORMAT_VERSION: usize = 0;
 /// The latest version of the format the neoforge model structs deserialize to
 pub const CURRENT_NEOFORGE_FORMAT_VERSION: usize = 0;
+/// The latest version of the format the legacy fabric model structs deserialize to
+pub const CURRENT_LEGACY_FABRIC_FORMAT_VERSION: usize = 0;
+/// The latest version of the format the babric model structs deserialize to
+pub const CURRENT_BABRIC_FORMAT_VERSION: usize = 0;
+/// The latest version of the format the liteloader model structs deserialize to
+pub const CURRENT_LITELOADER_FORMAT_VERSION: usize = 0;
+/// The latest version of the format the rift model structs deserialize to
+pub const CURRENT_RIFT_FORMAT_VERSION: usize = 0;
 
 /// The dummy replace string library names, inheritsFrom, and version names should be replaced with
 pub const DUMMY_REPLACE_STRING: &str = "${modrinth.gameVersion}";
@@ -122,9 +130,11 @@ pub fn merge_partial_version(
         }
     }
 
-    VersionInfo {
-        arguments: if let Some(partial_args) = partial.arguments {
-            if let Some(merge_args) = merge.arguments {
+    let minecraft_arguments =
+        partial.minecraft_arguments.or(merge.minecraft_arguments);
+    let (arguments, minecraft_arguments) =
+        match (partial.arguments, merge.arguments, minecraft_arguments) {
+            (Some(partial_args), Some(merge_args), minecraft_arguments) => {
                 let mut new_map = HashMap::new();
 
                 fn add_keys(
@@ -145,13 +155,64 @@ pub fn merge_partial_version(
                 add_keys(&mut new_map, merge_args);
                 add_keys(&mut new_map, partial_args);
 
-                Some(new_map)
-            } else {
-                Some(partial_args)
+                (Some(new_map), minecraft_arguments)
             }
-        } else {
-            merge.arguments
-        },
+            // Versions before 1.13 only have legacy arguments, which launchers
+            // ignore once any arguments are set. Game arguments of loaders
+            // running on them are appended to the legacy arguments, and JVM
+            // arguments are added after the ones legacy versions launch with
+            (Some(mut partial_args), None, Some(mut minecraft_arguments)) => {
+                for arg in partial_args
+                    .remove(&ArgumentType::Game)
+                    .into_iter()
+                    .flatten()
+                {
+                    let values = match arg {
+                        Argument::Normal(value) => vec![value],
+                        Argument::Ruled { rules, value }
+                            if rules.is_empty() =>
+                        {
+                            match value {
+                                ArgumentValue::Single(value) => vec![value],
+                                ArgumentValue::Many(values) => values,
+                            }
+                        }
+                        Argument::Ruled { .. } => continue,
+                    };
+
+                    for value in values {
+                        minecraft_arguments.push(' ');
+                        minecraft_arguments.push_str(&value);
+                    }
+                }
+
+                let jvm_args = partial_args
+                    .remove(&ArgumentType::Jvm)
+                    .filter(|x| !x.is_empty());
+                let arguments = jvm_args.map(|jvm_args| {
+                    HashMap::from([(
+                        ArgumentType::Jvm,
+                        [
+                            "-Djava.library.path=${natives_directory}",
+                            "-cp",
+                            "${classpath}",
+                        ]
+                        .into_iter()
+                        .map(|x| Argument::Normal(x.to_string()))
+                        .chain(jvm_args)
+                        .collect(),
+                    )])
+                });
+
+                (arguments, Some(minecraft_arguments))
+            }
+            (partial_args, merge_args, minecraft_arguments) => {
+                (partial_args.or(merge_args), minecraft_arguments)
+            }
+        };
+
+    VersionInfo {
+        arguments,
         asset_index: merge.asset_index,
         assets: merge.assets,
         downloads: merge.downloads,
@@ -172,7 +233,7 @@ pub fn merge_partial_version(
         } else {
             merge.main_class
         },
-        minecraft_arguments: partial.minecraft_arguments,
+        minecraft_arguments,
         minimum_launcher_version: merge.minimum_launcher_version,
         release_time: partial.release_time,
         time: partial.time,
@@ -211,3 +272,229 @@ pub struct LoaderVersion {
     /// Whether the loader is stable or not
     pub stable: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn vanilla(
+        id: &str,
+        minecraft_arguments: Option<&str>,
+        arguments: Option<Value>,
+    ) -> VersionInfo {
+        serde_json::from_value(json!({
+            "arguments": arguments,
+            "assetIndex": {
+                "id": id,
+                "sha1": "",
+                "size": 0,
+                "totalSize": 0,
+                "url": "",
+            },
+            "assets": id,
+            "downloads": {},
+            "id": id,
+            "libraries": [{ "name": "com.mojang:brigadier:1.0.18" }],
+            "mainClass": "net.minecraft.client.main.Main",
+            "minecraftArguments": minecraft_arguments,
+            "minimumLauncherVersion": 0,
+            "releaseTime": "2017-09-18T08:39:46+00:00",
+            "time": "2017-09-18T08:39:46+00:00",
+            "type": "release",
+        }))
+        .unwrap()
+    }
+
+    fn partial(
+        main_class: &str,
+        minecraft_arguments: Option<&str>,
+        arguments: Option<Value>,
+    ) -> PartialVersionInfo {
+        serde_json::from_value(json!({
+            "id": format!("{DUMMY_REPLACE_STRING}-loader"),
+            "inheritsFrom": DUMMY_REPLACE_STRING,
+            "releaseTime": "2023-06-01T00:00:00+00:00",
+            "time": "2023-06-01T00:00:00+00:00",
+            "mainClass": main_class,
+            "minecraftArguments": minecraft_arguments,
+            "arguments": arguments,
+            "libraries": [{ "name": "net.example:loader:1.0" }],
+            "type": "release",
+        }))
+        .unwrap()
+    }
+
+    fn arguments(version: &VersionInfo) -> Value {
+        serde_json::to_value(&version.arguments).unwrap()
+    }
+
+    #[test]
+    fn merges_forge_into_modern_versions() {
+        let merged = merge_partial_version(
+            partial(
+                "cpw.mods.bootstraplauncher.BootstrapLauncher",
+                None,
+                Some(json!({
+                    "game": ["--launchTarget", "forgeclient"],
+                    "jvm": ["-DlibraryDirectory=${library_directory}"],
+                })),
+            ),
+            vanilla(
+                "1.20.1",
+                None,
+                Some(json!({
+                    "game": ["--username", "${auth_player_name}"],
+                    "jvm": ["-cp", "${classpath}"],
+                })),
+            ),
+        );
+
+        assert_eq!(merged.id, "1.20.1-loader");
+        assert_eq!(
+            merged.main_class,
+            "cpw.mods.bootstraplauncher.BootstrapLauncher"
+        );
+        assert_eq!(merged.minecraft_arguments, None);
+        assert_eq!(
+            arguments(&merged),
+            json!({
+                "game": [
+                    "--username",
+                    "${auth_player_name}",
+                    "--launchTarget",
+                    "forgeclient",
+                ],
+                "jvm": [
+                    "-cp",
+                    "${classpath}",
+                    "-DlibraryDirectory=${library_directory}",
+                ],
+            })
+        );
+        assert_eq!(
+            merged
+                .libraries
+                .iter()
+                .map(|x| &*x.name)
+                .collect::<Vec<_>>(),
+            ["com.mojang:brigadier:1.0.18", "net.example:loader:1.0"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_legacy_arguments_of_legacy_forge() {
+        let forge_arguments = "--username ${auth_player_name} --tweakClass \
+                               net.minecraftforge.fml.common.launcher.FMLTweaker";
+        let merged = merge_partial_version(
+            partial(
+                "net.minecraft.launchwrapper.Launch",
+                Some(forge_arguments),
+                None,
+            ),
+            vanilla("1.12.2", Some("--username ${auth_player_name}"), None),
+        );
+
+        assert_eq!(
+            merged.minecraft_arguments.as_deref(),
+            Some(forge_arguments)
+        );
+        assert_eq!(arguments(&merged), Value::Null);
+    }
+
+    #[test]
+    fn merges_fabric_into_modern_versions() {
+        let merged = merge_partial_version(
+            partial(
+                "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                None,
+                Some(json!({
+                    "game": [],
+                    "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "],
+                })),
+            ),
+            vanilla(
+                "1.20.1",
+                None,
+                Some(json!({ "game": ["--username", "${auth_player_name}"] })),
+            ),
+        );
+
+        assert_eq!(merged.minecraft_arguments, None);
+        assert_eq!(
+            arguments(&merged),
+            json!({
+                "game": ["--username", "${auth_player_name}"],
+                "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "],
+            })
+        );
+    }
+
+    #[test]
+    fn merges_fabric_into_legacy_versions() {
+        // Before legacy arguments were merged, this produced no legacy
+        // arguments and `{ "game": [] }`, launching the game without any
+        // arguments
+        let merged = merge_partial_version(
+            partial(
+                "net.fabricmc.loader.impl.launch.knot.KnotClient",
+                None,
+                Some(json!({ "game": [] })),
+            ),
+            vanilla("1.8.9", Some("--username ${auth_player_name}"), None),
+        );
+
+        assert_eq!(
+            merged.minecraft_arguments.as_deref(),
+            Some("--username ${auth_player_name}")
+        );
+        assert_eq!(arguments(&merged), Value::Null);
+    }
+
+    #[test]
+    fn merges_liteloader_into_legacy_versions() {
+        // Before legacy arguments were merged, this produced no legacy
+        // arguments and only the tweak class as game arguments
+        let merged = merge_partial_version(
+            partial(
+                "net.minecraft.launchwrapper.Launch",
+                None,
+                Some(json!({
+                    "game": [
+                        "--tweakClass",
+                        "com.mumfrey.liteloader.launch.LiteLoaderTweaker",
+                        { "value": ["--versionType", "LiteLoader"] },
+                        {
+                            "rules": [{
+                                "action": "allow",
+                                "features": { "is_demo_user": true },
+                            }],
+                            "value": "--demo",
+                        },
+                    ],
+                    "jvm": ["-Dliteloader.debug=false"],
+                })),
+            ),
+            vanilla("1.12.2", Some("--username ${auth_player_name}"), None),
+        );
+
+        assert_eq!(
+            merged.minecraft_arguments.as_deref(),
+            Some(
+                "--username ${auth_player_name} --tweakClass \
+                 com.mumfrey.liteloader.launch.LiteLoaderTweaker --versionType LiteLoader"
+            )
+        );
+        assert_eq!(
+            arguments(&merged),
+            json!({
+                "jvm": [
+                    "-Djava.library.path=${natives_directory}",
+                    "-cp",
+                    "${classpath}",
+                    "-Dliteloader.debug=false",
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::Error;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+
+/// The suffix added to the path of a file to get the path of its detached
+/// signature, e.g. `fabric/v0/manifest.json.sig`
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// Prefixed to every signed message, so signatures of metadata can't be
+/// used for anything else
+const SIGNATURE_CONTEXT: &[u8] = b"daedalus-signature-v1\n";
+
+/// The detached signature of a metadata file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// The ID of the key the file was signed with
+    pub key_id: String,
+    /// The base64-encoded Ed25519 signature
+    pub signature: String,
+}
+
+/// A public key metadata files are trusted to be signed with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    /// The ID signatures refer to the key with
+    pub key_id: String,
+    /// The base64-encoded Ed25519 public key
+    pub public_key: String,
+}
+
+impl TrustedKey {
+    /// Parses keys in the `key_id:public_key` format, separated by commas.
+    /// Fails if a public key isn't a base64-encoded 32 byte Ed25519 key
+    pub fn parse_list(keys: &str) -> Result<Vec<Self>, Error> {
+        keys.split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|key| {
+                let (key_id, public_key) =
+                    key.split_once(':').ok_or_else(|| {
+                        Error::ParseError(format!("Invalid trusted key {key}"))
+                    })?;
+                let public_key = public_key.trim();
+                if BASE64.decode(public_key).map(|x| x.len()).ok() != Some(32) {
+                    return Err(Error::ParseError(format!(
+                        "Invalid public key of trusted key {key_id}"
+                    )));
+                }
+
+                Ok(Self {
+                    key_id: key_id.trim().to_string(),
+                    public_key: public_key.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The message that is signed for a file. The path of the file relative to
+/// the root of the metadata is part of it, so a signed file can't be served
+/// in place of another one
+fn signed_message(path: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(
+        SIGNATURE_CONTEXT.len() + path.len() + 1 + body.len(),
+    );
+    message.extend_from_slice(SIGNATURE_CONTEXT);
+    message.extend_from_slice(path.as_bytes());
+    message.push(b'\n');
+    message.extend_from_slice(body);
+    message
+}
+
+/// Signs metadata files with an Ed25519 key
+pub struct Signer {
+    key_id: String,
+    key_pair: Ed25519KeyPair,
+}
+
+impl std::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signer")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer {
+    /// Creates a signer from a base64-encoded 32 byte Ed25519 seed
+    pub fn from_seed(key_id: String, seed: &str) -> Result<Self, Error> {
+        let seed = BASE64.decode(seed.trim()).map_err(|err| {
+            Error::SignatureError(format!("Invalid signing key: {err}"))
+        })?;
+        let key_pair =
+            Ed25519KeyPair::from_seed_unchecked(&seed).map_err(|err| {
+                Error::SignatureError(format!("Invalid signing key: {err}"))
+            })?;
+
+        Ok(Self { key_id, key_pair })
+    }
+
+    /// The public key launchers need to trust to verify the signatures
+    pub fn trusted_key(&self) -> TrustedKey {
+        TrustedKey {
+            key_id: self.key_id.clone(),
+            public_key: BASE64.encode(self.key_pair.public_key().as_ref()),
+        }
+    }
+
+    /// Signs the file at `path`, relative to the root of the metadata
+    pub fn sign(&self, path: &str, body: &[u8]) -> Signature {
+        Signature {
+            key_id: self.key_id.clone(),
+            signature: BASE64.encode(
+                self.key_pair.sign(&signed_message(path, body)).as_ref(),
+            ),
+        }
+    }
+}
+
+/// Verifies that the file at `path`, relative to the root of the metadata,
+/// was signed by one of the trusted keys
+pub fn verify(
+    trusted_keys: &[TrustedKey],
+    path: &str,
+    body: &[u8],
+    signature: &Signature,
+) -> Result<(), Error> {
+    let key = trusted_keys
+        .iter()
+        .find(|x| x.key_id == signature.key_id)
+        .ok_or_else(|| {
+            Error::SignatureError(format!(
+                "{path} is signed with untrusted key {}",
+                signature.key_id
+            ))
+        })?;
+
+    let public_key = BASE64.decode(&key.public_key).map_err(|err| {
+        Error::SignatureError(format!(
+            "Invalid trusted key {}: {err}",
+            key.key_id
+        ))
+    })?;
+    let signature_bytes =
+        BASE64.decode(&signature.signature).map_err(|err| {
+            Error::SignatureError(format!(
+                "Malformed signature of {path}: {err}"
+            ))
+        })?;
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(&signed_message(path, body), &signature_bytes)
+        .map_err(|_| {
+            Error::SignatureError(format!("Signature of {path} doesn't match"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+    #[test]
+    fn signature_round_trip() {
+        let signer = Signer::from_seed("test".to_string(), SEED).unwrap();
+        let keys = vec![signer.trusted_key()];
+        let signature = signer.sign("fabric/v0/manifest.json", b"{}");
+
+        verify(&keys, "fabric/v0/manifest.json", b"{}", &signature).unwrap();
+        assert!(
+            verify(&keys, "fabric/v0/manifest.json", b"{ }", &signature)
+                .is_err()
+        );
+        assert!(
+            verify(&keys, "quilt/v0/manifest.json", b"{}", &signature).is_err()
+        );
+        assert!(
+            verify(&[], "fabric/v0/manifest.json", b"{}", &signature).is_err()
+        );
+    }
+
+    #[test]
+    fn parses_trusted_keys() {
+        let key = Signer::from_seed("test".to_string(), SEED)
+            .unwrap()
+            .trusted_key();
+        let keys =
+            TrustedKey::parse_list(&format!(" test:{} , ,", key.public_key))
+                .unwrap();
+        assert_eq!(keys, vec![key]);
+
+        assert_eq!(TrustedKey::parse_list("").unwrap(), Vec::new());
+        assert!(TrustedKey::parse_list("test").is_err());
+        assert!(TrustedKey::parse_list("test:not base64").is_err());
+        assert!(TrustedKey::parse_list("test:AAECAwQ=").is_err());
+    }
+}
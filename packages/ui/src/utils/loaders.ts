@@ -4,6 +4,10 @@ export const loaderDisplayNames: Record<string, string> = {
 	neo_forge: 'NeoForge',
 	forge: 'Forge',
 	quilt: 'Quilt',
+	liteloader: 'LiteLoader',
+	rift: 'Rift',
+	'legacy-fabric': 'Legacy Fabric',
+	babric: 'Babric',
 	paper: 'Paper',
 	purpur: 'Purpur',
 	vanilla: 'Vanilla',